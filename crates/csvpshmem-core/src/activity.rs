@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use crate::data::{Event, ProfileData};
+
+// how busy one function keeps the PEs over time
+#[derive(Debug, Clone)]
+pub struct FunctionActivity {
+    pub function: String,
+    // seconds spent in it over the whole range, all PEs together
+    pub total: f64,
+    // per bin, PEs in the function on average: 0 for none, pe_count when
+    // every PE was in it the whole bin
+    pub bins: Vec<f32>,
+}
+
+// one row per function called in [start, end], split into `bins` equal
+// bins, busiest function first. calls `keep` turns down are left out
+pub fn by_function(
+    data: &ProfileData,
+    start: f64,
+    end: f64,
+    bins: usize,
+    keep: impl Fn(&Event) -> bool,
+) -> Vec<FunctionActivity> {
+    if bins == 0 || end <= start {
+        return Vec::new();
+    }
+    let width = (end - start) / bins as f64;
+    let mut rows: Vec<FunctionActivity> = Vec::new();
+    let mut row_of: HashMap<&str, usize> = HashMap::new();
+    for i in data.overlapping(start, end) {
+        let e = data.event(i);
+        if !keep(&e) {
+            continue;
+        }
+        let (a, b) = (
+            e.raw.time.max(start),
+            (e.raw.time + e.raw.duration_sec).min(end),
+        );
+        if b <= a {
+            continue;
+        }
+        let row = *row_of.entry(e.raw.function).or_insert_with(|| {
+            rows.push(FunctionActivity {
+                function: e.raw.function.to_string(),
+                total: 0.0,
+                bins: vec![0.0; bins],
+            });
+            rows.len() - 1
+        });
+        let row = &mut rows[row];
+        row.total += b - a;
+        let first = ((a - start) / width) as usize;
+        for (k, bin) in row.bins.iter_mut().enumerate().skip(first) {
+            let (lo, hi) = (start + k as f64 * width, start + (k + 1) as f64 * width);
+            if lo >= b {
+                break;
+            }
+            *bin += ((b.min(hi) - a.max(lo)) / width) as f32;
+        }
+    }
+    rows.sort_by(|a, b| b.total.total_cmp(&a.total));
+    rows
+}
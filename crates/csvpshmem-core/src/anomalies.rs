@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+use crate::data::ProfileData;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Anomaly {
+    // index into ProfileData::events
+    pub event: usize,
+    // standard deviations above the function's mean duration
+    pub sigma: f64,
+}
+
+// functions called fewer times than this don't have a meaningful spread
+const MIN_SAMPLES: usize = 8;
+
+// events whose duration is more than `threshold` sigma above the mean for
+// their function, most severe first
+pub fn detect(data: &ProfileData, threshold: f64) -> Vec<Anomaly> {
+    // function -> (count, sum, sum of squares)
+    let mut moments: HashMap<&str, (usize, f64, f64)> = HashMap::new();
+    for e in data.iter() {
+        let m = moments.entry(e.raw.function).or_default();
+        m.0 += 1;
+        m.1 += e.raw.duration_sec;
+        m.2 += e.raw.duration_sec * e.raw.duration_sec;
+    }
+    let stats: HashMap<&str, (f64, f64)> = moments
+        .into_iter()
+        .filter(|(_, (n, _, _))| *n >= MIN_SAMPLES)
+        .filter_map(|(f, (n, sum, sq))| {
+            let mean = sum / n as f64;
+            let std_dev = (sq / n as f64 - mean * mean).max(0.0).sqrt();
+            (std_dev > 0.0).then_some((f, (mean, std_dev)))
+        })
+        .collect();
+
+    let mut anomalies: Vec<Anomaly> = data
+        .events
+        .iter()
+        .enumerate()
+        .filter_map(|(i, e)| {
+            let (mean, std_dev) = stats.get(e.raw.function)?;
+            let sigma = (e.raw.duration_sec - mean) / std_dev;
+            (sigma > threshold).then_some(Anomaly { event: i, sigma })
+        })
+        .collect();
+    anomalies.sort_by(|a, b| b.sigma.total_cmp(&a.sigma));
+    anomalies
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use crate::data::ProfileData;
+
+// one direction between two PEs, as told by each end
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    pub src: u32,
+    pub dst: u32,
+    // bytes_tx of src's calls targeting dst
+    pub sent: u64,
+    pub sends: usize,
+    // bytes_rx of dst's calls targeting src
+    pub received: u64,
+    pub receives: usize,
+}
+
+impl Mismatch {
+    fn new(src: u32, dst: u32) -> Self {
+        Self {
+            src,
+            dst,
+            sent: 0,
+            sends: 0,
+            received: 0,
+            receives: 0,
+        }
+    }
+
+    // |sent - received| over the larger of the two
+    pub fn difference(&self) -> f64 {
+        self.sent.abs_diff(self.received) as f64 / self.sent.max(self.received).max(1) as f64
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AsymmetryReport {
+    // directions where both ends had something to say
+    pub compared: usize,
+    // worst first
+    pub mismatches: Vec<Mismatch>,
+}
+
+// compares what each PE says it sent to another with what that one says it
+// received back from it. a side only counts as silent when that PE never
+// reports any bytes of that kind, so one-sided puts and gets, where only the
+// initiator logs, aren't flagged
+pub fn detect(data: &ProfileData, tolerance: f64) -> AsymmetryReport {
+    let pe_count = data.pe_count as usize;
+    let mut logs_tx = vec![false; pe_count];
+    let mut logs_rx = vec![false; pe_count];
+    let mut pairs: HashMap<(u32, u32), Mismatch> = HashMap::new();
+    for e in data.iter() {
+        let (tx, rx) = (e.raw.bytes_tx, e.raw.bytes_rx);
+        let pe = e.source_pe;
+        logs_tx[pe as usize] |= tx > 0;
+        logs_rx[pe as usize] |= rx > 0;
+        if e.raw.target_pe < 0 || pe == e.raw.target_pe as u32 {
+            continue;
+        }
+        let target = e.raw.target_pe as u32;
+        if tx > 0 {
+            let m = pairs
+                .entry((pe, target))
+                .or_insert_with(|| Mismatch::new(pe, target));
+            m.sent += tx;
+            m.sends += 1;
+        }
+        if rx > 0 {
+            let m = pairs
+                .entry((target, pe))
+                .or_insert_with(|| Mismatch::new(target, pe));
+            m.received += rx;
+            m.receives += 1;
+        }
+    }
+
+    let mut report = AsymmetryReport::default();
+    for m in pairs.into_values() {
+        let heard = |logs: &[bool], pe: u32| logs.get(pe as usize).copied().unwrap_or(false);
+        if !heard(&logs_tx, m.src) || !heard(&logs_rx, m.dst) {
+            continue;
+        }
+        report.compared += 1;
+        if m.difference() > tolerance {
+            report.mismatches.push(m);
+        }
+    }
+    report.mismatches.sort_by(|a, b| {
+        b.difference()
+            .total_cmp(&a.difference())
+            .then((a.src, a.dst).cmp(&(b.src, b.dst)))
+    });
+    report
+}
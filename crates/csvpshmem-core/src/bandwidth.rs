@@ -0,0 +1,492 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::data::{Event, ProfileData};
+use crate::prefix::ByteSums;
+use crate::units;
+
+// comms[(src, dst)] = (tx bytes, rx bytes), or messages or average message
+// size, see EdgeWeight. a PE's traffic to itself (local puts) is keyed
+// (pe, pe)
+pub type Comms = HashMap<(u32, u32), (u64, u64)>;
+
+// target_pe of a call that goes to every PE, e.g. a broadcast. the trace
+// doesn't say which team other negative targets mean, so they count as
+// every PE too
+pub const ALL_PES: i32 = -1;
+
+// collectives[pe] = (tx, rx) of the calls of `pe` with a negative target,
+// weighed like Comms
+pub type Collectives = HashMap<u32, (u64, u64)>;
+
+// indices of the events starting in [start_time, end_time]
+fn event_range(data: &ProfileData, start_time: f64, end_time: f64) -> std::ops::Range<usize> {
+    let start = data.events.time.partition_point(|&t| t < start_time);
+    let end = data.events.time.partition_point(|&t| t <= end_time);
+    start..end.max(start)
+}
+
+// what an event adds to each direction: its bytes, or one message
+fn amounts(event: &Event, count: bool) -> (u64, u64) {
+    let (tx, rx) = (event.raw.bytes_tx, event.raw.bytes_rx);
+    if count {
+        (u64::from(tx > 0), u64::from(rx > 0))
+    } else {
+        (tx, rx)
+    }
+}
+
+// keeps messages of at least `min` bytes, or with `invert` only the smaller
+// ones: bulk transfers without the tiny control messages, or the other way
+// round
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ByteFilter {
+    pub enabled: bool,
+    pub min: u64,
+    pub invert: bool,
+}
+
+impl Default for ByteFilter {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min: 4096,
+            invert: false,
+        }
+    }
+}
+
+impl ByteFilter {
+    pub fn keeps(&self, bytes: u64) -> bool {
+        !self.enabled || (bytes >= self.min) != self.invert
+    }
+}
+
+// which directions of an event count: shown, non empty and through the
+// byte filter
+fn directions(event: &Event, show_tx: bool, show_rx: bool, filter: &ByteFilter) -> (bool, bool) {
+    let (tx, rx) = (event.raw.bytes_tx, event.raw.bytes_rx);
+    (
+        show_tx && tx > 0 && filter.keeps(tx),
+        show_rx && rx > 0 && filter.keeps(rx),
+    )
+}
+
+fn add_event(
+    comms: &mut Comms,
+    collectives: &mut Collectives,
+    event: &Event,
+    show_tx: bool,
+    show_rx: bool,
+    filter: &ByteFilter,
+    count: bool,
+) {
+    let src = event.source_pe;
+    let (tx, rx) = amounts(event, count);
+    let (show_tx, show_rx) = directions(event, show_tx, show_rx, filter);
+    if event.raw.target_pe < 0 {
+        if show_tx || show_rx {
+            let v = collectives.entry(src).or_insert((0, 0));
+            v.0 += if show_tx { tx } else { 0 };
+            v.1 += if show_rx { rx } else { 0 };
+        }
+        return;
+    }
+    let dst = event.raw.target_pe as u32;
+    if show_tx {
+        comms.entry((src, dst)).or_insert((0, 0)).0 += tx;
+    }
+    if show_rx {
+        comms.entry((dst, src)).or_insert((0, 0)).1 += rx;
+    }
+}
+
+// undoes add_event, dropping edges that end up empty so the result matches
+// summing the window from scratch
+fn remove_event(
+    comms: &mut Comms,
+    collectives: &mut Collectives,
+    event: &Event,
+    show_tx: bool,
+    show_rx: bool,
+    filter: &ByteFilter,
+    count: bool,
+) {
+    let src = event.source_pe;
+    let (tx, rx) = amounts(event, count);
+    let (show_tx, show_rx) = directions(event, show_tx, show_rx, filter);
+    if event.raw.target_pe < 0 {
+        if let Some(v) = collectives.get_mut(&src) {
+            v.0 -= if show_tx { tx } else { 0 };
+            v.1 -= if show_rx { rx } else { 0 };
+            if *v == (0, 0) {
+                collectives.remove(&src);
+            }
+        }
+        return;
+    }
+    let dst = event.raw.target_pe as u32;
+    let mut take = |edge: (u32, u32), tx: u64, rx: u64| {
+        if let Some(v) = comms.get_mut(&edge) {
+            v.0 -= tx;
+            v.1 -= rx;
+            if *v == (0, 0) {
+                comms.remove(&edge);
+            }
+        }
+    };
+    if show_tx {
+        take((src, dst), tx, 0);
+    }
+    if show_rx {
+        take((dst, src), 0, rx);
+    }
+}
+
+// the byte and message comms of the last window, and its collectives,
+// handed back while the window and filters stay put
+// (a still cursor redraws every frame). when the window slides, e.g. during
+// playback, only the events entering and leaving it are added or taken out.
+// big jumps start over from the prefix sums, or from the events when the
+// byte filter is on since the sums can't leave messages out
+#[derive(Debug, Default)]
+pub struct AggregateCache {
+    // show_tx, show_rx, bytes
+    filters: Option<(bool, bool, ByteFilter)>,
+    // events summed into comms
+    range: std::ops::Range<usize>,
+    bytes: Comms,
+    messages: Comms,
+    collective_bytes: Collectives,
+    collective_messages: Collectives,
+}
+
+impl AggregateCache {
+    // forget everything, e.g. when other data was loaded
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn get(
+        &mut self,
+        data: &ProfileData,
+        sums: &ByteSums,
+        start_time: f64,
+        end_time: f64,
+        show_tx: bool,
+        show_rx: bool,
+        filter: ByteFilter,
+    ) -> (&Comms, &Comms) {
+        let range = event_range(data, start_time, end_time);
+        let old = self.range.clone();
+        let overlaps = range.start < old.end && old.start < range.end;
+        let changed = range.start.abs_diff(old.start) + range.end.abs_diff(old.end);
+        let add = |(comms, collectives): (&mut Comms, &mut Collectives),
+                   r: std::ops::Range<usize>,
+                   count: bool| {
+            for e in data.range(r) {
+                add_event(comms, collectives, &e, show_tx, show_rx, &filter, count);
+            }
+        };
+        let remove = |(comms, collectives): (&mut Comms, &mut Collectives),
+                      r: std::ops::Range<usize>,
+                      count: bool| {
+            for e in data.range(r) {
+                remove_event(comms, collectives, &e, show_tx, show_rx, &filter, count);
+            }
+        };
+        let filters = Some((show_tx, show_rx, filter));
+        let sides = [
+            (&mut self.bytes, &mut self.collective_bytes, false),
+            (&mut self.messages, &mut self.collective_messages, true),
+        ];
+        if self.filters != filters || !overlaps || changed > range.len() {
+            for (comms, collectives, count) in sides {
+                if filter.enabled {
+                    comms.clear();
+                    collectives.clear();
+                    add((comms, collectives), range.clone(), count);
+                } else if count {
+                    *comms = sums.messages(start_time, end_time, show_tx, show_rx);
+                    *collectives = sums.collective_messages(start_time, end_time, show_tx, show_rx);
+                } else {
+                    *comms = sums.comms(start_time, end_time, show_tx, show_rx);
+                    *collectives = sums.collectives(start_time, end_time, show_tx, show_rx);
+                }
+            }
+        } else {
+            for (comms, collectives, count) in sides {
+                if range.start < old.start {
+                    add((comms, collectives), range.start..old.start, count);
+                } else {
+                    remove((comms, collectives), old.start..range.start, count);
+                }
+                if range.end > old.end {
+                    add((comms, collectives), old.end..range.end, count);
+                } else {
+                    remove((comms, collectives), range.end..old.end, count);
+                }
+            }
+        }
+        self.filters = filters;
+        self.range = range;
+        (&self.bytes, &self.messages)
+    }
+
+    // the byte and message collectives of the window of the last get()
+    pub fn collectives(&self) -> (&Collectives, &Collectives) {
+        (&self.collective_bytes, &self.collective_messages)
+    }
+}
+
+// the comms (or collectives) in `weight`, from the byte and message comms
+// of the same window (after any collapse, so averages are taken over the
+// merged edges)
+pub fn weigh<K: Copy + Eq + Hash>(
+    bytes: &HashMap<K, (u64, u64)>,
+    messages: &HashMap<K, (u64, u64)>,
+    weight: EdgeWeight,
+) -> HashMap<K, (u64, u64)> {
+    match weight {
+        EdgeWeight::Bytes => bytes.clone(),
+        EdgeWeight::Messages => messages.clone(),
+        EdgeWeight::MessageSize => bytes
+            .iter()
+            .map(|(edge, (tx, rx))| {
+                let (n_tx, n_rx) = messages.get(edge).copied().unwrap_or((0, 0));
+                (*edge, (tx / n_tx.max(1), rx / n_rx.max(1)))
+            })
+            .collect(),
+    }
+}
+
+// busiest directed pairs first
+pub fn top_talkers(comms: &Comms, n: usize) -> Vec<((u32, u32), (u64, u64))> {
+    let mut pairs: Vec<_> = comms.iter().map(|(k, v)| (*k, *v)).collect();
+    pairs.sort_by(|a, b| (b.1.0 + b.1.1).cmp(&(a.1.0 + a.1.1)).then(a.0.cmp(&b.0)));
+    pairs.truncate(n);
+    pairs
+}
+
+// indices of the events that make up one directed edge of the comms,
+// with PEs mapped onto ring nodes by `node_of`. (node, node) is the local
+// traffic of the node, everything within it as kept by collapse()
+#[allow(clippy::too_many_arguments)]
+pub fn contributing_events(
+    data: &ProfileData,
+    start_time: f64,
+    end_time: f64,
+    (src, dst): (u32, u32),
+    show_tx: bool,
+    show_rx: bool,
+    filter: ByteFilter,
+    node_of: impl Fn(u32) -> u32,
+) -> Vec<usize> {
+    let start_idx = data.events.time.partition_point(|&t| t < start_time);
+    data.range(start_idx..)
+        .enumerate()
+        .take_while(|(_, e)| e.raw.time <= end_time)
+        .filter(|(_, e)| {
+            if e.raw.target_pe < 0 {
+                return false;
+            }
+            let from = node_of(e.source_pe);
+            let to = node_of(e.raw.target_pe as u32);
+            let (show_tx, show_rx) = directions(e, show_tx, show_rx, &filter);
+            let sent = show_tx && from == src && to == dst;
+            let received = show_rx && from == dst && to == src;
+            sent || received
+        })
+        .map(|(i, _)| start_idx + i)
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BandwidthMode {
+    // nodes and arrows
+    Graph,
+    Chord,
+}
+
+// what the comms of the ring, chord and top talkers add up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EdgeWeight {
+    #[default]
+    Bytes,
+    Messages,
+    // bytes per message
+    MessageSize,
+}
+
+impl EdgeWeight {
+    pub const ALL: [EdgeWeight; 3] = [
+        EdgeWeight::Bytes,
+        EdgeWeight::Messages,
+        EdgeWeight::MessageSize,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EdgeWeight::Bytes => "Bytes",
+            EdgeWeight::Messages => "Messages",
+            EdgeWeight::MessageSize => "Avg size",
+        }
+    }
+
+    // a comms value as text, per second when `per_second`
+    pub fn format(self, value: f64, per_second: bool) -> String {
+        match (self, per_second) {
+            (EdgeWeight::Bytes, false) | (EdgeWeight::MessageSize, _) => units::bytes(value as u64),
+            (EdgeWeight::Bytes, true) => units::rate(value),
+            (EdgeWeight::Messages, false) => {
+                format!("{value} msg{}", if value == 1.0 { "" } else { "s" })
+            }
+            (EdgeWeight::Messages, true) => format!("{} msg/s", units::significant(value)),
+        }
+    }
+
+    // the unit of format(), for input fields
+    pub fn suffix(self, per_second: bool) -> &'static str {
+        match (self, per_second) {
+            (EdgeWeight::Bytes, false) | (EdgeWeight::MessageSize, _) => " B",
+            (EdgeWeight::Bytes, true) => " B/s",
+            (EdgeWeight::Messages, false) => " msgs",
+            (EdgeWeight::Messages, true) => " msg/s",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EdgeScale {
+    Linear,
+    Sqrt,
+    Log,
+}
+
+// how edge values map to arrow width / opacity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EdgeScaling {
+    pub weight: EdgeWeight,
+    pub scale: EdgeScale,
+    // divide by the window length, i.e. bytes/sec or messages/sec. means
+    // nothing for message sizes
+    pub per_second: bool,
+    // values at or above this saturate; None = busiest edge in view
+    pub max: Option<f64>,
+}
+
+impl Default for EdgeScaling {
+    fn default() -> Self {
+        Self {
+            weight: EdgeWeight::Bytes,
+            scale: EdgeScale::Log,
+            per_second: false,
+            max: None,
+        }
+    }
+}
+
+impl EdgeScaling {
+    pub fn value(&self, amount: u64, window_seconds: f64) -> f64 {
+        if self.per_second() && window_seconds > 0.0 {
+            amount as f64 / window_seconds
+        } else {
+            amount as f64
+        }
+    }
+
+    pub fn per_second(&self) -> bool {
+        self.per_second && self.weight != EdgeWeight::MessageSize
+    }
+
+    // `amount` of an edge as text, e.g. for labels and tables
+    pub fn format(&self, amount: u64, window_seconds: f64) -> String {
+        self.weight
+            .format(self.value(amount, window_seconds), self.per_second())
+    }
+
+    // 0..=1, relative to the user max or `auto_max`
+    pub fn normalize(&self, value: f64, auto_max: f64) -> f32 {
+        let max = self.max.unwrap_or(auto_max);
+        if max <= 0.0 {
+            return 0.0;
+        }
+        let f = |v: f64| match self.scale {
+            EdgeScale::Linear => v,
+            EdgeScale::Sqrt => v.sqrt(),
+            EdgeScale::Log => v.ln_1p(),
+        };
+        (f(value.min(max)) / f(max)).clamp(0.0, 1.0) as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MuteStyle {
+    // fade to gray at `opacity`
+    Ghost,
+    // leave unrelated edges out entirely
+    Hide,
+}
+
+// what happens to edges and nodes unrelated to the focused PE or edge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Muting {
+    pub style: MuteStyle,
+    // alpha multiplier for ghosted edges, nodes get twice that
+    pub opacity: f32,
+    // clicking a node keeps it focused until it's clicked again
+    pub lock_on_click: bool,
+}
+
+impl Default for Muting {
+    fn default() -> Self {
+        Self {
+            style: MuteStyle::Ghost,
+            opacity: 0.1,
+            lock_on_click: false,
+        }
+    }
+}
+
+// re-key pe level comms onto coarser nodes, summing the traffic between
+// them. traffic between PEs of the same node becomes its local traffic
+pub fn collapse(comms: &Comms, node_of: impl Fn(u32) -> u32) -> Comms {
+    let mut out = Comms::new();
+    for ((src, dst), (tx, rx)) in comms {
+        let e = out.entry((node_of(*src), node_of(*dst))).or_insert((0, 0));
+        e.0 += tx;
+        e.1 += rx;
+    }
+    out
+}
+
+// re-key pe level collectives onto coarser nodes
+pub fn collapse_collectives(
+    collectives: &Collectives,
+    node_of: impl Fn(u32) -> u32,
+) -> Collectives {
+    let mut out = Collectives::new();
+    for (pe, (tx, rx)) in collectives {
+        let e = out.entry(node_of(*pe)).or_insert((0, 0));
+        e.0 += tx;
+        e.1 += rx;
+    }
+    out
+}
+
+// moves the local traffic out of `comms`, by node
+pub fn take_local(comms: &mut Comms) -> HashMap<u32, (u64, u64)> {
+    let mut local = HashMap::new();
+    comms.retain(|(src, dst), v| {
+        if src == dst {
+            local.insert(*src, *v);
+        }
+        src != dst
+    });
+    local
+}
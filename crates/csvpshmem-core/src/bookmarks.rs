@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub time: f64,
+    // set when the bookmark was placed on a specific event
+    #[serde(default)]
+    pub pe: Option<u32>,
+}
+
+// kept sorted by time so next/prev are simple scans
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bookmarks {
+    items: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn add(&mut self, name: String, time: f64, pe: Option<u32>) {
+        let idx = self.items.partition_point(|b| b.time <= time);
+        self.items.insert(idx, Bookmark { name, time, pe });
+    }
+
+    pub fn remove(&mut self, idx: usize) {
+        if idx < self.items.len() {
+            self.items.remove(idx);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Bookmark> {
+        self.items.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Bookmark> {
+        self.items.iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    // small epsilon so repeated presses don't get stuck on the current one
+    pub fn next_after(&self, time: f64) -> Option<&Bookmark> {
+        self.items.iter().find(|b| b.time > time + 1e-12)
+    }
+
+    pub fn prev_before(&self, time: f64) -> Option<&Bookmark> {
+        self.items.iter().rev().find(|b| b.time < time - 1e-12)
+    }
+}
@@ -0,0 +1,386 @@
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use crate::data::{self, Events, Interner, ProfileData, Run};
+use crate::manifest::Manifest;
+
+// the parsed events of a trace, written next to its csv files after the
+// first load so the next one skips parsing. it records the size and
+// modification time of every file it was made from and is ignored as soon
+// as any of them changes
+pub const FILE_NAME: &str = ".csvpshmem.cache";
+
+const MAGIC: &[u8; 8] = b"PPERFCA1";
+
+// where the cache of a trace made of `sources` lives
+pub fn path(sources: &[PathBuf]) -> Option<PathBuf> {
+    Some(sources.first()?.parent()?.join(FILE_NAME))
+}
+
+// what has to match for a cache to be used: the files with their size and
+// modification time, any manifests, and how directories were merged
+fn key(sources: &[PathBuf], runs: &[Run]) -> Result<Vec<u8>> {
+    let mut key = Vec::new();
+    let manifests: Vec<PathBuf> = data::trace_dirs(sources, runs)
+        .into_iter()
+        .map(|(dir, _)| Manifest::path(&dir))
+        .filter(|path| path.is_file())
+        .collect();
+    put_u64(&mut key, (sources.len() + manifests.len()) as u64);
+    for source in sources.iter().chain(&manifests) {
+        let meta = fs::metadata(source)?;
+        let modified = meta.modified()?.duration_since(UNIX_EPOCH)?;
+        put_str(&mut key, &source.to_string_lossy());
+        put_u64(&mut key, meta.len());
+        put_u64(&mut key, modified.as_secs());
+        put_u64(&mut key, modified.subsec_nanos() as u64);
+    }
+    put_u64(&mut key, runs.len() as u64);
+    for run in runs {
+        put_str(&mut key, &run.dir.to_string_lossy());
+        put_u64(&mut key, run.first_pe as u64);
+        put_u64(&mut key, run.pe_count as u64);
+    }
+    Ok(key)
+}
+
+// the trace as cached, if there is a cache and it is current
+pub fn read(sources: &[PathBuf], runs: &[Run]) -> Option<ProfileData> {
+    let path = path(sources)?;
+    let bytes = fs::read(path).ok()?;
+    let key = key(sources, runs).ok()?;
+    let mut r = Reader { bytes: &bytes };
+    if r.take(MAGIC.len())? != MAGIC || r.take(key.len())? != key {
+        return None;
+    }
+    let pe_count = r.u64()? as u32;
+    let epoch = match r.u8()? {
+        0 => None,
+        _ => Some(r.f64()?),
+    };
+    let mut pe_hostnames = HashMap::new();
+    for _ in 0..r.u64()? {
+        pe_hostnames.insert(r.u64()? as u32, r.str()?);
+    }
+
+    let n = r.u64()? as usize;
+    let mut events = Events {
+        time: r.column(n, f64::from_le_bytes)?,
+        duration: r.column(n, f64::from_le_bytes)?,
+        pe: r.column(n, u32::from_le_bytes)?,
+        context: r
+            .column(n, u64::from_le_bytes)?
+            .into_iter()
+            .map(|c| (c != u64::MAX).then_some(c as u32))
+            .collect(),
+        run: r.column(n, u16::from_le_bytes)?,
+        target: r.column(n, i32::from_le_bytes)?,
+        bytes_rx: r.column(n, u64::from_le_bytes)?,
+        bytes_tx: r.column(n, u64::from_le_bytes)?,
+        function: r.column(n, u32::from_le_bytes)?,
+        stack: r.column(n, u32::from_le_bytes)?,
+        extra: r.column(n, u32::from_le_bytes)?,
+        symbols: r.column(n, u32::from_le_bytes)?,
+        ..Default::default()
+    };
+    events.functions = r.interner()?;
+    events.stacks = r.interner()?;
+    events.extras = r.interner()?;
+    events.symboltraces = r.interner()?;
+    if !r.bytes.is_empty() {
+        return None;
+    }
+    // ids past the end of an interner would panic later
+    for (ids, interner) in [
+        (&events.function, &events.functions),
+        (&events.stack, &events.stacks),
+        (&events.extra, &events.extras),
+        (&events.symbols, &events.symboltraces),
+    ] {
+        if ids.iter().any(|&id| id as usize >= interner.len()) {
+            return None;
+        }
+    }
+    Some(ProfileData::from_events(
+        events,
+        pe_count,
+        pe_hostnames,
+        epoch,
+        sources.to_vec(),
+        runs.to_vec(),
+    ))
+}
+
+// saves a trace loaded straight from its files, written to a temporary file
+// first so an interrupted write never leaves a cache that looks complete
+pub fn write(data: &ProfileData) -> Result<()> {
+    let Some(path) = path(&data.sources) else {
+        bail!("no files to cache");
+    };
+    let part = path.with_extension("cache.part");
+    let file = fs::File::create(&part).with_context(|| format!("creating {}", part.display()))?;
+    let mut w = BufWriter::new(file);
+    let mut head = MAGIC.to_vec();
+    head.extend(key(&data.sources, &data.runs)?);
+    put_u64(&mut head, data.pe_count as u64);
+    match data.epoch {
+        Some(epoch) => {
+            head.push(1);
+            head.extend(epoch.to_le_bytes());
+        }
+        None => head.push(0),
+    }
+    let mut hostnames: Vec<_> = data.pe_hostnames.iter().collect();
+    hostnames.sort();
+    put_u64(&mut head, hostnames.len() as u64);
+    for (&pe, host) in hostnames {
+        put_u64(&mut head, pe as u64);
+        put_str(&mut head, host);
+    }
+    let events = &data.events;
+    put_u64(&mut head, events.len() as u64);
+    w.write_all(&head)?;
+
+    fn column<T: Copy, const N: usize>(
+        w: &mut impl Write,
+        values: &[T],
+        to_bytes: fn(T) -> [u8; N],
+    ) -> Result<()> {
+        for &v in values {
+            w.write_all(&to_bytes(v))?;
+        }
+        Ok(())
+    }
+    column(&mut w, &events.time, f64::to_le_bytes)?;
+    column(&mut w, &events.duration, f64::to_le_bytes)?;
+    column(&mut w, &events.pe, u32::to_le_bytes)?;
+    for c in &events.context {
+        w.write_all(&c.map_or(u64::MAX, |c| c as u64).to_le_bytes())?;
+    }
+    column(&mut w, &events.run, u16::to_le_bytes)?;
+    column(&mut w, &events.target, i32::to_le_bytes)?;
+    column(&mut w, &events.bytes_rx, u64::to_le_bytes)?;
+    column(&mut w, &events.bytes_tx, u64::to_le_bytes)?;
+    column(&mut w, &events.function, u32::to_le_bytes)?;
+    column(&mut w, &events.stack, u32::to_le_bytes)?;
+    column(&mut w, &events.extra, u32::to_le_bytes)?;
+    column(&mut w, &events.symbols, u32::to_le_bytes)?;
+    for interner in [
+        &events.functions,
+        &events.stacks,
+        &events.extras,
+        &events.symboltraces,
+    ] {
+        let mut buf = Vec::new();
+        put_u64(&mut buf, interner.len() as u64);
+        for s in interner.strings() {
+            put_str(&mut buf, s);
+        }
+        w.write_all(&buf)?;
+    }
+    w.into_inner()?.sync_all()?;
+    fs::rename(&part, &path)?;
+    Ok(())
+}
+
+fn put_u64(buf: &mut Vec<u8>, v: u64) {
+    buf.extend(v.to_le_bytes());
+}
+
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    put_u64(buf, s.len() as u64);
+    buf.extend(s.as_bytes());
+}
+
+// reads a cache front to back, None on anything truncated or malformed
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if n > self.bytes.len() {
+            return None;
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        Some(f64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn str(&mut self) -> Option<String> {
+        let len = self.u64()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    fn column<T, const N: usize>(
+        &mut self,
+        n: usize,
+        from_bytes: fn([u8; N]) -> T,
+    ) -> Option<Vec<T>> {
+        let bytes = self.take(n.checked_mul(N)?)?;
+        Some(
+            bytes
+                .chunks_exact(N)
+                .map(|c| from_bytes(c.try_into().unwrap()))
+                .collect(),
+        )
+    }
+
+    fn interner(&mut self) -> Option<Interner> {
+        let n = self.u64()? as usize;
+        let strings = (0..n).map(|_| self.str()).collect::<Option<Vec<_>>>()?;
+        Interner::from_strings(strings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const HEADER: &str =
+        "Time,Function,Duration_Sec,Target_PE,Bytes_RX,Bytes_TX,Stacktrace,Extra,Symboltrace\n";
+
+    // a fresh directory with a two PE trace in it
+    fn trace(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("csvpshmem-cache-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for pe in 0..2 {
+            let rows = format!(
+                "{HEADER}0.0,shmem_init,0.001,-1,0,0,0x1,host=n{pe};epoch=1700000000,main\n\
+                 0.{pe}1,shmem_putmem,0.002,{},0,64,0x1;0x2,,main;put\n\
+                 0.{pe}2,shmem_getmem,0.003,{},32,0,0x1;0x3,heap=5,main;get\n",
+                1 - pe,
+                1 - pe
+            );
+            fs::write(dir.join(format!("pperf.{pe}.csv")), rows).unwrap();
+        }
+        dir
+    }
+
+    fn same(a: &ProfileData, b: &ProfileData) {
+        assert_eq!(a.pe_count, b.pe_count);
+        assert_eq!(a.pe_hostnames, b.pe_hostnames);
+        assert_eq!(a.epoch, b.epoch);
+        assert_eq!(a.events.len(), b.events.len());
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert_eq!(
+                (x.source_pe, x.context, x.run),
+                (y.source_pe, y.context, y.run)
+            );
+            assert_eq!(x.raw.time, y.raw.time);
+            assert_eq!(x.raw.function, y.raw.function);
+            assert_eq!(x.raw.duration_sec, y.raw.duration_sec);
+            assert_eq!(x.raw.target_pe, y.raw.target_pe);
+            assert_eq!(
+                (x.raw.bytes_rx, x.raw.bytes_tx),
+                (y.raw.bytes_rx, y.raw.bytes_tx)
+            );
+            assert_eq!(x.raw.stacktrace, y.raw.stacktrace);
+            assert_eq!(x.raw.extra, y.raw.extra);
+            assert_eq!(x.raw.symboltrace, y.raw.symboltrace);
+        }
+    }
+
+    fn sources(dir: &Path) -> Vec<PathBuf> {
+        let mut files = data::pperf_files(dir).unwrap();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = trace("round-trip");
+        let loaded = ProfileData::load_from_dir(&dir).unwrap();
+        assert!(dir.join(FILE_NAME).is_file());
+        let cached = read(&sources(&dir), &[]).expect("a current cache");
+        same(&loaded, &cached);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stale_after_a_change() {
+        let dir = trace("stale");
+        ProfileData::load_from_dir(&dir).unwrap();
+        let files = sources(&dir);
+        assert!(read(&files, &[]).is_some());
+
+        // a different size
+        let mut text = fs::read_to_string(&files[1]).unwrap();
+        text.push_str("0.5,shmem_quiet,0.001,-1,0,0,0x1,,main\n");
+        fs::write(&files[1], text).unwrap();
+        assert!(read(&files, &[]).is_none());
+        let reloaded = ProfileData::load_from_dir(&dir).unwrap();
+        assert_eq!(reloaded.events.len(), 7);
+        assert!(read(&files, &[]).is_some());
+
+        // a manifest appearing
+        fs::write(Manifest::path(&dir), r#"{"pe_count": 3}"#).unwrap();
+        assert!(read(&files, &[]).is_none());
+
+        // other files than the cache was made from
+        assert!(read(&files[..1], &[]).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_caches_are_ignored() {
+        let dir = trace("corrupt");
+        let data = ProfileData::load_from_dir(&dir).unwrap();
+        let files = sources(&dir);
+        let cache = dir.join(FILE_NAME);
+        let good = fs::read(&cache).unwrap();
+
+        for len in (0..good.len()).step_by(7) {
+            fs::write(&cache, &good[..len]).unwrap();
+            assert!(read(&files, &[]).is_none(), "cut at {len}");
+        }
+        let mut longer = good.clone();
+        longer.push(0);
+        fs::write(&cache, &longer).unwrap();
+        assert!(read(&files, &[]).is_none());
+        let mut magic = good.clone();
+        magic[7] ^= 1;
+        fs::write(&cache, &magic).unwrap();
+        assert!(read(&files, &[]).is_none());
+        // the last function id pointing past the function names. the ids
+        // are followed by three more u32 columns and the interners
+        let events = &data.events;
+        let interners: usize = [
+            &events.functions,
+            &events.stacks,
+            &events.extras,
+            &events.symboltraces,
+        ]
+        .iter()
+        .map(|i| 8 + i.strings().iter().map(|s| 8 + s.len()).sum::<usize>())
+        .sum();
+        let end = good.len() - interners - 3 * 4 * events.len();
+        let mut ids = good.clone();
+        ids[end - 4..end].copy_from_slice(&99u32.to_le_bytes());
+        fs::write(&cache, &ids).unwrap();
+        assert!(read(&files, &[]).is_none());
+
+        fs::write(&cache, &good).unwrap();
+        assert!(read(&files, &[]).is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
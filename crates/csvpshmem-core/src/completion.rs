@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use crate::data::ProfileData;
+
+// a put or get from its call returning until the quiet, fence or barrier
+// after it on the same PE and context returned, when it is known to be done
+#[derive(Debug, Clone, Copy)]
+pub struct InFlight {
+    pub init: usize,
+    pub done: usize,
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct Completion {
+    // in initiation order
+    pub flights: Vec<InFlight>,
+    // initiating event -> index into flights
+    by_init: HashMap<usize, usize>,
+    // puts and gets with no completion after them
+    pub unfinished: usize,
+}
+
+impl Completion {
+    pub fn of(&self, init: usize) -> Option<&InFlight> {
+        self.by_init.get(&init).map(|&f| &self.flights[f])
+    }
+}
+
+// one-sided calls whose data may still be moving after they return
+pub fn is_initiation(function: &str) -> bool {
+    let f = function.to_ascii_lowercase();
+    (f.contains("put") || f.contains("get")) && !is_completion(&f)
+}
+
+// calls that wait for the puts and gets before them. a fence only orders
+// them, but it's the last point the trace shows before they land
+pub fn is_completion(function: &str) -> bool {
+    let f = function.to_ascii_lowercase();
+    f.contains("quiet") || f.contains("fence") || f.contains("barrier")
+}
+
+pub fn track(data: &ProfileData) -> Completion {
+    let mut completion = Completion::default();
+    // (pe, context) -> initiations not completed yet
+    let mut pending: HashMap<(u32, Option<u32>), Vec<usize>> = HashMap::new();
+    for (i, e) in data.iter().enumerate() {
+        let function = e.raw.function;
+        if is_completion(function) {
+            let Some(waiting) = pending.get_mut(&(e.source_pe, e.context)) else {
+                continue;
+            };
+            let end = e.raw.time + e.raw.duration_sec;
+            for init in waiting.drain(..) {
+                let raw = data.event(init).raw;
+                completion.flights.push(InFlight {
+                    init,
+                    done: i,
+                    start: raw.time + raw.duration_sec,
+                    end,
+                });
+            }
+        } else if e.raw.target_pe >= 0 && is_initiation(function) {
+            pending.entry((e.source_pe, e.context)).or_default().push(i);
+        }
+    }
+    completion.unfinished = pending.values().map(Vec::len).sum();
+    completion.flights.sort_by_key(|f| f.init);
+    for (f, flight) in completion.flights.iter().enumerate() {
+        completion.by_init.insert(flight.init, f);
+    }
+    completion
+}
+
+// how much of a PE's communication ran behind its own computation
+#[derive(Debug, Clone, Copy)]
+pub struct PeOverlap {
+    pub pe: u32,
+    // time with at least one put or get in flight
+    pub in_flight: f64,
+    // the part of it with no call running on the PE
+    pub overlapped: f64,
+}
+
+impl PeOverlap {
+    pub fn share(&self) -> f64 {
+        if self.in_flight > 0.0 {
+            self.overlapped / self.in_flight
+        } else {
+            0.0
+        }
+    }
+}
+
+// per PE over [start, end], only PEs with something in flight
+pub fn overlap(
+    data: &ProfileData,
+    completion: &Completion,
+    start: f64,
+    end: f64,
+) -> Vec<PeOverlap> {
+    let mut flights: HashMap<u32, Vec<(f64, f64)>> = HashMap::new();
+    for f in &completion.flights {
+        let (a, b) = (f.start.max(start), f.end.min(end));
+        if b > a {
+            let pe = data.event(f.init).source_pe;
+            flights.entry(pe).or_default().push((a, b));
+        }
+    }
+    let mut calls: HashMap<u32, Vec<(f64, f64)>> = HashMap::new();
+    for i in data.overlapping(start, end) {
+        let e = data.event(i);
+        if !flights.contains_key(&e.source_pe) {
+            continue;
+        }
+        let (a, b) = (
+            e.raw.time.max(start),
+            (e.raw.time + e.raw.duration_sec).min(end),
+        );
+        if b > a {
+            calls.entry(e.source_pe).or_default().push((a, b));
+        }
+    }
+    let mut rows: Vec<PeOverlap> = flights
+        .into_iter()
+        .map(|(pe, spans)| {
+            let spans = union(spans);
+            let busy = union(calls.remove(&pe).unwrap_or_default());
+            let in_flight: f64 = spans.iter().map(|(a, b)| b - a).sum();
+            let blocked: f64 = intersection(&spans, &busy).iter().map(|(a, b)| b - a).sum();
+            PeOverlap {
+                pe,
+                in_flight,
+                overlapped: in_flight - blocked,
+            }
+        })
+        .collect();
+    rows.sort_by_key(|r| r.pe);
+    rows
+}
+
+// sorted, disjoint spans covering the same time
+fn union(mut spans: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut merged: Vec<(f64, f64)> = Vec::with_capacity(spans.len());
+    for (a, b) in spans {
+        match merged.last_mut() {
+            Some(last) if a <= last.1 => last.1 = last.1.max(b),
+            _ => merged.push((a, b)),
+        }
+    }
+    merged
+}
+
+// spans covered by both, for two results of `union`
+fn intersection(x: &[(f64, f64)], y: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let (mut i, mut j, mut both) = (0, 0, Vec::new());
+    while i < x.len() && j < y.len() {
+        let (a, b) = (x[i].0.max(y[j].0), x[i].1.min(y[j].1));
+        if b > a {
+            both.push((a, b));
+        }
+        if x[i].1 < y[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    both
+}
+
+// adds the length of each span falling in each of `bins` equal bins
+// starting at `start`
+fn add_to_bins(spans: &[(f64, f64)], start: f64, width: f64, bins: &mut [f64]) {
+    for &(a, b) in spans {
+        let first = ((a - start) / width).max(0.0) as usize;
+        for (k, bin) in bins.iter_mut().enumerate().skip(first) {
+            let (lo, hi) = (start + k as f64 * width, start + (k + 1) as f64 * width);
+            if lo >= b {
+                break;
+            }
+            *bin += b.min(hi) - a.max(lo);
+        }
+    }
+}
+
+// pe -> share per bin, from `overlap_bins`
+pub type OverlapBins = HashMap<u32, Vec<Option<f32>>>;
+
+// per PE and per bin of [start, end], the share of communication time
+// (calls with a target, completions, and puts and gets in flight) the PE
+// spent outside any call instead of blocked in one. None for bins without
+// communication
+pub fn overlap_bins(
+    data: &ProfileData,
+    completion: &Completion,
+    start: f64,
+    end: f64,
+    bins: usize,
+) -> OverlapBins {
+    if bins == 0 || end <= start {
+        return HashMap::new();
+    }
+    let mut comm: HashMap<u32, Vec<(f64, f64)>> = HashMap::new();
+    let mut busy: HashMap<u32, Vec<(f64, f64)>> = HashMap::new();
+    for f in &completion.flights {
+        let (a, b) = (f.start.max(start), f.end.min(end));
+        if b > a {
+            let pe = data.event(f.init).source_pe;
+            comm.entry(pe).or_default().push((a, b));
+        }
+    }
+    for i in data.overlapping(start, end) {
+        let e = data.event(i);
+        let (a, b) = (
+            e.raw.time.max(start),
+            (e.raw.time + e.raw.duration_sec).min(end),
+        );
+        if b <= a {
+            continue;
+        }
+        busy.entry(e.source_pe).or_default().push((a, b));
+        if e.raw.target_pe >= 0 || is_completion(e.raw.function) {
+            comm.entry(e.source_pe).or_default().push((a, b));
+        }
+    }
+    let width = (end - start) / bins as f64;
+    comm.into_iter()
+        .map(|(pe, spans)| {
+            let spans = union(spans);
+            let blocked = intersection(&spans, &union(busy.remove(&pe).unwrap_or_default()));
+            let (mut total, mut waiting) = (vec![0.0; bins], vec![0.0; bins]);
+            add_to_bins(&spans, start, width, &mut total);
+            add_to_bins(&blocked, start, width, &mut waiting);
+            let shares = total
+                .iter()
+                .zip(&waiting)
+                .map(|(&t, &w)| (t > 0.0).then(|| (1.0 - w / t).clamp(0.0, 1.0) as f32))
+                .collect();
+            (pe, shares)
+        })
+        .collect()
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use crate::data::ProfileData;
+use crate::topology::{LinkCapacity, Topology};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkLevel {
+    // a host to its switch
+    Host,
+    // a switch to its rack
+    Switch,
+    // a rack to the rest of the network
+    Rack,
+}
+
+impl LinkLevel {
+    pub fn label(self) -> &'static str {
+        match self {
+            LinkLevel::Host => "host",
+            LinkLevel::Switch => "switch",
+            LinkLevel::Rack => "rack",
+        }
+    }
+}
+
+// the uplink of one host, switch or rack
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub level: LinkLevel,
+    pub name: String,
+    // bytes/s, None when the topology doesn't say
+    pub capacity: Option<f64>,
+}
+
+impl Link {
+    pub fn title(&self) -> String {
+        format!("{} {}", self.level.label(), self.name)
+    }
+}
+
+// a stretch of consecutive bins where a link is asked for more than it carries
+#[derive(Debug, Clone)]
+pub struct Overload {
+    pub link: usize,
+    pub start: f64,
+    pub end: f64,
+    // highest demand / capacity over the stretch
+    pub peak: f64,
+}
+
+// bytes/s each link is asked to carry over [start, end], in equal bins.
+// the bytes of a call are spread evenly over its duration and walk every
+// uplink between the two hosts; both directions share a link
+#[derive(Debug, Default)]
+pub struct Contention {
+    pub start: f64,
+    pub end: f64,
+    pub links: Vec<Link>,
+    // [link][bin]
+    pub demand: Vec<Vec<f64>>,
+}
+
+impl Contention {
+    pub fn new(data: &ProfileData, topology: &Topology, start: f64, end: f64, bins: usize) -> Self {
+        let mut contention = Self {
+            start,
+            end,
+            ..Default::default()
+        };
+        if end <= start || bins == 0 {
+            return contention;
+        }
+        let width = (end - start) / bins as f64;
+        let mut index: HashMap<(LinkLevel, &str), usize> = HashMap::new();
+        let mut routes: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+        for i in data.overlapping(start, end) {
+            let e = data.event(i);
+            let bytes = (e.raw.bytes_tx + e.raw.bytes_rx) as f64;
+            if e.raw.target_pe < 0 || bytes == 0.0 {
+                continue;
+            }
+            let host = |pe: u32| data.pe_hostnames.get(&pe).map_or("?", String::as_str);
+            let (a, b) = (host(e.source_pe), host(e.raw.target_pe as u32));
+            if a == b {
+                continue;
+            }
+            let path = routes.entry((a, b)).or_insert_with(|| {
+                route(topology, a, b)
+                    .into_iter()
+                    .map(|(level, name)| {
+                        *index.entry((level, name)).or_insert_with(|| {
+                            contention.links.push(Link {
+                                level,
+                                name: name.to_string(),
+                                capacity: None,
+                            });
+                            contention.demand.push(vec![0.0; bins]);
+                            contention.links.len() - 1
+                        })
+                    })
+                    .collect()
+            });
+
+            let (t0, t1) = (e.raw.time, e.raw.time + e.raw.duration_sec.max(0.0));
+            let bin_of = |t: f64| (((t - start) / width) as usize).min(bins - 1);
+            for &link in path.iter() {
+                let demand = &mut contention.demand[link];
+                if t1 <= t0 {
+                    if (start..=end).contains(&t0) {
+                        demand[bin_of(t0)] += bytes / width;
+                    }
+                    continue;
+                }
+                let rate = bytes / (t1 - t0);
+                for (bin, d) in demand
+                    .iter_mut()
+                    .enumerate()
+                    .take(bin_of(t1.min(end)) + 1)
+                    .skip(bin_of(t0.max(start)))
+                {
+                    let lo = start + bin as f64 * width;
+                    let overlap = t1.min(lo + width) - t0.max(lo);
+                    *d += rate * overlap.max(0.0) / width;
+                }
+            }
+        }
+        contention.set_capacities(&topology.links);
+        contention
+    }
+
+    pub fn set_capacities(&mut self, capacity: &LinkCapacity) {
+        for link in &mut self.links {
+            link.capacity = match link.level {
+                LinkLevel::Host => capacity.host,
+                LinkLevel::Switch => capacity.switch,
+                LinkLevel::Rack => capacity.rack,
+            }
+            .filter(|c| *c > 0.0);
+        }
+    }
+
+    pub fn bins(&self) -> usize {
+        self.demand.first().map_or(0, Vec::len)
+    }
+
+    pub fn bin_width(&self) -> f64 {
+        (self.end - self.start) / self.bins().max(1) as f64
+    }
+
+    // demand / capacity, None when the capacity is unknown
+    pub fn utilization(&self, link: usize, bin: usize) -> Option<f64> {
+        let capacity = self.links[link].capacity?;
+        Some(self.demand[link][bin] / capacity)
+    }
+
+    pub fn peak_utilization(&self, link: usize) -> Option<f64> {
+        let capacity = self.links[link].capacity?;
+        Some(self.demand[link].iter().fold(0.0, |m: f64, d| m.max(*d)) / capacity)
+    }
+
+    // whether any link is over capacity in `bin`
+    pub fn over_capacity(&self, bin: usize) -> bool {
+        (0..self.links.len()).any(|l| self.utilization(l, bin).is_some_and(|u| u > 1.0))
+    }
+
+    // every stretch of over capacity bins, by start time
+    pub fn overloads(&self) -> Vec<Overload> {
+        let width = self.bin_width();
+        let mut overloads = Vec::new();
+        for link in 0..self.links.len() {
+            let mut current: Option<Overload> = None;
+            for bin in 0..self.bins() {
+                match self.utilization(link, bin) {
+                    Some(u) if u > 1.0 => {
+                        let t = self.start + bin as f64 * width;
+                        let o = current.get_or_insert(Overload {
+                            link,
+                            start: t,
+                            end: t,
+                            peak: u,
+                        });
+                        o.end = t + width;
+                        o.peak = o.peak.max(u);
+                    }
+                    _ => overloads.extend(current.take()),
+                }
+            }
+            overloads.extend(current);
+        }
+        overloads.sort_by(|a, b| a.start.total_cmp(&b.start));
+        overloads
+    }
+}
+
+// the uplinks between two different hosts: up from `a` to the first level
+// the two share, then down to `b`. hosts the topology doesn't place only
+// have their own link
+fn route<'a>(topology: &'a Topology, a: &'a str, b: &'a str) -> Vec<(LinkLevel, &'a str)> {
+    let mut links = vec![(LinkLevel::Host, a), (LinkLevel::Host, b)];
+    let same = |x: Option<&str>, y: Option<&str>| x.is_some() && x == y;
+    let (sa, sb) = (topology.switch(a), topology.switch(b));
+    if same(sa, sb) {
+        return links;
+    }
+    links.extend(sa.map(|s| (LinkLevel::Switch, s)));
+    links.extend(sb.map(|s| (LinkLevel::Switch, s)));
+    let (ra, rb) = (topology.rack(a), topology.rack(b));
+    if same(ra, rb) {
+        return links;
+    }
+    links.extend(ra.map(|r| (LinkLevel::Rack, r)));
+    links.extend(rb.map(|r| (LinkLevel::Rack, r)));
+    links
+}
@@ -0,0 +1,916 @@
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::ops::{Bound, RangeBounds};
+use std::path::{Path, PathBuf};
+
+use crate::cache;
+use crate::intervals::IntervalIndex;
+use crate::manifest::Manifest;
+use crate::parquet;
+use crate::skew::ClockOffsets;
+
+// one row of a pperf csv, as read
+#[derive(Debug, Deserialize, Clone)]
+pub struct RawEvent {
+    #[serde(rename = "Time")]
+    pub time: f64,
+    #[serde(rename = "Function")]
+    pub function: String,
+    #[serde(rename = "Duration_Sec")]
+    pub duration_sec: f64,
+    #[serde(rename = "Target_PE")]
+    pub target_pe: i32,
+    #[serde(rename = "Bytes_RX")]
+    pub bytes_rx: u64,
+    #[serde(rename = "Bytes_TX")]
+    pub bytes_tx: u64,
+    #[serde(rename = "Stacktrace")]
+    pub stacktrace: String,
+    #[serde(rename = "Extra", default)]
+    pub extra: Option<String>,
+    #[serde(rename = "Symboltrace", default)]
+    pub symboltrace: Option<String>,
+}
+
+impl RawEvent {
+    // Extra is a `key=value;key=value` list
+    pub fn extra_fields(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.extra
+            .as_deref()
+            .unwrap_or("")
+            .split(';')
+            .filter_map(|kv| kv.split_once('='))
+            .map(|(k, v)| (k.trim(), v.trim()))
+    }
+
+    pub fn extra_field(&self, key: &str) -> Option<&str> {
+        self.extra_fields().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+}
+
+// one event of a loaded trace, borrowed from its columns
+#[derive(Debug, Clone, Copy)]
+pub struct Event<'a> {
+    pub source_pe: u32,
+    // thread / context within the PE, from a pperf.PE.CTX.csv file name or
+    // a `thread=` / `ctx=` entry in Extra
+    pub context: Option<u32>,
+    // index into ProfileData::runs, 0 when only one directory was loaded
+    pub run: u16,
+    pub raw: Fields<'a>,
+}
+
+// the csv columns of an event
+#[derive(Debug, Clone, Copy)]
+pub struct Fields<'a> {
+    pub time: f64,
+    pub function: &'a str,
+    pub duration_sec: f64,
+    pub target_pe: i32,
+    pub bytes_rx: u64,
+    pub bytes_tx: u64,
+    pub stacktrace: &'a str,
+    pub extra: Option<&'a str>,
+    pub symboltrace: Option<&'a str>,
+}
+
+impl<'a> Fields<'a> {
+    // Extra is a `key=value;key=value` list
+    pub fn extra_fields(&self) -> impl Iterator<Item = (&'a str, &'a str)> + use<'a> {
+        self.extra
+            .unwrap_or("")
+            .split(';')
+            .filter_map(|kv| kv.split_once('='))
+            .map(|(k, v)| (k.trim(), v.trim()))
+    }
+}
+
+// strings that repeat across events (function names, stacks), each kept
+// once. id 0 is the empty string
+#[derive(Debug, Clone)]
+pub struct Interner {
+    strings: Vec<String>,
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Self {
+            strings: vec![String::new()],
+        }
+    }
+}
+
+impl Interner {
+    pub fn get(&self, id: u32) -> &str {
+        &self.strings[id as usize]
+    }
+
+    // distinct strings, the empty one included
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.len() <= 1
+    }
+
+    // every string by id
+    pub fn strings(&self) -> &[String] {
+        &self.strings
+    }
+
+    // the strings of `strings()` back, None unless id 0 is the empty string
+    pub fn from_strings(strings: Vec<String>) -> Option<Self> {
+        strings
+            .first()
+            .is_some_and(String::is_empty)
+            .then_some(Self { strings })
+    }
+
+    fn heap_size(&self) -> usize {
+        self.strings.capacity() * std::mem::size_of::<String>()
+            + self.strings.iter().map(String::capacity).sum::<usize>()
+    }
+}
+
+// fills an Interner, remembering the ids handed out
+#[derive(Default)]
+struct InternerBuilder {
+    interner: Interner,
+    ids: HashMap<String, u32>,
+}
+
+impl InternerBuilder {
+    fn intern(&mut self, s: String) -> u32 {
+        if s.is_empty() {
+            return 0;
+        }
+        if let Some(&id) = self.ids.get(&s) {
+            return id;
+        }
+        let id = self.interner.strings.len() as u32;
+        self.interner.strings.push(s.clone());
+        self.ids.insert(s, id);
+        id
+    }
+}
+
+// every event of a trace, a vector per field. index i of each vector is
+// event i, in time order once loaded
+#[derive(Debug, Default)]
+pub struct Events {
+    pub time: Vec<f64>,
+    pub duration: Vec<f64>,
+    pub pe: Vec<u32>,
+    pub context: Vec<Option<u32>>,
+    pub run: Vec<u16>,
+    pub target: Vec<i32>,
+    pub bytes_rx: Vec<u64>,
+    pub bytes_tx: Vec<u64>,
+    // ids into the interners below
+    pub function: Vec<u32>,
+    pub stack: Vec<u32>,
+    pub extra: Vec<u32>,
+    pub symbols: Vec<u32>,
+    pub functions: Interner,
+    pub stacks: Interner,
+    pub extras: Interner,
+    pub symboltraces: Interner,
+}
+
+impl Events {
+    // (pe, context, run, row) in any order
+    fn from_rows(rows: Vec<(u32, Option<u32>, u16, RawEvent)>) -> Self {
+        let n = rows.len();
+        let mut events = Events {
+            time: Vec::with_capacity(n),
+            duration: Vec::with_capacity(n),
+            pe: Vec::with_capacity(n),
+            context: Vec::with_capacity(n),
+            run: Vec::with_capacity(n),
+            target: Vec::with_capacity(n),
+            bytes_rx: Vec::with_capacity(n),
+            bytes_tx: Vec::with_capacity(n),
+            function: Vec::with_capacity(n),
+            stack: Vec::with_capacity(n),
+            extra: Vec::with_capacity(n),
+            symbols: Vec::with_capacity(n),
+            ..Default::default()
+        };
+        let mut functions = InternerBuilder::default();
+        let mut stacks = InternerBuilder::default();
+        let mut extras = InternerBuilder::default();
+        let mut symboltraces = InternerBuilder::default();
+        for (pe, context, run, raw) in rows {
+            events.time.push(raw.time);
+            events.duration.push(raw.duration_sec);
+            events.pe.push(pe);
+            events.context.push(context);
+            events.run.push(run);
+            events.target.push(raw.target_pe);
+            events.bytes_rx.push(raw.bytes_rx);
+            events.bytes_tx.push(raw.bytes_tx);
+            events.function.push(functions.intern(raw.function));
+            events.stack.push(stacks.intern(raw.stacktrace));
+            events
+                .extra
+                .push(extras.intern(raw.extra.unwrap_or_default()));
+            events
+                .symbols
+                .push(symboltraces.intern(raw.symboltrace.unwrap_or_default()));
+        }
+        events.functions = functions.interner;
+        events.stacks = stacks.interner;
+        events.extras = extras.interner;
+        events.symboltraces = symboltraces.interner;
+        events
+    }
+
+    pub fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<Event<'_>> {
+        (i < self.len()).then(|| self.at(i))
+    }
+
+    // event i, panicking past the end like indexing would
+    pub fn at(&self, i: usize) -> Event<'_> {
+        fn optional(s: &str) -> Option<&str> {
+            (!s.is_empty()).then_some(s)
+        }
+        Event {
+            source_pe: self.pe[i],
+            context: self.context[i],
+            run: self.run[i],
+            raw: Fields {
+                time: self.time[i],
+                function: self.functions.get(self.function[i]),
+                duration_sec: self.duration[i],
+                target_pe: self.target[i],
+                bytes_rx: self.bytes_rx[i],
+                bytes_tx: self.bytes_tx[i],
+                stacktrace: self.stacks.get(self.stack[i]),
+                extra: optional(self.extras.get(self.extra[i])),
+                symboltrace: optional(self.symboltraces.get(self.symbols[i])),
+            },
+        }
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = Event<'_>> + ExactSizeIterator {
+        (0..self.len()).map(|i| self.at(i))
+    }
+
+    // end of event i
+    pub fn end(&self, i: usize) -> f64 {
+        self.time[i] + self.duration[i]
+    }
+
+    // reorders every column so event i becomes event order[i]
+    fn permute(&mut self, order: &[usize]) {
+        fn apply<T: Copy>(column: &mut Vec<T>, order: &[usize]) {
+            *column = order.iter().map(|&i| column[i]).collect();
+        }
+        apply(&mut self.time, order);
+        apply(&mut self.duration, order);
+        apply(&mut self.pe, order);
+        apply(&mut self.context, order);
+        apply(&mut self.run, order);
+        apply(&mut self.target, order);
+        apply(&mut self.bytes_rx, order);
+        apply(&mut self.bytes_tx, order);
+        apply(&mut self.function, order);
+        apply(&mut self.stack, order);
+        apply(&mut self.extra, order);
+        apply(&mut self.symbols, order);
+    }
+
+    fn heap_size(&self) -> usize {
+        fn column<T>(v: &Vec<T>) -> usize {
+            v.capacity() * std::mem::size_of::<T>()
+        }
+        column(&self.time)
+            + column(&self.duration)
+            + column(&self.pe)
+            + column(&self.context)
+            + column(&self.run)
+            + column(&self.target)
+            + column(&self.bytes_rx)
+            + column(&self.bytes_tx)
+            + column(&self.function)
+            + column(&self.stack)
+            + column(&self.extra)
+            + column(&self.symbols)
+            + self.functions.heap_size()
+            + self.stacks.heap_size()
+            + self.extras.heap_size()
+            + self.symboltraces.heap_size()
+    }
+}
+
+// one directory of a trace merged from several (job steps, nodes). its PEs
+// are renumbered to follow those of the directories before it
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub dir: PathBuf,
+    pub first_pe: u32,
+    pub pe_count: u32,
+}
+
+// numeric values reported through Extra, e.g. `heap=1024;pending=3`
+#[derive(Debug, Default)]
+pub struct Counter {
+    // pe -> (time, value), in time order
+    pub samples: BTreeMap<u32, Vec<(f64, f64)>>,
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct ProfileData {
+    pub events: Events,
+    pub pe_count: u32,
+    pub pe_hostnames: HashMap<u32, String>,
+    pub min_time: f64,
+    pub max_time: f64,
+    pub counters: BTreeMap<String, Counter>,
+    // unix time of trace time zero, from an `epoch=` entry in the Extra of
+    // a first event
+    pub epoch: Option<f64>,
+    // contexts seen on PEs that have more than one, None (no context) first.
+    // each gets its own sub-row on the timeline
+    pub pe_contexts: HashMap<u32, Vec<Option<u32>>>,
+    // the csv files this was read from
+    pub sources: Vec<PathBuf>,
+    // events by the time they span, see overlapping()
+    pub intervals: IntervalIndex,
+    // seconds currently added to each PE's timestamps to correct clock skew
+    pub clock_offsets: HashMap<u32, f64>,
+    // the directories merged into this trace, empty unless there are several
+    pub runs: Vec<Run>,
+    // what shed() dropped to fit a memory budget
+    pub elided: Elided,
+}
+
+// strings dropped from the events to save memory, counted in events
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Elided {
+    pub symboltraces: usize,
+    pub stacktraces: usize,
+    // heap bytes given back
+    pub bytes: usize,
+}
+
+impl Elided {
+    pub fn is_empty(&self) -> bool {
+        self.symboltraces == 0 && self.stacktraces == 0
+    }
+}
+
+// host of a PE whose file has no events to name it
+pub const UNKNOWN_HOST: &str = "unknown";
+
+// PE and context of a pperf.XXX.csv, or pperf.XXX.CTX.csv for a thread of PE XXX
+pub fn parse_file_name(path: &Path) -> Option<(u32, Option<u32>)> {
+    let name = path.file_name()?.to_str()?;
+    if !name.starts_with("pperf.") || !name.ends_with(".csv") {
+        return None;
+    }
+    let parts: Vec<&str> = name.split('.').collect();
+    let context = match parts.len() {
+        3 => None,
+        4 => Some(parts[2].parse::<u32>().ok()?),
+        _ => return None,
+    };
+    Some((parts[1].parse().ok()?, context))
+}
+
+// the directories of a trace with the first PE of each: the runs when
+// several were merged, otherwise wherever the files are
+pub(crate) fn trace_dirs(sources: &[PathBuf], runs: &[Run]) -> Vec<(PathBuf, u32)> {
+    if !runs.is_empty() {
+        return runs.iter().map(|r| (r.dir.clone(), r.first_pe)).collect();
+    }
+    let dirs: BTreeSet<&Path> = sources.iter().filter_map(|s| s.parent()).collect();
+    dirs.into_iter().map(|d| (d.to_path_buf(), 0)).collect()
+}
+
+pub fn is_pperf_file(path: &Path) -> bool {
+    parse_file_name(path).is_some()
+}
+
+// any file a trace is read from: the pperf files and the manifest
+pub fn is_trace_file(path: &Path) -> bool {
+    is_pperf_file(path)
+        || path
+            .file_name()
+            .is_some_and(|n| n == crate::manifest::FILE_NAME)
+}
+
+// the pperf csv files directly inside `dir`
+pub fn pperf_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if parse_file_name(&path).is_some() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+impl ProfileData {
+    pub fn load_from_dir(dir: &Path) -> Result<Self> {
+        Self::load_from_files(pperf_files(dir)?)
+    }
+
+    // csv files have to be named like pperf.XXX.csv, the PE comes from the
+    // name; a .parquet file holds any number of PEs, each row naming its own
+    // in the Source_PE column
+    pub fn load_from_files(sources: Vec<PathBuf>) -> Result<Self> {
+        Self::load(sources, Vec::new())
+    }
+
+    // several directories side by side, each keeping its own PE numbering
+    // offset past the ones before it. targets are renumbered to match
+    pub fn load_runs(dirs: &[PathBuf]) -> Result<Self> {
+        let mut runs = Vec::new();
+        let mut sources = Vec::new();
+        let mut first_pe = 0;
+        for dir in dirs {
+            let files = pperf_files(dir)?;
+            let pe_count = files
+                .iter()
+                .filter_map(|f| parse_file_name(f))
+                .map(|(pe, _)| pe + 1)
+                .max()
+                .unwrap_or(0);
+            if pe_count == 0 {
+                bail!("no pperf.<PE>.csv files in {}", dir.display());
+            }
+            let declared = Manifest::read(dir)?.map_or(0, |m| m.pe_count());
+            let pe_count = pe_count.max(declared);
+            runs.push(Run {
+                dir: dir.clone(),
+                first_pe,
+                pe_count,
+            });
+            sources.extend(files);
+            first_pe += pe_count;
+        }
+        Self::load(sources, runs)
+    }
+
+    fn load(mut sources: Vec<PathBuf>, runs: Vec<Run>) -> Result<Self> {
+        sources.sort();
+        sources.dedup();
+        if let Some(data) = cache::read(&sources, &runs) {
+            return Ok(data);
+        }
+
+        let mut rows = Vec::new();
+        let mut max_pe = 0;
+        let mut pe_hostnames = HashMap::default();
+        let mut epoch = None;
+
+        // hosts from the manifests first, the Extra of first events wins
+        let mut declared_pe_count = 0;
+        for (dir, first_pe) in trace_dirs(&sources, &runs) {
+            let Some(manifest) = Manifest::read(&dir)? else {
+                continue;
+            };
+            for (pe, host) in manifest.hostnames.iter().enumerate() {
+                if !host.is_empty() {
+                    pe_hostnames.insert(first_pe + pe as u32, host.clone());
+                }
+            }
+            declared_pe_count = declared_pe_count.max(first_pe + manifest.pe_count());
+        }
+
+        for path in &sources {
+            let run = runs
+                .iter()
+                .position(|r| path.parent() == Some(r.dir.as_path()))
+                .unwrap_or(0);
+            let offset = runs.get(run).map_or(0, |r| r.first_pe);
+            // every PE in one file, each named by its first row like a csv
+            if parquet::is_parquet_file(path) {
+                let mut seen = HashSet::new();
+                for (pe, context, mut raw) in parquet::read(path)? {
+                    let pe = pe + offset;
+                    if raw.target_pe >= 0 {
+                        raw.target_pe += offset as i32;
+                    }
+                    if seen.insert(pe) {
+                        if let Some(host) = raw.extra_field("host") {
+                            pe_hostnames.entry(pe).or_insert_with(|| host.to_string());
+                        }
+                        epoch = epoch.or_else(|| raw.extra_field("epoch")?.parse().ok());
+                    }
+                    max_pe = max_pe.max(pe);
+                    rows.push((pe, context, run as u16, raw));
+                }
+                continue;
+            }
+            let Some((pe_id, context)) = parse_file_name(path) else {
+                bail!("{} is not named pperf.<PE>.csv", path.display());
+            };
+            let pe_id = pe_id + offset;
+            if pe_id > max_pe {
+                max_pe = pe_id;
+            }
+            let mut loaded_events = Self::load_file(path, context)?;
+            for (_, raw) in &mut loaded_events {
+                if raw.target_pe >= 0 {
+                    raw.target_pe += offset as i32;
+                }
+            }
+            // a PE that died before its first call writes nothing but the header
+            let Some((_, initialize)) = loaded_events.first() else {
+                if context.is_none() {
+                    eprintln!("warning: {} has no events", path.display());
+                    pe_hostnames
+                        .entry(pe_id)
+                        .or_insert_with(|| UNKNOWN_HOST.to_string());
+                }
+                continue;
+            };
+            // first event is the initialize (hopefully)
+            let hostname = initialize.extra_field("host");
+            // thread files may leave the host to the main one
+            match hostname {
+                Some(hostname) => {
+                    pe_hostnames.insert(pe_id, hostname.to_string());
+                }
+                None if context.is_none() && !pe_hostnames.contains_key(&pe_id) => {
+                    eprintln!(
+                        "warning: no host= in the first event of {}, PE {pe_id} goes under {UNKNOWN_HOST}",
+                        path.display()
+                    );
+                    pe_hostnames.insert(pe_id, UNKNOWN_HOST.to_string());
+                }
+                None => {}
+            }
+            epoch = epoch.or_else(|| initialize.extra_field("epoch")?.parse().ok());
+            rows.extend(
+                loaded_events
+                    .into_iter()
+                    .map(|(context, raw)| (pe_id, context, run as u16, raw)),
+            );
+        }
+        let data = Self::from_events(
+            Events::from_rows(rows),
+            (max_pe + 1).max(declared_pe_count),
+            pe_hostnames,
+            epoch,
+            sources,
+            runs,
+        );
+        // a read-only directory just means parsing again next time
+        let _ = cache::write(&data);
+        Ok(data)
+    }
+
+    // a trace from events in any order, e.g. ones not read from csv files
+    pub fn from_events(
+        events: Events,
+        pe_count: u32,
+        pe_hostnames: HashMap<u32, String>,
+        epoch: Option<f64>,
+        sources: Vec<PathBuf>,
+        runs: Vec<Run>,
+    ) -> Self {
+        let mut contexts: HashMap<u32, BTreeSet<Option<u32>>> = HashMap::default();
+        for (&pe, &context) in events.pe.iter().zip(&events.context) {
+            contexts.entry(pe).or_default().insert(context);
+        }
+        let pe_contexts = contexts
+            .into_iter()
+            .filter(|(_, c)| c.len() > 1)
+            .map(|(pe, c)| (pe, c.into_iter().collect()))
+            .collect();
+
+        let mut data = Self {
+            events,
+            pe_count,
+            pe_hostnames,
+            epoch,
+            pe_contexts,
+            sources,
+            runs,
+            ..Default::default()
+        };
+        data.reindex();
+        data
+    }
+
+    // a trace of the events of each PE in turn, for unit tests
+    #[cfg(test)]
+    pub(crate) fn from_pes(pes: Vec<Vec<RawEvent>>) -> Self {
+        let pe_count = pes.len() as u32;
+        let rows = pes
+            .into_iter()
+            .enumerate()
+            .flat_map(|(pe, events)| events.into_iter().map(move |e| (pe as u32, None, 0, e)))
+            .collect();
+        Self::from_events(
+            Events::from_rows(rows),
+            pe_count,
+            HashMap::new(),
+            None,
+            Vec::new(),
+            Vec::new(),
+        )
+    }
+
+    // puts the events back in time order and redoes what depends on it,
+    // after they were read or moved. returns the old index of each event
+    fn reindex(&mut self) -> Vec<usize> {
+        // probably would be faster to use some sort of
+        // merging algorithm but \shrug
+        let time = &self.events.time;
+        let mut order: Vec<usize> = (0..time.len()).collect();
+        order.sort_by(|&a, &b| {
+            time[a]
+                .partial_cmp(&time[b])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        self.events.permute(&order);
+
+        self.min_time = self.events.time.first().copied().unwrap_or(0.0);
+        self.max_time = (0..self.events.len())
+            .map(|i| self.events.end(i))
+            .fold(0.0, f64::max);
+
+        self.counters = Self::collect_counters(&self.events);
+        self.intervals = Self::index_events(&self.events);
+        order
+    }
+
+    // PEs without a file of their own, e.g. one that died before writing
+    // it, or ones past the last file when a manifest says the job was bigger
+    pub fn missing_pes(&self) -> Vec<u32> {
+        let mut present = vec![false; self.pe_count as usize];
+        for &pe in &self.events.pe {
+            present[pe as usize] = true;
+        }
+        let dirs = trace_dirs(&self.sources, &self.runs);
+        for path in &self.sources {
+            let first_pe = dirs
+                .iter()
+                .find(|(dir, _)| path.parent() == Some(dir.as_path()))
+                .map_or(0, |(_, first_pe)| *first_pe);
+            if let Some((pe, _)) = parse_file_name(path)
+                && let Some(p) = present.get_mut((first_pe + pe) as usize)
+            {
+                *p = true;
+            }
+        }
+        (0..self.pe_count)
+            .filter(|pe| !present[*pe as usize])
+            .collect()
+    }
+
+    pub fn clock_offset(&self, pe: u32) -> f64 {
+        self.clock_offsets.get(&pe).copied().unwrap_or(0.0)
+    }
+
+    // shifts every PE by the offset of its host, replacing the offsets
+    // applied before. the events are put back in time order, the result
+    // holds the old index of each one, None when nothing moved
+    pub fn set_clock_offsets(&mut self, offsets: &ClockOffsets) -> Option<Vec<usize>> {
+        let target: Vec<f64> = (0..self.pe_count)
+            .map(|pe| {
+                self.pe_hostnames
+                    .get(&pe)
+                    .and_then(|h| offsets.get(h))
+                    .copied()
+                    .unwrap_or(0.0)
+            })
+            .collect();
+        let delta: Vec<f64> = (0..self.pe_count)
+            .map(|pe| target[pe as usize] - self.clock_offset(pe))
+            .collect();
+        if delta.iter().all(|d| *d == 0.0) {
+            return None;
+        }
+        for (time, &pe) in self.events.time.iter_mut().zip(&self.events.pe) {
+            *time += delta[pe as usize];
+        }
+        self.clock_offsets = (0..self.pe_count)
+            .filter(|pe| target[*pe as usize] != 0.0)
+            .map(|pe| (pe, target[pe as usize]))
+            .collect();
+        Some(self.reindex())
+    }
+
+    fn index_events(events: &Events) -> IntervalIndex {
+        IntervalIndex::new(
+            events
+                .time
+                .iter()
+                .zip(&events.duration)
+                .map(|(&t, &d)| (t, t + d.max(0.0))),
+        )
+    }
+
+    pub fn event(&self, i: usize) -> Event<'_> {
+        self.events.at(i)
+    }
+
+    // every event, in time order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = Event<'_>> + ExactSizeIterator {
+        self.events.iter()
+    }
+
+    // the events with indices in `range`
+    pub fn range(
+        &self,
+        range: impl RangeBounds<usize>,
+    ) -> impl DoubleEndedIterator<Item = Event<'_>> + ExactSizeIterator {
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i + 1,
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => self.events.len(),
+        };
+        (start..end).map(|i| self.events.at(i))
+    }
+
+    // indices of the events running at some point in [start, end], in start
+    // order. unlike a search on start times this finds long events that began
+    // well before the range
+    pub fn overlapping(&self, start: f64, end: f64) -> impl Iterator<Item = usize> + '_ {
+        self.intervals.overlapping(start, end)
+    }
+
+    fn collect_counters(events: &Events) -> BTreeMap<String, Counter> {
+        let mut counters: BTreeMap<String, Counter> = BTreeMap::new();
+        for e in events.iter() {
+            for (key, value) in e.raw.extra_fields() {
+                // these describe the process, they aren't counters
+                if matches!(key, "host" | "pe" | "epoch" | "thread" | "ctx") {
+                    continue;
+                }
+                let Ok(value) = value.parse::<f64>() else {
+                    continue;
+                };
+                let counter = counters.entry(key.to_string()).or_insert_with(|| Counter {
+                    min: value,
+                    max: value,
+                    ..Default::default()
+                });
+                counter.min = counter.min.min(value);
+                counter.max = counter.max.max(value);
+                counter
+                    .samples
+                    .entry(e.source_pe)
+                    .or_default()
+                    .push((e.raw.time, value));
+            }
+        }
+        counters
+    }
+
+    // rough heap use in bytes, for the performance overlay. counts the
+    // event columns with their strings and the counter samples, not
+    // allocator slack
+    pub fn heap_size(&self) -> usize {
+        let counters: usize = self
+            .counters
+            .iter()
+            .map(|(name, c)| {
+                name.capacity()
+                    + c.samples
+                        .values()
+                        .map(|s| s.capacity() * std::mem::size_of::<(f64, f64)>())
+                        .sum::<usize>()
+            })
+            .sum();
+        let hosts: usize = self
+            .pe_hostnames
+            .values()
+            .map(|h| h.capacity() + std::mem::size_of::<(u32, String)>())
+            .sum();
+        self.events.heap_size() + counters + hosts + self.intervals.heap_size()
+    }
+
+    // drops the symbol traces, then the stack traces, until heap_size() is
+    // at most `budget` bytes or nothing is left to drop. function, times,
+    // PEs, bytes and Extra are always kept
+    pub fn shed(&mut self, budget: usize) -> Elided {
+        let before = self.heap_size();
+        let mut elided = Elided::default();
+        let events = &mut self.events;
+        if before > budget {
+            elided.symboltraces = events.symbols.iter().filter(|&&s| s != 0).count();
+            events.symbols.fill(0);
+            events.symboltraces = Interner::default();
+        }
+        if self.heap_size() > budget {
+            let events = &mut self.events;
+            elided.stacktraces = events.stack.iter().filter(|&&s| s != 0).count();
+            events.stack.fill(0);
+            events.stacks = Interner::default();
+        }
+        elided.bytes = before - self.heap_size();
+        self.elided = elided;
+        elided
+    }
+
+    // timeline sub-row of an event within its PE's track
+    pub fn sub_row(&self, e: &Event) -> usize {
+        self.pe_contexts
+            .get(&e.source_pe)
+            .and_then(|c| c.iter().position(|c| *c == e.context))
+            .unwrap_or(0)
+    }
+
+    pub fn sub_rows(&self, pe: u32) -> usize {
+        self.pe_contexts.get(&pe).map_or(1, Vec::len)
+    }
+
+    // the rows of a file with the context of each
+    fn load_file(path: &Path, context: Option<u32>) -> Result<Vec<(Option<u32>, RawEvent)>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_path(path)?;
+
+        let mut events = Vec::new();
+        let mut rows = rdr.deserialize().peekable();
+        while let Some(result) = rows.next() {
+            // a run killed mid-write leaves half a row at the end
+            let raw: RawEvent = match result {
+                Ok(raw) => raw,
+                Err(e) if rows.peek().is_none() => {
+                    eprintln!(
+                        "warning: {}: dropped the truncated last row: {e}",
+                        path.display()
+                    );
+                    break;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            // the file name wins over Extra
+            let context = context.or_else(|| {
+                raw.extra_fields()
+                    .find(|(k, _)| matches!(*k, "thread" | "ctx"))
+                    .and_then(|(_, v)| v.parse().ok())
+            });
+            events.push((context, raw));
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::event;
+
+    #[test]
+    fn clock_offsets_move_events_in_place() {
+        let mut data = ProfileData::from_pes(vec![
+            vec![
+                event(0.0, "a0", 0.5, -1, 0, 0),
+                event(2.0, "a2", 0.5, -1, 0, 0),
+            ],
+            vec![
+                event(1.0, "b1", 0.5, -1, 0, 0),
+                event(3.0, "b3", 0.5, -1, 0, 0),
+            ],
+        ]);
+        data.pe_hostnames = [(0, "x".to_string()), (1, "y".to_string())].into();
+        let functions = |data: &ProfileData| -> Vec<String> {
+            data.iter().map(|e| e.raw.function.to_string()).collect()
+        };
+        assert_eq!(functions(&data), ["a0", "b1", "a2", "b3"]);
+
+        let offsets = ClockOffsets::from([("y".to_string(), -1.5)]);
+        let order = data.set_clock_offsets(&offsets).unwrap();
+        assert_eq!(functions(&data), ["b1", "a0", "b3", "a2"]);
+        assert_eq!(order, [1, 0, 3, 2]);
+        assert_eq!(data.events.time, [-0.5, 0.0, 1.5, 2.0]);
+        assert_eq!((data.min_time, data.max_time), (-0.5, 2.5));
+        assert_eq!(data.clock_offset(1), -1.5);
+        // the interval index follows
+        assert_eq!(data.overlapping(0.9, 1.0).count(), 0);
+        assert_eq!(data.overlapping(1.6, 1.7).collect::<Vec<_>>(), [2]);
+
+        // the same offsets again change nothing, and back to none undoes them
+        assert!(data.set_clock_offsets(&offsets).is_none());
+        assert!(data.set_clock_offsets(&ClockOffsets::new()).is_some());
+        assert_eq!(functions(&data), ["a0", "b1", "a2", "b3"]);
+        assert_eq!(data.events.time, [0.0, 1.0, 2.0, 3.0]);
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use crate::anomalies::{self, Anomaly};
+use crate::asymmetry::{self, AsymmetryReport};
+use crate::completion::{self, Completion};
+use crate::data::ProfileData;
+use crate::matching::{self, Matching, PairLatency};
+use crate::prefix::ByteSums;
+use crate::search::SearchIndex;
+use crate::stragglers::{self, StragglerReport};
+
+// the analyses a frontend keeps around for a loaded trace, made once per
+// load or clock change instead of per frame
+
+// knobs of the analyses that take one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    // sigma above the mean duration for an anomaly
+    pub anomaly_threshold: f64,
+    // seconds all PEs have to return within for a collective instance
+    pub straggler_spread: f64,
+    // share two ends of a pair may disagree about the bytes
+    pub asymmetry_tolerance: f64,
+    // seconds a receive may start after its send ends
+    pub match_window: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            anomaly_threshold: 3.0,
+            straggler_spread: 0.01,
+            asymmetry_tolerance: 0.01,
+            match_window: 0.001,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Derived {
+    pub settings: Settings,
+
+    // every function with its number of calls, most called first
+    pub function_counts: Vec<(String, usize)>,
+    // PEs of the job that left no file, drawn as empty tracks
+    pub missing_pes: Vec<u32>,
+    pub asymmetry: AsymmetryReport,
+
+    pub anomalies: Vec<Anomaly>,
+    // event index -> sigma, for the timeline
+    pub anomaly_sigma: HashMap<usize, f64>,
+    pub stragglers: StragglerReport,
+    pub matching: Matching,
+    pub message_pairs: Vec<PairLatency>,
+    pub completion: Completion,
+    pub search_index: Option<SearchIndex>,
+    // bytes moved in a time range
+    pub byte_sums: ByteSums,
+}
+
+impl Derived {
+    // everything, for a newly loaded trace or none
+    pub fn rebuild(&mut self, data: Option<&ProfileData>) {
+        self.rebuild_untimed(data);
+        self.rebuild_timed(data);
+    }
+
+    // what only depends on which events there are
+    pub fn rebuild_untimed(&mut self, data: Option<&ProfileData>) {
+        self.function_counts = data.map(function_counts).unwrap_or_default();
+        self.missing_pes = data.map(ProfileData::missing_pes).unwrap_or_default();
+        self.rebuild_asymmetry(data);
+    }
+
+    // what depends on the times or the order of the events, redone when
+    // either changes
+    pub fn rebuild_timed(&mut self, data: Option<&ProfileData>) {
+        self.rebuild_anomalies(data);
+        self.rebuild_stragglers(data);
+        self.rebuild_matching(data);
+        self.completion = data.map(completion::track).unwrap_or_default();
+        self.search_index = data.map(SearchIndex::new);
+        self.byte_sums = data.map(ByteSums::new).unwrap_or_default();
+    }
+
+    // new knobs, redoing only the analyses whose knob changed
+    pub fn set_settings(&mut self, settings: Settings, data: Option<&ProfileData>) {
+        let old = std::mem::replace(&mut self.settings, settings);
+        if old.anomaly_threshold != settings.anomaly_threshold {
+            self.rebuild_anomalies(data);
+        }
+        if old.straggler_spread != settings.straggler_spread {
+            self.rebuild_stragglers(data);
+        }
+        if old.asymmetry_tolerance != settings.asymmetry_tolerance {
+            self.rebuild_asymmetry(data);
+        }
+        if old.match_window != settings.match_window {
+            self.rebuild_matching(data);
+        }
+    }
+
+    pub fn rebuild_anomalies(&mut self, data: Option<&ProfileData>) {
+        self.anomalies = data
+            .map(|data| anomalies::detect(data, self.settings.anomaly_threshold))
+            .unwrap_or_default();
+        self.anomaly_sigma = self.anomalies.iter().map(|a| (a.event, a.sigma)).collect();
+    }
+
+    pub fn rebuild_stragglers(&mut self, data: Option<&ProfileData>) {
+        self.stragglers = data
+            .map(|data| stragglers::detect(data, self.settings.straggler_spread))
+            .unwrap_or_default();
+    }
+
+    pub fn rebuild_asymmetry(&mut self, data: Option<&ProfileData>) {
+        self.asymmetry = data
+            .map(|data| asymmetry::detect(data, self.settings.asymmetry_tolerance))
+            .unwrap_or_default();
+    }
+
+    pub fn rebuild_matching(&mut self, data: Option<&ProfileData>) {
+        self.matching = data
+            .map(|data| matching::match_messages(data, self.settings.match_window))
+            .unwrap_or_default();
+        self.message_pairs = self.matching.by_pair();
+    }
+}
+
+fn function_counts(data: &ProfileData) -> Vec<(String, usize)> {
+    let names = data.events.functions.strings();
+    let mut counts = vec![0; names.len()];
+    for &id in &data.events.function {
+        counts[id as usize] += 1;
+    }
+    let mut rows: Vec<(String, usize)> = names
+        .iter()
+        .cloned()
+        .zip(counts)
+        .filter(|(_, n)| *n > 0)
+        .collect();
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::event;
+
+    #[test]
+    fn rebuild_and_clear() {
+        let data = ProfileData::from_pes(vec![
+            vec![
+                event(0.0, "shmem_init", 0.1, -1, 0, 0),
+                event(1.0, "shmem_putmem", 0.1, 1, 0, 64),
+                event(2.0, "shmem_putmem", 0.1, 1, 0, 64),
+            ],
+            vec![event(0.0, "shmem_init", 0.1, -1, 0, 0)],
+        ]);
+        let mut derived = Derived::default();
+        derived.rebuild(Some(&data));
+        assert_eq!(
+            derived.function_counts,
+            [
+                ("shmem_init".to_string(), 2),
+                ("shmem_putmem".to_string(), 2)
+            ]
+        );
+        assert_eq!(derived.byte_sums.total(0.0, 3.0).0, 128);
+        assert!(derived.search_index.is_some());
+
+        derived.rebuild(None);
+        assert!(derived.function_counts.is_empty());
+        assert_eq!(derived.byte_sums.total(0.0, 3.0).0, 0);
+        assert!(derived.search_index.is_none());
+    }
+
+    #[test]
+    fn settings_redo_their_analysis() {
+        let mut pe = (0..20)
+            .map(|i| {
+                event(
+                    i as f64,
+                    "shmem_barrier_all",
+                    1.0 + (i % 2) as f64 * 0.1,
+                    -1,
+                    0,
+                    0,
+                )
+            })
+            .collect::<Vec<_>>();
+        pe.push(event(30.0, "shmem_barrier_all", 2.0, -1, 0, 0));
+        let data = ProfileData::from_pes(vec![pe]);
+        let mut derived = Derived::default();
+        derived.rebuild(Some(&data));
+        assert_eq!(derived.anomalies.len(), 1);
+
+        let settings = Settings {
+            anomaly_threshold: 100.0,
+            ..derived.settings
+        };
+        derived.set_settings(settings, Some(&data));
+        assert_eq!(derived.settings, settings);
+        assert!(derived.anomalies.is_empty());
+        assert!(derived.anomaly_sigma.is_empty());
+    }
+}
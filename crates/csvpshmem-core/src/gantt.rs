@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::data::{Event, ProfileData};
+use crate::grouping::Grouping;
+
+// time one PE spent in one function, one row per pair, for plotting
+// elsewhere (pandas, ggplot) without parsing the trace again
+#[derive(Debug, Clone, Serialize)]
+pub struct Row {
+    pub pe: u32,
+    pub host: String,
+    pub function: String,
+    pub group: String,
+    pub calls: usize,
+    // inside the range only, calls straddling an edge are cut there
+    pub time_sec: f64,
+    pub first_start: f64,
+    pub last_end: f64,
+    pub bytes_tx: u64,
+    pub bytes_rx: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Csv,
+    Json,
+}
+
+impl TableFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            TableFormat::Csv => "csv",
+            TableFormat::Json => "json",
+        }
+    }
+}
+
+// rows for the calls overlapping [start, end] that `keep` lets through, by
+// PE then function
+pub fn rows(
+    data: &ProfileData,
+    grouping: &Grouping,
+    start: f64,
+    end: f64,
+    keep: impl Fn(&Event) -> bool,
+) -> Vec<Row> {
+    let mut rows: BTreeMap<(u32, &str), Row> = BTreeMap::new();
+    for i in data.overlapping(start, end) {
+        let e = data.event(i);
+        if !keep(&e) {
+            continue;
+        }
+        let (t0, t1) = (e.raw.time, e.raw.time + e.raw.duration_sec);
+        let row = rows
+            .entry((e.source_pe, e.raw.function))
+            .or_insert_with(|| Row {
+                pe: e.source_pe,
+                host: data
+                    .pe_hostnames
+                    .get(&e.source_pe)
+                    .cloned()
+                    .unwrap_or_default(),
+                function: e.raw.function.to_string(),
+                group: grouping.group(e.raw.function).to_string(),
+                calls: 0,
+                time_sec: 0.0,
+                first_start: t0,
+                last_end: t1,
+                bytes_tx: 0,
+                bytes_rx: 0,
+            });
+        row.calls += 1;
+        row.time_sec += (t1.min(end) - t0.max(start)).max(0.0);
+        row.first_start = row.first_start.min(t0);
+        row.last_end = row.last_end.max(t1);
+        row.bytes_tx += e.raw.bytes_tx;
+        row.bytes_rx += e.raw.bytes_rx;
+    }
+    rows.into_values().collect()
+}
+
+// writes `rows` next to `path` with the format's extension, returning where
+pub fn write(rows: &[Row], format: TableFormat, path: &Path) -> Result<PathBuf> {
+    let path = path.with_extension(format.extension());
+    match format {
+        TableFormat::Csv => {
+            let mut writer = csv::Writer::from_path(&path)
+                .with_context(|| format!("writing {}", path.display()))?;
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+        TableFormat::Json => {
+            let text = serde_json::to_string_pretty(rows)?;
+            fs::write(&path, text).with_context(|| format!("writing {}", path.display()))?;
+        }
+    }
+    Ok(path)
+}
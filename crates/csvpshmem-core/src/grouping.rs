@@ -0,0 +1,70 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+// maps function names matching `pattern` to `group`, e.g.
+// `^shmem_put` -> "PUT". the group may use captures: `^shmem_(\w+?)_` -> "$1"
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GroupRule {
+    pub pattern: String,
+    pub group: String,
+}
+
+// compiled rules plus the resolved group of every function in the trace
+#[derive(Debug, Default)]
+pub struct Grouping {
+    group_of: HashMap<String, String>,
+}
+
+impl Grouping {
+    // first matching rule wins, unmatched functions are their own group
+    pub fn new<'a>(rules: &[GroupRule], functions: impl Iterator<Item = &'a str>) -> Result<Self> {
+        let compiled = rules
+            .iter()
+            .map(|r| {
+                Regex::new(&r.pattern)
+                    .with_context(|| format!("bad pattern '{}'", r.pattern))
+                    .map(|re| (re, r.group.as_str()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut group_of = HashMap::new();
+        for function in functions {
+            if group_of.contains_key(function) {
+                continue;
+            }
+            let group = compiled
+                .iter()
+                .find_map(|(re, group)| {
+                    let caps = re.captures(function)?;
+                    let mut out = String::new();
+                    caps.expand(group, &mut out);
+                    Some(out)
+                })
+                .unwrap_or_else(|| function.to_string());
+            group_of.insert(function.to_string(), group);
+        }
+        Ok(Self { group_of })
+    }
+
+    pub fn group<'a>(&'a self, function: &'a str) -> &'a str {
+        self.group_of.get(function).map_or(function, String::as_str)
+    }
+
+    pub fn groups(&self) -> BTreeSet<&str> {
+        self.group_of.values().map(String::as_str).collect()
+    }
+
+    // functions that fall into `group`
+    pub fn members(&self, group: &str) -> Vec<&str> {
+        let mut members: Vec<&str> = self
+            .group_of
+            .iter()
+            .filter(|(_, g)| *g == group)
+            .map(|(f, _)| f.as_str())
+            .collect();
+        members.sort();
+        members
+    }
+}
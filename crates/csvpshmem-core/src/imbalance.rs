@@ -0,0 +1,100 @@
+use crate::data::ProfileData;
+use crate::units;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PeLoad {
+    pub time_in_calls: f64,
+    pub bytes_tx: u64,
+    pub bytes_rx: u64,
+    pub calls: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadMetric {
+    Time,
+    BytesTx,
+    BytesRx,
+    Calls,
+}
+
+impl LoadMetric {
+    pub const ALL: [LoadMetric; 4] = [Self::Time, Self::BytesTx, Self::BytesRx, Self::Calls];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Time => "Time in calls",
+            Self::BytesTx => "Bytes sent",
+            Self::BytesRx => "Bytes received",
+            Self::Calls => "Calls",
+        }
+    }
+
+    pub fn of(self, load: &PeLoad) -> f64 {
+        match self {
+            Self::Time => load.time_in_calls,
+            Self::BytesTx => load.bytes_tx as f64,
+            Self::BytesRx => load.bytes_rx as f64,
+            Self::Calls => load.calls as f64,
+        }
+    }
+
+    // a value of this metric with its unit
+    pub fn format(self, value: f64) -> String {
+        match self {
+            Self::Time => units::duration(value),
+            Self::BytesTx | Self::BytesRx => units::bytes(value.round() as u64),
+            Self::Calls => format!("{value:.1}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Imbalance {
+    pub mean: f64,
+    pub max: f64,
+    // 1.0 is perfectly balanced
+    pub max_over_mean: f64,
+    // coefficient of variation, std dev / mean
+    pub cv: f64,
+}
+
+// indexed by pe. calls and bytes are of the events starting in [start, end],
+// time in calls is clipped to the range so a long call straddling either
+// edge only counts its inside part
+pub fn per_pe(data: &ProfileData, start: f64, end: f64) -> Vec<PeLoad> {
+    let mut loads = vec![PeLoad::default(); data.pe_count as usize];
+    for i in data.overlapping(start, end) {
+        let e = data.event(i);
+        let load = &mut loads[e.source_pe as usize];
+        load.time_in_calls += (e.raw.time + e.raw.duration_sec).min(end) - e.raw.time.max(start);
+        if e.raw.time >= start {
+            load.bytes_tx += e.raw.bytes_tx;
+            load.bytes_rx += e.raw.bytes_rx;
+            load.calls += 1;
+        }
+    }
+    loads
+}
+
+pub fn imbalance(values: &[f64]) -> Imbalance {
+    if values.is_empty() {
+        return Imbalance::default();
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if mean <= 0.0 {
+        return Imbalance {
+            mean,
+            max,
+            ..Default::default()
+        };
+    }
+    let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / n;
+    Imbalance {
+        mean,
+        max,
+        max_over_mean: max / mean,
+        cv: variance.sqrt() / mean,
+    }
+}
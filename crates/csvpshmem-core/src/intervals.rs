@@ -0,0 +1,163 @@
+// finds the events overlapping a time range. events are sorted by start, so
+// the ones starting before the range ends are a prefix; a segment tree of
+// the latest end time under each node skips the parts of that prefix that
+// finished before the range begins
+#[derive(Debug, Default)]
+pub struct IntervalIndex {
+    starts: Vec<f64>,
+    // leaves at [size, size + n), padded with -inf. node k covers 2k and 2k+1
+    max_end: Vec<f64>,
+    size: usize,
+}
+
+impl IntervalIndex {
+    // `spans` are (start, end) in start order
+    pub fn new(spans: impl ExactSizeIterator<Item = (f64, f64)>) -> Self {
+        let n = spans.len();
+        let size = n.next_power_of_two().max(1);
+        let mut starts = Vec::with_capacity(n);
+        let mut max_end = vec![f64::NEG_INFINITY; 2 * size];
+        for (i, (start, end)) in spans.enumerate() {
+            starts.push(start);
+            max_end[size + i] = end.max(start);
+        }
+        for k in (1..size).rev() {
+            max_end[k] = max_end[2 * k].max(max_end[2 * k + 1]);
+        }
+        Self {
+            starts,
+            max_end,
+            size,
+        }
+    }
+
+    // indices of the spans with start <= end and end >= start, ascending
+    pub fn overlapping(&self, start: f64, end: f64) -> Overlapping<'_> {
+        let limit = self.starts.partition_point(|t| *t <= end);
+        Overlapping {
+            index: self,
+            start,
+            limit,
+            stack: if limit > 0 {
+                vec![(1, 0, self.size)]
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    pub fn heap_size(&self) -> usize {
+        (self.starts.capacity() + self.max_end.capacity()) * std::mem::size_of::<f64>()
+    }
+}
+
+pub struct Overlapping<'a> {
+    index: &'a IntervalIndex,
+    start: f64,
+    // spans from here on start after the range
+    limit: usize,
+    // (node, first leaf, leaves) still to visit, leftmost on top
+    stack: Vec<(usize, usize, usize)>,
+}
+
+impl Iterator for Overlapping<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while let Some((node, first, len)) = self.stack.pop() {
+            if first >= self.limit || self.index.max_end[node] < self.start {
+                continue;
+            }
+            if len == 1 {
+                return Some(first);
+            }
+            let half = len / 2;
+            self.stack.push((2 * node + 1, first + half, half));
+            self.stack.push((2 * node, first, half));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(spans: &[(f64, f64)], start: f64, end: f64) -> Vec<usize> {
+        (0..spans.len())
+            .filter(|&i| spans[i].0 <= end && spans[i].1.max(spans[i].0) >= start)
+            .collect()
+    }
+
+    fn check(spans: &[(f64, f64)], queries: &[(f64, f64)]) {
+        let index = IntervalIndex::new(spans.iter().copied());
+        for &(start, end) in queries {
+            let found: Vec<usize> = index.overlapping(start, end).collect();
+            assert_eq!(found, scan(spans, start, end), "{start}..{end}");
+        }
+    }
+
+    #[test]
+    fn empty() {
+        check(&[], &[(0.0, 1.0), (-1.0, -1.0)]);
+    }
+
+    #[test]
+    fn hand_made() {
+        let spans = [
+            (0.0, 10.0),
+            (1.0, 1.0),
+            (2.0, 3.0),
+            (2.5, 2.6),
+            // an end before its start counts as an instant
+            (4.0, 3.0),
+            (5.0, 9.0),
+            (9.5, 12.0),
+        ];
+        check(
+            &spans,
+            &[
+                (-5.0, -1.0),
+                (-1.0, 0.0),
+                (1.0, 1.0),
+                (2.55, 2.55),
+                (3.5, 4.0),
+                (4.0, 4.0),
+                (10.5, 11.0),
+                (12.0, 20.0),
+                (12.5, 20.0),
+                (-100.0, 100.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn random_spans() {
+        // a fixed lcg, the index has no reason to care which numbers
+        let mut state = 12345u64;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        for n in [1, 2, 3, 7, 64, 100, 1000] {
+            let mut spans: Vec<(f64, f64)> = (0..n)
+                .map(|_| {
+                    let start = next() * 100.0;
+                    // mostly short calls, a few long ones
+                    let length = if next() < 0.05 { next() * 50.0 } else { next() };
+                    (start, start + length)
+                })
+                .collect();
+            spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let queries: Vec<(f64, f64)> = (0..50)
+                .map(|_| {
+                    let start = next() * 110.0 - 5.0;
+                    (start, start + next() * next() * 20.0)
+                })
+                .collect();
+            check(&spans, &queries);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::data::ProfileData;
+
+// names shown for PEs instead of their number, e.g. an MPI rank or node:core
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeLabels {
+    // for every PE without a name of its own. {pe}, {host}, {core} (position
+    // among the PEs of the host), {local} (number within its directory of a
+    // merged trace) and {run} (that directory's name) are filled in
+    pub template: String,
+    pub names: BTreeMap<u32, String>,
+    // per PE, empty when it has no label. see resolve()
+    #[serde(skip)]
+    resolved: Vec<String>,
+}
+
+impl PeLabels {
+    // fills in the template for `data`, call again when either changes
+    pub fn resolve(&mut self, data: &ProfileData) {
+        let mut core_of = HashMap::new();
+        let mut per_host: HashMap<&str, u32> = HashMap::new();
+        for pe in 0..data.pe_count {
+            if let Some(host) = data.pe_hostnames.get(&pe) {
+                let n = per_host.entry(host.as_str()).or_default();
+                core_of.insert(pe, *n);
+                *n += 1;
+            }
+        }
+        self.resolved = (0..data.pe_count)
+            .map(|pe| {
+                if let Some(name) = self.names.get(&pe) {
+                    return name.clone();
+                }
+                if self.template.trim().is_empty() {
+                    return String::new();
+                }
+                let run = data
+                    .runs
+                    .iter()
+                    .find(|r| (r.first_pe..r.first_pe + r.pe_count).contains(&pe));
+                let local = run.map_or(pe, |r| pe - r.first_pe);
+                let run_name = run
+                    .and_then(|r| r.dir.file_name())
+                    .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+                self.template
+                    .replace("{pe}", &pe.to_string())
+                    .replace(
+                        "{host}",
+                        data.pe_hostnames.get(&pe).map_or("?", String::as_str),
+                    )
+                    .replace(
+                        "{core}",
+                        &core_of.get(&pe).map_or("?".to_string(), u32::to_string),
+                    )
+                    .replace("{local}", &local.to_string())
+                    .replace("{run}", &run_name)
+            })
+            .collect();
+    }
+
+    pub fn label(&self, pe: u32) -> Option<&str> {
+        self.resolved
+            .get(pe as usize)
+            .map(String::as_str)
+            .filter(|l| !l.is_empty())
+    }
+
+    // "PE 3" unless it has a label
+    pub fn name(&self, pe: u32) -> String {
+        self.label(pe)
+            .map_or_else(|| format!("PE {pe}"), str::to_string)
+    }
+
+    // the bare number unless it has a label, for tight spots like ring nodes
+    pub fn short(&self, pe: u32) -> String {
+        self.label(pe)
+            .map_or_else(|| pe.to_string(), str::to_string)
+    }
+}
+
+// a mapping file has one PE per line, `<pe> <label>`, `<pe>,<label>` or
+// `<pe> = <label>`. blank lines and lines starting with # are skipped
+pub fn load_names(path: &Path) -> Result<BTreeMap<u32, String>> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let mut names = BTreeMap::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((pe, label)) =
+            line.split_once(|c: char| c == ',' || c == '=' || c.is_whitespace())
+        else {
+            bail!("{}:{}: expected `<pe> <label>`", path.display(), n + 1);
+        };
+        let Ok(pe) = pe.trim().parse::<u32>() else {
+            bail!(
+                "{}:{}: `{}` is not a PE number",
+                path.display(),
+                n + 1,
+                pe.trim()
+            );
+        };
+        let label = label.trim().trim_start_matches(['=', ',']).trim();
+        names.insert(pe, label.to_string());
+    }
+    Ok(names)
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use crate::data::ProfileData;
+
+// tail of a set of durations (or any metric), nearest rank
+#[derive(Debug, Clone, Copy)]
+pub struct Percentiles {
+    pub count: usize,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub p999: f64,
+    pub max: f64,
+}
+
+impl Percentiles {
+    // sorts `values` in place, None when it's empty
+    pub fn of(values: &mut [f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        let n = values.len();
+        let at = |p: f64| values[((p * n as f64).ceil() as usize).clamp(1, n) - 1];
+        Some(Self {
+            count: n,
+            p50: at(0.5),
+            p95: at(0.95),
+            p99: at(0.99),
+            p999: at(0.999),
+            max: values[n - 1],
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyBy {
+    Function,
+    // source and target PE
+    Pair,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LatencyKey {
+    Function(String),
+    Pair(u32, u32),
+}
+
+pub type LatencyTable = Vec<(LatencyKey, Percentiles)>;
+
+// percentiles of the durations of calls with a target PE (puts, gets,
+// atomics...) starting in [start, end], worst p99 first
+pub fn table(data: &ProfileData, start: f64, end: f64, by: LatencyBy) -> LatencyTable {
+    let first = data.events.time.partition_point(|&t| t < start);
+    let calls = data
+        .range(first..)
+        .take_while(|e| e.raw.time <= end)
+        .filter(|e| e.raw.target_pe >= 0);
+    // keyed by &str while collecting, so only the final rows allocate
+    let mut by_function: HashMap<&str, Vec<f64>> = HashMap::new();
+    let mut by_pair: HashMap<(u32, u32), Vec<f64>> = HashMap::new();
+    for e in calls {
+        let d = e.raw.duration_sec;
+        match by {
+            LatencyBy::Function => by_function.entry(e.raw.function).or_default().push(d),
+            LatencyBy::Pair => by_pair
+                .entry((e.source_pe, e.raw.target_pe as u32))
+                .or_default()
+                .push(d),
+        }
+    }
+    let functions = by_function
+        .into_iter()
+        .map(|(f, d)| (LatencyKey::Function(f.to_string()), d));
+    let pairs = by_pair
+        .into_iter()
+        .map(|((src, dst), d)| (LatencyKey::Pair(src, dst), d));
+    let mut rows: Vec<_> = functions
+        .chain(pairs)
+        .filter_map(|(key, mut d)| Some((key, Percentiles::of(&mut d)?)))
+        .collect();
+    rows.sort_by(|a, b| b.1.p99.total_cmp(&a.1.p99));
+    rows
+}
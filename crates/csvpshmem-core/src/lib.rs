@@ -0,0 +1,77 @@
+//! Loading, indexing and analysis of csvpshmem traces, the
+//! `pperf.PE[.CTX].csv` files written by the profiler, without any UI.
+//!
+//! The viewer is one frontend over this crate; command line tools and tests
+//! can use it the same way:
+//!
+//! ```no_run
+//! use csvpshmem_core::data::ProfileData;
+//! use csvpshmem_core::stats;
+//!
+//! let data = ProfileData::load_from_dir("trace".as_ref())?;
+//! for f in stats::by_call_site(&data, data.min_time, data.max_time, |_| true) {
+//!     println!("{} {} calls {:.3}s", f.function, f.calls, f.total_time);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! [`data::ProfileData`] holds every event of a trace in time order, one
+//! column per field with function names and traces interned, and an interval
+//! index for range queries. The other modules build on it:
+//!
+//! - loading and indexing: [`data`], [`cache`] (parsed traces saved for
+//!   the next load), [`parquet`] (traces in one Parquet file),
+//!   [`manifest`] (job size and hosts from `pperf.meta.json`),
+//!   [`intervals`], [`skew`] (clock offsets between PEs), [`trim`]
+//!   (cutting a trace down to a range)
+//! - communication: [`prefix`] (prefix sums of bytes per PE and pair),
+//!   [`bandwidth`], [`asymmetry`], [`matching`] (pairing sends with
+//!   receives), [`completion`] (when puts and gets land), [`scatter`]
+//!   (alpha-beta fits), [`topology`] and [`contention`] (link load),
+//!   [`rundiff`] (two merged runs compared pair by pair)
+//! - time in calls: [`stats`], [`latency`], [`metrics`], [`imbalance`],
+//!   [`stragglers`], [`anomalies`], [`phases`], [`gantt`], [`activity`]
+//!   (PEs in each function over time)
+//! - naming and lookup: [`grouping`] (function groups), [`labels`] (PE
+//!   names), [`search`], [`sql`] (queries over the events), [`bookmarks`],
+//!   [`units`] (human readable numbers)
+//! - [`derived`]: the analyses above that a frontend keeps per loaded
+//!   trace, rebuilt together
+//! - [`synthetic`]: made up traces for demos, benchmarks and tests
+//!
+//! Times are trace seconds as `f64`, PEs are `u32` and events are referred
+//! to by their index in [`data::ProfileData::events`].
+
+pub mod activity;
+pub mod anomalies;
+pub mod asymmetry;
+pub mod bandwidth;
+pub mod bookmarks;
+pub mod cache;
+pub mod completion;
+pub mod contention;
+pub mod data;
+pub mod derived;
+pub mod gantt;
+pub mod grouping;
+pub mod imbalance;
+pub mod intervals;
+pub mod labels;
+pub mod latency;
+pub mod manifest;
+pub mod matching;
+pub mod metrics;
+pub mod parquet;
+pub mod phases;
+pub mod prefix;
+pub mod rundiff;
+pub mod scatter;
+pub mod search;
+pub mod skew;
+pub mod sql;
+pub mod stats;
+pub mod stragglers;
+pub mod synthetic;
+pub mod topology;
+pub mod trim;
+pub mod units;
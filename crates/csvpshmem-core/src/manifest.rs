@@ -0,0 +1,46 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// pperf.meta.json, written next to the csv files by whatever launched the
+// job, e.g.
+//
+//     {"pe_count": 4, "hostnames": ["n0", "n0", "n1", "n1"]}
+//
+// the job size isn't guessed from the highest file then, so PEs that left
+// no file still get a track, and hosts don't have to be in the Extra of
+// every first event
+pub const FILE_NAME: &str = "pperf.meta.json";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Manifest {
+    // PEs in the job, one per hostname when left out
+    pub pe_count: Option<u32>,
+    // by PE
+    pub hostnames: Vec<String>,
+}
+
+impl Manifest {
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(FILE_NAME)
+    }
+
+    // the manifest of the trace in `dir`, if it has one
+    pub fn read(dir: &Path) -> Result<Option<Self>> {
+        let path = Self::path(dir);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let text =
+            fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        let manifest =
+            serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+        Ok(Some(manifest))
+    }
+
+    pub fn pe_count(&self) -> u32 {
+        self.pe_count.unwrap_or(self.hostnames.len() as u32)
+    }
+}
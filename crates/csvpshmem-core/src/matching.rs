@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use crate::data::ProfileData;
+use crate::latency::Percentiles;
+
+// a call that sent bytes paired with the call on the target PE that
+// received them
+#[derive(Debug, Clone, Copy)]
+pub struct Message {
+    pub send: usize,
+    pub recv: usize,
+    pub src: u32,
+    pub dst: u32,
+    pub bytes: u64,
+    // from the send starting to the receive completing, in trace seconds.
+    // negative means the clocks of the two PEs disagree
+    pub latency: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct Matching {
+    // in send order
+    pub messages: Vec<Message>,
+    // event -> index into messages, for either end
+    by_event: HashMap<usize, usize>,
+    pub unmatched_sends: usize,
+    pub unmatched_recvs: usize,
+}
+
+// latency of the messages from one PE to another
+#[derive(Debug, Clone)]
+pub struct PairLatency {
+    pub src: u32,
+    pub dst: u32,
+    pub bytes: u64,
+    pub latency: Percentiles,
+    // index into `Matching::messages`
+    pub slowest: usize,
+}
+
+impl Matching {
+    pub fn of(&self, event: usize) -> Option<&Message> {
+        self.by_event.get(&event).map(|&m| &self.messages[m])
+    }
+
+    pub fn latency(&self) -> Option<Percentiles> {
+        let mut all: Vec<f64> = self.messages.iter().map(|m| m.latency).collect();
+        Percentiles::of(&mut all)
+    }
+
+    // slowest pairs (by p99) first
+    pub fn by_pair(&self) -> Vec<PairLatency> {
+        let mut pairs: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+        for (m, message) in self.messages.iter().enumerate() {
+            pairs.entry((message.src, message.dst)).or_default().push(m);
+        }
+        let mut rows: Vec<PairLatency> = pairs
+            .into_iter()
+            .filter_map(|((src, dst), messages)| {
+                let mut latencies: Vec<f64> =
+                    messages.iter().map(|&m| self.messages[m].latency).collect();
+                let slowest = *messages.iter().max_by(|&&a, &&b| {
+                    self.messages[a]
+                        .latency
+                        .total_cmp(&self.messages[b].latency)
+                })?;
+                Some(PairLatency {
+                    src,
+                    dst,
+                    bytes: messages.iter().map(|&m| self.messages[m].bytes).sum(),
+                    latency: Percentiles::of(&mut latencies)?,
+                    slowest,
+                })
+            })
+            .collect();
+        rows.sort_by(|a, b| {
+            b.latency
+                .p99
+                .total_cmp(&a.latency.p99)
+                .then((a.src, a.dst).cmp(&(b.src, b.dst)))
+        });
+        rows
+    }
+}
+
+// pairs sends (bytes_tx > 0 towards a target) with receives on that target
+// from the sender (bytes_rx > 0 towards the sender) moving the same number of
+// bytes. a receive qualifies if it ends after the send starts and starts at
+// most `max_gap` seconds after the send ends. among those, matching
+// function names (send / recv, put / get...) win, then the closest end times
+pub fn match_messages(data: &ProfileData, max_gap: f64) -> Matching {
+    // (sender, receiver) -> calls in time order
+    let mut sends: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    let mut recvs: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for (i, e) in data.iter().enumerate() {
+        if e.raw.target_pe < 0 || e.raw.target_pe as u32 == e.source_pe {
+            continue;
+        }
+        let (pe, target) = (e.source_pe, e.raw.target_pe as u32);
+        if e.raw.bytes_tx > 0 {
+            sends.entry((pe, target)).or_default().push(i);
+        }
+        if e.raw.bytes_rx > 0 {
+            recvs.entry((target, pe)).or_default().push(i);
+        }
+    }
+
+    let mut matching = Matching::default();
+    let end = |i: usize| data.event(i).raw.time + data.event(i).raw.duration_sec;
+    let mut pairs: Vec<_> = sends.into_iter().collect();
+    pairs.sort_by_key(|(pair, _)| *pair);
+    for (pair, sends) in pairs {
+        let Some(recvs) = recvs.get(&pair) else {
+            matching.unmatched_sends += sends.len();
+            continue;
+        };
+        let mut taken = vec![false; recvs.len()];
+        // receives before this index are taken or ended before the current
+        // send started, so later sends can't use them either
+        let mut first = 0;
+        for &s in &sends {
+            let send = data.event(s).raw;
+            let send_end = end(s);
+            while first < recvs.len() && (taken[first] || end(recvs[first]) < send.time) {
+                first += 1;
+            }
+            let best = (first..recvs.len())
+                .take_while(|&k| data.event(recvs[k]).raw.time <= send_end + max_gap)
+                .filter(|&k| {
+                    let r = recvs[k];
+                    !taken[k] && end(r) >= send.time && data.event(r).raw.bytes_rx == send.bytes_tx
+                })
+                .min_by(|&a, &b| {
+                    let key = |k: usize| {
+                        let r = recvs[k];
+                        (
+                            !complements(send.function, data.event(r).raw.function),
+                            (end(r) - send_end).abs(),
+                        )
+                    };
+                    let (a, b) = (key(a), key(b));
+                    a.0.cmp(&b.0).then(a.1.total_cmp(&b.1))
+                });
+            let Some(k) = best else {
+                matching.unmatched_sends += 1;
+                continue;
+            };
+            taken[k] = true;
+            let r = recvs[k];
+            matching.messages.push(Message {
+                send: s,
+                recv: r,
+                src: pair.0,
+                dst: pair.1,
+                bytes: send.bytes_tx,
+                latency: end(r) - send.time,
+            });
+        }
+    }
+    matching.unmatched_recvs =
+        recvs.values().map(Vec::len).sum::<usize>() - matching.messages.len();
+    matching.messages.sort_by_key(|m| m.send);
+    for (m, message) in matching.messages.iter().enumerate() {
+        matching.by_event.insert(message.send, m);
+        matching.by_event.insert(message.recv, m);
+    }
+    matching
+}
+
+// whether two function names look like the two ends of one transfer
+fn complements(send: &str, recv: &str) -> bool {
+    const PAIRS: [(&str, &str); 4] = [
+        ("send", "recv"),
+        ("put", "get"),
+        ("put", "wait"),
+        ("signal", "wait"),
+    ];
+    let (send, recv) = (send.to_ascii_lowercase(), recv.to_ascii_lowercase());
+    send == recv
+        || PAIRS
+            .iter()
+            .any(|(s, r)| send.contains(s) && recv.contains(r) && send.replace(s, r) == recv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::event;
+
+    // (time, function, duration, target, rx, tx)
+
+    // index of the event of `pe` at `time`
+    fn find(data: &ProfileData, pe: u32, time: f64) -> usize {
+        data.iter()
+            .position(|e| e.source_pe == pe && e.raw.time == time)
+            .unwrap()
+    }
+
+    #[test]
+    fn put_meets_get() {
+        let data = ProfileData::from_pes(vec![
+            vec![event(1.0, "shmem_putmem", 0.1, 1, 0, 64)],
+            vec![event(1.05, "shmem_getmem", 0.1, 0, 64, 0)],
+        ]);
+        let matching = match_messages(&data, 0.01);
+        assert_eq!(matching.messages.len(), 1);
+        let m = matching.messages[0];
+        assert_eq!((m.src, m.dst, m.bytes), (0, 1, 64));
+        assert_eq!(m.send, find(&data, 0, 1.0));
+        assert_eq!(m.recv, find(&data, 1, 1.05));
+        assert!((m.latency - 0.15).abs() < 1e-9);
+        assert_eq!(matching.of(m.recv).map(|m| m.send), Some(m.send));
+        assert_eq!((matching.unmatched_sends, matching.unmatched_recvs), (0, 0));
+    }
+
+    #[test]
+    fn sizes_and_gaps_must_fit() {
+        let data = ProfileData::from_pes(vec![
+            vec![
+                event(1.0, "shmem_putmem", 0.1, 1, 0, 64),
+                event(5.0, "shmem_putmem", 0.1, 1, 0, 8),
+            ],
+            vec![
+                // wrong size
+                event(1.0, "shmem_getmem", 0.1, 0, 32, 0),
+                // starts too long after the send ended
+                event(5.5, "shmem_getmem", 0.1, 0, 8, 0),
+            ],
+        ]);
+        let matching = match_messages(&data, 0.1);
+        assert!(matching.messages.is_empty());
+        assert_eq!((matching.unmatched_sends, matching.unmatched_recvs), (2, 2));
+        assert_eq!(match_messages(&data, 1.0).messages.len(), 1);
+    }
+
+    #[test]
+    fn complementary_names_win() {
+        let data = ProfileData::from_pes(vec![
+            vec![event(1.0, "shmem_putmem", 0.1, 1, 0, 64)],
+            vec![
+                // ends closer to the send, but isn't its other half
+                event(1.0, "shmem_barrier", 0.1, 0, 64, 0),
+                event(1.05, "shmem_getmem", 0.5, 0, 64, 0),
+            ],
+        ]);
+        let matching = match_messages(&data, 0.1);
+        assert_eq!(matching.messages.len(), 1);
+        assert_eq!(matching.messages[0].recv, find(&data, 1, 1.05));
+        assert_eq!(matching.unmatched_recvs, 1);
+    }
+
+    #[test]
+    fn a_receive_is_used_once() {
+        let data = ProfileData::from_pes(vec![
+            vec![
+                event(1.0, "send", 0.1, 1, 0, 16),
+                event(1.01, "send", 0.1, 1, 0, 16),
+            ],
+            vec![event(1.02, "recv", 0.1, 0, 16, 0)],
+        ]);
+        let matching = match_messages(&data, 0.1);
+        assert_eq!(matching.messages.len(), 1);
+        assert_eq!(matching.unmatched_sends, 1);
+        assert!(matching.of(find(&data, 0, 1.01)).is_none());
+    }
+
+    #[test]
+    fn local_and_collective_calls_are_skipped() {
+        let data = ProfileData::from_pes(vec![
+            vec![
+                event(1.0, "shmem_putmem", 0.1, 0, 0, 64),
+                event(2.0, "shmem_broadcastmem", 0.1, -1, 0, 64),
+            ],
+            vec![event(1.0, "shmem_getmem", 0.1, 1, 64, 0)],
+        ]);
+        let matching = match_messages(&data, 1.0);
+        assert!(matching.messages.is_empty());
+        assert_eq!((matching.unmatched_sends, matching.unmatched_recvs), (0, 0));
+    }
+
+    #[test]
+    fn slowest_pair_first() {
+        let data = ProfileData::from_pes(vec![
+            vec![
+                event(1.0, "put", 0.1, 1, 0, 8),
+                event(2.0, "put", 0.1, 2, 0, 8),
+            ],
+            vec![event(1.0, "get", 0.1, 0, 8, 0)],
+            vec![event(2.0, "get", 0.9, 0, 8, 0)],
+        ]);
+        let pairs = match_messages(&data, 0.1).by_pair();
+        let order: Vec<_> = pairs.iter().map(|p| (p.src, p.dst)).collect();
+        assert_eq!(order, [(0, 2), (0, 1)]);
+    }
+
+    #[test]
+    fn function_pairs() {
+        assert!(complements("shmem_putmem", "shmem_getmem"));
+        assert!(complements("MPI_Send", "MPI_Recv"));
+        assert!(complements("shmem_put_signal", "shmem_put_signal"));
+        assert!(!complements("shmem_putmem", "shmem_barrier"));
+    }
+}
@@ -0,0 +1,473 @@
+use anyhow::{Result, anyhow, bail};
+use std::collections::HashMap;
+
+use crate::data::{Event, ProfileData};
+use crate::grouping::Grouping;
+use crate::latency::Percentiles;
+
+// user defined per-event quantities, e.g.
+//   bytes_tx / duration_sec
+//   rx + tx grouped by function
+#[derive(Debug, Clone)]
+pub struct Metric {
+    pub source: String,
+    pub expr: Expr,
+    pub group_by: Option<GroupBy>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Number(f64),
+    Field(String),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Function,
+    // function after the grouping rules
+    Group,
+    Pe,
+    TargetPe,
+    Host,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    Sum,
+    Mean,
+    Max,
+}
+
+// one line of the stats table
+#[derive(Debug, Clone)]
+pub struct MetricRow {
+    pub group: String,
+    pub count: usize,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl MetricRow {
+    pub fn mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+}
+
+// per group, one value per time bin (NaN where the bin had no events)
+#[derive(Debug, Clone)]
+pub struct MetricSeries {
+    pub group: String,
+    pub values: Vec<f64>,
+}
+
+impl Metric {
+    pub fn parse(source: &str) -> Result<Self> {
+        let (expr_src, group_by) = split_group_by(source)?;
+        let tokens = tokenize(expr_src)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.expr()?;
+        if let Some(tok) = parser.tokens.get(parser.pos) {
+            bail!("unexpected {:?}", tok);
+        }
+        Ok(Self {
+            source: source.trim().to_string(),
+            expr,
+            group_by,
+        })
+    }
+
+    // None when the event lacks a referenced field or the result isn't finite
+    pub fn eval(&self, e: &Event) -> Option<f64> {
+        eval(&self.expr, e).filter(|v| v.is_finite())
+    }
+
+    pub fn group_of(&self, data: &ProfileData, grouping: &Grouping, e: &Event) -> String {
+        match self.group_by {
+            None => "all".to_string(),
+            Some(GroupBy::Function) => e.raw.function.to_string(),
+            Some(GroupBy::Group) => grouping.group(e.raw.function).to_string(),
+            Some(GroupBy::Pe) => format!("PE {}", e.source_pe),
+            Some(GroupBy::TargetPe) => format!("PE {}", e.raw.target_pe),
+            Some(GroupBy::Host) => data
+                .pe_hostnames
+                .get(&e.source_pe)
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+fn split_group_by(source: &str) -> Result<(&str, Option<GroupBy>)> {
+    let lower = source.to_ascii_lowercase();
+    let Some(idx) = lower.rfind(" by ") else {
+        return Ok((source, None));
+    };
+    let key = lower[idx + 4..].trim();
+    let group_by = match key {
+        "function" | "func" => GroupBy::Function,
+        "group" => GroupBy::Group,
+        "pe" | "source_pe" => GroupBy::Pe,
+        "target_pe" | "target" => GroupBy::TargetPe,
+        "host" | "hostname" => GroupBy::Host,
+        _ => bail!("can't group by '{key}' (function, group, pe, target_pe or host)"),
+    };
+    let mut expr = source[..idx].trim_end();
+    if expr.to_ascii_lowercase().ends_with("grouped") {
+        expr = expr[..expr.len() - "grouped".len()].trim_end();
+    }
+    Ok((expr, Some(group_by)))
+}
+
+fn eval(expr: &Expr, e: &Event) -> Option<f64> {
+    Some(match expr {
+        Expr::Number(n) => *n,
+        Expr::Field(name) => field(name, e)?,
+        Expr::Neg(inner) => -eval(inner, e)?,
+        Expr::Binary(op, lhs, rhs) => {
+            let (a, b) = (eval(lhs, e)?, eval(rhs, e)?);
+            match op {
+                Op::Add => a + b,
+                Op::Sub => a - b,
+                Op::Mul => a * b,
+                Op::Div => a / b,
+            }
+        }
+    })
+}
+
+fn field(name: &str, e: &Event) -> Option<f64> {
+    let raw = &e.raw;
+    Some(match name {
+        "time" => raw.time,
+        "duration" | "duration_sec" => raw.duration_sec,
+        "tx" | "bytes_tx" => raw.bytes_tx as f64,
+        "rx" | "bytes_rx" => raw.bytes_rx as f64,
+        "bytes" => (raw.bytes_tx + raw.bytes_rx) as f64,
+        "pe" | "source_pe" => e.source_pe as f64,
+        "target_pe" => raw.target_pe as f64,
+        // anything else is looked up among the Extra counters
+        _ => raw
+            .extra_fields()
+            .find(|(k, _)| *k == name)
+            .and_then(|(_, v)| v.parse().ok())?,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    Open,
+    Close,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.' || c == 'e') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let text = &src[start..end];
+            let n = text.parse().map_err(|_| anyhow!("bad number '{text}'"))?;
+            tokens.push(Token::Number(n));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Ident(src[start..end].to_ascii_lowercase()));
+        } else {
+            chars.next();
+            tokens.push(match c {
+                '+' | '-' | '*' | '/' => Token::Op(c),
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => bail!("unexpected '{c}'"),
+            });
+        }
+    }
+    if tokens.is_empty() {
+        bail!("empty expression");
+    }
+    Ok(tokens)
+}
+
+// expr   := term (('+' | '-') term)*
+// term   := unary (('*' | '/') unary)*
+// unary  := '-' unary | atom
+// atom   := number | ident | '(' expr ')'
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn peek_op(&self, ops: &[char]) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(c)) if ops.contains(c) => Some(*c),
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut lhs = self.term()?;
+        while let Some(c) = self.peek_op(&['+', '-']) {
+            self.pos += 1;
+            let op = if c == '+' { Op::Add } else { Op::Sub };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        while let Some(c) = self.peek_op(&['*', '/']) {
+            self.pos += 1;
+            let op = if c == '*' { Op::Mul } else { Op::Div };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.peek_op(&['-']).is_some() {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Ident(name)) => Ok(Expr::Field(name)),
+            Some(Token::Open) => {
+                let inner = self.expr()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => bail!("missing ')'"),
+                }
+            }
+            Some(tok) => bail!("unexpected {:?}", tok),
+            None => bail!("expression ends early"),
+        }
+    }
+}
+
+fn in_range(data: &ProfileData, start: f64, end: f64) -> impl Iterator<Item = Event<'_>> {
+    let first = data.events.time.partition_point(|&t| t < start);
+    let last = data.events.time.partition_point(|&t| t <= end);
+    data.range(first..last)
+}
+
+// stats per group for events starting in [start, end], largest sum first
+pub fn table(
+    data: &ProfileData,
+    grouping: &Grouping,
+    metric: &Metric,
+    start: f64,
+    end: f64,
+) -> Vec<MetricRow> {
+    let mut rows: HashMap<String, MetricRow> = HashMap::new();
+    for e in in_range(data, start, end) {
+        let Some(v) = metric.eval(&e) else {
+            continue;
+        };
+        let group = metric.group_of(data, grouping, &e);
+        let row = rows.entry(group.clone()).or_insert(MetricRow {
+            group,
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        });
+        row.count += 1;
+        row.sum += v;
+        row.min = row.min.min(v);
+        row.max = row.max.max(v);
+    }
+    let mut rows: Vec<MetricRow> = rows.into_values().collect();
+    rows.sort_by(|a, b| b.sum.total_cmp(&a.sum).then_with(|| a.group.cmp(&b.group)));
+    rows
+}
+
+// the metric binned over [start, end], one series per group
+pub fn series(
+    data: &ProfileData,
+    grouping: &Grouping,
+    metric: &Metric,
+    start: f64,
+    end: f64,
+    bins: usize,
+    aggregation: Aggregation,
+) -> Vec<MetricSeries> {
+    let bins = bins.max(1);
+    let bin_width = (end - start).max(f64::EPSILON) / bins as f64;
+    // group -> per bin (sum, count, max)
+    let mut acc: HashMap<String, Vec<(f64, usize, f64)>> = HashMap::new();
+    for e in in_range(data, start, end) {
+        let Some(v) = metric.eval(&e) else {
+            continue;
+        };
+        let bin = (((e.raw.time - start) / bin_width) as usize).min(bins - 1);
+        let slots = acc
+            .entry(metric.group_of(data, grouping, &e))
+            .or_insert_with(|| vec![(0.0, 0, f64::NEG_INFINITY); bins]);
+        let slot = &mut slots[bin];
+        slot.0 += v;
+        slot.1 += 1;
+        slot.2 = slot.2.max(v);
+    }
+    let mut series: Vec<MetricSeries> = acc
+        .into_iter()
+        .map(|(group, slots)| MetricSeries {
+            group,
+            values: slots
+                .into_iter()
+                .map(|(sum, count, max)| match (count, aggregation) {
+                    (0, _) => f64::NAN,
+                    (_, Aggregation::Sum) => sum,
+                    (_, Aggregation::Mean) => sum / count as f64,
+                    (_, Aggregation::Max) => max,
+                })
+                .collect(),
+        })
+        .collect();
+    series.sort_by(|a, b| a.group.cmp(&b.group));
+    series
+}
+
+// percentiles of the metric over all events in each time bin, for bands
+// behind the per group lines
+pub fn percentile_bands(
+    data: &ProfileData,
+    metric: &Metric,
+    start: f64,
+    end: f64,
+    bins: usize,
+) -> Vec<Option<Percentiles>> {
+    let bins = bins.max(1);
+    let bin_width = (end - start).max(f64::EPSILON) / bins as f64;
+    let mut values = vec![Vec::new(); bins];
+    for e in in_range(data, start, end) {
+        if let Some(v) = metric.eval(&e).filter(|v| v.is_finite()) {
+            let bin = (((e.raw.time - start) / bin_width) as usize).min(bins - 1);
+            values[bin].push(v);
+        }
+    }
+    values.iter_mut().map(|v| Percentiles::of(v)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::RawEvent;
+    use crate::synthetic::event;
+
+    fn trace() -> ProfileData {
+        ProfileData::from_pes(vec![vec![RawEvent {
+            extra: Some("heap=1024;pending=x".to_string()),
+            ..event(1.0, "shmem_putmem", 0.5, 3, 10, 30)
+        }]])
+    }
+
+    fn value(source: &str) -> Option<f64> {
+        let data = trace();
+        Metric::parse(source).unwrap().eval(&data.event(0))
+    }
+
+    fn error(source: &str) -> String {
+        Metric::parse(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(value("1 + 2 * 3"), Some(7.0));
+        assert_eq!(value("(1 + 2) * 3"), Some(9.0));
+        assert_eq!(value("10 - 4 - 3"), Some(3.0));
+        assert_eq!(value("8 / 4 / 2"), Some(1.0));
+        assert_eq!(value("-2 * 3 + 1"), Some(-5.0));
+        assert_eq!(value("--2"), Some(2.0));
+        assert_eq!(value("2 * -(3 - 1)"), Some(-4.0));
+        assert_eq!(value("1.5e2 + .5"), Some(150.5));
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(value("bytes_tx / duration"), Some(60.0));
+        assert_eq!(value("TX + rx"), Some(40.0));
+        assert_eq!(value("bytes"), Some(40.0));
+        assert_eq!(value("target_pe - pe + time"), Some(4.0));
+        // Extra counters, missing or unparsable ones leave the event out
+        assert_eq!(value("heap / 1024"), Some(1.0));
+        assert_eq!(value("pending"), None);
+        assert_eq!(value("queue + 1"), None);
+        // so does anything that isn't finite
+        assert_eq!(value("tx / 0"), None);
+    }
+
+    #[test]
+    fn group_by() {
+        let by = |source: &str| Metric::parse(source).unwrap().group_by;
+        assert_eq!(by("tx"), None);
+        assert_eq!(by("tx grouped by function"), Some(GroupBy::Function));
+        assert_eq!(by("tx + rx by PE"), Some(GroupBy::Pe));
+        assert_eq!(by("tx by target"), Some(GroupBy::TargetPe));
+        assert_eq!(by("duration grouped by hostname"), Some(GroupBy::Host));
+        assert_eq!(by("tx by group"), Some(GroupBy::Group));
+        let metric = Metric::parse(" tx * 2  grouped by function").unwrap();
+        assert_eq!(metric.source, "tx * 2  grouped by function");
+        let data = trace();
+        assert_eq!(metric.eval(&data.event(0)), Some(60.0));
+        assert_eq!(
+            metric.group_of(&data, &Grouping::default(), &data.event(0)),
+            "shmem_putmem"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error(""), "empty expression");
+        assert_eq!(error("(tx + rx"), "missing ')'");
+        assert_eq!(error("tx +"), "expression ends early");
+        assert_eq!(error("tx rx"), "unexpected Ident(\"rx\")");
+        assert_eq!(error("tx + )"), "unexpected Close");
+        assert_eq!(error("tx % 2"), "unexpected '%'");
+        assert_eq!(error("1.2.3"), "bad number '1.2.3'");
+        assert_eq!(
+            error("tx by colour"),
+            "can't group by 'colour' (function, group, pe, target_pe or host)"
+        );
+    }
+}
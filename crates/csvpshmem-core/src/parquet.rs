@@ -0,0 +1,417 @@
+use anyhow::{Context, Result, bail};
+use arrow_array::{
+    Array, ArrayRef, Float64Array, Int32Array, Int64Array, RecordBatch, StringArray,
+};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::data::{ProfileData, RawEvent};
+
+// events as parquet: the csv columns plus Source_PE (and Context for the
+// threads of a PE), one row per call. files written here are zstd
+// compressed, reading takes whatever the parquet crate does as long as the
+// columns are flat
+
+// rows per record batch, both ways
+const BATCH_ROWS: usize = 64 * 1024;
+
+pub fn is_parquet_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("parquet"))
+}
+
+// (name, type, nullable) of the columns written, in file order
+const COLUMNS: [(&str, DataType, bool); 11] = [
+    ("Time", DataType::Float64, false),
+    ("Function", DataType::Utf8, false),
+    ("Duration_Sec", DataType::Float64, false),
+    ("Target_PE", DataType::Int32, false),
+    ("Bytes_RX", DataType::Int64, false),
+    ("Bytes_TX", DataType::Int64, false),
+    ("Stacktrace", DataType::Utf8, false),
+    ("Extra", DataType::Utf8, true),
+    ("Symboltrace", DataType::Utf8, true),
+    ("Source_PE", DataType::Int32, false),
+    ("Context", DataType::Int32, true),
+];
+
+// writes every event of `data` to `path` with a .parquet extension. times
+// are the ones in the files, before clock skew correction, and PEs are
+// numbered as on the timeline
+pub fn write(data: &ProfileData, path: &Path) -> Result<PathBuf> {
+    let path = path.with_extension("parquet");
+    let file = fs::File::create(&path).with_context(|| format!("writing {}", path.display()))?;
+    let schema = Arc::new(Schema::new(
+        COLUMNS
+            .iter()
+            .map(|(name, kind, nullable)| Field::new(*name, kind.clone(), *nullable))
+            .collect::<Vec<_>>(),
+    ));
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .set_created_by("csvpshmem-viewer".to_string())
+        .build();
+    let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+
+    let n = data.events.len();
+    for first in (0..n).step_by(BATCH_ROWS) {
+        let events: Vec<_> = (first..(first + BATCH_ROWS).min(n))
+            .map(|i| data.event(i))
+            .collect();
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(Float64Array::from_iter_values(
+                events
+                    .iter()
+                    .map(|e| e.raw.time - data.clock_offset(e.source_pe)),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                events.iter().map(|e| e.raw.function),
+            )),
+            Arc::new(Float64Array::from_iter_values(
+                events.iter().map(|e| e.raw.duration_sec),
+            )),
+            Arc::new(Int32Array::from_iter_values(
+                events.iter().map(|e| e.raw.target_pe),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                events.iter().map(|e| e.raw.bytes_rx as i64),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                events.iter().map(|e| e.raw.bytes_tx as i64),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                events.iter().map(|e| e.raw.stacktrace),
+            )),
+            Arc::new(StringArray::from_iter(events.iter().map(|e| e.raw.extra))),
+            Arc::new(StringArray::from_iter(
+                events.iter().map(|e| e.raw.symboltrace),
+            )),
+            Arc::new(Int32Array::from_iter_values(
+                events.iter().map(|e| e.source_pe as i32),
+            )),
+            Arc::new(Int32Array::from_iter(
+                events.iter().map(|e| e.context.map(|c| c as i32)),
+            )),
+        ];
+        writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+    }
+    writer.close()?;
+    Ok(path)
+}
+
+// (pe, context, row) for every row of a parquet file, in file order
+pub fn read(path: &Path) -> Result<Vec<(u32, Option<u32>, RawEvent)>> {
+    let file = fs::File::open(path).with_context(|| format!("reading {}", path.display()))?;
+    read_file(file).with_context(|| format!("reading {}", path.display()))
+}
+
+fn read_file(file: fs::File) -> Result<Vec<(u32, Option<u32>, RawEvent)>> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+    let schema = builder.schema().clone();
+    for field in schema.fields() {
+        if field.data_type().is_nested() {
+            bail!(
+                "column {} is nested, only flat schemas are supported",
+                field.name()
+            );
+        }
+    }
+    for required in ["Time", "Function", "Source_PE"] {
+        if schema.column_with_name(required).is_none() {
+            bail!("no {required} column");
+        }
+    }
+
+    let mut rows = Vec::new();
+    for batch in builder.with_batch_size(BATCH_ROWS).build()? {
+        let batch = batch?;
+        // every column as the widest type of its kind, whatever the writer
+        // picked; ints that don't fit, like a u64 past i64::MAX, become nulls
+        let column = |name: &str, kind: &DataType| -> Result<Option<ArrayRef>> {
+            let Some(array) = batch.column_by_name(name) else {
+                return Ok(None);
+            };
+            let cast = arrow_cast::cast(array, kind).with_context(|| format!("column {name}"))?;
+            Ok(Some(cast))
+        };
+        let ints = |name| -> Result<Option<Int64Array>> {
+            let array = column(name, &DataType::Int64)?;
+            Ok(array.map(|a| a.as_any().downcast_ref::<Int64Array>().unwrap().clone()))
+        };
+        let floats = |name| -> Result<Option<Float64Array>> {
+            let array = column(name, &DataType::Float64)?;
+            Ok(array.map(|a| a.as_any().downcast_ref::<Float64Array>().unwrap().clone()))
+        };
+        let strings = |name| -> Result<Option<StringArray>> {
+            let array = column(name, &DataType::Utf8)?;
+            Ok(array.map(|a| a.as_any().downcast_ref::<StringArray>().unwrap().clone()))
+        };
+        let (pes, contexts, targets) = (ints("Source_PE")?, ints("Context")?, ints("Target_PE")?);
+        let (rx, tx) = (ints("Bytes_RX")?, ints("Bytes_TX")?);
+        let (times, durations) = (floats("Time")?, floats("Duration_Sec")?);
+        let (functions, stacktraces) = (strings("Function")?, strings("Stacktrace")?);
+        let (extras, symboltraces) = (strings("Extra")?, strings("Symboltrace")?);
+
+        let int =
+            |c: &Option<Int64Array>, i| c.as_ref().filter(|c| c.is_valid(i)).map(|c| c.value(i));
+        let float =
+            |c: &Option<Float64Array>, i| c.as_ref().filter(|c| c.is_valid(i)).map(|c| c.value(i));
+        let string = |c: &Option<StringArray>, i| {
+            c.as_ref()
+                .filter(|c| c.is_valid(i))
+                .map(|c| c.value(i).to_string())
+        };
+        for i in 0..batch.num_rows() {
+            let row = rows.len();
+            let Some(pe) = int(&pes, i).and_then(|pe| u32::try_from(pe).ok()) else {
+                bail!("row {row} has no Source_PE");
+            };
+            let (Some(time), Some(function)) = (float(&times, i), string(&functions, i)) else {
+                bail!("row {row} has no Time or Function");
+            };
+            let context = int(&contexts, i).and_then(|c| u32::try_from(c).ok());
+            rows.push((
+                pe,
+                context,
+                RawEvent {
+                    time,
+                    function,
+                    duration_sec: float(&durations, i).unwrap_or(0.0),
+                    target_pe: int(&targets, i).map_or(-1, |t| t as i32),
+                    bytes_rx: int(&rx, i).unwrap_or(0).max(0) as u64,
+                    bytes_tx: int(&tx, i).unwrap_or(0).max(0) as u64,
+                    stacktrace: string(&stacktraces, i).unwrap_or_default(),
+                    extra: string(&extras, i),
+                    symboltrace: string(&symboltraces, i),
+                },
+            ));
+        }
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::event;
+    use parquet::data_type::{ByteArray, DoubleType, FloatType, Int32Type, Int64Type};
+    use parquet::file::properties::WriterVersion;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    fn temp(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("csvpshmem-parquet-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut pes = vec![
+            vec![
+                RawEvent {
+                    extra: Some("host=n0".to_string()),
+                    ..event(0.0, "shmem_init", 0.25, -1, 0, 0)
+                },
+                event(1.0, "shmem_putmem", 0.25, 1, 32, 64),
+            ],
+            vec![
+                RawEvent {
+                    extra: Some("host=n1;heap=5".to_string()),
+                    ..event(0.5, "shmem_init", 0.25, -1, 0, 0)
+                },
+                event(2.0, "shmem_getmem", 0.25, 0, 1 << 39, 1 << 40),
+            ],
+        ];
+        // traces on every call, symbols only on the ones moving bytes
+        for raw in pes.iter_mut().flatten() {
+            raw.stacktrace = "0x1;0x2".to_string();
+            raw.symboltrace = (raw.bytes_tx > 0).then(|| "main;put".to_string());
+        }
+        let data = ProfileData::from_pes(pes);
+        let path = write(&data, &temp("round-trip")).unwrap();
+        assert_eq!(path.extension().unwrap(), "parquet");
+        let rows = read(&path).unwrap();
+        assert_eq!(rows.len(), data.events.len());
+        for ((pe, context, raw), e) in rows.iter().zip(data.iter()) {
+            assert_eq!((*pe, *context), (e.source_pe, e.context));
+            assert_eq!(raw.time, e.raw.time);
+            assert_eq!(raw.function, e.raw.function);
+            assert_eq!(raw.duration_sec, e.raw.duration_sec);
+            assert_eq!(raw.target_pe, e.raw.target_pe);
+            assert_eq!(
+                (raw.bytes_rx, raw.bytes_tx),
+                (e.raw.bytes_rx, e.raw.bytes_tx)
+            );
+            assert_eq!(raw.stacktrace, e.raw.stacktrace);
+            assert_eq!(raw.extra.as_deref(), e.raw.extra);
+            assert_eq!(raw.symboltrace.as_deref(), e.raw.symboltrace);
+        }
+
+        // and loads like the trace it came from
+        let loaded = ProfileData::load_from_files(vec![path.clone()]).unwrap();
+        assert_eq!(loaded.pe_count, 2);
+        assert_eq!(loaded.pe_hostnames[&1], "n1");
+        assert_eq!(loaded.events.len(), data.events.len());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn empty_trace() {
+        let data = ProfileData::from_pes(Vec::new());
+        let path = write(&data, &temp("empty")).unwrap();
+        assert!(read(&path).unwrap().is_empty());
+        fs::remove_file(path).unwrap();
+    }
+
+    // a file laid out the way other tools write them: columns in another
+    // order and of other widths, unsigned and missing ones, dictionary
+    // pages, data page v2, snappy and several row groups
+    #[test]
+    fn other_writers() {
+        let path = temp("other").with_extension("parquet");
+        let schema = parse_message_type(
+            "message trace {
+                REQUIRED INT64 Source_PE;
+                REQUIRED BINARY Function (STRING);
+                REQUIRED DOUBLE Time;
+                OPTIONAL FLOAT Duration_Sec;
+                OPTIONAL INT64 Target_PE;
+                REQUIRED INT64 Bytes_TX (INTEGER(64, false));
+                OPTIONAL BINARY Extra (STRING);
+                OPTIONAL INT32 Context;
+            }",
+        )
+        .unwrap();
+        let properties = WriterProperties::builder()
+            .set_writer_version(WriterVersion::PARQUET_2_0)
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let file = fs::File::create(&path).unwrap();
+        let mut writer =
+            SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties)).unwrap();
+        for pe in 0..2i64 {
+            let mut group = writer.next_row_group().unwrap();
+            let mut column = group.next_column().unwrap().unwrap();
+            column
+                .typed::<Int64Type>()
+                .write_batch(&[pe, pe, pe], None, None)
+                .unwrap();
+            column.close().unwrap();
+            let mut column = group.next_column().unwrap().unwrap();
+            let functions = ["shmem_init", "shmem_putmem", "shmem_putmem"];
+            let functions: Vec<ByteArray> = functions.iter().map(|&f| f.into()).collect();
+            column
+                .typed::<parquet::data_type::ByteArrayType>()
+                .write_batch(&functions, None, None)
+                .unwrap();
+            column.close().unwrap();
+            let mut column = group.next_column().unwrap().unwrap();
+            let base = pe as f64;
+            column
+                .typed::<DoubleType>()
+                .write_batch(&[base, base + 0.5, base + 0.75], None, None)
+                .unwrap();
+            column.close().unwrap();
+            let mut column = group.next_column().unwrap().unwrap();
+            column
+                .typed::<FloatType>()
+                .write_batch(&[0.5, 0.125], Some(&[1, 0, 1]), None)
+                .unwrap();
+            column.close().unwrap();
+            let mut column = group.next_column().unwrap().unwrap();
+            column
+                .typed::<Int64Type>()
+                .write_batch(&[1 - pe, 1 - pe], Some(&[0, 1, 1]), None)
+                .unwrap();
+            column.close().unwrap();
+            let mut column = group.next_column().unwrap().unwrap();
+            // u64::MAX as the bits of an i64
+            column
+                .typed::<Int64Type>()
+                .write_batch(&[0, 4096, -1], None, None)
+                .unwrap();
+            column.close().unwrap();
+            let mut column = group.next_column().unwrap().unwrap();
+            let host: ByteArray = format!("host=n{pe}").as_str().into();
+            column
+                .typed::<parquet::data_type::ByteArrayType>()
+                .write_batch(&[host], Some(&[1, 0, 0]), None)
+                .unwrap();
+            column.close().unwrap();
+            let mut column = group.next_column().unwrap().unwrap();
+            column
+                .typed::<Int32Type>()
+                .write_batch(&[1], Some(&[0, 0, 1]), None)
+                .unwrap();
+            column.close().unwrap();
+            group.close().unwrap();
+        }
+        writer.close().unwrap();
+
+        let rows = read(&path).unwrap();
+        assert_eq!(rows.len(), 6);
+        let (pe, context, raw) = &rows[4];
+        assert_eq!((*pe, *context), (1, None));
+        assert_eq!((raw.time, raw.function.as_str()), (1.5, "shmem_putmem"));
+        assert_eq!(raw.duration_sec, 0.0);
+        assert_eq!(raw.target_pe, 0);
+        assert_eq!((raw.bytes_rx, raw.bytes_tx), (0, 4096));
+        assert_eq!((raw.stacktrace.as_str(), raw.extra.as_deref()), ("", None));
+        let (pe, context, raw) = &rows[5];
+        assert_eq!((*pe, *context), (1, Some(1)));
+        assert_eq!(raw.duration_sec, 0.125);
+        // past i64::MAX, read as unknown
+        assert_eq!(raw.bytes_tx, 0);
+        let (_, _, first) = &rows[0];
+        assert_eq!(first.target_pe, -1);
+        assert_eq!(first.extra.as_deref(), Some("host=n0"));
+
+        let loaded = ProfileData::load_from_files(vec![path.clone()]).unwrap();
+        assert_eq!(loaded.pe_count, 2);
+        assert_eq!(loaded.pe_hostnames[&1], "n1");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn errors() {
+        let error = |name: &str, schema: &str| {
+            let path = temp(name).with_extension("parquet");
+            let file = fs::File::create(&path).unwrap();
+            let schema = Arc::new(parse_message_type(schema).unwrap());
+            let properties = Arc::new(WriterProperties::builder().build());
+            SerializedFileWriter::new(file, schema, properties)
+                .unwrap()
+                .close()
+                .unwrap();
+            let error = format!("{:#}", read(&path).unwrap_err());
+            fs::remove_file(path).unwrap();
+            error
+        };
+        assert!(
+            error(
+                "no-pe",
+                "message t { REQUIRED DOUBLE Time; REQUIRED BINARY Function (STRING); }"
+            )
+            .ends_with("no Source_PE column")
+        );
+        assert!(
+            error(
+                "nested",
+                "message t { REQUIRED DOUBLE Time; REQUIRED BINARY Function (STRING);
+                 REQUIRED INT32 Source_PE; OPTIONAL group Tags (LIST) {
+                 REPEATED group list { OPTIONAL BINARY element (STRING); } } }"
+            )
+            .ends_with("column Tags is nested, only flat schemas are supported")
+        );
+
+        let path = temp("garbage").with_extension("parquet");
+        fs::write(&path, b"Time,Function\n0.0,shmem_init\n").unwrap();
+        assert!(read(&path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::bookmarks::Bookmarks;
+use crate::data::ProfileData;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Phase {
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Debug, Clone)]
+pub enum PhaseSource {
+    // boundaries are the points where every PE calling it has left the
+    // nth call
+    Barrier(String),
+    // fixed period found in the activity signal
+    Autocorrelation { period: f64 },
+    // consecutive user bookmarks
+    Bookmarks,
+}
+
+#[derive(Debug, Clone)]
+pub struct PhaseDetection {
+    pub source: PhaseSource,
+    pub phases: Vec<Phase>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PhaseStats {
+    pub duration: f64,
+    pub events: usize,
+    pub bytes_tx: u64,
+    pub bytes_rx: u64,
+    // (function, total seconds in calls), largest first
+    pub top_functions: Vec<(String, f64)>,
+    // the PE whose last call in the phase starts latest, i.e. the one
+    // everyone else ends up waiting on
+    pub slowest_pe: Option<u32>,
+}
+
+const TOP_FUNCTIONS: usize = 3;
+
+const SIGNAL_BINS: usize = 2048;
+const MIN_CORRELATION: f64 = 0.3;
+
+pub fn detect_phases(data: &ProfileData) -> Option<PhaseDetection> {
+    if data.events.is_empty() || data.max_time <= data.min_time {
+        return None;
+    }
+    detect_from_barriers(data).or_else(|| detect_from_autocorrelation(data))
+}
+
+pub fn from_bookmarks(bookmarks: &Bookmarks) -> Option<PhaseDetection> {
+    let times: Vec<f64> = bookmarks.iter().map(|b| b.time).collect();
+    if times.len() < 2 {
+        return None;
+    }
+    let phases = times
+        .windows(2)
+        .map(|w| Phase {
+            start: w[0],
+            end: w[1],
+        })
+        .collect();
+    Some(PhaseDetection {
+        source: PhaseSource::Bookmarks,
+        phases,
+    })
+}
+
+fn is_barrier_like(function: &str) -> bool {
+    let f = function.to_ascii_lowercase();
+    f.contains("barrier") || f.contains("sync_all")
+}
+
+fn detect_from_barriers(data: &ProfileData) -> Option<PhaseDetection> {
+    // function -> pe -> end times, in time order since events are sorted
+    let mut ends: HashMap<&str, HashMap<u32, Vec<f64>>> = HashMap::new();
+    for e in data.iter() {
+        if is_barrier_like(e.raw.function) {
+            ends.entry(e.raw.function)
+                .or_default()
+                .entry(e.source_pe)
+                .or_default()
+                .push(e.raw.time + e.raw.duration_sec);
+        }
+    }
+
+    // pick the function called the most times by the PEs that call it at
+    // all, as long as they agree on how often. PEs without any barrier, or
+    // without a file, don't hold up the rest
+    let (function, per_pe, rounds) = ends
+        .into_iter()
+        .filter_map(|(function, per_pe)| {
+            let mut counts = per_pe.values().map(Vec::len);
+            let rounds = counts.next()?;
+            counts
+                .all(|n| n == rounds)
+                .then_some((function, per_pe, rounds))
+        })
+        .max_by(|a, b| a.2.cmp(&b.2).then_with(|| b.0.cmp(a.0)))?;
+    if rounds < 2 {
+        return None;
+    }
+
+    let boundaries: Vec<f64> = (0..rounds)
+        .map(|k| per_pe.values().map(|t| t[k]).fold(f64::MIN, f64::max))
+        .collect();
+    let phases = boundaries
+        .windows(2)
+        .map(|w| Phase {
+            start: w[0],
+            end: w[1],
+        })
+        .collect();
+
+    Some(PhaseDetection {
+        source: PhaseSource::Barrier(function.to_string()),
+        phases,
+    })
+}
+
+fn detect_from_autocorrelation(data: &ProfileData) -> Option<PhaseDetection> {
+    let span = data.max_time - data.min_time;
+    let bin_width = span / SIGNAL_BINS as f64;
+
+    let mut signal = vec![0.0f64; SIGNAL_BINS];
+    for e in data.iter() {
+        let bin = (((e.raw.time - data.min_time) / bin_width) as usize).min(SIGNAL_BINS - 1);
+        signal[bin] += 1.0;
+    }
+    let mean = signal.iter().sum::<f64>() / SIGNAL_BINS as f64;
+    for v in &mut signal {
+        *v -= mean;
+    }
+
+    let acf = |lag: usize| -> f64 {
+        signal[..SIGNAL_BINS - lag]
+            .iter()
+            .zip(&signal[lag..])
+            .map(|(a, b)| a * b)
+            .sum()
+    };
+    let zero = acf(0);
+    if zero <= 0.0 {
+        return None;
+    }
+
+    // first peak after the correlation has dropped below zero
+    let max_lag = SIGNAL_BINS / 2;
+    let values: Vec<f64> = (0..=max_lag).map(|lag| acf(lag) / zero).collect();
+    let first_negative = values.iter().position(|v| *v < 0.0)?;
+    let lag = (first_negative + 1..max_lag).find(|&l| {
+        values[l] > MIN_CORRELATION && values[l] >= values[l - 1] && values[l] >= values[l + 1]
+    })?;
+
+    // start boundaries at the quietest bin of the first period
+    let offset = (0..lag)
+        .min_by(|a, b| signal[*a].total_cmp(&signal[*b]))
+        .unwrap_or(0);
+
+    let period = lag as f64 * bin_width;
+    let mut phases = Vec::new();
+    let mut start = data.min_time + offset as f64 * bin_width;
+    while start + period <= data.max_time + bin_width {
+        phases.push(Phase {
+            start,
+            end: start + period,
+        });
+        start += period;
+    }
+    if phases.len() < 2 {
+        return None;
+    }
+
+    Some(PhaseDetection {
+        source: PhaseSource::Autocorrelation { period },
+        phases,
+    })
+}
+
+pub fn phase_stats(data: &ProfileData, phase: &Phase) -> PhaseStats {
+    let start_idx = data.events.time.partition_point(|&t| t < phase.start);
+    let mut stats = PhaseStats {
+        duration: phase.end - phase.start,
+        ..Default::default()
+    };
+    let mut function_time: HashMap<&str, f64> = HashMap::new();
+    // in PE order, so a tie goes to the lowest PE every time
+    let mut last_start: BTreeMap<u32, f64> = BTreeMap::new();
+    for e in data
+        .range(start_idx..)
+        .take_while(|e| e.raw.time < phase.end)
+    {
+        stats.events += 1;
+        stats.bytes_tx += e.raw.bytes_tx;
+        stats.bytes_rx += e.raw.bytes_rx;
+        *function_time.entry(e.raw.function).or_default() += e.raw.duration_sec;
+        last_start.insert(e.source_pe, e.raw.time);
+    }
+
+    let mut top: Vec<(String, f64)> = function_time
+        .into_iter()
+        .map(|(f, t)| (f.to_string(), t))
+        .collect();
+    top.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top.truncate(TOP_FUNCTIONS);
+    stats.top_functions = top;
+
+    stats.slowest_pe = last_start
+        .into_iter()
+        .rev()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(pe, _)| pe);
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::event;
+
+    // one PE per entry of `barriers`, each calling that many barriers a
+    // second apart, with some work in between on every PE
+    fn trace(barriers: &[usize]) -> ProfileData {
+        let rounds = barriers.iter().copied().max().unwrap_or(0);
+        ProfileData::from_pes(
+            barriers
+                .iter()
+                .enumerate()
+                .map(|(pe, &n)| {
+                    let mut calls = vec![event(0.0, "shmem_init", 0.1, -1, 0, 0)];
+                    for k in 0..rounds {
+                        let t = k as f64 + 1.0;
+                        calls.push(event(t - 0.5, "shmem_putmem", 0.1, 0, 0, 8));
+                        if k < n {
+                            let wait = 0.01 * pe as f64;
+                            calls.push(event(t, "shmem_barrier_all", wait, -1, 0, 0));
+                        }
+                    }
+                    calls
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn barriers() {
+        let detection = detect_from_barriers(&trace(&[4, 4, 4])).unwrap();
+        assert!(
+            matches!(detection.source, PhaseSource::Barrier(ref f) if f == "shmem_barrier_all")
+        );
+        assert_eq!(detection.phases.len(), 3);
+        // a boundary is where the last PE leaves
+        assert!((detection.phases[0].start - 1.02).abs() < 1e-9);
+        assert!((detection.phases[0].end - 2.02).abs() < 1e-9);
+
+        // a PE that never reaches a barrier doesn't turn detection off
+        let detection = detect_from_barriers(&trace(&[4, 0, 4])).unwrap();
+        assert_eq!(detection.phases.len(), 3);
+        // PEs that disagree on how many there were do
+        assert!(detect_from_barriers(&trace(&[4, 3, 4])).is_none());
+        assert!(detect_from_barriers(&trace(&[1, 1])).is_none());
+    }
+
+    #[test]
+    fn slowest_pe_ties() {
+        // every PE's last call starts at the same time
+        let data = trace(&[2, 2, 2, 2]);
+        let phase = Phase {
+            start: 0.0,
+            end: 1.9,
+        };
+        let stats = phase_stats(&data, &phase);
+        assert_eq!(stats.slowest_pe, Some(0));
+        assert_eq!(stats.events, 4 * 4);
+        assert_eq!(stats.bytes_tx, 4 * 2 * 8);
+    }
+}
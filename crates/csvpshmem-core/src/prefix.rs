@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::bandwidth::{Collectives, Comms};
+use crate::data::ProfileData;
+
+// running byte and message totals of a list of events in time order
+#[derive(Debug, Default)]
+struct Series {
+    times: Vec<f64>,
+    // totals of the events before each index, so one longer than times
+    tx: Vec<u64>,
+    rx: Vec<u64>,
+    // events that sent / received anything
+    tx_msgs: Vec<u32>,
+    rx_msgs: Vec<u32>,
+}
+
+impl Series {
+    fn push(&mut self, time: f64, tx: u64, rx: u64) {
+        if self.tx.is_empty() {
+            self.tx.push(0);
+            self.rx.push(0);
+            self.tx_msgs.push(0);
+            self.rx_msgs.push(0);
+        }
+        self.times.push(time);
+        self.tx.push(self.tx.last().unwrap() + tx);
+        self.rx.push(self.rx.last().unwrap() + rx);
+        self.tx_msgs
+            .push(self.tx_msgs.last().unwrap() + u32::from(tx > 0));
+        self.rx_msgs
+            .push(self.rx_msgs.last().unwrap() + u32::from(rx > 0));
+    }
+
+    // events starting in [start, end]
+    fn range(&self, start: f64, end: f64) -> Range<usize> {
+        let first = self.times.partition_point(|t| *t < start);
+        let last = self.times.partition_point(|t| *t <= end);
+        first..last.max(first)
+    }
+
+    fn sum(&self, start: f64, end: f64) -> (u64, u64) {
+        let r = self.range(start, end);
+        if r.is_empty() {
+            return (0, 0);
+        }
+        (
+            self.tx[r.end] - self.tx[r.start],
+            self.rx[r.end] - self.rx[r.start],
+        )
+    }
+
+    // totals of the events starting at or before `time`
+    fn upto(&self, time: f64) -> (u64, u64) {
+        let i = self.times.partition_point(|t| *t <= time);
+        if i == 0 {
+            return (0, 0);
+        }
+        (self.tx[i], self.rx[i])
+    }
+
+    fn messages(&self, start: f64, end: f64) -> (u64, u64) {
+        let r = self.range(start, end);
+        if r.is_empty() {
+            return (0, 0);
+        }
+        (
+            (self.tx_msgs[r.end] - self.tx_msgs[r.start]) as u64,
+            (self.rx_msgs[r.end] - self.rx_msgs[r.start]) as u64,
+        )
+    }
+}
+
+// prefix sums of the bytes and messages moved, per directed PE pair, per PE
+// and overall,
+// so the total of any time range takes a couple of binary searches instead
+// of a scan over the events
+#[derive(Debug, Default)]
+pub struct ByteSums {
+    // keyed like bandwidth::Comms: tx of src -> dst, rx of dst <- src
+    edges: HashMap<(u32, u32), Series>,
+    // calls with a negative target, by sending PE
+    collectives: HashMap<u32, Series>,
+    // everything a PE sent or received, whatever the target
+    pes: Vec<Series>,
+    all: Series,
+}
+
+impl ByteSums {
+    pub fn new(data: &ProfileData) -> Self {
+        let mut sums = Self {
+            pes: (0..data.pe_count).map(|_| Series::default()).collect(),
+            ..Default::default()
+        };
+        for e in data.iter() {
+            let (t, tx, rx) = (e.raw.time, e.raw.bytes_tx, e.raw.bytes_rx);
+            sums.all.push(t, tx, rx);
+            sums.pes[e.source_pe as usize].push(t, tx, rx);
+            if e.raw.target_pe < 0 {
+                if tx > 0 || rx > 0 {
+                    sums.collectives
+                        .entry(e.source_pe)
+                        .or_default()
+                        .push(t, tx, rx);
+                }
+                continue;
+            }
+            let (src, dst) = (e.source_pe, e.raw.target_pe as u32);
+            if tx > 0 {
+                sums.edges.entry((src, dst)).or_default().push(t, tx, 0);
+            }
+            if rx > 0 {
+                sums.edges.entry((dst, src)).or_default().push(t, 0, rx);
+            }
+        }
+        sums
+    }
+
+    // bytes between each directed pair of PEs over [start_time, end_time]
+    pub fn comms(&self, start_time: f64, end_time: f64, show_tx: bool, show_rx: bool) -> Comms {
+        self.edges(show_tx, show_rx, |s| s.sum(start_time, end_time))
+    }
+
+    // messages between each directed pair of PEs over [start_time, end_time],
+    // keyed and split like comms()
+    pub fn messages(&self, start_time: f64, end_time: f64, show_tx: bool, show_rx: bool) -> Comms {
+        self.edges(show_tx, show_rx, |s| s.messages(start_time, end_time))
+    }
+
+    // bytes of the collective calls of each PE over [start_time, end_time]
+    pub fn collectives(
+        &self,
+        start_time: f64,
+        end_time: f64,
+        show_tx: bool,
+        show_rx: bool,
+    ) -> Collectives {
+        totals(&self.collectives, show_tx, show_rx, |s| {
+            s.sum(start_time, end_time)
+        })
+    }
+
+    // messages of the collective calls of each PE, split like collectives()
+    pub fn collective_messages(
+        &self,
+        start_time: f64,
+        end_time: f64,
+        show_tx: bool,
+        show_rx: bool,
+    ) -> Collectives {
+        totals(&self.collectives, show_tx, show_rx, |s| {
+            s.messages(start_time, end_time)
+        })
+    }
+
+    fn edges(&self, show_tx: bool, show_rx: bool, total: impl Fn(&Series) -> (u64, u64)) -> Comms {
+        totals(&self.edges, show_tx, show_rx, total)
+    }
+
+    // (tx, rx) of the events of `pe` starting in [start_time, end_time]
+    pub fn pe_bytes(&self, pe: u32, start_time: f64, end_time: f64) -> (u64, u64) {
+        self.pes
+            .get(pe as usize)
+            .map_or((0, 0), |s| s.sum(start_time, end_time))
+    }
+
+    // (tx, rx) of all events starting in [start_time, end_time]
+    pub fn total(&self, start_time: f64, end_time: f64) -> (u64, u64) {
+        self.all.sum(start_time, end_time)
+    }
+
+    // bytes moved by `pe` (every PE when None) from the start of the trace
+    // up to each of `times`
+    pub fn cumulative(
+        &self,
+        pe: Option<u32>,
+        times: &[f64],
+        show_tx: bool,
+        show_rx: bool,
+    ) -> Vec<u64> {
+        let series = match pe {
+            Some(pe) => self.pes.get(pe as usize),
+            None => Some(&self.all),
+        };
+        times
+            .iter()
+            .map(|t| {
+                let (tx, rx) = series.map_or((0, 0), |s| s.upto(*t));
+                (if show_tx { tx } else { 0 }) + (if show_rx { rx } else { 0 })
+            })
+            .collect()
+    }
+}
+
+// the non zero totals of each series, without the hidden direction
+fn totals<K: Copy + Eq + std::hash::Hash>(
+    series: &HashMap<K, Series>,
+    show_tx: bool,
+    show_rx: bool,
+    total: impl Fn(&Series) -> (u64, u64),
+) -> HashMap<K, (u64, u64)> {
+    let mut out = HashMap::new();
+    for (key, series) in series {
+        let (tx, rx) = total(series);
+        let v = (if show_tx { tx } else { 0 }, if show_rx { rx } else { 0 });
+        if v != (0, 0) {
+            out.insert(*key, v);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::ProfileData;
+    use crate::synthetic::{self, Config, Pattern};
+
+    fn trace() -> ProfileData {
+        ProfileData::from_pes(synthetic::generate(&Config {
+            pe_count: 6,
+            seconds: 0.05,
+            pattern: Pattern::AllToAll,
+            pes_per_host: 2,
+            ..Default::default()
+        }))
+    }
+
+    // the same totals as ByteSums, by looking at every event
+    fn scan(data: &ProfileData, start: f64, end: f64) -> (Comms, Collectives, Vec<(u64, u64)>) {
+        let mut comms = Comms::new();
+        let mut collectives = Collectives::new();
+        let mut pes = vec![(0, 0); data.pe_count as usize];
+        for e in data.iter() {
+            let (t, tx, rx) = (e.raw.time, e.raw.bytes_tx, e.raw.bytes_rx);
+            if t < start || t > end {
+                continue;
+            }
+            pes[e.source_pe as usize].0 += tx;
+            pes[e.source_pe as usize].1 += rx;
+            if e.raw.target_pe < 0 {
+                let c = collectives.entry(e.source_pe).or_default();
+                c.0 += tx;
+                c.1 += rx;
+                continue;
+            }
+            let (src, dst) = (e.source_pe, e.raw.target_pe as u32);
+            comms.entry((src, dst)).or_default().0 += tx;
+            comms.entry((dst, src)).or_default().1 += rx;
+        }
+        comms.retain(|_, v| *v != (0, 0));
+        collectives.retain(|_, v| *v != (0, 0));
+        (comms, collectives, pes)
+    }
+
+    #[test]
+    fn ranges_match_a_scan() {
+        let data = trace();
+        let sums = ByteSums::new(&data);
+        let (min, max) = (data.min_time, data.max_time);
+        let ranges = [
+            (min, max),
+            (min - 1.0, max + 1.0),
+            (0.01, 0.02),
+            (0.0123, 0.0124),
+            (max / 3.0, max / 2.0),
+            // a single event's start on both ends
+            (data.events.time[100], data.events.time[100]),
+            (0.03, 0.01),
+        ];
+        for (start, end) in ranges {
+            let (comms, collectives, pes) = scan(&data, start, end);
+            assert_eq!(sums.comms(start, end, true, true), comms);
+            assert_eq!(sums.collectives(start, end, true, true), collectives);
+            for (pe, bytes) in pes.iter().enumerate() {
+                assert_eq!(sums.pe_bytes(pe as u32, start, end), *bytes);
+            }
+            let total = pes.iter().fold((0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
+            assert_eq!(sums.total(start, end), total);
+        }
+    }
+
+    #[test]
+    fn hidden_directions() {
+        let data = trace();
+        let sums = ByteSums::new(&data);
+        let (start, end) = (0.01, 0.04);
+        let both = sums.comms(start, end, true, true);
+        let tx = sums.comms(start, end, true, false);
+        let rx = sums.comms(start, end, false, true);
+        for (edge, (t, r)) in &both {
+            assert_eq!(tx.get(edge).map_or(0, |v| v.0), *t);
+            assert_eq!(rx.get(edge).map_or(0, |v| v.1), *r);
+        }
+        assert!(tx.values().all(|v| v.1 == 0));
+        assert!(sums.comms(start, end, false, false).is_empty());
+    }
+
+    #[test]
+    fn messages_and_cumulative() {
+        let data = trace();
+        let sums = ByteSums::new(&data);
+        let (start, end) = (0.005, 0.03);
+        let mut expected = Comms::new();
+        for e in data
+            .iter()
+            .filter(|e| e.raw.time >= start && e.raw.time <= end)
+        {
+            if e.raw.target_pe < 0 {
+                continue;
+            }
+            let (src, dst) = (e.source_pe, e.raw.target_pe as u32);
+            if e.raw.bytes_tx > 0 {
+                expected.entry((src, dst)).or_default().0 += 1;
+            }
+            if e.raw.bytes_rx > 0 {
+                expected.entry((dst, src)).or_default().1 += 1;
+            }
+        }
+        assert_eq!(sums.messages(start, end, true, true), expected);
+
+        let times = [start, end, data.max_time];
+        let upto = |t: f64| -> u64 {
+            data.iter()
+                .filter(|e| e.source_pe == 2 && e.raw.time <= t)
+                .map(|e| e.raw.bytes_tx + e.raw.bytes_rx)
+                .sum()
+        };
+        let expected: Vec<u64> = times.iter().map(|t| upto(*t)).collect();
+        assert_eq!(sums.cumulative(Some(2), &times, true, true), expected);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::data::ProfileData;
+use crate::prefix::ByteSums;
+
+// two runs of a merged trace set side by side: the bytes every pair of PEs
+// moved over the same stretch of each, timed from the start of its own run,
+// so a changed communication pattern shows as the cells that grew or shrank
+
+#[derive(Debug, Clone)]
+pub struct RunDiff {
+    // indices into ProfileData::runs
+    pub runs: (usize, usize),
+    // seconds after the start of each run, and how long
+    pub offset: f64,
+    pub length: f64,
+    // PEs of the larger run, the smaller one's cells past its PEs stay empty
+    pub pe_count: u32,
+    // bytes from each PE (row) to each PE (column), numbered within its run
+    pub a: Vec<u64>,
+    pub b: Vec<u64>,
+}
+
+impl RunDiff {
+    pub fn bytes(&self, src: u32, dst: u32) -> (u64, u64) {
+        let i = (src * self.pe_count + dst) as usize;
+        (self.a[i], self.b[i])
+    }
+
+    // positive when run b moved more
+    pub fn delta(&self, src: u32, dst: u32) -> i64 {
+        let (a, b) = self.bytes(src, dst);
+        b as i64 - a as i64
+    }
+
+    pub fn max_delta(&self) -> u64 {
+        self.a
+            .iter()
+            .zip(&self.b)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0)
+    }
+
+    // pairs whose traffic changed, biggest change first
+    pub fn changes(&self) -> Vec<(u32, u32, i64)> {
+        let n = self.pe_count;
+        let mut changes: Vec<_> = (0..n)
+            .flat_map(|src| (0..n).map(move |dst| (src, dst)))
+            .map(|(src, dst)| (src, dst, self.delta(src, dst)))
+            .filter(|(_, _, delta)| *delta != 0)
+            .collect();
+        changes
+            .sort_by_key(|(src, dst, delta)| (std::cmp::Reverse(delta.unsigned_abs()), *src, *dst));
+        changes
+    }
+}
+
+// first and last moment of run `run`
+pub fn run_span(data: &ProfileData, run: usize) -> Option<(f64, f64)> {
+    let start = data.iter().find(|e| e.run as usize == run)?.raw.time;
+    let end = (0..data.events.len())
+        .filter(|i| data.events.run[*i] as usize == run)
+        .map(|i| data.events.end(i))
+        .fold(start, f64::max);
+    Some((start, end))
+}
+
+// the traffic of runs `a` and `b` over [offset, offset + length] after
+// each one's start
+pub fn compare(
+    data: &ProfileData,
+    sums: &ByteSums,
+    runs: (usize, usize),
+    offset: f64,
+    length: f64,
+) -> Option<RunDiff> {
+    let (a, b) = (data.runs.get(runs.0)?, data.runs.get(runs.1)?);
+    let pe_count = a.pe_count.max(b.pe_count);
+    let matrix = |run: usize| -> Option<Vec<u64>> {
+        let info = &data.runs[run];
+        let (start, _) = run_span(data, run)?;
+        let pes = info.first_pe..info.first_pe + info.pe_count;
+        let mut cells = vec![0; (pe_count * pe_count) as usize];
+        let from = start + offset;
+        for ((src, dst), (tx, rx)) in sums.comms(from, from + length, true, true) {
+            if pes.contains(&src) && pes.contains(&dst) {
+                let (src, dst) = (src - info.first_pe, dst - info.first_pe);
+                cells[(src * pe_count + dst) as usize] += tx + rx;
+            }
+        }
+        Some(cells)
+    };
+    Some(RunDiff {
+        runs,
+        offset,
+        length,
+        pe_count,
+        a: matrix(runs.0)?,
+        b: matrix(runs.1)?,
+    })
+}
@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use crate::data::ProfileData;
+
+// one call that moved bytes to or from another PE
+#[derive(Debug, Clone, Copy)]
+pub struct Point {
+    pub event: usize,
+    pub bytes: u64,
+    pub duration: f64,
+}
+
+// calls with a target PE and bytes starting in [start, end], optionally only
+// those between `pair` (either direction)
+pub fn points(data: &ProfileData, start: f64, end: f64, pair: Option<(u32, u32)>) -> Vec<Point> {
+    let first = data.events.time.partition_point(|&t| t < start);
+    data.range(first..)
+        .enumerate()
+        .take_while(|(_, e)| e.raw.time <= end)
+        .filter(|(_, e)| {
+            let bytes = e.raw.bytes_tx + e.raw.bytes_rx;
+            if e.raw.target_pe < 0 || bytes == 0 {
+                return false;
+            }
+            let (src, dst) = (e.source_pe, e.raw.target_pe as u32);
+            pair.is_none_or(|(a, b)| (src, dst) == (a, b) || (src, dst) == (b, a))
+        })
+        .map(|(i, e)| Point {
+            event: first + i,
+            bytes: e.raw.bytes_tx + e.raw.bytes_rx,
+            duration: e.raw.duration_sec,
+        })
+        .collect()
+}
+
+// the points of a range and the line through them
+#[derive(Debug, Default)]
+pub struct Scatter {
+    pub range: (f64, f64),
+    pub pair: Option<(u32, u32)>,
+    pub points: Vec<Point>,
+    pub fit: Option<Fit>,
+}
+
+impl Scatter {
+    pub fn new(data: &ProfileData, start: f64, end: f64, pair: Option<(u32, u32)>) -> Self {
+        let points = points(data, start, end, pair);
+        let fit = fit(points.iter().map(|p| (p.bytes as f64, p.duration)));
+        Self {
+            range: (start, end),
+            pair,
+            points,
+            fit,
+        }
+    }
+}
+
+// duration = alpha + bytes / beta, least squares
+#[derive(Debug, Clone, Copy)]
+pub struct Fit {
+    // seconds of latency per call
+    pub alpha: f64,
+    // bytes/s once the latency is paid, infinite when size doesn't matter
+    pub beta: f64,
+    // share of the duration variance explained, 0..=1
+    pub r2: f64,
+    pub samples: usize,
+}
+
+impl Fit {
+    pub fn duration(&self, bytes: f64) -> f64 {
+        self.alpha + bytes / self.beta
+    }
+}
+
+// None with fewer than two distinct sizes, which can't separate alpha
+// from beta
+pub fn fit(points: impl IntoIterator<Item = (f64, f64)>) -> Option<Fit> {
+    let (mut n, mut sx, mut sy, mut sxx, mut sxy, mut syy) = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for (x, y) in points {
+        n += 1.0;
+        sx += x;
+        sy += y;
+        sxx += x * x;
+        sxy += x * y;
+        syy += y * y;
+    }
+    let var_x = n * sxx - sx * sx;
+    if n < 2.0 || var_x <= f64::EPSILON * n * sxx {
+        return None;
+    }
+    let slope = (n * sxy - sx * sy) / var_x;
+    // bigger messages finishing sooner says nothing about bandwidth
+    let alpha = if slope > 0.0 {
+        (sy - slope * sx) / n
+    } else {
+        sy / n
+    };
+    let var_y = n * syy - sy * sy;
+    let r2 = if slope <= 0.0 {
+        0.0
+    } else if var_y > 0.0 {
+        ((n * sxy - sx * sy).powi(2) / (var_x * var_y)).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    Some(Fit {
+        alpha,
+        beta: if slope > 0.0 {
+            1.0 / slope
+        } else {
+            f64::INFINITY
+        },
+        r2,
+        samples: n as usize,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FitBy {
+    PePair,
+    HostPair,
+}
+
+// either direction between two PEs or two hosts, lowest first
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PairKey {
+    Pes(u32, u32),
+    Hosts(String, String),
+}
+
+// one alpha-beta fit per pair, over the calls with a target PE starting in
+// [start, end]. pairs with a single message size get no row
+#[derive(Debug)]
+pub struct PairFits {
+    pub range: (f64, f64),
+    pub by: FitBy,
+    pub rows: Vec<(PairKey, Fit)>,
+}
+
+impl PairFits {
+    pub fn new(data: &ProfileData, start: f64, end: f64, by: FitBy) -> Self {
+        let host = |pe: u32| data.pe_hostnames.get(&pe).map_or("?", String::as_str);
+        let mut samples: HashMap<PairKey, Vec<(f64, f64)>> = HashMap::new();
+        for p in points(data, start, end, None) {
+            let e = data.event(p.event);
+            let (a, b) = (e.source_pe, e.raw.target_pe as u32);
+            let key = match by {
+                FitBy::PePair => PairKey::Pes(a.min(b), a.max(b)),
+                FitBy::HostPair => {
+                    let (a, b) = (host(a), host(b));
+                    PairKey::Hosts(a.min(b).to_string(), a.max(b).to_string())
+                }
+            };
+            samples
+                .entry(key)
+                .or_default()
+                .push((p.bytes as f64, p.duration));
+        }
+        let mut rows: Vec<_> = samples
+            .into_iter()
+            .filter_map(|(key, s)| Some((key, fit(s)?)))
+            .collect();
+        rows.sort_by(|a, b| a.0.cmp(&b.0));
+        Self {
+            range: (start, end),
+            by,
+            rows,
+        }
+    }
+
+    // the table as csv, times in seconds and beta in bytes/s
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("a,b,calls,alpha_sec,beta_bytes_per_sec,r2\n");
+        for (key, fit) in &self.rows {
+            let (a, b) = match key {
+                PairKey::Pes(a, b) => (a.to_string(), b.to_string()),
+                PairKey::Hosts(a, b) => (a.clone(), b.clone()),
+            };
+            out += &format!(
+                "{a},{b},{},{:e},{:e},{:.4}\n",
+                fit.samples, fit.alpha, fit.beta, fit.r2
+            );
+        }
+        out
+    }
+}
@@ -0,0 +1,468 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+
+use crate::data::{Event, ProfileData};
+use crate::grouping::Grouping;
+
+// whitespace separated terms, all of which must match, e.g.
+//   function:shmem_put bytes>1e6 pe:3..7 time:0.5..0.6
+// a bare word is a function name substring
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    functions: Vec<String>,
+    // exact group names, after the grouping rules
+    groups: Vec<String>,
+    hosts: Vec<String>,
+    numeric: Vec<(Field, Bound)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Time,
+    Duration,
+    Pe,
+    Target,
+    Bytes,
+    Tx,
+    Rx,
+}
+
+// inclusive range, either end may be open
+#[derive(Debug, Clone, Copy)]
+struct Bound {
+    min: f64,
+    max: f64,
+}
+
+impl Bound {
+    fn contains(&self, v: f64) -> bool {
+        self.min <= v && v <= self.max
+    }
+}
+
+impl Field {
+    fn parse(key: &str) -> Option<Self> {
+        Some(match key {
+            "time" | "t" => Self::Time,
+            "duration" | "dur" => Self::Duration,
+            "pe" => Self::Pe,
+            "target" | "target_pe" => Self::Target,
+            "bytes" => Self::Bytes,
+            "tx" | "bytes_tx" => Self::Tx,
+            "rx" | "bytes_rx" => Self::Rx,
+            _ => return None,
+        })
+    }
+
+    fn of(self, e: &Event) -> f64 {
+        match self {
+            Self::Time => e.raw.time,
+            Self::Duration => e.raw.duration_sec,
+            Self::Pe => e.source_pe as f64,
+            Self::Target => e.raw.target_pe as f64,
+            Self::Bytes => (e.raw.bytes_tx + e.raw.bytes_rx) as f64,
+            Self::Tx => e.raw.bytes_tx as f64,
+            Self::Rx => e.raw.bytes_rx as f64,
+        }
+    }
+}
+
+fn number(s: &str) -> Result<f64> {
+    match s.parse() {
+        Ok(v) => Ok(v),
+        Err(_) => bail!("'{s}' is not a number"),
+    }
+}
+
+// `a..b`, `a..`, `..b` or a single value
+fn range(s: &str) -> Result<Bound> {
+    Ok(match s.split_once("..") {
+        Some((lo, hi)) => Bound {
+            min: if lo.is_empty() {
+                f64::NEG_INFINITY
+            } else {
+                number(lo)?
+            },
+            max: if hi.is_empty() {
+                f64::INFINITY
+            } else {
+                number(hi)?
+            },
+        },
+        None => {
+            let v = number(s)?;
+            Bound { min: v, max: v }
+        }
+    })
+}
+
+impl Query {
+    pub fn parse(src: &str) -> Result<Self> {
+        let mut query = Query::default();
+        for term in src.split_whitespace() {
+            // comparisons first so `bytes>=5` isn't read as key `bytes>`
+            if let Some(idx) = term.find(['<', '>', '=']) {
+                let (key, rest) = term.split_at(idx);
+                let Some(field) = Field::parse(&key.to_ascii_lowercase()) else {
+                    bail!("unknown field '{key}'");
+                };
+                let (op, value) = match rest.get(..2) {
+                    Some(op @ (">=" | "<=")) => (op, &rest[2..]),
+                    _ => rest.split_at(1),
+                };
+                let v = number(value)?;
+                let bound = match op {
+                    ">" => Bound {
+                        min: v.next_up(),
+                        max: f64::INFINITY,
+                    },
+                    ">=" => Bound {
+                        min: v,
+                        max: f64::INFINITY,
+                    },
+                    "<" => Bound {
+                        min: f64::NEG_INFINITY,
+                        max: v.next_down(),
+                    },
+                    "<=" => Bound {
+                        min: f64::NEG_INFINITY,
+                        max: v,
+                    },
+                    _ => Bound { min: v, max: v },
+                };
+                query.numeric.push((field, bound));
+            } else if let Some((key, value)) = term.split_once(':') {
+                match key.to_ascii_lowercase().as_str() {
+                    "function" | "fn" | "f" => query.functions.push(value.to_string()),
+                    "group" | "g" => query.groups.push(value.to_string()),
+                    "host" => query.hosts.push(value.to_string()),
+                    k => match Field::parse(k) {
+                        Some(field) => query.numeric.push((field, range(value)?)),
+                        None => bail!("unknown field '{key}'"),
+                    },
+                }
+            } else {
+                query.functions.push(term.to_string());
+            }
+        }
+        if query.functions.is_empty()
+            && query.groups.is_empty()
+            && query.hosts.is_empty()
+            && query.numeric.is_empty()
+        {
+            bail!("empty query");
+        }
+        Ok(query)
+    }
+
+    fn matches(&self, data: &ProfileData, grouping: &Grouping, e: &Event) -> bool {
+        self.functions
+            .iter()
+            .all(|f| e.raw.function.contains(f.as_str()))
+            && self
+                .groups
+                .iter()
+                .all(|g| grouping.group(e.raw.function) == g)
+            && self.hosts.iter().all(|h| {
+                data.pe_hostnames
+                    .get(&e.source_pe)
+                    .is_some_and(|name| name.contains(h.as_str()))
+            })
+            && self
+                .numeric
+                .iter()
+                .all(|(field, b)| b.contains(field.of(e)))
+    }
+
+    fn time_bound(&self) -> Bound {
+        self.numeric.iter().filter(|(f, _)| *f == Field::Time).fold(
+            Bound {
+                min: f64::NEG_INFINITY,
+                max: f64::INFINITY,
+            },
+            |acc, (_, b)| Bound {
+                min: acc.min.max(b.min),
+                max: acc.max.min(b.max),
+            },
+        )
+    }
+}
+
+// function name -> indices of its events, in time order
+pub struct SearchIndex {
+    by_function: HashMap<String, Vec<usize>>,
+}
+
+impl SearchIndex {
+    pub fn new(data: &ProfileData) -> Self {
+        let mut by_function: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, e) in data.iter().enumerate() {
+            by_function
+                .entry(e.raw.function.to_string())
+                .or_default()
+                .push(i);
+        }
+        Self { by_function }
+    }
+
+    // indices of matching events, in time order
+    pub fn search(&self, data: &ProfileData, grouping: &Grouping, query: &Query) -> Vec<usize> {
+        let time = query.time_bound();
+        let first = data.events.time.partition_point(|&t| t < time.min);
+        let last = data.events.time.partition_point(|&t| t <= time.max);
+        if first >= last {
+            return Vec::new();
+        }
+
+        let mut hits: Vec<usize> = if query.functions.is_empty() {
+            (first..last).collect()
+        } else {
+            // only walk the events of functions whose names match
+            self.by_function
+                .iter()
+                .filter(|(name, _)| query.functions.iter().all(|f| name.contains(f.as_str())))
+                .flat_map(|(_, idxs)| {
+                    let lo = idxs.partition_point(|i| *i < first);
+                    let hi = idxs.partition_point(|i| *i < last);
+                    idxs[lo..hi].iter().copied()
+                })
+                .collect()
+        };
+        hits.retain(|i| query.matches(data, grouping, &data.event(*i)));
+        hits.sort_unstable();
+        hits
+    }
+}
+
+// a search as run from a search bar: the query, its matches and the one
+// being shown. the methods that move to another match return its event
+#[derive(Debug, Clone, Default)]
+pub struct Search {
+    // query the current results came from
+    pub query: String,
+    pub error: Option<String>,
+    // matching events, in time order
+    pub results: Vec<usize>,
+    // index into `results` of the match shown
+    pub pos: Option<usize>,
+}
+
+impl Search {
+    // runs `source` and moves to the first match at or after `from`
+    pub fn run(
+        &mut self,
+        source: &str,
+        data: &ProfileData,
+        index: &SearchIndex,
+        grouping: &Grouping,
+        from: f64,
+    ) -> Option<usize> {
+        self.query = source.trim().to_string();
+        self.pos = None;
+        match Query::parse(&self.query) {
+            Ok(query) => {
+                self.error = None;
+                self.results = index.search(data, grouping, &query);
+                let next = self
+                    .results
+                    .partition_point(|i| data.events.time[*i] < from);
+                self.show(next.min(self.results.len().saturating_sub(1)))
+            }
+            Err(e) => {
+                self.error = Some(format!("{:#}", e));
+                self.results.clear();
+                None
+            }
+        }
+    }
+
+    // whether `source` is what the results came from, so running it again
+    // would only step through them
+    pub fn is_current(&self, source: &str) -> bool {
+        source.trim() == self.query && self.error.is_none()
+    }
+
+    // moves `direction` matches on, wrapping around at either end
+    pub fn step(&mut self, direction: isize) -> Option<usize> {
+        let n = self.results.len();
+        if n == 0 {
+            return None;
+        }
+        let next = match self.pos {
+            Some(pos) => (pos as isize + direction).rem_euclid(n as isize) as usize,
+            None => 0,
+        };
+        self.show(next)
+    }
+
+    fn show(&mut self, pos: usize) -> Option<usize> {
+        let event = *self.results.get(pos)?;
+        self.pos = Some(pos);
+        Some(event)
+    }
+
+    // Some(true) for the match shown, Some(false) for the others
+    pub fn hit(&self, event: usize) -> Option<bool> {
+        let pos = self.results.binary_search(&event).ok()?;
+        Some(self.pos == Some(pos))
+    }
+
+    // follows the events to their new indices after their times changed,
+    // `moved` holding the new index of each old one. events are in time
+    // order so the results stay sorted by index
+    pub fn remap(&mut self, moved: &[usize]) {
+        for i in &mut self.results {
+            *i = moved[*i];
+        }
+        self.results.sort_unstable();
+        self.pos = None;
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grouping::GroupRule;
+    use crate::synthetic::event;
+
+    fn trace() -> ProfileData {
+        let mut data = ProfileData::from_pes(vec![
+            vec![
+                event(0.0, "shmem_init", 0.01, -1, 0, 0),
+                event(0.1, "shmem_putmem", 0.01, 1, 0, 100),
+                event(0.2, "shmem_getmem", 0.01, 1, 2000, 0),
+            ],
+            vec![
+                event(0.0, "shmem_init", 0.01, -1, 0, 0),
+                event(0.15, "shmem_putmem", 0.01, 0, 0, 1_000_000),
+                event(0.3, "shmem_barrier_all", 0.01, -1, 0, 0),
+            ],
+        ]);
+        data.pe_hostnames.insert(0, "alpha01".to_string());
+        data.pe_hostnames.insert(1, "beta02".to_string());
+        data
+    }
+
+    // (pe, time) of the events `query` finds
+    fn find(query: &str) -> Vec<(u32, f64)> {
+        let data = trace();
+        let rules = [GroupRule {
+            pattern: "^shmem_(put|get)".to_string(),
+            group: "RMA".to_string(),
+        }];
+        let functions: Vec<&str> = data.iter().map(|e| e.raw.function).collect();
+        let grouping = Grouping::new(&rules, functions.into_iter()).unwrap();
+        let index = SearchIndex::new(&data);
+        let query = Query::parse(query).unwrap();
+        index
+            .search(&data, &grouping, &query)
+            .into_iter()
+            .map(|i| (data.event(i).source_pe, data.event(i).raw.time))
+            .collect()
+    }
+
+    fn error(query: &str) -> String {
+        Query::parse(query).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn function_names() {
+        assert_eq!(find("putmem"), [(0, 0.1), (1, 0.15)]);
+        assert_eq!(find("function:getmem"), [(0, 0.2)]);
+        assert_eq!(find("f:shmem_ mem").len(), 6);
+        assert_eq!(find("fn:mem_ get"), [(0, 0.2)]);
+        // every term has to match, there is no or
+        assert!(find("putmem getmem").is_empty());
+        // quotes aren't special, they're part of the name looked for
+        assert!(find("\"putmem\"").is_empty());
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(find("bytes>100"), [(1, 0.15), (0, 0.2)]);
+        assert_eq!(find("bytes>=100"), [(0, 0.1), (1, 0.15), (0, 0.2)]);
+        assert_eq!(find("tx<1000 tx>0"), [(0, 0.1)]);
+        assert_eq!(find("rx<=2000 rx>=2000"), [(0, 0.2)]);
+        assert_eq!(find("target=-1"), [(0, 0.0), (1, 0.0), (1, 0.3)]);
+        assert_eq!(find("bytes>1e5"), [(1, 0.15)]);
+        // field names are case insensitive, values are not
+        assert_eq!(find("PE=1 Function:put"), [(1, 0.15)]);
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(find("time:0.1..0.2"), [(0, 0.1), (1, 0.15), (0, 0.2)]);
+        assert_eq!(find("t:0.2.."), [(0, 0.2), (1, 0.3)]);
+        assert_eq!(find("t:..0.1 pe:0"), [(0, 0.0), (0, 0.1)]);
+        assert_eq!(find("pe:1 dur:0.01"), [(1, 0.0), (1, 0.15), (1, 0.3)]);
+        assert!(find("time:0.5..").is_empty());
+    }
+
+    #[test]
+    fn groups_and_hosts() {
+        assert_eq!(find("group:RMA"), [(0, 0.1), (1, 0.15), (0, 0.2)]);
+        assert_eq!(find("g:RMA host:beta"), [(1, 0.15)]);
+        assert_eq!(find("host:alpha init"), [(0, 0.0)]);
+        // groups are exact, not substrings
+        assert!(find("group:RM").is_empty());
+    }
+
+    #[test]
+    fn stepping() {
+        let data = trace();
+        let functions: Vec<&str> = data.iter().map(|e| e.raw.function).collect();
+        let grouping = Grouping::new(&[], functions.into_iter()).unwrap();
+        let index = SearchIndex::new(&data);
+        let mut search = Search::default();
+
+        // the putmem calls are events 2 and 3, the run starts at the first
+        // one after the time given
+        assert_eq!(
+            search.run(" putmem ", &data, &index, &grouping, 0.12),
+            Some(3)
+        );
+        assert!(search.is_current("putmem"));
+        assert_eq!(search.hit(3), Some(true));
+        assert_eq!(search.hit(2), Some(false));
+        assert_eq!(search.hit(1), None);
+        assert_eq!(search.step(1), Some(2));
+        assert_eq!(search.step(-1), Some(3));
+        // past the last match it stays on the last one
+        assert_eq!(search.run("putmem", &data, &index, &grouping, 9.0), Some(3));
+
+        // the two swapped
+        search.remap(&[0, 1, 3, 2, 4, 5]);
+        assert_eq!(
+            (search.results.as_slice(), search.pos),
+            ([2, 3].as_slice(), None)
+        );
+        assert_eq!(search.step(1), Some(2));
+
+        assert_eq!(
+            search.run("time:0.5..", &data, &index, &grouping, 0.0),
+            None
+        );
+        assert_eq!(search.pos, None);
+        assert_eq!(search.run("size>5", &data, &index, &grouping, 0.0), None);
+        assert_eq!(search.error.as_deref(), Some("unknown field 'size'"));
+        assert!(!search.is_current("size>5"));
+        search.clear();
+        assert_eq!(search.step(1), None);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error(""), "empty query");
+        assert_eq!(error("   "), "empty query");
+        assert_eq!(error("size>5"), "unknown field 'size'");
+        assert_eq!(error("colour:red"), "unknown field 'colour'");
+        assert_eq!(error("bytes>lots"), "'lots' is not a number");
+        assert_eq!(error("bytes>"), "'' is not a number");
+        assert_eq!(error("time:0.1..x"), "'x' is not a number");
+        assert_eq!(error("pe:"), "'' is not a number");
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use crate::data::ProfileData;
+
+// host -> seconds added to the timestamps of its PEs
+pub type ClockOffsets = BTreeMap<String, f64>;
+
+// events that happen at about the same moment on every PE, to line hosts up on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    // the first event of each PE, normally shmem_init
+    Initialize,
+    // the end of each PE's first barrier, which everyone leaves together
+    FirstBarrier,
+}
+
+impl Anchor {
+    pub fn label(self) -> &'static str {
+        match self {
+            Anchor::Initialize => "Align on initialize",
+            Anchor::FirstBarrier => "Align on first barrier",
+        }
+    }
+}
+
+// offsets that make the anchor events of every host coincide with those of
+// the host of the lowest PE. hosts without an anchor event are left out
+pub fn estimate(data: &ProfileData, anchor: Anchor) -> ClockOffsets {
+    // earliest anchor per host, on the unshifted clocks
+    let mut anchors: BTreeMap<&str, (u32, f64)> = BTreeMap::new();
+    let mut seen = vec![false; data.pe_count as usize];
+    for e in data.iter() {
+        let pe = e.source_pe;
+        if seen[pe as usize] {
+            continue;
+        }
+        let time = match anchor {
+            Anchor::Initialize => e.raw.time,
+            Anchor::FirstBarrier if e.raw.function.to_lowercase().contains("barrier") => {
+                e.raw.time + e.raw.duration_sec
+            }
+            Anchor::FirstBarrier => continue,
+        };
+        seen[pe as usize] = true;
+        let Some(host) = data.pe_hostnames.get(&pe) else {
+            continue;
+        };
+        let time = time - data.clock_offset(pe);
+        let entry = anchors.entry(host.as_str()).or_insert((pe, time));
+        *entry = (entry.0.min(pe), entry.1.min(time));
+    }
+
+    let Some(&(_, reference)) = anchors.values().min_by_key(|(pe, _)| *pe) else {
+        return ClockOffsets::new();
+    };
+    anchors
+        .into_iter()
+        .map(|(host, (_, time))| (host.to_string(), reference - time))
+        .collect()
+}
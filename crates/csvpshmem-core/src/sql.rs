@@ -0,0 +1,388 @@
+use anyhow::{Result, bail};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::{ToSql, ValueRef};
+use rusqlite::{Connection, params_from_iter};
+use std::fmt;
+
+use crate::data::ProfileData;
+
+// ad-hoc queries over the events of a trace, run by an in-memory sqlite
+// database holding one table, `events`, with a row per call, e.g.
+//   SELECT function, count(*), sum(duration) FROM events
+//   WHERE bytes_tx > 0 GROUP BY function ORDER BY 3 DESC LIMIT 10
+// numeric Extra counters get a column each, anything else in Extra is
+// reachable with extra_field(extra, 'key')
+
+// the columns of `events` before the counters, in table order. id is the
+// index of the event, rows that select it link back to the timeline
+pub const COLUMNS: [&str; 15] = [
+    "id",
+    "time",
+    "end",
+    "duration",
+    "function",
+    "pe",
+    "context",
+    "host",
+    "target_pe",
+    "bytes_tx",
+    "bytes_rx",
+    "bytes",
+    "stacktrace",
+    "extra",
+    "symboltrace",
+];
+
+// sqlite types of COLUMNS
+const TYPES: [&str; 15] = [
+    "INTEGER PRIMARY KEY",
+    "REAL",
+    "REAL",
+    "REAL",
+    "TEXT",
+    "INTEGER",
+    "INTEGER",
+    "TEXT",
+    "INTEGER",
+    "INTEGER",
+    "INTEGER",
+    "INTEGER",
+    "TEXT",
+    "TEXT",
+    "TEXT",
+];
+
+// results are cut off past this many rows
+pub const MAX_ROWS: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Number(f64),
+    Text(String),
+}
+
+impl Value {
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Null => None,
+            Value::Number(n) => Some(*n),
+            Value::Text(s) => s.trim().parse().ok(),
+        }
+    }
+
+    fn from_sql(value: ValueRef) -> Self {
+        match value {
+            ValueRef::Null => Value::Null,
+            ValueRef::Integer(n) => Value::Number(n as f64),
+            ValueRef::Real(n) => Value::Number(n),
+            ValueRef::Text(s) | ValueRef::Blob(s) => {
+                Value::Text(String::from_utf8_lossy(s).into_owned())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("NULL"),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Value::Number(n) => write!(f, "{n}"),
+            Value::Text(s) => f.write_str(s),
+        }
+    }
+}
+
+// the rows a query returned
+#[derive(Debug, Clone, Default)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    // the event behind each row, from its id column
+    pub events: Vec<Option<usize>>,
+    // more rows matched than MAX_ROWS
+    pub truncated: bool,
+}
+
+// the events of a trace copied into sqlite, built once per loaded trace
+pub struct Database {
+    conn: Connection,
+    // the counter columns after COLUMNS
+    pub counters: Vec<String>,
+}
+
+impl Database {
+    pub fn new(data: &ProfileData) -> Result<Self> {
+        let conn = Connection::open_in_memory()?;
+        conn.create_scalar_function(
+            "extra_field",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let extra: Option<String> = ctx.get(0)?;
+                let key: String = ctx.get(1)?;
+                Ok(extra.and_then(|extra| {
+                    extra
+                        .split(';')
+                        .filter_map(|kv| kv.split_once('='))
+                        .find(|(k, _)| k.trim().eq_ignore_ascii_case(&key))
+                        .map(|(_, v)| v.trim().to_string())
+                }))
+            },
+        )?;
+
+        // counters named like a column stay in Extra only
+        let counters: Vec<String> = data
+            .counters
+            .keys()
+            .filter(|k| !COLUMNS.iter().any(|c| c.eq_ignore_ascii_case(k)))
+            .cloned()
+            .collect();
+        let quote = |name: &str| format!("\"{}\"", name.replace('"', "\"\""));
+        let definitions: Vec<String> = COLUMNS
+            .iter()
+            .zip(TYPES)
+            .map(|(name, kind)| format!("{} {kind}", quote(name)))
+            .chain(counters.iter().map(|c| format!("{} REAL", quote(c))))
+            .collect();
+        conn.execute_batch(&format!(
+            "CREATE TABLE events ({});",
+            definitions.join(", ")
+        ))?;
+
+        let columns = COLUMNS.len() + counters.len();
+        let placeholders = vec!["?"; columns].join(", ");
+        let tx = conn.unchecked_transaction()?;
+        {
+            let mut insert = tx.prepare(&format!("INSERT INTO events VALUES ({placeholders})"))?;
+            for (i, e) in data.iter().enumerate() {
+                let raw = &e.raw;
+                let counts: Vec<Option<f64>> = counters
+                    .iter()
+                    .map(|c| {
+                        let (_, value) = raw.extra_fields().find(|(k, _)| k == c)?;
+                        value.parse().ok()
+                    })
+                    .collect();
+                let (id, end) = (i as i64, raw.time + raw.duration_sec);
+                let bytes = (raw.bytes_tx + raw.bytes_rx) as i64;
+                let (tx, rx) = (raw.bytes_tx as i64, raw.bytes_rx as i64);
+                let host = data.pe_hostnames.get(&e.source_pe);
+                let mut row: Vec<&dyn ToSql> = vec![
+                    &id,
+                    &raw.time,
+                    &end,
+                    &raw.duration_sec,
+                    &raw.function,
+                    &e.source_pe,
+                    &e.context,
+                    &host,
+                    &raw.target_pe,
+                    &tx,
+                    &rx,
+                    &bytes,
+                    &raw.stacktrace,
+                    &raw.extra,
+                    &raw.symboltrace,
+                ];
+                row.extend(counts.iter().map(|c| c as &dyn ToSql));
+                insert.execute(params_from_iter(row))?;
+            }
+        }
+        tx.execute_batch("CREATE INDEX events_time ON events (time);")?;
+        tx.commit()?;
+        Ok(Database { conn, counters })
+    }
+
+    // runs `source` over the events starting in `range`, or all of them.
+    // only a single statement that doesn't change the table is taken
+    pub fn run(&self, source: &str, range: Option<(f64, f64)>) -> Result<QueryResult> {
+        if source.trim().is_empty() {
+            bail!("empty query");
+        }
+        // a temporary view shadows the table for the length of the query
+        if let Some((start, end)) = range {
+            self.conn.execute_batch(&format!(
+                "CREATE TEMP VIEW events AS SELECT * FROM main.events \
+                 WHERE time >= {start:?} AND time <= {end:?};"
+            ))?;
+        }
+        let result = self.query(source);
+        if range.is_some() {
+            self.conn.execute_batch("DROP VIEW temp.events;")?;
+        }
+        result
+    }
+
+    fn query(&self, source: &str) -> Result<QueryResult> {
+        let mut statement = self.conn.prepare(source).map_err(|e| match e {
+            rusqlite::Error::MultipleStatement => anyhow::anyhow!("one statement at a time"),
+            e => anyhow::Error::new(e),
+        })?;
+        if !statement.readonly() {
+            bail!("only queries are run, the events can't be changed");
+        }
+        let columns: Vec<String> = statement
+            .column_names()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let id = columns.iter().position(|c| c.eq_ignore_ascii_case("id"));
+        let mut result = QueryResult {
+            columns,
+            ..Default::default()
+        };
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            if result.rows.len() == MAX_ROWS {
+                result.truncated = true;
+                break;
+            }
+            let values = (0..result.columns.len())
+                .map(|c| Ok(Value::from_sql(row.get_ref(c)?)))
+                .collect::<Result<Vec<_>>>()?;
+            let event = id
+                .and_then(|c| values[c].as_number())
+                .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+                .map(|n| n as usize);
+            result.rows.push(values);
+            result.events.push(event);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::RawEvent;
+    use crate::synthetic::event;
+
+    fn database() -> Database {
+        let mut data = ProfileData::from_pes(vec![
+            vec![
+                RawEvent {
+                    extra: Some("host=a;heap=10".to_string()),
+                    ..event(0.0, "shmem_init", 0.5, -1, 0, 0)
+                },
+                RawEvent {
+                    extra: Some("heap=20;tag=x".to_string()),
+                    ..event(1.0, "shmem_putmem", 0.5, 1, 0, 64)
+                },
+                event(3.0, "shmem_putmem", 0.5, 1, 0, 128),
+            ],
+            vec![
+                RawEvent {
+                    extra: Some("host=b".to_string()),
+                    ..event(0.0, "shmem_init", 0.5, -1, 0, 0)
+                },
+                event(2.0, "shmem_getmem", 0.5, 0, 0, 32),
+            ],
+        ]);
+        data.pe_hostnames = [(0, "a".to_string()), (1, "b".to_string())].into();
+        Database::new(&data).unwrap()
+    }
+
+    fn rows(db: &Database, source: &str) -> Vec<String> {
+        let result = db.run(source, None).unwrap();
+        result
+            .rows
+            .iter()
+            .map(|row| {
+                let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+                row.join(" ")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn columns() {
+        let db = database();
+        let result = db.run("SELECT * FROM events", None).unwrap();
+        let mut expected: Vec<&str> = COLUMNS.to_vec();
+        expected.push("heap");
+        assert_eq!(result.columns, expected);
+        assert_eq!(result.rows.len(), 5);
+        assert_eq!(
+            rows(
+                &db,
+                "SELECT id, function, pe, host, heap FROM events WHERE time > 0"
+            ),
+            [
+                "2 shmem_putmem 0 a 20",
+                "3 shmem_getmem 1 b NULL",
+                "4 shmem_putmem 0 a NULL",
+            ]
+        );
+        assert_eq!(
+            rows(
+                &db,
+                "SELECT end, bytes, context, extra_field(extra, 'TAG') FROM events WHERE id = 2"
+            ),
+            ["1.5 64 NULL x"]
+        );
+    }
+
+    #[test]
+    fn grouping_and_order() {
+        let db = database();
+        assert_eq!(
+            rows(
+                &db,
+                "SELECT function, count(*) AS calls, sum(bytes) FROM events \
+                 WHERE function LIKE 'shmem_%mem' GROUP BY function ORDER BY 3 DESC"
+            ),
+            ["shmem_putmem 2 192", "shmem_getmem 1 32"]
+        );
+        assert_eq!(
+            rows(
+                &db,
+                "SELECT pe, max(time) FROM events GROUP BY pe HAVING count(*) > 2"
+            ),
+            ["0 3"]
+        );
+        assert_eq!(
+            rows(
+                &db,
+                "SELECT DISTINCT function FROM events ORDER BY function LIMIT 2"
+            ),
+            ["shmem_getmem", "shmem_init"]
+        );
+    }
+
+    #[test]
+    fn events_and_ranges() {
+        let db = database();
+        let result = db
+            .run("SELECT id, time FROM events", Some((0.5, 2.0)))
+            .unwrap();
+        assert_eq!(result.events, [Some(2), Some(3)]);
+        // without an id, rows don't link anywhere
+        let result = db.run("SELECT time FROM events LIMIT 1", None).unwrap();
+        assert_eq!(result.events, [None]);
+        // the range is gone again after a query
+        assert_eq!(rows(&db, "SELECT count(*) FROM events"), ["5"]);
+        assert!(db.run("SELECT nothing", Some((0.0, 1.0))).is_err());
+        assert_eq!(rows(&db, "SELECT count(*) FROM events"), ["5"]);
+    }
+
+    #[test]
+    fn errors() {
+        let db = database();
+        let error = |source: &str| format!("{:#}", db.run(source, None).unwrap_err());
+        assert_eq!(error("  "), "empty query");
+        assert!(error("SELEKT 1").contains("syntax error"));
+        assert!(error("SELECT size FROM events").contains("no such column: size"));
+        assert_eq!(error("SELECT 1; SELECT 2"), "one statement at a time");
+        assert_eq!(
+            error("DELETE FROM events"),
+            "only queries are run, the events can't be changed"
+        );
+        assert_eq!(
+            error("DROP TABLE events"),
+            "only queries are run, the events can't be changed"
+        );
+        assert_eq!(rows(&db, "SELECT count(*) FROM events"), ["5"]);
+    }
+}
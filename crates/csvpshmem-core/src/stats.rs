@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use crate::data::{Event, ProfileData};
+
+#[derive(Debug, Clone, Default)]
+pub struct CallSiteStats {
+    pub site: String,
+    pub calls: usize,
+    pub total_time: f64,
+    pub bytes: u64,
+    // indices into ProfileData::events, in time order
+    pub events: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FunctionStats {
+    pub function: String,
+    pub calls: usize,
+    pub total_time: f64,
+    pub bytes: u64,
+    // busiest first
+    pub sites: Vec<CallSiteStats>,
+}
+
+// the innermost Stacktrace frame, i.e. where the function was called from
+pub fn call_site<'a>(e: &Event<'a>) -> &'a str {
+    e.raw.stacktrace.split('|').next().unwrap_or("").trim()
+}
+
+// events starting in [start, end] that `keep` lets through, grouped by
+// function and then call site. functions with the most time in calls come
+// first
+pub fn by_call_site(
+    data: &ProfileData,
+    start: f64,
+    end: f64,
+    keep: impl Fn(&Event) -> bool,
+) -> Vec<FunctionStats> {
+    let first = data.events.time.partition_point(|&t| t < start);
+    let last = data.events.time.partition_point(|&t| t <= end);
+
+    let mut sites: HashMap<&str, HashMap<&str, CallSiteStats>> = HashMap::new();
+    for i in first..last {
+        let e = data.event(i);
+        if !keep(&e) {
+            continue;
+        }
+        let site = call_site(&e);
+        let stats = sites
+            .entry(e.raw.function)
+            .or_default()
+            .entry(site)
+            .or_insert_with(|| CallSiteStats {
+                site: site.to_string(),
+                ..Default::default()
+            });
+        stats.calls += 1;
+        stats.total_time += e.raw.duration_sec;
+        stats.bytes += e.raw.bytes_tx + e.raw.bytes_rx;
+        stats.events.push(i);
+    }
+
+    let mut functions: Vec<FunctionStats> = sites
+        .into_iter()
+        .map(|(function, sites)| {
+            let mut sites: Vec<CallSiteStats> = sites.into_values().collect();
+            sites.sort_by(|a, b| b.total_time.total_cmp(&a.total_time));
+            FunctionStats {
+                function: function.to_string(),
+                calls: sites.iter().map(|s| s.calls).sum(),
+                total_time: sites.iter().map(|s| s.total_time).sum(),
+                bytes: sites.iter().map(|s| s.bytes).sum(),
+                sites,
+            }
+        })
+        .collect();
+    functions.sort_by(|a, b| b.total_time.total_cmp(&a.total_time));
+    functions
+}
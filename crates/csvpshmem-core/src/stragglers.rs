@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use crate::data::ProfileData;
+
+// how late one PE tends to show up to collectives
+#[derive(Debug, Clone)]
+pub struct PeLateness {
+    pub pe: u32,
+    // instances where this PE entered last
+    pub last_count: usize,
+    // seconds behind the first PE to enter, averaged over all instances
+    pub mean_lateness: f64,
+    pub worst_lateness: f64,
+    // start of the instance with the worst lateness
+    pub worst_time: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StragglerReport {
+    // functions treated as collectives
+    pub functions: Vec<String>,
+    pub instances: usize,
+    // latest on average first
+    pub pes: Vec<PeLateness>,
+}
+
+// a call counts as a collective instance when the nth call of a function
+// happens on every PE and all of them return within `max_spread` seconds.
+// arrival times are left free since that spread is what we're measuring
+pub fn detect(data: &ProfileData, max_spread: f64) -> StragglerReport {
+    let pe_count = data.pe_count as usize;
+    if pe_count < 2 {
+        return StragglerReport::default();
+    }
+
+    // function -> pe -> (start, end), in time order since events are sorted
+    let mut calls: HashMap<&str, Vec<Vec<(f64, f64)>>> = HashMap::new();
+    for e in data.iter() {
+        calls
+            .entry(e.raw.function)
+            .or_insert_with(|| vec![Vec::new(); pe_count])[e.source_pe as usize]
+            .push((e.raw.time, e.raw.time + e.raw.duration_sec));
+    }
+
+    let mut last_count = vec![0usize; pe_count];
+    let mut total = vec![0.0f64; pe_count];
+    let mut worst = vec![(0.0f64, 0.0f64); pe_count];
+    let mut functions = Vec::new();
+    let mut instances = 0;
+
+    for (function, per_pe) in calls {
+        let count = per_pe.iter().map(Vec::len).min().unwrap_or(0);
+        let mut found = 0;
+        for n in 0..count {
+            let ends = per_pe.iter().map(|c| c[n].1);
+            let spread =
+                ends.clone().fold(f64::NEG_INFINITY, f64::max) - ends.fold(f64::INFINITY, f64::min);
+            if spread > max_spread {
+                continue;
+            }
+            found += 1;
+            let arrivals = per_pe.iter().map(|c| c[n].0);
+            let first = arrivals.clone().fold(f64::INFINITY, f64::min);
+            let last = arrivals.clone().fold(f64::NEG_INFINITY, f64::max);
+            for (pe, t) in arrivals.enumerate() {
+                let lateness = t - first;
+                total[pe] += lateness;
+                // nobody is last if they all arrived together
+                if t == last && last > first {
+                    last_count[pe] += 1;
+                }
+                if lateness > worst[pe].0 {
+                    worst[pe] = (lateness, first);
+                }
+            }
+        }
+        if found > 0 {
+            functions.push(function.to_string());
+            instances += found;
+        }
+    }
+    functions.sort();
+
+    let mut pes: Vec<PeLateness> = (0..pe_count)
+        .map(|pe| PeLateness {
+            pe: pe as u32,
+            last_count: last_count[pe],
+            mean_lateness: if instances > 0 {
+                total[pe] / instances as f64
+            } else {
+                0.0
+            },
+            worst_lateness: worst[pe].0,
+            worst_time: worst[pe].1,
+        })
+        .collect();
+    pes.sort_by(|a, b| b.mean_lateness.total_cmp(&a.mean_lateness));
+
+    StragglerReport {
+        functions,
+        instances,
+        pes,
+    }
+}
@@ -0,0 +1,301 @@
+use anyhow::{Context, Result, bail};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::data::{self, RawEvent};
+use crate::manifest;
+
+// fake traces shaped like real ones, for demos, benchmarks and tests of the
+// loader and views. every PE alternates puts and gets to the peers of the
+// pattern with compute in between, and all of them meet in a barrier ten
+// times over the run, the slow ones arriving last. now and then one
+// broadcasts. transfers across hosts are slower than within one
+
+const HEADER: [&str; 9] = [
+    "Time",
+    "Function",
+    "Duration_Sec",
+    "Target_PE",
+    "Bytes_RX",
+    "Bytes_TX",
+    "Stacktrace",
+    "Extra",
+    "Symboltrace",
+];
+
+// barriers over the whole run
+const ITERATIONS: u32 = 10;
+// message sizes to pick from, small ones more likely
+const SIZES: [u64; 8] = [8, 8, 64, 512, 4096, 32768, 262144, 1048576];
+// seconds of latency, and bytes per second within a host and across hosts
+const ALPHA: f64 = 1.5e-6;
+const BETA_LOCAL: f64 = 12e9;
+const BETA_REMOTE: f64 = 4e9;
+
+// who a PE talks to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    // the next PE
+    Ring,
+    // any other PE
+    AllToAll,
+    // the PE on either side
+    NearestNeighbor,
+}
+
+impl Pattern {
+    pub const ALL: [Pattern; 3] = [Pattern::Ring, Pattern::AllToAll, Pattern::NearestNeighbor];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pattern::Ring => "ring",
+            Pattern::AllToAll => "all-to-all",
+            Pattern::NearestNeighbor => "nearest-neighbor",
+        }
+    }
+
+    // a peer of `pe` out of `pe_count`
+    fn target(self, pe: u32, pe_count: u32, rng: &mut Rng) -> u32 {
+        if pe_count < 2 {
+            return pe;
+        }
+        match self {
+            Pattern::Ring => (pe + 1) % pe_count,
+            Pattern::AllToAll => (pe + 1 + rng.below(pe_count as u64 - 1) as u32) % pe_count,
+            Pattern::NearestNeighbor if rng.chance(0.5) => (pe + 1) % pe_count,
+            Pattern::NearestNeighbor => (pe + pe_count - 1) % pe_count,
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        Pattern::ALL
+            .into_iter()
+            .find(|p| p.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Pattern::ALL.iter().map(|p| p.name()).collect();
+                format!("expected one of {}", names.join(", "))
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub pe_count: u32,
+    // calls per second on each PE
+    pub rate: f64,
+    // length of the run
+    pub seconds: f64,
+    pub pattern: Pattern,
+    pub pes_per_host: u32,
+    // the same seed makes the same trace
+    pub seed: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            pe_count: 8,
+            rate: 2000.0,
+            seconds: 1.0,
+            pattern: Pattern::Ring,
+            pes_per_host: 4,
+            seed: 1,
+        }
+    }
+}
+
+impl Config {
+    pub fn host(&self, pe: u32) -> String {
+        format!("node{:02}", pe / self.pes_per_host.max(1))
+    }
+}
+
+// splitmix64, plenty for made up timings
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n.max(1)
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        self.unit() < p
+    }
+
+    // exponential with this mean, the gaps between independent calls
+    fn exponential(&mut self, mean: f64) -> f64 {
+        -mean * (1.0 - self.unit()).ln()
+    }
+}
+
+// stack and symbol trace (without the call itself) a function is called from
+fn call_site(function: &str) -> (&'static str, &'static str) {
+    match function {
+        "shmem_init" | "shmem_finalize" => ("0x401136;0x7f3a12c29d90", "main"),
+        "shmem_barrier_all" => ("0x4012a8;0x401180;0x7f3a12c29d90", "main;step"),
+        "shmem_broadcastmem" => ("0x4012d4;0x401180;0x7f3a12c29d90", "main;step"),
+        _ => (
+            "0x401352;0x4012f0;0x401180;0x7f3a12c29d90",
+            "main;step;exchange",
+        ),
+    }
+}
+
+// a call with no traces or extra fields, also what the unit tests build
+// their traces from
+pub(crate) fn event(
+    time: f64,
+    function: &str,
+    duration: f64,
+    target: i32,
+    rx: u64,
+    tx: u64,
+) -> RawEvent {
+    RawEvent {
+        time,
+        function: function.to_string(),
+        duration_sec: duration,
+        target_pe: target,
+        bytes_rx: rx,
+        bytes_tx: tx,
+        stacktrace: String::new(),
+        extra: None,
+        symboltrace: None,
+    }
+}
+
+// the calls of every PE, in time order, by PE
+pub fn generate(config: &Config) -> Vec<Vec<RawEvent>> {
+    let n = config.pe_count.max(1);
+    let period = config.seconds / ITERATIONS as f64;
+    let gap = 1.0 / config.rate.max(1e-9);
+    // when every PE leaves each barrier
+    let exits: Vec<f64> = (1..=ITERATIONS).map(|k| k as f64 * period).collect();
+    (0..n)
+        .map(|pe| {
+            let mut rng = Rng(config.seed ^ (u64::from(pe) << 32));
+            // some PEs are slower, so others wait for them in the barriers
+            let slowness = 1.0 + rng.unit() * 0.3;
+            let mut init = event(0.0, "shmem_init", 2e-4, -1, 0, 0);
+            init.extra = Some(format!("host={}", config.host(pe)));
+            let mut events = vec![init];
+            let mut t = 2e-4;
+            for (k, exit) in exits.iter().enumerate() {
+                // every other iteration starts with a broadcast from one PE
+                if k % 2 == 1 && pe == k as u32 % n {
+                    let bytes = SIZES[4 + rng.below(4) as usize];
+                    let duration = ALPHA * 8.0 + bytes as f64 / BETA_REMOTE;
+                    events.push(event(t, "shmem_broadcastmem", duration, -1, 0, bytes));
+                    t += duration;
+                }
+                // the faster a PE, the earlier it stops to wait in the
+                // barrier
+                let idle = 0.02 + 0.1 * (1.3 - slowness) / 0.3;
+                let arrive = exit - period * idle * (0.8 + 0.2 * rng.unit());
+                loop {
+                    let compute = rng.exponential(gap) * slowness;
+                    let start = t + compute;
+                    let target = config.pattern.target(pe, n, &mut rng);
+                    let bytes = SIZES[rng.below(SIZES.len() as u64) as usize];
+                    let beta = if config.host(pe) == config.host(target) {
+                        BETA_LOCAL
+                    } else {
+                        BETA_REMOTE
+                    };
+                    let duration = (ALPHA + bytes as f64 / beta) * (1.0 + rng.unit() * 0.5);
+                    let call = if rng.chance(0.7) {
+                        event(start, "shmem_putmem", duration, target as i32, 0, bytes)
+                    } else {
+                        event(start, "shmem_getmem", duration, target as i32, bytes, 0)
+                    };
+                    if start + call.duration_sec >= arrive {
+                        break;
+                    }
+                    t = start + call.duration_sec;
+                    let quiet = call.function == "shmem_putmem" && rng.chance(0.3);
+                    events.push(call);
+                    if quiet {
+                        events.push(event(t, "shmem_quiet", ALPHA, -1, 0, 0));
+                        t += ALPHA;
+                    }
+                }
+                let exit = exit.max(t);
+                events.push(event(t, "shmem_barrier_all", exit - t, -1, 0, 0));
+                t = exit;
+            }
+            events.push(event(t, "shmem_finalize", 1e-4, -1, 0, 0));
+            for e in &mut events {
+                let (stack, caller) = call_site(&e.function);
+                e.stacktrace = stack.to_string();
+                e.symboltrace = Some(format!("{caller};{}", e.function));
+            }
+            events
+        })
+        .collect()
+}
+
+// writes pperf.N.csv for every PE, plus a manifest, into `dir`. refuses a
+// directory that already holds a trace rather than mixing two
+pub fn write(dir: &Path, config: &Config) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    if !data::pperf_files(dir)?.is_empty() {
+        bail!(
+            "{} already holds pperf files, pick another directory",
+            dir.display()
+        );
+    }
+    let mut paths = Vec::new();
+    for (pe, events) in generate(config).into_iter().enumerate() {
+        let path = dir.join(format!("pperf.{pe}.csv"));
+        let mut writer =
+            csv::Writer::from_path(&path).with_context(|| format!("writing {}", path.display()))?;
+        writer.write_record(HEADER)?;
+        for e in events {
+            writer.write_record([
+                e.time.to_string(),
+                e.function,
+                e.duration_sec.to_string(),
+                e.target_pe.to_string(),
+                e.bytes_rx.to_string(),
+                e.bytes_tx.to_string(),
+                e.stacktrace,
+                e.extra.unwrap_or_default(),
+                e.symboltrace.unwrap_or_default(),
+            ])?;
+        }
+        writer.flush()?;
+        paths.push(path);
+    }
+    let hostnames: Vec<String> = (0..config.pe_count).map(|pe| config.host(pe)).collect();
+    let manifest = serde_json::json!({
+        "pe_count": config.pe_count,
+        "hostnames": hostnames,
+    });
+    fs::write(manifest::Manifest::path(dir), manifest.to_string())?;
+    Ok(paths)
+}
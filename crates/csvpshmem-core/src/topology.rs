@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::bandwidth::Comms;
+use crate::data::ProfileData;
+
+// what the ring groups PEs by. switches and racks need a topology
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GroupLevel {
+    #[default]
+    Host,
+    Switch,
+    Rack,
+}
+
+impl GroupLevel {
+    pub const ALL: [GroupLevel; 3] = [GroupLevel::Host, GroupLevel::Switch, GroupLevel::Rack];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GroupLevel::Host => "host",
+            GroupLevel::Switch => "switch",
+            GroupLevel::Rack => "rack",
+        }
+    }
+
+    pub fn plural(self) -> &'static str {
+        match self {
+            GroupLevel::Host => "hosts",
+            GroupLevel::Switch => "switches",
+            GroupLevel::Rack => "racks",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Location {
+    pub switch: Option<String>,
+    pub rack: Option<String>,
+}
+
+// bytes/s of the uplinks at each level, None when unknown
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkCapacity {
+    // host to its switch
+    pub host: Option<f64>,
+    // switch to its rack
+    pub switch: Option<f64>,
+    // rack to the rest of the network
+    pub rack: Option<f64>,
+}
+
+// where each host sits in the network, e.g. in toml
+//   [hosts]
+//   node0 = { switch = "sw0", rack = "r0" }
+//   [links]
+//   host = 12.5e9
+// or the same shape in json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Topology {
+    pub hosts: BTreeMap<String, Location>,
+    pub links: LinkCapacity,
+}
+
+impl Topology {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let topology = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&text)?
+        } else {
+            toml::from_str(&text)?
+        };
+        Ok(topology)
+    }
+
+    pub fn switch(&self, host: &str) -> Option<&str> {
+        self.hosts.get(host)?.switch.as_deref()
+    }
+
+    pub fn rack(&self, host: &str) -> Option<&str> {
+        self.hosts.get(host)?.rack.as_deref()
+    }
+
+    // the group `host` belongs to at `level`. hosts missing from the
+    // topology stay on their own
+    pub fn group(&self, host: &str, level: GroupLevel) -> String {
+        let group = match level {
+            GroupLevel::Host => None,
+            GroupLevel::Switch => self.switch(host),
+            GroupLevel::Rack => self.rack(host).or_else(|| self.switch(host)),
+        };
+        group.unwrap_or(host).to_string()
+    }
+}
+
+// bytes split by the widest part of the network they cross
+#[derive(Debug, Default)]
+pub struct LevelTraffic {
+    pub same_host: u64,
+    pub same_switch: u64,
+    pub same_rack: u64,
+    // between racks, or hosts the topology doesn't place
+    pub across: u64,
+}
+
+impl LevelTraffic {
+    pub fn total(&self) -> u64 {
+        self.same_host + self.same_switch + self.same_rack + self.across
+    }
+}
+
+pub fn level_traffic(comms: &Comms, data: &ProfileData, topology: &Topology) -> LevelTraffic {
+    let mut traffic = LevelTraffic::default();
+    for ((src, dst), (tx, rx)) in comms {
+        let host = |pe: &u32| data.pe_hostnames.get(pe).map_or("?", String::as_str);
+        let (a, b) = (host(src), host(dst));
+        let same = |x: Option<&str>, y: Option<&str>| x.is_some() && x == y;
+        let bucket = if a == b {
+            &mut traffic.same_host
+        } else if same(topology.switch(a), topology.switch(b)) {
+            &mut traffic.same_switch
+        } else if same(topology.rack(a), topology.rack(b)) {
+            &mut traffic.same_rack
+        } else {
+            &mut traffic.across
+        };
+        *bucket += tx + rx;
+    }
+    traffic
+}
@@ -0,0 +1,72 @@
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::data::{self, ProfileData};
+use crate::parquet;
+
+// copies the source csv files into `out_dir`, keeping only the rows of calls
+// running at some point in [start, end]. rows are copied as they are, times
+// included. the first row of every file stays too, since it names the host.
+// the directories of a merged trace go to run0, run1, ... under `out_dir`
+pub fn write_trimmed(data: &ProfileData, start: f64, end: f64, out_dir: &Path) -> Result<usize> {
+    fs::create_dir_all(out_dir).with_context(|| format!("creating {}", out_dir.display()))?;
+    let out_dir = out_dir.canonicalize()?;
+    for source in &data.sources {
+        if source.parent().and_then(|p| p.canonicalize().ok()).as_ref() == Some(&out_dir) {
+            bail!(
+                "{} holds the trace itself, pick another directory",
+                out_dir.display()
+            );
+        }
+    }
+    if let Some(source) = data.sources.iter().find(|s| parquet::is_parquet_file(s)) {
+        bail!("{} isn't a csv file to trim", source.display());
+    }
+    let mut rows = 0;
+    for source in &data.sources {
+        let Some((pe, _)) = data::parse_file_name(source) else {
+            continue;
+        };
+        let run = data
+            .runs
+            .iter()
+            .position(|r| source.parent() == Some(r.dir.as_path()));
+        let (pe, dir) = match run {
+            Some(i) => (pe + data.runs[i].first_pe, out_dir.join(format!("run{i}"))),
+            None => (pe, out_dir.clone()),
+        };
+        fs::create_dir_all(&dir)?;
+        // the timeline shows skew corrected times, the files hold the originals
+        let offset = data.clock_offset(pe);
+        let (start, end) = (start - offset, end - offset);
+
+        let mut reader = csv::Reader::from_path(source)
+            .with_context(|| format!("reading {}", source.display()))?;
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim() == name)
+                .with_context(|| format!("{} has no {name} column", source.display()))
+        };
+        let (time_col, duration_col) = (column("Time")?, column("Duration_Sec")?);
+
+        let path: PathBuf = dir.join(source.file_name().unwrap_or_default());
+        let mut writer =
+            csv::Writer::from_path(&path).with_context(|| format!("writing {}", path.display()))?;
+        writer.write_record(&headers)?;
+        for (i, record) in reader.records().enumerate() {
+            let record = record?;
+            let field = |col: usize| record.get(col).and_then(|v| v.trim().parse::<f64>().ok());
+            let time = field(time_col).unwrap_or(f64::NAN);
+            let duration = field(duration_col).unwrap_or(0.0).max(0.0);
+            if i == 0 || (time <= end && time + duration >= start) {
+                writer.write_record(&record)?;
+                rows += 1;
+            }
+        }
+        writer.flush()?;
+    }
+    Ok(rows)
+}
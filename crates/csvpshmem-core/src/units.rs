@@ -0,0 +1,94 @@
+// human readable bytes, rates and durations, shared by every view
+
+// decimals needed to tell apart values `step` apart, in a unit of `scale`
+pub fn decimals(step: f64, scale: f64) -> usize {
+    (-(step / scale).log10().floor()).clamp(0.0, 9.0) as usize
+}
+
+// 1536 -> "1.5 KiB"
+pub fn bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{} {}", significant(value), UNITS[unit])
+    }
+}
+
+// bytes per second, decimal units like link speeds: 2.5e9 -> "2.50 GB/s"
+pub fn rate(bytes_per_sec: f64) -> String {
+    if !bytes_per_sec.is_finite() {
+        return "-".to_string();
+    }
+    const UNITS: [&str; 5] = ["B/s", "KB/s", "MB/s", "GB/s", "TB/s"];
+    let mut value = bytes_per_sec;
+    let mut unit = 0;
+    while value.abs() >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    format!("{} {}", significant(value), UNITS[unit])
+}
+
+// seconds with about 3 significant digits: 0.0001234 -> "123 µs"
+pub fn duration(secs: f64) -> String {
+    if secs == 0.0 {
+        return "0 s".to_string();
+    }
+    let (unit, scale) = unit_for(secs.abs());
+    format!("{} {unit}", significant(secs / scale))
+}
+
+// seconds in the unit that suits the value, precise enough to resolve `step`.
+// used for ruler labels and cursor distances
+pub fn time(secs: f64, step: f64) -> String {
+    let (unit, scale) = unit_for(secs.abs().max(step));
+    if secs == 0.0 {
+        return format!("0{unit}");
+    }
+    format!("{:.*}{unit}", decimals(step, scale), secs / scale)
+}
+
+// "1.5ms", "20 us", "3" -> seconds. a bare number is seconds
+pub fn parse_duration(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let number = s.trim_end_matches(|c: char| c.is_alphabetic() || c == 'µ');
+    let unit = &s[number.len()..];
+    let scale = match unit.trim() {
+        "" | "s" => 1.0,
+        "ms" => 1e-3,
+        "us" | "µs" => 1e-6,
+        "ns" => 1e-9,
+        _ => return None,
+    };
+    let value: f64 = number.trim().parse().ok()?;
+    value.is_finite().then_some(value * scale)
+}
+
+fn unit_for(magnitude: f64) -> (&'static str, f64) {
+    if magnitude >= 1.0 {
+        ("s", 1.0)
+    } else if magnitude >= 1e-3 {
+        ("ms", 1e-3)
+    } else if magnitude >= 1e-6 {
+        ("µs", 1e-6)
+    } else {
+        ("ns", 1e-9)
+    }
+}
+
+// 3 significant digits for values in [1, 1000), fewer decimals as they grow
+pub fn significant(value: f64) -> String {
+    let places = match value.abs() {
+        v if v >= 100.0 => 0,
+        v if v >= 10.0 => 1,
+        _ => 2,
+    };
+    format!("{value:.places$}")
+}
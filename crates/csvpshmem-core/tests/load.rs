@@ -0,0 +1,138 @@
+use csvpshmem_core::data::{ProfileData, UNKNOWN_HOST};
+use std::fs;
+use std::path::PathBuf;
+
+const HEADER: &str =
+    "Time,Function,Duration_Sec,Target_PE,Bytes_RX,Bytes_TX,Stacktrace,Extra,Symboltrace\n";
+
+// a trace directory of its own under the temp dir, removed when dropped
+struct Trace {
+    dir: PathBuf,
+}
+
+impl Trace {
+    fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("csvpshmem-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        Self { dir }
+    }
+
+    fn load(&self) -> anyhow::Result<ProfileData> {
+        ProfileData::load_from_dir(&self.dir)
+    }
+}
+
+impl Drop for Trace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn pe_file(host: &str) -> String {
+    format!(
+        "{HEADER}0.0,shmem_init,0.001,-1,0,0,0x1,host={host},a\n\
+         0.01,shmem_put,0.001,1,0,64,0x2,,b\n"
+    )
+}
+
+#[test]
+fn header_only_file() {
+    let trace = Trace::new(
+        "header-only",
+        &[("pperf.0.csv", &pe_file("n0")), ("pperf.1.csv", HEADER)],
+    );
+    let data = trace.load().unwrap();
+    assert_eq!(data.pe_count, 2);
+    assert_eq!(data.events.len(), 2);
+    assert_eq!(data.pe_hostnames[&0], "n0");
+    assert_eq!(data.pe_hostnames[&1], UNKNOWN_HOST);
+    assert!(data.missing_pes().is_empty());
+}
+
+#[test]
+fn empty_file() {
+    let trace = Trace::new(
+        "empty",
+        &[("pperf.0.csv", ""), ("pperf.1.csv", &pe_file("n1"))],
+    );
+    let data = trace.load().unwrap();
+    assert_eq!(data.pe_count, 2);
+    assert_eq!(data.events.len(), 2);
+    assert_eq!(data.pe_hostnames[&0], UNKNOWN_HOST);
+    assert_eq!(data.pe_hostnames[&1], "n1");
+}
+
+#[test]
+fn only_empty_files() {
+    let trace = Trace::new("all-empty", &[("pperf.0.csv", HEADER), ("pperf.1.csv", "")]);
+    let data = trace.load().unwrap();
+    assert_eq!(data.pe_count, 2);
+    assert!(data.events.is_empty());
+    assert_eq!(data.min_time, 0.0);
+    assert_eq!(data.max_time, 0.0);
+}
+
+#[test]
+fn manifest_host_wins_over_unknown() {
+    let trace = Trace::new(
+        "empty-manifest",
+        &[
+            ("pperf.0.csv", &pe_file("n0")),
+            ("pperf.1.csv", HEADER),
+            ("pperf.meta.json", r#"{"hostnames": ["n0", "n7"]}"#),
+        ],
+    );
+    let data = trace.load().unwrap();
+    assert_eq!(data.pe_hostnames[&1], "n7");
+}
+
+#[test]
+fn no_host_in_first_event() {
+    let no_host = format!("{HEADER}0.0,shmem_init,0.001,-1,0,0,0x1,,a\n");
+    let trace = Trace::new(
+        "no-host",
+        &[("pperf.0.csv", &no_host), ("pperf.1.csv", &pe_file("n1"))],
+    );
+    let data = trace.load().unwrap();
+    assert_eq!(data.events.len(), 3);
+    assert_eq!(data.pe_hostnames[&0], UNKNOWN_HOST);
+    assert_eq!(data.pe_hostnames[&1], "n1");
+}
+
+#[test]
+fn truncated_last_row() {
+    let truncated = format!("{}0.02,shmem_put,0.0", pe_file("n0"));
+    let trace = Trace::new("truncated", &[("pperf.0.csv", &truncated)]);
+    let data = trace.load().unwrap();
+    assert_eq!(data.events.len(), 2);
+    assert_eq!(data.event(1).raw.function, "shmem_put");
+}
+
+#[test]
+fn truncated_header() {
+    let trace = Trace::new(
+        "truncated-header",
+        &[
+            ("pperf.0.csv", "Time,Function,Dur"),
+            ("pperf.1.csv", &pe_file("n1")),
+        ],
+    );
+    let data = trace.load().unwrap();
+    assert_eq!(data.events.len(), 2);
+    assert_eq!(data.pe_hostnames[&0], UNKNOWN_HOST);
+}
+
+#[test]
+fn damage_before_the_end_is_an_error() {
+    let damaged = format!(
+        "{HEADER}0.0,shmem_init,0.001,-1,0,0,0x1,host=n0,a\n\
+         0.01,shmem_put\n\
+         0.02,shmem_put,0.001,1,0,64,0x2,,b\n"
+    );
+    let trace = Trace::new("damaged", &[("pperf.0.csv", &damaged)]);
+    assert!(trace.load().is_err());
+}
@@ -0,0 +1,86 @@
+use csvpshmem_core::data::ProfileData;
+use csvpshmem_core::synthetic::{self, Config, Pattern};
+use std::fs;
+use std::path::PathBuf;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("csvpshmem-synthetic-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn small(pattern: Pattern) -> Config {
+    Config {
+        pe_count: 6,
+        rate: 5000.0,
+        seconds: 0.05,
+        pattern,
+        pes_per_host: 2,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn same_seed_same_trace() {
+    let a = synthetic::generate(&small(Pattern::AllToAll));
+    let b = synthetic::generate(&small(Pattern::AllToAll));
+    let times = |pes: &[Vec<csvpshmem_core::data::RawEvent>]| -> Vec<f64> {
+        pes.iter().flatten().map(|e| e.time).collect()
+    };
+    assert_eq!(times(&a), times(&b));
+}
+
+#[test]
+fn patterns_load_back() {
+    for pattern in Pattern::ALL {
+        let config = small(pattern);
+        let dir = temp_dir(pattern.name());
+        let generated = synthetic::generate(&config);
+        synthetic::write(&dir, &config).unwrap();
+        let data = ProfileData::load_from_dir(&dir).unwrap();
+
+        assert_eq!(data.pe_count, 6);
+        assert!(data.missing_pes().is_empty());
+        assert_eq!(
+            data.events.len(),
+            generated.iter().map(Vec::len).sum::<usize>()
+        );
+        assert_eq!(data.pe_hostnames[&5], "node02");
+
+        let n = config.pe_count;
+        for e in data.iter() {
+            if e.raw.target_pe < 0 {
+                continue;
+            }
+            let (pe, target) = (e.source_pe, e.raw.target_pe as u32);
+            assert_ne!(pe, target);
+            match pattern {
+                Pattern::Ring => assert_eq!(target, (pe + 1) % n),
+                Pattern::NearestNeighbor => {
+                    assert!(target == (pe + 1) % n || target == (pe + n - 1) % n)
+                }
+                Pattern::AllToAll => assert!(target < n),
+            }
+        }
+        for pe in 0..n {
+            let barriers = data
+                .iter()
+                .filter(|e| e.source_pe == pe && e.raw.function == "shmem_barrier_all")
+                .count();
+            assert_eq!(barriers, 10);
+        }
+
+        // a second demo doesn't land on top of the first
+        assert!(synthetic::write(&dir, &config).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[test]
+fn pattern_names() {
+    for pattern in Pattern::ALL {
+        assert_eq!(pattern.name().parse::<Pattern>(), Ok(pattern));
+    }
+    assert!("mesh".parse::<Pattern>().is_err());
+}
@@ -0,0 +1,58 @@
+use egui::Color32;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::bandwidth::ByteFilter;
+use crate::bookmarks::Bookmarks;
+use crate::data::Event;
+use crate::filters::DurationFilter;
+use crate::grouping::Grouping;
+
+// what the user marked up or filtered out of the trace, as opposed to where
+// they're looking. saved with the session and stepped through by undo
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotations {
+    pub show_tx: bool,
+    pub show_rx: bool,
+    // set from the legend and the event context menu
+    pub hidden_functions: BTreeSet<String>,
+    pub hidden_groups: BTreeSet<String>,
+    pub solo_function: Option<String>,
+    // calls outside a range of durations, left out like hidden functions
+    pub duration_filter: DurationFilter,
+    // messages too small (or too big) for the bandwidth views
+    pub byte_filter: ByteFilter,
+    pub color_overrides: BTreeMap<String, Color32>,
+    pub bookmarks: Bookmarks,
+}
+
+impl Default for Annotations {
+    fn default() -> Self {
+        Self {
+            show_tx: true,
+            show_rx: true,
+            hidden_functions: BTreeSet::new(),
+            hidden_groups: BTreeSet::new(),
+            solo_function: None,
+            duration_filter: DurationFilter::default(),
+            byte_filter: ByteFilter::default(),
+            color_overrides: BTreeMap::new(),
+            bookmarks: Bookmarks::default(),
+        }
+    }
+}
+
+impl Annotations {
+    // whether hidden functions and groups and the solo function let the
+    // calls of `function` through
+    pub fn function_shown(&self, grouping: &Grouping, function: &str) -> bool {
+        !self.hidden_functions.contains(function)
+            && !self.hidden_groups.contains(grouping.group(function))
+            && self.solo_function.as_deref().is_none_or(|f| f == function)
+    }
+
+    // function_shown, and the call's duration is in the filter's range
+    pub fn event_shown(&self, grouping: &Grouping, e: &Event) -> bool {
+        self.duration_filter.keeps(e.raw.duration_sec)
+            && self.function_shown(grouping, e.raw.function)
+    }
+}
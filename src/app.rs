@@ -1,686 +1,3337 @@
-use egui::{Color32, Id, LayerId, Order, PopupAnchor, Pos2, Rect, Sense, Stroke, StrokeKind, Vec2};
-use std::collections::HashMap;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
-
-use crate::data::ProfileData;
+use egui::{Color32, Id, LayerId, Order, Pos2, Rect, Sense, Stroke, Vec2};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::annotations::Annotations;
+use crate::bandwidth::{BandwidthMode, EdgeWeight};
+use crate::cli::Args;
+use crate::coloring::{self, ColorBy, ColorMap, generate_color};
+use crate::config::Config;
+use crate::contention::Contention;
+use crate::data::{self, ProfileData};
+use crate::derived::Derived;
+use crate::dock::{DockLayout, Pane, Slot};
+use crate::export::{self, CapturedView, ExportFormat, ExportRequest, ExportTarget};
+use crate::flame::{self, FoldedWeight};
+use crate::gantt::{self, TableFormat};
+use crate::goto::Goto;
+use crate::grouping::{GroupRule, Grouping};
+use crate::hud::PerfHud;
+use crate::imbalance;
+use crate::keys::{self, Action};
+use crate::labels::{self, PeLabels};
+use crate::lanes::{Lanes, Stacking};
+use crate::layout::{Placement, RingGrouping};
+use crate::outliers;
+use crate::panes::Panes;
+use crate::parquet;
+use crate::remote::{Remote, RemoteFetch};
+use crate::report::{self, ReportInput};
+use crate::ruler::TimeDisplay;
+use crate::search::Search;
+use crate::session::Session;
+use crate::skew::{self, Anchor, ClockOffsets};
+use crate::symbols::Symbolizer;
+use crate::synthetic::{self, Pattern};
+use crate::tabs::{Parked, Tabs};
+use crate::theme::{self, Palette, ThemeChoice};
+use crate::timeline;
+use crate::topology::{self, GroupLevel, Topology};
+use crate::tracks::TrackOrder;
+use crate::traffic;
+use crate::trim;
+use crate::undo::{Snapshot, UndoHistory};
+use crate::units;
+use crate::video::{VideoFormat, VideoRecorder, VideoSettings};
+use crate::views::{ViewCommand, ViewRegistry, ViewState, paint_crosshair};
+use crate::watch::DataWatcher;
+use crate::windows::Windows;
+use crate::zoom::ZoomHistory;
+
+const CONTENTION_BINS: usize = 200;
+// links stacked in the contention chart, the rest are summed as "other"
+const CONTENTION_PLOT_LINKS: usize = 8;
+// frames drawn before --screenshot captures the window
+const SCREENSHOT_WARMUP_FRAMES: u32 = 3;
 
 pub struct VisualizerApp {
     profile_data: Option<ProfileData>,
     error_msg: Option<String>,
+    // data_dir holds no trace, offer to open one or make one up instead
+    show_welcome: bool,
+    // the trace the welcome screen generates, and where
+    demo_config: synthetic::Config,
+    demo_dir: String,
+    data_dir: PathBuf,
+    // files picked one by one (e.g. dropped), empty when all of data_dir is loaded
+    data_files: Vec<PathBuf>,
+    // directories merged side by side, each with its own PE numbering
+    data_dirs: Vec<PathBuf>,
+    // other open traces
+    tabs: Tabs,
+    open_path: String,
 
     // state
     cursor_time: f64,
+    // secondary measurement cursor, set with M or the context menu
+    cursor_b: Option<f64>,
+    // time under the pointer in any view with a time axis, which all of them
+    // draw as a crosshair. views set hover_next while drawing and it takes
+    // over at the end of the frame
     hover_time: Option<f64>,
+    hover_next: Option<f64>,
     window_size_seconds: f64,
 
     // playback
     playing: bool,
     playback_speed: f64,
+    loop_a: Option<f64>,
+    loop_b: Option<f64>,
+    loop_enabled: bool,
+    selected_pe: Option<u32>,
+    // time, offset or event to go to, focused by ctrl+G
+    time_input: String,
+    focus_time_input: bool,
 
-    // cache
-    // this isn't working as intended
-    function_colors: HashMap<String, Color32>,
+    // how calls are colored, and each function's color
+    colors: ColorMap,
 
-    // filters
-    show_rx: bool,
-    show_tx: bool,
+    // the timeline, bandwidth, stats and event panes
+    panes: Panes,
 
     // timeline state
     timeline_start_time: f64,
     timeline_end_time: f64,
-    timeline_pe_scroll: f32,
-    timeline_track_height: f32,
+    track_order: TrackOrder,
+    zoom_history: ZoomHistory,
+    // filters, color overrides, bookmarks and hidden PEs
+    undo_history: UndoHistory,
+    stacking: Stacking,
+    // set once the user asked for what the memory budget dropped, which
+    // then stays loaded until the viewer is restarted
+    ignore_memory_budget: bool,
+    lanes: Lanes,
+    visible_counters: BTreeSet<String>,
+    time_display: TimeDisplay,
+    // event whose details stay open in their own window
+    pinned_event: Option<usize>,
+    pinned_event_pos: Pos2,
+    // shift-dragged range
+    selection: Option<(f64, f64)>,
+
+    // filters, colors and bookmarks, what undo steps through
+    annotations: Annotations,
+    show_bookmarks_window: bool,
+
+    // analyses kept for the loaded trace
+    derived: Derived,
+    // phases, outliers, messages, queries and the other analysis windows
+    windows: Windows,
+
+    // event search
+    search_input: String,
+    search: Search,
+
+    // export
+    folded_per_pe: bool,
+    gantt_format: TableFormat,
+    // where a trimmed copy of the trace goes
+    trim_dir: String,
+    folded_weight: FoldedWeight,
+    export_target: ExportTarget,
+    export_format: ExportFormat,
+    export_width: u32,
+    export_path: String,
+    pending_export: Option<ExportRequest>,
+    video_format: VideoFormat,
+    video_fps: u32,
+    video_path: String,
+    recorder: Option<VideoRecorder>,
+
+    // session ui
+    session_path: String,
+    status_msg: Option<String>,
+    show_colors_window: bool,
+    show_legend_window: bool,
+
+    // function grouping rules
+    group_rules: Vec<GroupRule>,
+    grouping: Grouping,
+    rule_error: Option<String>,
+    show_rules_window: bool,
+
+    // stack frames
+    symbolizer: Symbolizer,
+    demangle_frames: bool,
+    symbol_binary_input: String,
+
+    // where the views are placed
+    dock: DockLayout,
+    dock_generation: u32,
+    // extra views beyond the built-in panes
+    views: ViewRegistry,
+
+    // appearance
+    theme: ThemeChoice,
+    ui_scale: f32,
+    // what was last handed to egui, to notice ctrl +/- zooming
+    applied_appearance: Option<(ThemeChoice, f32)>,
+    palette: Palette,
+
+    // defaults from the config file, and the settings window editing them
+    config: Config,
+    config_path: String,
+    show_settings_window: bool,
+
+    // clock skew correction, applied to every trace loaded
+    clock_offsets: ClockOffsets,
+    show_clocks_window: bool,
+
+    // names shown for PEs
+    pe_labels: PeLabels,
+    pe_labels_path: String,
+    show_labels_window: bool,
+
+    // network layout of the hosts
+    topology: Option<Topology>,
+    topology_path: String,
+    group_level: GroupLevel,
+    show_topology_window: bool,
+    // estimated link demand, made on request since it walks every event
+    contention: Option<Contention>,
+    show_contention_window: bool,
+
+    // files of the open trace changing on disk
+    watcher: Option<DataWatcher>,
+    data_changed: bool,
+    // a trace being copied from a server before it's loaded
+    remote_fetch: Option<RemoteFetch>,
+
+    // --screenshot target, and frames drawn while waiting to take it
+    screenshot_path: Option<PathBuf>,
+    screenshot_frames: u32,
+
+    // performance overlay
+    hud: PerfHud,
+    show_hud: bool,
+    hud_continuous: bool,
 }
 
 impl VisualizerApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        let root_dir = PathBuf::from(".");
+    pub fn new(cc: &eframe::CreationContext<'_>, args: Args) -> Self {
+        let (config, config_path, config_msg) = match Config::locate() {
+            Some(path) => match Config::load(&path) {
+                Ok(config) => (config, path, None),
+                Err(e) => (Config::default(), path, Some(format!("{e:#}"))),
+            },
+            None => (Config::default(), Config::default_path(), None),
+        };
+        let root_dir = args
+            .data_dir
+            .clone()
+            .or_else(|| config.data_dir.clone())
+            .unwrap_or_else(|| PathBuf::from("."));
         let mut app = Self {
             profile_data: None,
             error_msg: None,
+            show_welcome: false,
+            demo_config: args.demo_config(),
+            demo_dir: "demo".to_string(),
+            data_dir: root_dir.clone(),
+            data_files: Vec::new(),
+            data_dirs: Vec::new(),
+            tabs: Tabs::default(),
+            open_path: String::new(),
             cursor_time: 0.0,
+            cursor_b: None,
             hover_time: None,
+            hover_next: None,
             window_size_seconds: 0.01,
             playing: false,
             playback_speed: 1.0,
-            function_colors: HashMap::new(),
-            show_rx: true,
-            show_tx: true,
+            loop_a: None,
+            loop_b: None,
+            loop_enabled: false,
+            selected_pe: None,
+            time_input: String::new(),
+            focus_time_input: false,
+            colors: ColorMap::default(),
+            panes: Panes::default(),
             timeline_start_time: 0.0,
             timeline_end_time: 1.0,
-            timeline_pe_scroll: 0.0,
-            timeline_track_height: 16.0,
+            track_order: TrackOrder::default(),
+            zoom_history: ZoomHistory::default(),
+            undo_history: UndoHistory::default(),
+            stacking: Stacking::Overlap,
+            ignore_memory_budget: false,
+            lanes: Lanes::default(),
+            visible_counters: BTreeSet::new(),
+            time_display: TimeDisplay::Absolute,
+            pinned_event: None,
+            pinned_event_pos: Pos2::ZERO,
+            selection: None,
+            annotations: Annotations::default(),
+            show_bookmarks_window: false,
+            derived: Derived::default(),
+            windows: Windows::default(),
+            search_input: String::new(),
+            search: Search::default(),
+            folded_per_pe: false,
+            gantt_format: TableFormat::Csv,
+            trim_dir: "trimmed".to_string(),
+            folded_weight: FoldedWeight::Micros,
+            export_target: ExportTarget::Bandwidth,
+            export_format: ExportFormat::Png,
+            export_width: 1920,
+            export_path: "export".to_string(),
+            pending_export: None,
+            video_format: VideoFormat::Gif,
+            video_fps: 30,
+            video_path: "playback".to_string(),
+            recorder: None,
+            session_path: format!("session.{}", crate::session::SESSION_EXTENSION),
+            status_msg: None,
+            show_colors_window: false,
+            show_legend_window: false,
+            group_rules: Vec::new(),
+            grouping: Grouping::default(),
+            rule_error: None,
+            show_rules_window: false,
+            symbolizer: Symbolizer::default(),
+            demangle_frames: true,
+            symbol_binary_input: String::new(),
+            dock: DockLayout::default(),
+            dock_generation: 0,
+            views: ViewRegistry::builtin(),
+            theme: ThemeChoice::System,
+            ui_scale: 1.0,
+            applied_appearance: None,
+            palette: Palette::dark(),
+            config: Config::default(),
+            config_path: String::new(),
+            show_settings_window: false,
+            clock_offsets: ClockOffsets::new(),
+            show_clocks_window: false,
+            pe_labels: PeLabels::default(),
+            pe_labels_path: String::new(),
+            show_labels_window: false,
+            topology: None,
+            topology_path: String::new(),
+            group_level: GroupLevel::Host,
+            show_topology_window: false,
+            contention: None,
+            show_contention_window: false,
+            watcher: None,
+            data_changed: false,
+            remote_fetch: None,
+            screenshot_path: None,
+            screenshot_frames: 0,
+            hud: PerfHud::default(),
+            show_hud: false,
+            hud_continuous: false,
         };
 
-        match ProfileData::load_from_dir(&root_dir) {
-            Ok(data) => {
-                if !data.events.is_empty() {
-                    app.cursor_time = data.min_time;
-                }
-                let mut colors = HashMap::new();
-                for e in &data.events {
-                    if !colors.contains_key(&e.raw.function) {
-                        colors.insert(e.raw.function.clone(), generate_color(&e.raw.function));
+        app.annotations.color_overrides = config
+            .color_overrides
+            .iter()
+            .map(|(f, [r, g, b])| (f.clone(), Color32::from_rgb(*r, *g, *b)))
+            .collect();
+        app.group_rules = config.group_rules.clone();
+        if let Some(path) = &config.topology {
+            app.topology_path = path.display().to_string();
+            app.load_topology();
+        }
+        app.config = config;
+        app.config_path = config_path.display().to_string();
+        match root_dir.to_str().and_then(Remote::parse) {
+            Some(remote) => app.fetch_remote(remote, &cc.egui_ctx),
+            None => app.load_data(&root_dir),
+        }
+        app.apply_args(&cc.egui_ctx, &args);
+        if app.status_msg.is_none() {
+            app.status_msg = config_msg;
+        }
+        app.screenshot_path = args.screenshot;
+
+        app
+    }
+
+    // command line options that override the defaults of a freshly loaded trace
+    fn apply_args(&mut self, ctx: &egui::Context, args: &Args) {
+        if let Some((start, end)) = args.range {
+            self.timeline_start_time = start;
+            self.timeline_end_time = end;
+            self.cursor_time = start;
+        }
+        if let (Some(pes), Some(data)) = (&args.pes, &self.profile_data) {
+            self.track_order.hidden = (0..data.pe_count)
+                .filter(|pe| !pes.0.contains(pe))
+                .collect();
+        }
+        if let Some(speed) = args.speed {
+            self.playback_speed = speed;
+        }
+        if let Some(window) = args.window {
+            self.window_size_seconds = window;
+        }
+        if let Some(path) = &args.script {
+            match std::fs::read_to_string(path) {
+                Ok(source) => {
+                    self.windows.script.source = source;
+                    if let Some(data) = &self.profile_data {
+                        let commands = self.windows.script.run(data, self.analysis_range());
+                        self.apply_view_commands(ctx, commands);
                     }
+                    self.windows.script.open = true;
+                }
+                Err(e) => {
+                    self.status_msg = Some(format!("Error reading {}: {e}", path.display()));
                 }
-                app.function_colors = colors;
-                app.profile_data = Some(data);
-                app.timeline_start_time = app.profile_data.as_ref().unwrap().min_time;
-                app.timeline_end_time = app.profile_data.as_ref().unwrap().max_time;
             }
-            Err(e) => {
-                app.error_msg = Some(format!("failed to load data: {}", e));
+        }
+    }
+
+    // --screenshot: lets the layout settle for a few frames, saves the
+    // window and quits
+    fn handle_screenshot(&mut self, ctx: &egui::Context) {
+        let Some(path) = &self.screenshot_path else {
+            return;
+        };
+        let image = ctx.input(|i| {
+            i.events.iter().find_map(|e| match e {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        if let Some(image) = image {
+            match export::save_image(&image, path) {
+                Ok(()) => println!("saved {}", path.display()),
+                Err(e) => eprintln!("failed to save screenshot: {e:#}"),
             }
+            self.screenshot_path = None;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            return;
+        }
+        self.screenshot_frames += 1;
+        if self.screenshot_frames == SCREENSHOT_WARMUP_FRAMES {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot(Default::default()));
         }
+        ctx.request_repaint();
+    }
 
-        app
+    fn load_data(&mut self, dir: &Path) {
+        if parquet::is_parquet_file(dir) {
+            self.load_files(vec![dir.to_path_buf()]);
+            return;
+        }
+        self.data_dir = dir.to_path_buf();
+        self.data_files.clear();
+        self.data_dirs.clear();
+        // nothing to load is where a first run starts, not an error
+        if data::pperf_files(dir).is_ok_and(|files| files.is_empty()) {
+            self.profile_data = None;
+            self.error_msg = None;
+            self.show_welcome = true;
+            return;
+        }
+        self.set_data(ProfileData::load_from_dir(dir));
     }
 
-    fn ui_bandwidth(&mut self, ui: &mut egui::Ui) {
-        let Some(data) = self.profile_data.as_ref() else {
+    // the demo trace, written to the temp dir the first time
+    fn load_demo(&mut self) {
+        let dir = std::env::temp_dir().join("csvpshmem-demo");
+        let written = data::pperf_files(&dir).is_ok_and(|files| !files.is_empty());
+        if !written && let Err(e) = synthetic::write(&dir, &synthetic::Config::default()) {
+            self.status_msg = Some(format!("{e:#}"));
+            return;
+        }
+        self.load_data(&dir);
+    }
+
+    fn generate_demo(&mut self) {
+        let dir = PathBuf::from(self.demo_dir.trim());
+        match synthetic::write(&dir, &self.demo_config) {
+            Ok(files) => {
+                self.load_data(&dir);
+                self.status_msg = Some(format!(
+                    "generated {} files in {}",
+                    files.len(),
+                    dir.display()
+                ));
+            }
+            Err(e) => self.status_msg = Some(format!("{e:#}")),
+        }
+    }
+
+    // copies the trace into a local cache in the background, loaded by
+    // poll_remote_fetch once it's all there
+    fn fetch_remote(&mut self, remote: Remote, ctx: &egui::Context) {
+        self.status_msg = Some(format!("listing {}", remote.name()));
+        self.remote_fetch = Some(RemoteFetch::start(remote, ctx.clone()));
+    }
+
+    fn poll_remote_fetch(&mut self) {
+        let Some(fetch) = self.remote_fetch.as_mut() else {
+            return;
+        };
+        let Some(result) = fetch.poll() else {
             return;
         };
-        let rect = ui.available_rect_before_wrap();
-        let center = rect.center();
-        let radius = rect.width().min(rect.height()) / 3.0;
-        let node_radius = 15.0;
+        let fetch = self.remote_fetch.take().unwrap();
+        match result {
+            Ok(dir) => {
+                self.load_data(&dir);
+                if self.profile_data.is_some() {
+                    self.status_msg = Some(format!(
+                        "loaded {} ({} files fetched, {} cached)",
+                        fetch.remote.name(),
+                        fetch.downloaded,
+                        fetch.done - fetch.downloaded
+                    ));
+                }
+            }
+            Err(e) => self.status_msg = Some(format!("Error: {e:#}")),
+        }
+    }
 
-        // viewing around what time
-        let is_hovering = self.hover_time.is_some();
-        let view_time = self.hover_time.unwrap_or(self.cursor_time);
+    fn load_files(&mut self, files: Vec<PathBuf>) {
+        if let Some(dir) = files.first().and_then(|f| f.parent()) {
+            self.data_dir = dir.to_path_buf();
+        }
+        self.data_files = files.clone();
+        self.data_dirs.clear();
+        self.set_data(ProfileData::load_from_files(files));
+    }
 
-        ui.vertical_centered(|ui| {
-            if is_hovering {
-                ui.label(
-                    egui::RichText::new(format!("Showing bandwidth at Hover: {:.6}s", view_time))
-                        .color(Color32::YELLOW),
-                );
+    fn load_runs(&mut self, dirs: Vec<PathBuf>) {
+        if let Some(dir) = dirs.first() {
+            self.data_dir = dir.clone();
+        }
+        self.data_files.clear();
+        self.set_data(ProfileData::load_runs(&dirs));
+        self.data_dirs = dirs;
+    }
+
+    // loads whatever `session` points at: merged directories, some files or
+    // one directory
+    fn load_session_data(&mut self, session: &Session) {
+        if !session.data_dirs.is_empty() {
+            self.load_runs(session.data_dirs.clone());
+        } else if !session.data_files.is_empty() {
+            self.load_files(session.data_files.clone());
+        } else {
+            self.load_data(&session.data_dir);
+        }
+    }
+
+    // the directories to merge when `dirs` join the trace on screen
+    fn runs_with(&self, dirs: impl IntoIterator<Item = PathBuf>) -> Vec<PathBuf> {
+        let mut runs = if self.data_dirs.is_empty() {
+            vec![self.data_dir.clone()]
+        } else {
+            self.data_dirs.clone()
+        };
+        for dir in dirs {
+            if !runs.contains(&dir) {
+                runs.push(dir);
+            }
+        }
+        runs
+    }
+
+    // reads the trace again after a new run overwrote it, keeping the view,
+    // filters and track order. a view outside the new data falls back to all of it
+    fn reload_data(&mut self) {
+        self.data_changed = false;
+        let session = self.to_session();
+        let history = std::mem::take(&mut self.zoom_history);
+        self.load_session_data(&session);
+        let Some(data) = &self.profile_data else {
+            return;
+        };
+        let (min_time, max_time) = (data.min_time, data.max_time);
+        self.apply_session(session);
+        self.zoom_history = history;
+        self.cursor_time = self.cursor_time.clamp(min_time, max_time);
+        if self.timeline_end_time <= min_time || self.timeline_start_time >= max_time {
+            self.timeline_start_time = min_time;
+            self.timeline_end_time = max_time;
+        }
+        self.status_msg = Some(format!("reloaded {}", self.data_dir.display()));
+    }
+
+    // watches the directory of the open trace and reloads or offers to
+    // once a run has finished writing to it
+    fn poll_data_changes(&mut self, ctx: &egui::Context) {
+        if self
+            .watcher
+            .as_ref()
+            .is_none_or(|w| w.dir() != self.data_dir)
+        {
+            self.watcher = Some(
+                DataWatcher::new(&self.data_dir, ctx.clone())
+                    .unwrap_or_else(|_| DataWatcher::unwatched(&self.data_dir)),
+            );
+            self.data_changed = false;
+        }
+        let Some(changed) = self.watcher.as_mut().and_then(DataWatcher::take) else {
+            return;
+        };
+        // with only some files open, the others changing doesn't matter
+        let relevant = self.data_files.is_empty()
+            || changed.iter().any(|c| {
+                self.data_files
+                    .iter()
+                    .any(|f| f.file_name() == c.file_name())
+            });
+        if !relevant {
+            return;
+        }
+        if self.config.auto_reload {
+            self.reload_data();
+        } else {
+            self.data_changed = true;
+        }
+    }
+
+    fn ui_data_changed(&mut self, ctx: &egui::Context) {
+        if !self.data_changed {
+            return;
+        }
+        let mut reload = false;
+        egui::Area::new(Id::new("data_changed"))
+            .order(Order::Foreground)
+            .anchor(egui::Align2::RIGHT_BOTTOM, Vec2::new(-12.0, -12.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Data changed on disk");
+                        reload = ui.button("Reload").clicked();
+                        if ui.button("Dismiss").clicked() {
+                            self.data_changed = false;
+                        }
+                    });
+                    ui.checkbox(&mut self.config.auto_reload, "Reload automatically")
+                        .on_hover_text("Settings > Save keeps this for next time");
+                });
+            });
+        if reload {
+            self.reload_data();
+        }
+    }
+
+    // a dropped directory replaces the trace, dropped csv files are read
+    // together and several directories are merged. with shift held they join
+    // the current trace
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let (dropped, modifiers) = ctx.input(|i| {
+            let paths: Vec<PathBuf> = i
+                .raw
+                .dropped_files
+                .iter()
+                .filter_map(|f| f.path.clone())
+                .collect();
+            (paths, i.modifiers)
+        });
+        if dropped.is_empty() {
+            return;
+        }
+        // ctrl opens a new tab instead
+        let add = modifiers.shift && !modifiers.command;
+        if modifiers.command && self.profile_data.is_some() {
+            self.open_tab();
+        }
+        if let [dir] = dropped.as_slice()
+            && dir.is_dir()
+            && !add
+        {
+            self.load_data(dir);
+            return;
+        }
+        // whole directories are merged side by side rather than mixed, as
+        // each numbers its PEs from 0
+        if dropped.iter().all(|p| p.is_dir()) {
+            let runs = if add && self.profile_data.is_some() && self.data_files.is_empty() {
+                self.runs_with(dropped)
             } else {
-                ui.label(format!("Showing bandwidth at Cursor: {:.6}s", view_time));
+                dropped
+            };
+            self.load_runs(runs);
+            return;
+        }
+        let mut files = Vec::new();
+        for path in dropped {
+            if path.is_dir() {
+                match data::pperf_files(&path) {
+                    Ok(found) => files.extend(found),
+                    Err(e) => {
+                        self.status_msg = Some(format!("reading {}: {e}", path.display()));
+                        return;
+                    }
+                }
+            } else {
+                files.push(path);
             }
+        }
+        if add && let Some(data) = &self.profile_data {
+            files.extend(data.sources.iter().cloned());
+        }
+        self.load_files(files);
+    }
+
+    // first run, or a directory without a trace: open one, or look at a
+    // made up one
+    fn ui_welcome(&mut self, ui: &mut egui::Ui) {
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() * 0.15);
+            ui.heading("csvpshmem visualizer");
+            ui.label(format!(
+                "No pperf.<PE>.csv files in {}",
+                self.data_dir.display()
+            ));
         });
+        ui.add_space(16.0);
+        let width = 420.0f32.min(ui.available_width());
+        ui.horizontal(|ui| {
+            ui.add_space((ui.available_width() - width) / 2.0);
+            ui.vertical(|ui| {
+                ui.set_width(width);
+                ui.group(|ui| {
+                    ui.strong("Open a trace");
+                    ui.horizontal(|ui| {
+                        let edit = ui.text_edit_singleline(&mut self.open_path).on_hover_text(
+                            "a local path, http(s)://server/dir/ or ssh://host/path",
+                        );
+                        let enter =
+                            edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                        if (ui.button("Open").clicked() || enter)
+                            && !self.open_path.trim().is_empty()
+                        {
+                            match Remote::parse(&self.open_path) {
+                                Some(remote) => self.fetch_remote(remote, &ui.ctx().clone()),
+                                None => self.load_data(&PathBuf::from(self.open_path.trim())),
+                            }
+                        }
+                    });
+                    ui.small("or drop a directory or csv files on the window");
+                });
+                ui.group(|ui| {
+                    ui.set_width(width);
+                    ui.strong("Try the demo");
+                    ui.label("A made up run of 8 PEs on 2 hosts passing messages round a ring.");
+                    if ui.button("Load demo trace").clicked() {
+                        self.load_demo();
+                    }
+                });
+                ui.group(|ui| {
+                    ui.set_width(width);
+                    ui.strong("Generate a trace");
+                    egui::Grid::new("welcome_generate")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            let config = &mut self.demo_config;
+                            ui.label("PEs");
+                            ui.add(egui::DragValue::new(&mut config.pe_count).range(1..=1024));
+                            ui.end_row();
+                            ui.label("Pattern");
+                            egui::ComboBox::from_id_salt("welcome_pattern")
+                                .selected_text(config.pattern.name())
+                                .show_ui(ui, |ui| {
+                                    for pattern in Pattern::ALL {
+                                        ui.selectable_value(
+                                            &mut config.pattern,
+                                            pattern,
+                                            pattern.name(),
+                                        );
+                                    }
+                                });
+                            ui.end_row();
+                            ui.label("Calls per second");
+                            ui.add(
+                                egui::DragValue::new(&mut config.rate)
+                                    .range(1.0..=1e6)
+                                    .speed(10.0),
+                            )
+                            .on_hover_text("on each PE");
+                            ui.end_row();
+                            ui.label("Seconds");
+                            ui.add(
+                                egui::DragValue::new(&mut config.seconds)
+                                    .range(0.01..=60.0)
+                                    .speed(0.01),
+                            );
+                            ui.end_row();
+                            ui.label("Into");
+                            ui.text_edit_singleline(&mut self.demo_dir)
+                                .on_hover_text("a new or empty directory");
+                            ui.end_row();
+                        });
+                    let events = self.demo_config.pe_count as f64
+                        * self.demo_config.rate
+                        * self.demo_config.seconds;
+                    ui.small(format!("about {events:.0} events"));
+                    if ui
+                        .add_enabled(
+                            !self.demo_dir.trim().is_empty(),
+                            egui::Button::new("Generate and open"),
+                        )
+                        .clicked()
+                    {
+                        self.generate_demo();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.menu_button("Session", |ui| self.ui_session_menu(ui));
+                    if let Some(msg) = &self.status_msg {
+                        ui.label(msg);
+                    }
+                });
+            });
+        });
+    }
+
+    fn ui_drop_overlay(&self, ctx: &egui::Context) {
+        let hovering = ctx.input(|i| !i.raw.hovered_files.is_empty());
+        if !hovering {
+            return;
+        }
+        let painter = ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("drop_overlay")));
+        let screen = ctx.content_rect();
+        painter.rect_filled(screen, 0.0, Color32::from_black_alpha(160));
+        painter.text(
+            screen.center(),
+            egui::Align2::CENTER_CENTER,
+            "Drop a pperf directory or csv files to open them, several directories to merge them\nhold shift to add them to the current trace",
+            egui::FontId::proportional(18.0),
+            Color32::WHITE,
+        );
+    }
+
+    // takes the trace on screen out of the app, to be put back by unpark
+    fn park(&mut self) -> Parked {
+        let session = self.to_session();
+        self.error_msg = None;
+        self.show_welcome = false;
+        Parked {
+            data: self.profile_data.take(),
+            session,
+        }
+    }
+
+    fn unpark(&mut self, tab: Parked) {
+        self.data_dir = tab.session.data_dir.clone();
+        self.data_files = tab.session.data_files.clone();
+        self.data_dirs = tab.session.data_dirs.clone();
+        self.clock_offsets = tab.session.clock_offsets.clone();
+        // rebuilds the derived state (anomalies, lanes, search), a tab that
+        // failed to load is tried again by apply_session
+        if let Some(data) = tab.data {
+            self.set_data(Ok(data));
+        }
+        self.apply_session(tab.session);
+    }
 
-        // range
-        let start_time = view_time - self.window_size_seconds / 2.0;
-        let end_time = view_time + self.window_size_seconds / 2.0;
+    // parks the current trace and leaves an empty tab to load into
+    fn open_tab(&mut self) {
+        let current = self.park();
+        self.tabs.open(current);
+    }
 
-        let start_idx = data.events.partition_point(|e| e.raw.time < start_time);
+    fn switch_tab(&mut self, to: usize) {
+        if to == self.tabs.active() {
+            return;
+        }
+        let current = self.park();
+        let tab = self.tabs.switch(to, current);
+        self.unpark(tab);
+    }
 
-        // aggregation
-        // comms[(src, dst)] = bytes
-        let mut comms: HashMap<(u32, u32), (u64, u64)> = HashMap::new();
+    fn close_tab(&mut self, i: usize) {
+        if let Some(tab) = self.tabs.close(i) {
+            self.unpark(tab);
+        }
+    }
 
-        for i in start_idx..data.events.len() {
-            let event = &data.events[i];
-            if event.raw.time > end_time {
-                break;
+    fn ui_tab_bar(&mut self, ui: &mut egui::Ui) {
+        let mut switch = None;
+        let mut close = None;
+        ui.horizontal(|ui| {
+            for i in 0..self.tabs.count() {
+                let name = match self.tabs.parked(i) {
+                    Some(tab) => tab_name(
+                        &tab.session.data_dir,
+                        &tab.session.data_files,
+                        &tab.session.data_dirs,
+                    ),
+                    None => tab_name(&self.data_dir, &self.data_files, &self.data_dirs),
+                };
+                if ui.selectable_label(i == self.tabs.active(), name).clicked() {
+                    switch = Some(i);
+                }
+                if self.tabs.count() > 1
+                    && ui.small_button("×").on_hover_text("Close tab").clicked()
+                {
+                    close = Some(i);
+                }
+                ui.separator();
             }
-            if event.raw.target_pe >= 0 {
-                let src = event.source_pe;
-                let dst = event.raw.target_pe as u32;
-                if src != dst {
-                    if self.show_tx && event.raw.bytes_tx > 0 {
-                        comms.entry((src, dst)).or_insert((0, 0)).0 += event.raw.bytes_tx;
+            ui.menu_button("+", |ui| {
+                ui.label("Trace directory:");
+                ui.text_edit_singleline(&mut self.open_path)
+                    .on_hover_text("a local path, http(s)://server/dir/ or ssh://host/path");
+                if ui.button("Open in new tab").clicked() {
+                    self.open_tab();
+                    match Remote::parse(&self.open_path) {
+                        Some(remote) => self.fetch_remote(remote, &ui.ctx().clone()),
+                        None => self.load_data(&PathBuf::from(self.open_path.trim())),
                     }
-                    if self.show_rx && event.raw.bytes_rx > 0 {
-                        comms.entry((dst, src)).or_insert((0, 0)).1 += event.raw.bytes_rx;
+                    ui.close();
+                }
+                if ui
+                    .button("Merge into this tab")
+                    .on_hover_text("load it next to the current trace, its PEs numbered after")
+                    .clicked()
+                {
+                    let runs = self.runs_with([PathBuf::from(self.open_path.trim())]);
+                    self.load_runs(runs);
+                    ui.close();
+                }
+                if let Some(data) = &self.profile_data
+                    && !data.runs.is_empty()
+                {
+                    ui.separator();
+                    for run in &data.runs {
+                        ui.small(format!(
+                            "PEs {}-{}: {}",
+                            run.first_pe,
+                            run.first_pe + run.pe_count - 1,
+                            run.dir.display()
+                        ));
                     }
                 }
+                if ui.button("Duplicate this tab").clicked() {
+                    let session = self.to_session();
+                    self.open_tab();
+                    self.apply_session(session);
+                    ui.close();
+                }
+            })
+            .response
+            .on_hover_text("Open another trace (or ctrl-drop it on the window)");
+            if let Some(fetch) = &self.remote_fetch {
+                ui.spinner();
+                ui.label(if fetch.total == 0 {
+                    format!("listing {}", fetch.remote.name())
+                } else {
+                    format!(
+                        "fetching {}: {} of {} files",
+                        fetch.remote.name(),
+                        fetch.done,
+                        fetch.total
+                    )
+                });
             }
+        });
+        if let Some(i) = switch {
+            self.switch_tab(i);
         }
+        if let Some(i) = close {
+            self.close_tab(i);
+        }
+    }
 
-        let painter = ui.painter();
+    fn set_data(&mut self, data: anyhow::Result<ProfileData>) {
+        self.playing = false;
+        self.show_welcome = false;
+        self.panes.bandwidth.reset();
+        match data {
+            Ok(mut data) => {
+                data.set_clock_offsets(&self.clock_offsets);
+                if let Some(mb) = self.config.memory_budget_mb
+                    && !self.ignore_memory_budget
+                {
+                    data.shed(mb as usize * 1024 * 1024);
+                }
+                self.error_msg = None;
+                self.cursor_time = data.min_time;
+                self.cursor_b = None;
+                self.pinned_event = None;
+                self.timeline_start_time = data.min_time;
+                self.timeline_end_time = data.max_time;
+                self.panes.timeline.pe_scroll = 0.0;
+                self.track_order = TrackOrder::default();
+                self.zoom_history.clear();
+                self.undo_history.clear();
+                self.hud.data_bytes = data.heap_size();
+                self.profile_data = Some(data);
+                self.rebuild_grouping();
+                if let Some(data) = &self.profile_data {
+                    self.pe_labels.resolve(data);
+                }
+                self.search.clear();
+                self.windows.reset();
+            }
+            Err(e) => {
+                self.profile_data = None;
+                self.error_msg = Some(format!("failed to load data: {}", e));
+            }
+        }
+        self.derived.rebuild_untimed(self.profile_data.as_ref());
+        self.rebuild_timed();
+    }
 
-        // nodes
-        let count = data.pe_count;
-        let angle_step = std::f32::consts::TAU / count as f32;
+    // everything made from the times or the order of the events, redone
+    // when either changes
+    fn rebuild_timed(&mut self) {
+        self.panes.invalidate();
+        self.windows.invalidate();
+        self.contention = None;
+        self.derived.rebuild_timed(self.profile_data.as_ref());
+        self.rebuild_lanes();
+    }
 
-        let get_pos = |pe: u32| -> Pos2 {
-            let angle = pe as f32 * angle_step - std::f32::consts::PI / 2.0;
-            center + Vec2::new(angle.cos(), angle.sin()) * radius
+    // moves the loaded events to the current clock offsets in place. the
+    // view, undo history and everything not tied to time stay, so a tweaked
+    // offset slides the affected tracks under the view
+    fn apply_clock_offsets(&mut self) {
+        let Some(data) = &mut self.profile_data else {
+            return;
+        };
+        let Some(order) = data.set_clock_offsets(&self.clock_offsets) else {
+            return;
         };
+        // the new index of every old one, for what points at events
+        let mut moved = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            moved[old] = new;
+        }
+        self.pinned_event = self.pinned_event.map(|i| moved[i]);
+        self.panes.timeline.remap(&moved);
+        self.search.remap(&moved);
+        self.rebuild_timed();
+    }
 
-        // hovered node?
-        let mut hovered_pe = None;
-        if let Some(pointer_pos) = ui.input(|i| i.pointer.hover_pos()) {
-            for i in 0..count {
-                let pos = get_pos(i);
-                if pos.distance(pointer_pos) <= node_radius * 1.5 {
-                    hovered_pe = Some(i);
-                    break;
+    // PE names from a template, a mapping file or typed in one by one
+    fn ui_labels_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_labels_window;
+        let mut changed = false;
+        egui::Window::new("PE labels")
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let Some(data) = &self.profile_data else {
+                    ui.label("No trace loaded");
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.label("Template:");
+                    changed |= ui
+                        .add(
+                            egui::TextEdit::singleline(&mut self.pe_labels.template)
+                                .hint_text("{host}:{core}"),
+                        )
+                        .on_hover_text(
+                            "for PEs without a name below. {pe}, {host}, {core} (nth PE on \
+                             the host), {local} and {run} (within a merged directory)",
+                        )
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Mapping file:");
+                    ui.text_edit_singleline(&mut self.pe_labels_path)
+                        .on_hover_text("one `<pe> <label>` per line");
+                    if ui.button("Load").clicked() {
+                        let path = PathBuf::from(self.pe_labels_path.trim());
+                        match labels::load_names(&path) {
+                            Ok(names) => {
+                                self.status_msg = Some(format!("loaded {} PE labels", names.len()));
+                                self.pe_labels.names = names;
+                                changed = true;
+                            }
+                            Err(e) => self.status_msg = Some(format!("{e:#}")),
+                        }
+                    }
+                });
+                if ui.button("Clear names").clicked() {
+                    self.pe_labels.names.clear();
+                    changed = true;
                 }
+                ui.separator();
+                egui::Grid::new("labels_grid").striped(true).show(ui, |ui| {
+                    for pe in 0..data.pe_count {
+                        ui.label(format!("PE {pe}"));
+                        let mut name = self.pe_labels.names.get(&pe).cloned().unwrap_or_default();
+                        let hint = self.pe_labels.label(pe).unwrap_or("").to_string();
+                        if ui
+                            .add(
+                                egui::TextEdit::singleline(&mut name)
+                                    .hint_text(hint)
+                                    .desired_width(140.0),
+                            )
+                            .changed()
+                        {
+                            if name.trim().is_empty() {
+                                self.pe_labels.names.remove(&pe);
+                            } else {
+                                self.pe_labels.names.insert(pe, name);
+                            }
+                            changed = true;
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        self.show_labels_window = open;
+        if changed && let Some(data) = &self.profile_data {
+            self.pe_labels.resolve(data);
+        }
+    }
+
+    fn load_topology(&mut self) {
+        let path = PathBuf::from(self.topology_path.trim());
+        match Topology::load(&path) {
+            Ok(topology) => self.topology = Some(topology),
+            Err(e) => {
+                self.topology = None;
+                self.contention = None;
+                self.group_level = GroupLevel::Host;
+                self.status_msg = Some(format!("failed to load topology: {e:#}"));
             }
         }
+    }
+
+    // the topology file, the level the ring groups by and how much traffic
+    // stays within each level
+    fn ui_topology_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_topology_window;
+        let (start, end) = self.analysis_range();
+        let range_name = self.analysis_range_name();
+        egui::Window::new("Topology")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("File:");
+                    ui.text_edit_singleline(&mut self.topology_path)
+                        .on_hover_text(
+                            "toml or json, [hosts] node0 = { switch = \"sw0\", rack = \"r0\" }",
+                        );
+                    if ui.button("Load").clicked() {
+                        self.load_topology();
+                    }
+                    if ui
+                        .add_enabled(self.topology.is_some(), egui::Button::new("Clear"))
+                        .clicked()
+                    {
+                        self.topology = None;
+                        self.contention = None;
+                        self.group_level = GroupLevel::Host;
+                    }
+                    ui.add_enabled_ui(self.topology.is_some(), |ui| {
+                        ui.toggle_value(&mut self.show_contention_window, "Contention");
+                    });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Ring groups by:");
+                    for level in GroupLevel::ALL {
+                        ui.add_enabled_ui(
+                            self.topology.is_some() || level == GroupLevel::Host,
+                            |ui| {
+                                ui.radio_value(&mut self.group_level, level, level.label());
+                            },
+                        );
+                    }
+                });
+                let (Some(data), Some(topology)) = (&self.profile_data, &self.topology) else {
+                    return;
+                };
+                ui.separator();
+                ui.small(format!("{range_name} {start:.6}s to {end:.6}s"));
+                let comms = self.derived.byte_sums.comms(
+                    start,
+                    end,
+                    self.annotations.show_tx,
+                    self.annotations.show_rx,
+                );
+                let traffic = topology::level_traffic(&comms, data, topology);
+                let total = traffic.total().max(1) as f64;
+                egui::Grid::new("level_traffic_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (name, bytes) in [
+                            ("Within a host", traffic.same_host),
+                            ("Within a switch", traffic.same_switch),
+                            ("Within a rack", traffic.same_rack),
+                            ("Across racks / unplaced", traffic.across),
+                        ] {
+                            ui.label(name);
+                            ui.label(units::bytes(bytes));
+                            ui.label(format!("{:.1}%", bytes as f64 / total * 100.0));
+                            ui.end_row();
+                        }
+                    });
+            });
+        self.show_topology_window = open;
+    }
 
-        // interaction stats if hovered: (tx, rx)
-        let mut interaction_bytes: HashMap<u32, (u64, u64)> = HashMap::new();
-        let mut max_interaction = 0;
+    // link capacities, the estimated demand on each uplink over the analysis
+    // range as a stacked area chart, and when it was more than the link takes
+    fn ui_contention_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_contention_window;
+        let (start, end) = self.analysis_range();
+        let range_name = self.analysis_range_name();
+        let mut view = None;
+        egui::Window::new("Contention")
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let (Some(data), Some(topology)) = (&self.profile_data, &mut self.topology) else {
+                    ui.label("Load a topology first");
+                    return;
+                };
+                let mut capacity_changed = false;
+                ui.horizontal(|ui| {
+                    ui.label("Uplinks (GB/s):");
+                    for (name, capacity) in [
+                        ("host", &mut topology.links.host),
+                        ("switch", &mut topology.links.switch),
+                        ("rack", &mut topology.links.rack),
+                    ] {
+                        let mut gbs = capacity.map_or(0.0, |c| c / 1e9);
+                        ui.label(name);
+                        if ui
+                            .add(
+                                egui::DragValue::new(&mut gbs)
+                                    .speed(0.5)
+                                    .range(0.0..=f64::MAX),
+                            )
+                            .on_hover_text("0 for unknown")
+                            .changed()
+                        {
+                            *capacity = (gbs > 0.0).then_some(gbs * 1e9);
+                            capacity_changed = true;
+                        }
+                    }
+                });
+                if capacity_changed && let Some(contention) = &mut self.contention {
+                    contention.set_capacities(&topology.links);
+                }
+                if ui
+                    .button(format!("Estimate {range_name}"))
+                    .on_hover_text(format!("{start:.6}s to {end:.6}s"))
+                    .clicked()
+                {
+                    self.contention =
+                        Some(Contention::new(data, topology, start, end, CONTENTION_BINS));
+                }
+                let Some(contention) = &self.contention else {
+                    return;
+                };
+                ui.small(format!(
+                    "{:.6}s to {:.6}s, {} per bin",
+                    contention.start,
+                    contention.end,
+                    units::duration(contention.bin_width())
+                ));
+                if contention.links.is_empty() {
+                    ui.label("No traffic between hosts in range");
+                    return;
+                }
+                ui.separator();
 
-        if let Some(h) = hovered_pe {
-            for ((src, dst), (tx, rx)) in &comms {
-                if *src == h {
-                    let e = interaction_bytes.entry(*dst).or_insert((0, 0));
-                    e.0 += tx;
-                    e.1 += rx;
-                    max_interaction = max_interaction.max(e.0 + e.1);
-                } else if *dst == h {
-                    let e = interaction_bytes.entry(*src).or_insert((0, 0));
-                    e.0 += tx;
-                    e.1 += rx;
-                    max_interaction = max_interaction.max(e.0 + e.1);
+                // busiest links get their own band, the rest share one
+                let mut order: Vec<usize> = (0..contention.links.len()).collect();
+                order.sort_by(|a, b| {
+                    let total = |l: &usize| contention.demand[*l].iter().sum::<f64>();
+                    total(b).total_cmp(&total(a))
+                });
+                let (plotted, rest) = order.split_at(order.len().min(CONTENTION_PLOT_LINKS));
+                let bins = contention.bins();
+                let mut bands: Vec<(String, Color32, Vec<f64>)> = plotted
+                    .iter()
+                    .map(|l| {
+                        let link = &contention.links[*l];
+                        (
+                            link.title(),
+                            generate_color(&link.title()),
+                            contention.demand[*l].clone(),
+                        )
+                    })
+                    .collect();
+                if !rest.is_empty() {
+                    let other = (0..bins)
+                        .map(|bin| rest.iter().map(|l| contention.demand[*l][bin]).sum())
+                        .collect();
+                    bands.push(("other".to_string(), Color32::GRAY, other));
+                }
+                let peak = (0..bins)
+                    .map(|bin| bands.iter().map(|b| b.2[bin]).sum::<f64>())
+                    .fold(0.0, f64::max)
+                    .max(f64::EPSILON);
+
+                let (response, painter) =
+                    ui.allocate_painter(Vec2::new(ui.available_width(), 160.0), Sense::hover());
+                let plot_rect = response.rect.shrink(4.0);
+                painter.rect_filled(response.rect, 2.0, self.palette.plot_bg);
+                let bin_x = |i: f32| plot_rect.min.x + plot_rect.width() * i / bins as f32;
+                let value_y = |v: f64| plot_rect.max.y - plot_rect.height() * (v / peak) as f32;
+                for bin in (0..bins).filter(|b| contention.over_capacity(*b)) {
+                    painter.rect_filled(
+                        Rect::from_x_y_ranges(
+                            bin_x(bin as f32)..=bin_x(bin as f32 + 1.0),
+                            plot_rect.y_range(),
+                        ),
+                        0.0,
+                        Color32::from_rgba_unmultiplied(220, 40, 40, 50),
+                    );
                 }
+                // each band is a run of trapezoids between bin centres, on
+                // top of the bands before it
+                let mut below = vec![0.0; bins];
+                for (_, color, values) in &bands {
+                    let above: Vec<f64> = below.iter().zip(values).map(|(b, v)| b + v).collect();
+                    for i in 0..bins.saturating_sub(1) {
+                        let (x0, x1) = (bin_x(i as f32 + 0.5), bin_x(i as f32 + 1.5));
+                        painter.add(egui::Shape::convex_polygon(
+                            vec![
+                                Pos2::new(x0, value_y(above[i])),
+                                Pos2::new(x1, value_y(above[i + 1])),
+                                Pos2::new(x1, value_y(below[i + 1])),
+                                Pos2::new(x0, value_y(below[i])),
+                            ],
+                            color.gamma_multiply(0.8),
+                            Stroke::NONE,
+                        ));
+                    }
+                    below = above;
+                }
+                painter.text(
+                    plot_rect.left_top(),
+                    egui::Align2::LEFT_TOP,
+                    units::rate(peak),
+                    egui::FontId::proportional(9.0),
+                    self.palette.text_weak,
+                );
+                paint_crosshair(
+                    &painter,
+                    plot_rect,
+                    contention.start,
+                    contention.end,
+                    self.hover_time,
+                );
+                if let Some(pos) = response.hover_pos() {
+                    let share = ((pos.x - plot_rect.min.x) / plot_rect.width()) as f64;
+                    self.hover_next =
+                        Some(contention.start + (contention.end - contention.start) * share);
+                    let bin = (((pos.x - plot_rect.min.x) / plot_rect.width()) * bins as f32)
+                        .clamp(0.0, (bins - 1) as f32) as usize;
+                    let t = contention.start + contention.bin_width() * (bin as f64 + 0.5);
+                    let mut text = format!("{t:.6}s");
+                    for l in &order {
+                        let demand = contention.demand[*l][bin];
+                        if demand > 0.0 {
+                            text += &format!(
+                                "\n{}: {}",
+                                contention.links[*l].title(),
+                                units::rate(demand)
+                            );
+                            if let Some(u) = contention.utilization(*l, bin) {
+                                text += &format!(" ({:.0}%)", u * 100.0);
+                            }
+                        }
+                    }
+                    response.on_hover_text(text);
+                }
+                ui.horizontal_wrapped(|ui| {
+                    for (name, color, _) in &bands {
+                        ui.colored_label(*color, format!("■ {name}"));
+                    }
+                });
+
+                ui.separator();
+                let overloads = contention.overloads();
+                if overloads.is_empty() {
+                    ui.label("No link is over capacity");
+                } else {
+                    ui.strong(format!("{} stretches over capacity", overloads.len()));
+                    egui::Grid::new("contention_overloads_grid")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for o in overloads.iter().take(outliers::MAX_ROWS) {
+                                if ui
+                                    .link(format!("{:.6}s", o.start))
+                                    .on_hover_text("Zoom to this stretch")
+                                    .clicked()
+                                {
+                                    view = Some((o.start, o.end));
+                                }
+                                ui.label(units::duration(o.end - o.start));
+                                ui.label(contention.links[o.link].title());
+                                ui.label(format!("peak {:.0}%", o.peak * 100.0));
+                                ui.end_row();
+                            }
+                        });
+                }
+
+                ui.separator();
+                egui::Grid::new("contention_links_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Link");
+                        ui.strong("Capacity");
+                        ui.strong("Peak demand");
+                        ui.strong("Peak use");
+                        ui.end_row();
+                        for l in &order {
+                            let link = &contention.links[*l];
+                            ui.label(link.title());
+                            ui.label(link.capacity.map_or("?".to_string(), units::rate));
+                            ui.label(units::rate(
+                                contention.demand[*l]
+                                    .iter()
+                                    .fold(0.0, |m: f64, d| m.max(*d)),
+                            ));
+                            match contention.peak_utilization(*l) {
+                                Some(u) if u > 1.0 => {
+                                    ui.colored_label(
+                                        Color32::LIGHT_RED,
+                                        format!("{:.0}%", u * 100.0),
+                                    );
+                                }
+                                Some(u) => {
+                                    ui.label(format!("{:.0}%", u * 100.0));
+                                }
+                                None => {
+                                    ui.label("-");
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        self.show_contention_window = open;
+        if let Some((start, end)) = view {
+            self.set_view(start, end);
+        }
+    }
+
+    // per-host clock offsets, typed in or estimated from anchor events
+    fn ui_clocks_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_clocks_window;
+        let mut changed = false;
+        egui::Window::new("Clocks")
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let Some(data) = &self.profile_data else {
+                    ui.label("No trace loaded");
+                    return;
+                };
+                ui.label(
+                    egui::RichText::new(
+                        "added to the timestamps of every PE on the host, \
+                         to line up hosts whose clocks disagree",
+                    )
+                    .small()
+                    .weak(),
+                );
+                let hosts: BTreeSet<&String> = data.pe_hostnames.values().collect();
+                egui::Grid::new("clocks_grid").striped(true).show(ui, |ui| {
+                    for host in hosts {
+                        ui.label(host.as_str());
+                        let mut micros = self.clock_offsets.get(host).copied().unwrap_or(0.0) * 1e6;
+                        if ui
+                            .add(egui::DragValue::new(&mut micros).speed(1.0).suffix(" µs"))
+                            .changed()
+                        {
+                            self.clock_offsets.insert(host.clone(), micros / 1e6);
+                            changed = true;
+                        }
+                        ui.end_row();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    for anchor in [Anchor::FirstBarrier, Anchor::Initialize] {
+                        if ui.button(anchor.label()).clicked() {
+                            self.clock_offsets = skew::estimate(data, anchor);
+                            changed = true;
+                        }
+                    }
+                    if ui.button("Reset").clicked() {
+                        self.clock_offsets.clear();
+                        changed = true;
+                    }
+                });
+            });
+        self.show_clocks_window = open;
+        if changed {
+            self.clock_offsets.retain(|_, offset| *offset != 0.0);
+            self.apply_clock_offsets();
+        }
+    }
+
+    // recompiles the grouping rules, keeping the old grouping if one is bad
+    fn rebuild_grouping(&mut self) {
+        let functions = self
+            .profile_data
+            .iter()
+            .flat_map(|d| d.iter())
+            .map(|e| e.raw.function);
+        match Grouping::new(&self.group_rules, functions) {
+            Ok(grouping) => {
+                self.grouping = grouping;
+                self.rule_error = None;
             }
+            Err(e) => self.rule_error = Some(format!("{:#}", e)),
         }
+        self.rebuild_function_colors();
+    }
 
-        // bandwidth arrows
-        for ((src, dst), (tx, rx)) in &comms {
-            let p1 = get_pos(*src);
-            let p2 = get_pos(*dst);
+    // functions are colored by group, an override on the function itself wins
+    fn rebuild_function_colors(&mut self) {
+        let by = self.colors.by;
+        let Some(data) = &self.profile_data else {
+            self.colors = ColorMap::default();
+            self.colors.by = by;
+            return;
+        };
+        let mut colors = HashMap::new();
+        for e in data.iter() {
+            if !colors.contains_key(e.raw.function) {
+                let group = self.grouping.group(e.raw.function);
+                let color = self
+                    .annotations
+                    .color_overrides
+                    .get(e.raw.function)
+                    .or_else(|| self.annotations.color_overrides.get(group))
+                    .copied()
+                    .unwrap_or_else(|| generate_color(group));
+                colors.insert(e.raw.function.to_string(), color);
+            }
+        }
+        self.colors = ColorMap::new(data, colors, by);
+    }
 
-            let total = *tx + *rx;
-            if total == 0 {
-                continue;
+    fn to_session(&self) -> Session {
+        Session {
+            data_dir: self.data_dir.clone(),
+            data_files: self.data_files.clone(),
+            data_dirs: self.data_dirs.clone(),
+            cursor_time: self.cursor_time,
+            window_size_seconds: self.window_size_seconds,
+            playback_speed: self.playback_speed,
+            loop_a: self.loop_a,
+            loop_b: self.loop_b,
+            loop_enabled: self.loop_enabled,
+            show_rx: self.annotations.show_rx,
+            show_tx: self.annotations.show_tx,
+            edge_scaling: self.panes.bandwidth.scaling.clone(),
+            muting: self.panes.bandwidth.muting.clone(),
+            edge_label_limit: self.panes.bandwidth.edge_label_limit,
+            show_local: self.panes.bandwidth.show_local,
+            show_collectives: self.panes.bandwidth.show_collectives,
+            show_gauges: self.panes.bandwidth.show_gauges,
+            peak_bandwidth: self.panes.bandwidth.peak_bandwidth,
+            theme: self.theme,
+            ui_scale: self.ui_scale,
+            dock: self.dock.clone(),
+            bandwidth_mode: self.panes.bandwidth.mode,
+            ring_grouping: self.panes.bandwidth.ring_grouping,
+            placement: self.panes.bandwidth.placement,
+            timeline_start_time: self.timeline_start_time,
+            timeline_end_time: self.timeline_end_time,
+            timeline_pe_scroll: self.panes.timeline.pe_scroll,
+            timeline_track_height: self.panes.timeline.track_height,
+            visible_counters: self.visible_counters.clone(),
+            time_display: self.time_display,
+            ruler_density: self.panes.timeline.ruler_density,
+            track_order: self.track_order.clone(),
+            stacking: self.stacking,
+            color_overrides: self
+                .annotations
+                .color_overrides
+                .iter()
+                .map(|(f, c)| (f.clone(), [c.r(), c.g(), c.b()]))
+                .collect(),
+            group_rules: self.group_rules.clone(),
+            hidden_groups: self.annotations.hidden_groups.clone(),
+            hidden_functions: self.annotations.hidden_functions.clone(),
+            solo_function: self.annotations.solo_function.clone(),
+            duration_filter: self.annotations.duration_filter,
+            byte_filter: self.annotations.byte_filter,
+            color_by: self.colors.by,
+            symbol_binaries: self.symbolizer.binaries().map(Path::to_path_buf).collect(),
+            demangle_frames: self.demangle_frames,
+            bookmarks: self.annotations.bookmarks.clone(),
+            metrics: self.windows.metrics.sources(),
+            clock_offsets: self.clock_offsets.clone(),
+            pe_labels: self.pe_labels.clone(),
+            topology_path: self
+                .topology
+                .is_some()
+                .then(|| PathBuf::from(self.topology_path.trim())),
+            group_level: self.group_level,
+        }
+    }
+
+    fn apply_session(&mut self, session: Session) {
+        let offsets_changed = session.clock_offsets != self.clock_offsets;
+        self.clock_offsets = session.clock_offsets.clone();
+        if self.profile_data.is_none()
+            || session.data_dir != self.data_dir
+            || session.data_files != self.data_files
+            || session.data_dirs != self.data_dirs
+        {
+            self.load_session_data(&session);
+        } else if offsets_changed {
+            self.apply_clock_offsets();
+        }
+        self.cursor_time = session.cursor_time;
+        self.window_size_seconds = session.window_size_seconds;
+        self.playback_speed = session.playback_speed;
+        self.loop_a = session.loop_a;
+        self.loop_b = session.loop_b;
+        self.loop_enabled = session.loop_enabled;
+        self.annotations.show_rx = session.show_rx;
+        self.annotations.show_tx = session.show_tx;
+        self.panes.bandwidth.scaling = session.edge_scaling;
+        self.panes.bandwidth.muting = session.muting;
+        self.panes.bandwidth.edge_label_limit = session.edge_label_limit;
+        self.panes.bandwidth.show_local = session.show_local;
+        self.panes.bandwidth.show_collectives = session.show_collectives;
+        self.panes.bandwidth.show_gauges = session.show_gauges;
+        self.panes.bandwidth.peak_bandwidth = session.peak_bandwidth;
+        self.theme = session.theme;
+        if session.dock != self.dock {
+            self.dock = session.dock;
+            self.dock_generation += 1;
+        }
+        self.ui_scale = session
+            .ui_scale
+            .clamp(theme::MIN_UI_SCALE, theme::MAX_UI_SCALE);
+        self.panes.bandwidth.mode = session.bandwidth_mode;
+        self.panes.bandwidth.ring_grouping = session.ring_grouping;
+        self.panes.bandwidth.placement = session.placement;
+        self.panes.bandwidth.reset();
+        self.timeline_start_time = session.timeline_start_time;
+        self.timeline_end_time = session.timeline_end_time;
+        self.panes.timeline.pe_scroll = session.timeline_pe_scroll;
+        self.panes.timeline.track_height = session.timeline_track_height;
+        self.visible_counters = session.visible_counters;
+        self.time_display = session.time_display;
+        self.panes.timeline.ruler_density = session.ruler_density;
+        self.track_order = session.track_order;
+        if self.stacking != session.stacking {
+            self.stacking = session.stacking;
+            self.rebuild_lanes();
+        }
+        self.annotations.color_overrides = session
+            .color_overrides
+            .into_iter()
+            .map(|(f, [r, g, b])| (f, Color32::from_rgb(r, g, b)))
+            .collect();
+        self.group_rules = session.group_rules;
+        self.annotations.hidden_groups = session.hidden_groups;
+        self.annotations.hidden_functions = session.hidden_functions;
+        self.annotations.solo_function = session.solo_function;
+        self.annotations.duration_filter = session.duration_filter;
+        self.annotations.byte_filter = session.byte_filter;
+        self.colors.by = session.color_by;
+        self.demangle_frames = session.demangle_frames;
+        self.symbolizer = Symbolizer::default();
+        for path in &session.symbol_binaries {
+            if let Err(e) = self.symbolizer.add_binary(path) {
+                self.status_msg = Some(format!("{:#}", e));
+            }
+        }
+        self.annotations.bookmarks = session.bookmarks;
+        self.pe_labels = session.pe_labels;
+        if let Some(path) = session.topology_path {
+            self.topology_path = path.display().to_string();
+            self.load_topology();
+        }
+        self.group_level = session.group_level;
+        if let Some(data) = &self.profile_data {
+            self.pe_labels.resolve(data);
+        }
+        self.rebuild_grouping();
+        self.windows.metrics.set_sources(&session.metrics);
+        self.rebuild_function_colors();
+    }
+
+    fn save_session(&mut self) {
+        let path = PathBuf::from(self.session_path.trim());
+        self.status_msg = Some(match self.to_session().save(&path) {
+            Ok(written) => format!("saved session to {}", written.display()),
+            Err(e) => format!("failed to save session: {:#}", e),
+        });
+    }
+
+    fn open_session(&mut self) {
+        let path = PathBuf::from(self.session_path.trim());
+        match Session::load(&path) {
+            Ok(session) => {
+                self.apply_session(session);
+                self.status_msg = Some(format!("loaded session from {}", path.display()));
+            }
+            Err(e) => self.status_msg = Some(format!("failed to load session: {:#}", e)),
+        }
+    }
+
+    fn ui_session_menu(&mut self, ui: &mut egui::Ui) {
+        ui.label("Project file:");
+        ui.text_edit_singleline(&mut self.session_path);
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                self.save_session();
+                ui.close();
+            }
+            if ui.button("Load").clicked() {
+                self.open_session();
+                ui.close();
+            }
+        });
+    }
+
+    // ordered (start, end) of the A/B region, only while looping is on
+    fn loop_region(&self) -> Option<(f64, f64)> {
+        if !self.loop_enabled {
+            return None;
+        }
+        let (a, b) = (self.loop_a?, self.loop_b?);
+        if a == b {
+            return None;
+        }
+        Some((a.min(b), a.max(b)))
+    }
+
+    // moves the cursor and pans the timeline so `time` is centered
+    fn jump_to_time(&mut self, time: f64) {
+        let half = (self.timeline_end_time - self.timeline_start_time) / 2.0;
+        self.cursor_time = time;
+        self.timeline_start_time = time - half;
+        self.timeline_end_time = time + half;
+    }
+
+    // moves the timeline to [start, end], remembering where it was
+    fn set_view(&mut self, start: f64, end: f64) {
+        if end <= start {
+            return;
+        }
+        self.zoom_history
+            .push((self.timeline_start_time, self.timeline_end_time));
+        self.timeline_start_time = start;
+        self.timeline_end_time = end;
+    }
+
+    fn zoom_to_fit(&mut self) {
+        if let Some(data) = &self.profile_data {
+            let (start, end) = (data.min_time, data.max_time);
+            self.set_view(start, end);
+        }
+    }
+
+    // the shift-drag selection, else the loop region
+    fn zoom_to_selection(&mut self) {
+        let range = self.selection.or(match (self.loop_a, self.loop_b) {
+            (Some(a), Some(b)) => Some((a.min(b), a.max(b))),
+            _ => None,
+        });
+        if let Some((start, end)) = range {
+            self.set_view(start, end);
+        }
+    }
+
+    fn zoom_back(&mut self) {
+        let current = (self.timeline_start_time, self.timeline_end_time);
+        if let Some((start, end)) = self.zoom_history.back(current) {
+            self.timeline_start_time = start;
+            self.timeline_end_time = end;
+        }
+    }
+
+    // the state undo steps through
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            annotations: self.annotations.clone(),
+            hidden_pes: self.track_order.hidden.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.annotations = snapshot.annotations;
+        self.track_order.hidden = snapshot.hidden_pes;
+        self.rebuild_function_colors();
+    }
+
+    fn undo(&mut self) {
+        if let Some(snapshot) = self.undo_history.undo() {
+            self.restore(snapshot);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(snapshot) = self.undo_history.redo() {
+            self.restore(snapshot);
+        }
+    }
+
+    fn zoom_forward(&mut self) {
+        let current = (self.timeline_start_time, self.timeline_end_time);
+        if let Some((start, end)) = self.zoom_history.forward(current) {
+            self.timeline_start_time = start;
+            self.timeline_end_time = end;
+        }
+    }
+
+    fn ensure_cursor_visible(&mut self) {
+        if self.cursor_time < self.timeline_start_time || self.cursor_time > self.timeline_end_time
+        {
+            self.jump_to_time(self.cursor_time);
+        }
+    }
+
+    fn set_cursor(&mut self, time: f64) {
+        if let Some(data) = &self.profile_data {
+            self.cursor_time = time.clamp(data.min_time, data.max_time);
+        }
+        self.ensure_cursor_visible();
+    }
+
+    // centers the timeline on a time or event and puts the cursor there
+    fn goto(&mut self, goto: Goto) {
+        let Some(data) = &self.profile_data else {
+            return;
+        };
+        let time = match goto {
+            Goto::Time(t) => t,
+            Goto::Offset(d) => self.cursor_time + d,
+            Goto::Event(i) => {
+                let Some(e) = data.events.get(i) else {
+                    self.status_msg = Some(format!(
+                        "no event #{i}, the last is #{}",
+                        data.events.len().saturating_sub(1)
+                    ));
+                    return;
+                };
+                self.pinned_event = Some(i);
+                self.selected_pe = Some(e.source_pe);
+                e.raw.time
             }
-            let mut is_muted = false;
+        };
+        self.jump_to_time(time.clamp(data.min_time, data.max_time));
+    }
+
+    fn step_cursor_by_window(&mut self, direction: f64) {
+        self.playing = false;
+        self.set_cursor(self.cursor_time + direction * self.window_size_seconds);
+    }
+
+    // next/previous event start on the selected PE (or any PE)
+    fn step_cursor_to_event(&mut self, forward: bool) {
+        let Some(data) = &self.profile_data else {
+            return;
+        };
+        let on_pe = |e: &crate::data::Event| self.selected_pe.is_none_or(|pe| e.source_pe == pe);
+        let target = if forward {
+            let idx = data.events.time.partition_point(|&t| t <= self.cursor_time);
+            data.range(idx..).find(on_pe)
+        } else {
+            let idx = data.events.time.partition_point(|&t| t < self.cursor_time);
+            data.range(..idx).rev().find(on_pe)
+        };
+        if let Some(t) = target.map(|e| e.raw.time) {
+            self.playing = false;
+            self.set_cursor(t);
+        }
+    }
 
-            if let Some(h) = hovered_pe {
-                if *src != h && *dst != h {
-                    is_muted = true;
+    fn add_bookmark_at_cursor(&mut self) {
+        let name = format!("Bookmark {}", self.annotations.bookmarks.len() + 1);
+        self.annotations.bookmarks.add(name, self.cursor_time, None);
+    }
+
+    fn ui_bookmarks_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_bookmarks_window;
+        let mut jump = None;
+        let mut delete = None;
+        egui::Window::new("Bookmarks")
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                if ui.button("Add at cursor (B)").clicked() {
+                    self.add_bookmark_at_cursor();
                 }
+                ui.small("[ / ] jump to previous / next bookmark");
+                ui.separator();
+                egui::Grid::new("bookmarks_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, b) in self.annotations.bookmarks.iter_mut().enumerate() {
+                            ui.text_edit_singleline(&mut b.name);
+                            match b.pe {
+                                Some(pe) => ui.label(format!("{:.6}s (PE {})", b.time, pe)),
+                                None => ui.label(format!("{:.6}s", b.time)),
+                            };
+                            if ui.small_button("go").clicked() {
+                                jump = Some(b.time);
+                            }
+                            if ui.small_button("x").clicked() {
+                                delete = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        self.show_bookmarks_window = open;
+        if let Some(t) = jump {
+            self.jump_to_time(t);
+        }
+        if let Some(i) = delete {
+            self.annotations.bookmarks.remove(i);
+        }
+    }
+
+    // runs the actions bound to the keys pressed this frame, see keys.rs
+    fn handle_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        for action in self.config.keys.pressed(ctx) {
+            match action {
+                Action::ZoomToSelection => self.zoom_to_selection(),
+                Action::ZoomToFit => self.zoom_to_fit(),
+                Action::ZoomBack => self.zoom_back(),
+                Action::ZoomForward => self.zoom_forward(),
+                // under the pointer, or at the main cursor
+                Action::PlaceCursorB => {
+                    self.cursor_b = Some(self.hover_time.unwrap_or(self.cursor_time));
+                }
+                Action::ClearCursorB => self.cursor_b = None,
+                Action::AddBookmark => self.add_bookmark_at_cursor(),
+                Action::PrevBookmark | Action::NextBookmark => {
+                    let target = if action == Action::PrevBookmark {
+                        self.annotations.bookmarks.prev_before(self.cursor_time)
+                    } else {
+                        self.annotations.bookmarks.next_after(self.cursor_time)
+                    };
+                    if let Some(t) = target.map(|b| b.time) {
+                        self.jump_to_time(t);
+                    }
+                }
+                Action::ToggleHud => self.show_hud = !self.show_hud,
+                Action::Undo => self.undo(),
+                Action::Redo => self.redo(),
+                Action::Goto => self.focus_time_input = true,
             }
+        }
+    }
 
-            let width = ((total as f32).max(1.0).ln() / 2.0).clamp(0.5, 8.0);
-            let alpha = ((total as f32) / 1000.0).clamp(50.0, 200.0) as u8;
+    // events starting between the two cursors and the bytes they moved
+    fn cursor_span(&self) -> Option<(f64, usize, u64)> {
+        let b = self.cursor_b?;
+        let data = self.profile_data.as_ref()?;
+        let (lo, hi) = (self.cursor_time.min(b), self.cursor_time.max(b));
+        let first = data.events.time.partition_point(|&t| t < lo);
+        let last = data.events.time.partition_point(|&t| t <= hi);
+        let (tx, rx) = self.derived.byte_sums.total(lo, hi);
+        let bytes = tx + rx;
+        Some((b - self.cursor_time, last - first, bytes))
+    }
 
-            let r = (255.0 * (*tx as f32 / total as f32)) as u8;
-            let b = (255.0 * (*rx as f32 / total as f32)) as u8;
-            let g = 0;
+    fn run_search(&mut self) {
+        let (Some(data), Some(index)) = (&self.profile_data, &self.derived.search_index) else {
+            return;
+        };
+        // start from the first match at or after the cursor
+        let hit = self.search.run(
+            &self.search_input,
+            data,
+            index,
+            &self.grouping,
+            self.cursor_time,
+        );
+        self.show_search_hit(hit);
+    }
 
-            let color = Color32::from_rgba_premultiplied(r, g, b, alpha);
+    fn step_search(&mut self, direction: isize) {
+        let hit = self.search.step(direction);
+        self.show_search_hit(hit);
+    }
 
-            let color = if is_muted {
-                // convert to grayscale and lower alpha
-                let gray = (color.r() as f32 * 0.2126
-                    + color.g() as f32 * 0.7152
-                    + color.b() as f32 * 0.0722) as u8;
-                Color32::from_rgba_premultiplied(gray, gray, gray, (alpha as f32 * 0.1) as u8)
-            } else {
-                color
+    fn show_search_hit(&mut self, hit: Option<usize>) {
+        let Some(e) = hit.and_then(|i| self.profile_data.as_ref()?.events.get(i)) else {
+            return;
+        };
+        let (time, pe) = (e.raw.time, e.source_pe);
+        self.playing = false;
+        self.jump_to_time(time);
+        self.selected_pe = Some(pe);
+    }
+
+    fn ui_search_bar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Search");
+            let input = ui.add(
+                egui::TextEdit::singleline(&mut self.search_input)
+                    .hint_text("function:shmem_put bytes>1e6 pe:3..7 time:0.5..0.6")
+                    .desired_width(360.0),
+            );
+            if input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                // enter on an unchanged query steps through the results
+                if self.search.is_current(&self.search_input) {
+                    let back = ui.input(|i| i.modifiers.shift);
+                    self.step_search(if back { -1 } else { 1 });
+                } else {
+                    self.run_search();
+                }
+                input.request_focus();
+            }
+            let has_results = !self.search.results.is_empty();
+            if ui
+                .add_enabled(has_results, egui::Button::new("<"))
+                .clicked()
+            {
+                self.step_search(-1);
+            }
+            if ui
+                .add_enabled(has_results, egui::Button::new(">"))
+                .clicked()
+            {
+                self.step_search(1);
+            }
+            if let Some(err) = &self.search.error {
+                ui.colored_label(Color32::LIGHT_RED, err);
+            } else if !self.search.query.is_empty() {
+                match self.search.pos {
+                    Some(pos) => ui.label(format!("{}/{}", pos + 1, self.search.results.len())),
+                    None => ui.label("no matches"),
+                };
+                if ui.small_button("clear").clicked() {
+                    self.search_input.clear();
+                    self.search.clear();
+                }
+            }
+        });
+    }
+
+    fn rebuild_lanes(&mut self) {
+        self.lanes = match &self.profile_data {
+            Some(data) => Lanes::new(data, self.stacking),
+            None => Lanes::default(),
+        };
+    }
+
+    fn ui_export_menu(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(
+                &mut self.export_target,
+                ExportTarget::Bandwidth,
+                "Bandwidth",
+            );
+            ui.selectable_value(&mut self.export_target, ExportTarget::Timeline, "Timeline");
+        });
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.export_format, ExportFormat::Png, "PNG");
+            ui.radio_value(&mut self.export_format, ExportFormat::Svg, "SVG");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Width:");
+            ui.add(
+                egui::DragValue::new(&mut self.export_width)
+                    .range(64..=16384)
+                    .suffix(" px"),
+            );
+        });
+        ui.label("File:");
+        ui.text_edit_singleline(&mut self.export_path);
+        if ui.button("Export").clicked() {
+            // picked up by the view on its next draw
+            self.pending_export = Some(ExportRequest {
+                target: self.export_target,
+                format: self.export_format,
+                width: self.export_width,
+                path: PathBuf::from(self.export_path.trim()),
+            });
+            ui.ctx().request_repaint();
+            ui.close();
+        }
+
+        ui.separator();
+        ui.strong("Video of bandwidth playback");
+        let (start, end) = self.analysis_range();
+        ui.small(format!(
+            "{} {:.6}s to {:.6}s at {}x speed",
+            self.analysis_range_name(),
+            start,
+            end,
+            self.playback_speed
+        ));
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.video_format, VideoFormat::Gif, "GIF");
+            ui.radio_value(&mut self.video_format, VideoFormat::Mp4, "MP4 (ffmpeg)");
+        });
+        ui.horizontal(|ui| {
+            ui.label("FPS:");
+            ui.add(egui::DragValue::new(&mut self.video_fps).range(1..=120));
+        });
+        ui.label("File:");
+        ui.text_edit_singleline(&mut self.video_path);
+        if ui
+            .add_enabled(self.recorder.is_none(), egui::Button::new("Record"))
+            .clicked()
+        {
+            let settings = VideoSettings {
+                format: self.video_format,
+                start,
+                end,
+                fps: self.video_fps,
+                speed: self.playback_speed,
+                width: self.export_width,
+                path: PathBuf::from(self.video_path.trim()),
             };
+            match VideoRecorder::new(settings) {
+                Ok(recorder) => self.recorder = Some(recorder),
+                Err(e) => self.status_msg = Some(format!("can't record: {:#}", e)),
+            }
+            ui.close();
+        }
 
-            let stroke = Stroke::new(width, color);
+        ui.separator();
+        ui.strong("Folded stacks (flamegraph.pl / inferno)");
+        ui.small(format!(
+            "{} {:.6}s to {:.6}s",
+            self.analysis_range_name(),
+            start,
+            end
+        ));
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut self.folded_weight,
+                FoldedWeight::Micros,
+                "Microseconds",
+            );
+            ui.radio_value(&mut self.folded_weight, FoldedWeight::Calls, "Calls");
+        });
+        ui.checkbox(&mut self.folded_per_pe, "One file per PE");
+        if ui.button("Export stacks").clicked()
+            && let Some(data) = &self.profile_data
+        {
+            let written = flame::write_folded(
+                data,
+                start,
+                end,
+                self.folded_per_pe,
+                self.folded_weight,
+                self.demangle_frames,
+                |e| self.annotations.event_shown(&self.grouping, e),
+                &PathBuf::from(self.export_path.trim()),
+            );
+            self.status_msg = Some(match written {
+                Ok(paths) if paths.len() == 1 => format!("exported {}", paths[0].display()),
+                Ok(paths) => format!("exported {} folded files", paths.len()),
+                Err(e) => format!("export failed: {:#}", e),
+            });
+            ui.close();
+        }
 
-            // avoid overlaps
-            let dir = (p2 - p1).normalized();
-            let normal = Vec2::new(-dir.y, dir.x);
-            let offset = normal * 6.0; // offset
+        ui.separator();
+        ui.strong("Report (HTML)");
+        ui.small(format!(
+            "top functions and load balance over the {}, traffic around the cursor, anomalies",
+            self.analysis_range_name()
+        ));
+        if ui.button("Generate report").clicked()
+            && let Some(data) = &self.profile_data
+        {
+            let half = self.window_size_seconds / 2.0;
+            let title = format!(
+                "{} trace report",
+                tab_name(&self.data_dir, &self.data_files, &self.data_dirs)
+            );
+            let input = ReportInput {
+                data,
+                title: &title,
+                range: (start, end),
+                snapshot: (self.cursor_time - half, self.cursor_time + half),
+                sums: &self.derived.byte_sums,
+                anomalies: &self.derived.anomalies,
+                labels: &self.pe_labels,
+                function_colors: self.colors.functions(),
+                keep: &|e| self.annotations.event_shown(&self.grouping, e),
+            };
+            let written = report::write(&input, &PathBuf::from(self.export_path.trim()));
+            self.status_msg = Some(match written {
+                Ok(path) => format!("wrote {}", path.display()),
+                Err(e) => format!("report failed: {:#}", e),
+            });
+            ui.close();
+        }
 
-            // direction for shortening and head
-            let start_point = p1 + dir * node_radius + offset;
-            let end_point = p2 - dir * node_radius + offset;
+        ui.separator();
+        ui.strong("Time per PE and function");
+        ui.small(format!(
+            "one row per pair over the {}, functions hidden on the timeline left out",
+            self.analysis_range_name()
+        ));
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.gantt_format, TableFormat::Csv, "CSV");
+            ui.radio_value(&mut self.gantt_format, TableFormat::Json, "JSON");
+        });
+        if ui.button("Export table").clicked()
+            && let Some(data) = &self.profile_data
+        {
+            let rows = gantt::rows(data, &self.grouping, start, end, |e| {
+                self.annotations.event_shown(&self.grouping, e)
+            });
+            let written = gantt::write(
+                &rows,
+                self.gantt_format,
+                &PathBuf::from(self.export_path.trim()),
+            );
+            self.status_msg = Some(match written {
+                Ok(path) => format!("exported {} rows to {}", rows.len(), path.display()),
+                Err(e) => format!("export failed: {:#}", e),
+            });
+            ui.close();
+        }
 
-            painter.line_segment([start_point, end_point], stroke);
+        ui.separator();
+        ui.strong("Trace (Parquet)");
+        ui.small("every event, with a Source_PE column, to the file above");
+        if ui.button("Export Parquet").clicked()
+            && let Some(data) = &self.profile_data
+        {
+            let written = parquet::write(data, &PathBuf::from(self.export_path.trim()));
+            self.status_msg = Some(match written {
+                Ok(path) => format!(
+                    "exported {} events to {}",
+                    data.events.len(),
+                    path.display()
+                ),
+                Err(e) => format!("export failed: {:#}", e),
+            });
+            ui.close();
+        }
 
-            // head
-            let arrow_len = 8.0 + width;
-            let arrow_angle = std::f32::consts::PI / 6.0;
+        ui.separator();
+        ui.strong("Trimmed trace");
+        ui.small(format!(
+            "pperf csv files with the calls running in the {} {:.6}s to {:.6}s",
+            self.analysis_range_name(),
+            start,
+            end
+        ));
+        ui.label("Directory:");
+        ui.text_edit_singleline(&mut self.trim_dir);
+        if ui.button("Save trimmed trace").clicked()
+            && let Some(data) = &self.profile_data
+        {
+            let dir = PathBuf::from(self.trim_dir.trim());
+            self.status_msg = Some(match trim::write_trimmed(data, start, end, &dir) {
+                Ok(rows) => format!("wrote {rows} events to {}", dir.display()),
+                Err(e) => format!("trim failed: {:#}", e),
+            });
+            ui.close();
+        }
+    }
 
-            // rotation to target
-            let angle_vec = (end_point - start_point).normalized();
-            let angle = angle_vec.y.atan2(angle_vec.x);
-            let angle1 = angle + std::f32::consts::PI - arrow_angle;
-            let angle2 = angle + std::f32::consts::PI + arrow_angle;
+    fn ui_legend_window(&mut self, ctx: &egui::Context) {
+        if self.profile_data.is_none() {
+            return;
+        }
+        let mut open = self.show_legend_window;
+        egui::Window::new("Legend")
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let filtered = !self.annotations.hidden_functions.is_empty()
+                        || self.annotations.solo_function.is_some();
+                    if ui
+                        .add_enabled(filtered, egui::Button::new("Show all"))
+                        .clicked()
+                    {
+                        self.annotations.hidden_functions.clear();
+                        self.annotations.solo_function = None;
+                    }
+                    ui.small("filters the timeline and the stats");
+                });
+                egui::Grid::new("legend_grid").striped(true).show(ui, |ui| {
+                    for (function, calls) in &self.derived.function_counts {
+                        let mut shown = !self.annotations.hidden_functions.contains(function);
+                        if ui.checkbox(&mut shown, "").changed() {
+                            if shown {
+                                self.annotations.hidden_functions.remove(function);
+                            } else {
+                                self.annotations.hidden_functions.insert(function.clone());
+                            }
+                        }
+                        let color = self.colors.function(function);
+                        let (rect, _) = ui.allocate_exact_size(Vec2::splat(12.0), Sense::hover());
+                        ui.painter().rect_filled(rect, 2.0, color);
+                        let name = egui::RichText::new(function);
+                        if self.annotations.function_shown(&self.grouping, function) {
+                            ui.label(name);
+                        } else {
+                            ui.label(name.weak());
+                        }
+                        ui.label(calls.to_string());
+                        let solo = self.annotations.solo_function.as_ref() == Some(function);
+                        if ui.selectable_label(solo, "solo").clicked() {
+                            self.annotations.solo_function = (!solo).then(|| function.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        self.show_legend_window = open;
+    }
 
-            let p_arrow1 = end_point + Vec2::new(angle1.cos(), angle1.sin()) * arrow_len;
-            let p_arrow2 = end_point + Vec2::new(angle2.cos(), angle2.sin()) * arrow_len;
+    // selection, else the loop region, else whatever the timeline shows
+    fn analysis_range(&self) -> (f64, f64) {
+        if let Some(selection) = self.selection {
+            return selection;
+        }
+        if let (Some(a), Some(b)) = (self.loop_a, self.loop_b)
+            && a != b
+        {
+            return (a.min(b), a.max(b));
+        }
+        let (min, max) = self
+            .profile_data
+            .as_ref()
+            .map_or((0.0, 0.0), |d| (d.min_time, d.max_time));
+        (
+            self.timeline_start_time.max(min),
+            self.timeline_end_time.min(max),
+        )
+    }
 
-            painter.add(egui::Shape::convex_polygon(
-                vec![end_point, p_arrow1, p_arrow2],
-                color,
-                Stroke::NONE,
-            ));
+    fn analysis_range_name(&self) -> &'static str {
+        if self.selection.is_some() {
+            "selection"
+        } else if self.loop_a.zip(self.loop_b).is_some_and(|(a, b)| a != b) {
+            "A/B region"
+        } else {
+            "visible range"
+        }
+    }
+
+    fn ui_rules_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_rules_window;
+        let mut changed = false;
+        egui::Window::new("Function groups")
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(
+                        "functions matching a pattern (regex) are renamed to its group, \
+                         first match wins. the group may use captures like $1",
+                    )
+                    .small()
+                    .weak(),
+                );
+                let mut remove = None;
+                let mut swap = None;
+                let count = self.group_rules.len();
+                egui::Grid::new("rules_grid").striped(true).show(ui, |ui| {
+                    ui.strong("Pattern");
+                    ui.strong("Group");
+                    ui.end_row();
+                    for (i, rule) in self.group_rules.iter_mut().enumerate() {
+                        changed |= ui
+                            .add(egui::TextEdit::singleline(&mut rule.pattern).desired_width(180.0))
+                            .changed();
+                        changed |= ui
+                            .add(egui::TextEdit::singleline(&mut rule.group).desired_width(100.0))
+                            .changed();
+                        if ui
+                            .add_enabled(i > 0, egui::Button::new("^").small())
+                            .clicked()
+                        {
+                            swap = Some(i - 1);
+                        }
+                        if ui
+                            .add_enabled(i + 1 < count, egui::Button::new("v").small())
+                            .clicked()
+                        {
+                            swap = Some(i);
+                        }
+                        if ui.small_button("x").clicked() {
+                            remove = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(i) = swap {
+                    self.group_rules.swap(i, i + 1);
+                    changed = true;
+                }
+                if let Some(i) = remove {
+                    self.group_rules.remove(i);
+                    changed = true;
+                }
+                if ui.button("Add rule").clicked() {
+                    self.group_rules.push(GroupRule {
+                        pattern: "^shmem_put".to_string(),
+                        group: "PUT".to_string(),
+                    });
+                    changed = true;
+                }
+                if let Some(err) = &self.rule_error {
+                    ui.colored_label(Color32::LIGHT_RED, err);
+                }
+
+                ui.separator();
+                ui.label("Groups (uncheck to hide on the timeline)");
+                egui::Grid::new("groups_grid").striped(true).show(ui, |ui| {
+                    for group in self.grouping.groups() {
+                        let mut shown = !self.annotations.hidden_groups.contains(group);
+                        if ui
+                            .checkbox(
+                                &mut shown,
+                                egui::RichText::new(group).color(
+                                    self.annotations
+                                        .color_overrides
+                                        .get(group)
+                                        .copied()
+                                        .unwrap_or_else(|| generate_color(group)),
+                                ),
+                            )
+                            .changed()
+                        {
+                            if shown {
+                                self.annotations.hidden_groups.remove(group);
+                            } else {
+                                self.annotations.hidden_groups.insert(group.to_string());
+                            }
+                        }
+                        let members = self.grouping.members(group);
+                        if members != [group] {
+                            ui.label(egui::RichText::new(members.join(", ")).small());
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        self.show_rules_window = open;
+        if changed {
+            self.rebuild_grouping();
+        }
+    }
+
+    fn ui_symbols_menu(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.demangle_frames, "Demangle C++/Rust symbols");
+        ui.separator();
+        ui.label("Binaries for address -> file:line");
+        let mut remove = None;
+        for path in self.symbolizer.binaries() {
+            ui.horizontal(|ui| {
+                if ui.small_button("x").clicked() {
+                    remove = Some(path.to_path_buf());
+                }
+                ui.label(path.display().to_string());
+            });
+        }
+        if let Some(path) = remove {
+            self.symbolizer.remove_binary(&path);
+        }
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.symbol_binary_input)
+                    .hint_text("path/to/binary")
+                    .desired_width(200.0),
+            );
+            if ui.button("Add").clicked() {
+                let path = PathBuf::from(self.symbol_binary_input.trim());
+                self.status_msg = Some(match self.symbolizer.add_binary(&path) {
+                    Ok(()) => {
+                        self.symbol_binary_input.clear();
+                        format!("loaded symbols from {}", path.display())
+                    }
+                    Err(e) => format!("{:#}", e),
+                });
+            }
+        });
+    }
+
+    fn ui_colors_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_colors_window;
+        let mut changed = false;
+        egui::Window::new("Colors")
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let mut edits = Vec::new();
+                let mut resets = Vec::new();
+                egui::Grid::new("colors_grid").striped(true).show(ui, |ui| {
+                    for group in self.grouping.groups() {
+                        let mut color = self
+                            .annotations
+                            .color_overrides
+                            .get(group)
+                            .copied()
+                            .unwrap_or_else(|| generate_color(group));
+                        if ui.color_edit_button_srgba(&mut color).changed() {
+                            edits.push((group.to_string(), color));
+                        }
+                        ui.label(group);
+                        if self.annotations.color_overrides.contains_key(group)
+                            && ui.small_button("reset").clicked()
+                        {
+                            resets.push(group.to_string());
+                        }
+                        ui.end_row();
+                    }
+                });
+                changed = !edits.is_empty() || !resets.is_empty();
+                self.annotations.color_overrides.extend(edits);
+                for function in resets {
+                    self.annotations.color_overrides.remove(&function);
+                }
+            });
+        self.show_colors_window = open;
+        if changed {
+            self.rebuild_function_colors();
+        }
+    }
+
+    fn ui_counters_menu(&mut self, ui: &mut egui::Ui) {
+        let Some(data) = &self.profile_data else {
+            return;
+        };
+        if data.counters.is_empty() {
+            ui.label("no numeric key=value pairs in Extra");
+            return;
+        }
+        for (name, counter) in &data.counters {
+            let mut shown = self.visible_counters.contains(name);
+            let label = egui::RichText::new(format!("{name} ({} .. {})", counter.min, counter.max))
+                .color(generate_color(name));
+            if ui.checkbox(&mut shown, label).changed() {
+                if shown {
+                    self.visible_counters.insert(name.clone());
+                } else {
+                    self.visible_counters.remove(name);
+                }
+            }
+        }
+        ui.separator();
+        if ui.button("Show all").clicked() {
+            self.visible_counters = data.counters.keys().cloned().collect();
+        }
+        if ui.button("Hide all").clicked() {
+            self.visible_counters.clear();
+        }
+    }
+
+    fn pane_visible(&self, pane: Pane) -> bool {
+        match pane {
+            Pane::Timeline | Pane::Bandwidth => true,
+            Pane::Stats => self.panes.stats.open,
+            Pane::Inspector => self.pinned_event.is_some(),
+        }
+    }
+
+    // the panes are taken out of the app while drawing, like the views
+    fn ui_pane(&mut self, ui: &mut egui::Ui, pane: Pane) {
+        let mut panes = std::mem::take(&mut self.panes);
+        let commands = match self.view_state() {
+            Some((data, state)) => panes.view(pane).ui(ui, data, &state),
+            None => {
+                if pane == Pane::Bandwidth {
+                    ui.label("No data loaded.");
+                }
+                Vec::new()
+            }
+        };
+        self.panes = panes;
+        self.hud.timeline = self.panes.timeline.counts;
+        self.hud.edges_drawn = self.panes.bandwidth.edges_drawn;
+        self.apply_view_commands(ui.ctx(), commands);
+    }
+
+    // docked panes become panels around the center one, floating ones windows
+    fn ui_docked_panes(&mut self, ctx: &egui::Context) {
+        for pane in Pane::ALL {
+            let slot = self.dock.slot(pane);
+            if !self.pane_visible(pane) {
+                continue;
+            }
+            // a new id after loading a layout, so the saved size is used
+            let id = Id::new(("dock", pane, self.dock_generation));
+            let size = self.dock.size(pane, slot);
+            let min = if pane == Pane::Timeline { 200.0 } else { 100.0 };
+            let shown = match slot {
+                Slot::Top | Slot::Bottom => {
+                    let panel = if slot == Slot::Top {
+                        egui::TopBottomPanel::top(id)
+                    } else {
+                        egui::TopBottomPanel::bottom(id)
+                    };
+                    panel
+                        .resizable(true)
+                        .min_height(min)
+                        .default_height(size)
+                        .show(ctx, |ui| self.ui_pane(ui, pane))
+                        .response
+                        .rect
+                        .height()
+                }
+                Slot::Left | Slot::Right => {
+                    let panel = if slot == Slot::Left {
+                        egui::SidePanel::left(id)
+                    } else {
+                        egui::SidePanel::right(id)
+                    };
+                    panel
+                        .resizable(true)
+                        .min_width(min)
+                        .default_width(size)
+                        .show(ctx, |ui| self.ui_pane(ui, pane))
+                        .response
+                        .rect
+                        .width()
+                }
+                Slot::Detached => {
+                    self.ui_detached_pane(ctx, pane);
+                    continue;
+                }
+                Slot::Floating => {
+                    let mut open = true;
+                    let mut window = egui::Window::new(pane.title())
+                        .id(Id::new(("pane", pane)))
+                        .default_size(Vec2::new(600.0, 400.0));
+                    if matches!(pane, Pane::Stats | Pane::Inspector) {
+                        window = window.open(&mut open);
+                    }
+                    if pane == Pane::Inspector {
+                        window = window.default_pos(self.pinned_event_pos);
+                    }
+                    window.show(ctx, |ui| self.ui_pane(ui, pane));
+                    if !open {
+                        match pane {
+                            Pane::Stats => self.panes.stats.open = false,
+                            Pane::Inspector => self.pinned_event = None,
+                            _ => {}
+                        }
+                    }
+                    continue;
+                }
+                Slot::Center | Slot::Hidden => continue,
+            };
+            self.dock.sizes.insert(pane, shown);
         }
+    }
 
-        // draw nodes
-        for i in 0..count {
-            let pos = get_pos(i);
-
-            let mut fill_color = Color32::DARK_GRAY;
-            let mut stroke_color = Color32::WHITE;
-            let mut stroke_width = 1.0;
-
-            if let Some(h) = hovered_pe {
-                if i == h {
-                    // hovered node
-                    fill_color = Color32::from_rgb(100, 100, 200); // highlight
-                    stroke_width = 2.0;
-                } else if let Some((tx, rx)) = interaction_bytes.get(&i) {
-                    // node interacting with hovered node
-                    let total = tx + rx;
-                    if total > 0 && max_interaction > 0 {
-                        let ratio_tx = *tx as f32 / total as f32;
-                        let ratio_rx = *rx as f32 / total as f32;
-
-                        let r_target = (255.0 * ratio_tx) as u8;
-                        let b_target = (255.0 * ratio_rx) as u8;
-
-                        let intensity = (total as f32 / max_interaction as f32)
-                            .sqrt()
-                            .clamp(0.0, 1.0);
-
-                        let base = 50.0;
-                        let r = (base + (r_target as f32 - base) * intensity) as u8;
-                        let g = (base * (1.0 - intensity)) as u8;
-                        let b = (base + (b_target as f32 - base) * intensity) as u8;
-
-                        fill_color = Color32::from_rgb(r, g, b);
-                    }
+    // a pane in its own OS window. closing it docks the pane back where it
+    // goes by default (or hides the stats / event details)
+    fn ui_detached_pane(&mut self, ctx: &egui::Context, pane: Pane) {
+        let title = format!("{} - csvpshmem visualizer", pane.title());
+        let builder = egui::ViewportBuilder::default()
+            .with_title(title)
+            .with_inner_size([800.0, 600.0]);
+        let mut closed = false;
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of(("pane", pane)),
+            builder,
+            |ctx, class| {
+                if class == egui::ViewportClass::Embedded {
+                    // the backend can't open more windows, fall back to an inner one
+                    let mut open = true;
+                    egui::Window::new(pane.title())
+                        .id(Id::new(("pane", pane)))
+                        .open(&mut open)
+                        .default_size(Vec2::new(600.0, 400.0))
+                        .show(ctx, |ui| self.ui_pane(ui, pane));
+                    closed = !open;
                 } else {
-                    // irrelevant node
-                    fill_color = Color32::from_rgba_premultiplied(50, 50, 50, 50);
-                    stroke_color = Color32::from_rgba_premultiplied(200, 200, 200, 50);
+                    egui::CentralPanel::default().show(ctx, |ui| self.ui_pane(ui, pane));
+                    closed = ctx.input(|i| i.viewport().close_requested());
                 }
+            },
+        );
+        if closed {
+            match pane {
+                Pane::Stats => self.panes.stats.open = false,
+                Pane::Inspector => self.pinned_event = None,
+                _ => self.dock.place(pane, DockLayout::default().slot(pane)),
             }
-
-            painter.circle_filled(pos, node_radius, fill_color);
-            painter.circle_stroke(pos, node_radius, Stroke::new(stroke_width, stroke_color));
-            painter.text(
-                pos,
-                egui::Align2::CENTER_CENTER,
-                format!("{}", i),
-                egui::FontId::proportional(14.0),
-                stroke_color,
-            );
         }
     }
 
-    fn ui_timeline(&mut self, ui: &mut egui::Ui) {
-        let Some(data) = self.profile_data.as_ref() else {
-            return;
-        };
-        let available_size = ui.available_size();
-        let track_height = self.timeline_track_height;
-        let ruler_height = 30.0;
-        let label_width = 120.0;
-
-        let (response, painter) = ui.allocate_painter(available_size, Sense::click_and_drag());
-        let rect = response.rect;
-
-        let timeline_rect =
-            Rect::from_min_max(rect.min + Vec2::new(label_width, ruler_height), rect.max);
-
-        if response.hovered() {
-            let zoom_delta = ui.input(|i| i.smooth_scroll_delta.y);
-            if zoom_delta != 0.0 {
-                if ui.input(|i| i.modifiers.shift) {
-                    let zoom_factor = ((-zoom_delta / 200.0) as f32).exp();
-                    let old_track_height = self.timeline_track_height;
-                    self.timeline_track_height =
-                        (self.timeline_track_height * zoom_factor).clamp(8.0, 100.0);
-
-                    if let Some(hover_pos) = response.hover_pos() {
-                        let y_in_content =
-                            (hover_pos.y - timeline_rect.min.y) + self.timeline_pe_scroll;
-                        let new_y_in_content =
-                            y_in_content * (self.timeline_track_height / old_track_height);
-                        self.timeline_pe_scroll =
-                            new_y_in_content - (hover_pos.y - timeline_rect.min.y);
-                    }
-                } else {
-                    let zoom_factor = ((-zoom_delta / 200.0) as f64).exp();
-                    if let Some(hover_pos) = response.hover_pos() {
-                        let ratio =
-                            ((hover_pos.x - timeline_rect.min.x) / timeline_rect.width()) as f64;
-                        let hover_time = self.timeline_start_time
-                            + ratio * (self.timeline_end_time - self.timeline_start_time);
-
-                        self.timeline_start_time =
-                            hover_time - (hover_time - self.timeline_start_time) * zoom_factor;
-                        self.timeline_end_time =
-                            hover_time + (self.timeline_end_time - hover_time) * zoom_factor;
-
-                        if self.timeline_end_time - self.timeline_start_time < 0.000000001 {
-                            let center = (self.timeline_start_time + self.timeline_end_time) / 2.0;
-                            self.timeline_start_time = center - 0.0000000005;
-                            self.timeline_end_time = center + 0.0000000005;
+    fn ui_panels_menu(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("panels_grid").show(ui, |ui| {
+            for pane in Pane::ALL {
+                ui.label(pane.title());
+                let mut slot = self.dock.slot(pane);
+                egui::ComboBox::from_id_salt(("pane_slot", pane))
+                    .selected_text(slot.label())
+                    .show_ui(ui, |ui| {
+                        for s in Slot::ALL {
+                            ui.selectable_value(&mut slot, s, s.label());
                         }
-                    }
+                    });
+                if slot != self.dock.slot(pane) {
+                    self.dock.place(pane, slot);
                 }
+                ui.end_row();
             }
+        });
+        if ui.button("Reset layout").clicked() {
+            self.dock = DockLayout::default();
+            self.dock_generation += 1;
         }
-
-        if response.dragged() {
-            let delta = response.drag_delta();
-
-            let time_per_pixel =
-                (self.timeline_end_time - self.timeline_start_time) / timeline_rect.width() as f64;
-            let time_delta = delta.x as f64 * time_per_pixel;
-            self.timeline_start_time -= time_delta;
-            self.timeline_end_time -= time_delta;
-
-            self.timeline_pe_scroll -= delta.y;
+        ui.separator();
+        for (view, open) in self.views.iter_mut() {
+            ui.checkbox(open, view.name());
         }
+    }
 
-        let duration = self.timeline_end_time - self.timeline_start_time;
-        if self.timeline_start_time > data.max_time {
-            self.timeline_start_time = data.max_time;
-            self.timeline_end_time = self.timeline_start_time + duration;
-        }
-        if self.timeline_end_time < data.min_time {
-            self.timeline_end_time = data.min_time;
-            self.timeline_start_time = self.timeline_end_time - duration;
+    // the registered views that are open, each in its own window. they're
+    // taken out of the app while drawing, so it can hand them its state
+    fn ui_views(&mut self, ctx: &egui::Context) {
+        let mut views = std::mem::take(&mut self.views);
+        let mut commands = Vec::new();
+        if let Some((data, state)) = self.view_state() {
+            for (view, open) in views.iter_mut() {
+                if !*open {
+                    continue;
+                }
+                egui::Window::new(view.name().to_string())
+                    .id(Id::new(("view", view.name().to_string())))
+                    .open(open)
+                    .default_size(Vec2::new(420.0, 320.0))
+                    .show(ctx, |ui| commands.extend(view.ui(ui, data, &state)));
+            }
         }
+        self.views = views;
+        self.apply_view_commands(ctx, commands);
+    }
 
-        let total_content_height = data.pe_count as f32 * self.timeline_track_height;
-        let max_scroll = (total_content_height - (timeline_rect.height() - track_height)).max(0.0);
-        self.timeline_pe_scroll = self.timeline_pe_scroll.clamp(0.0, max_scroll);
-
-        let timeline_start = self.timeline_start_time;
-        let timeline_end = self.timeline_end_time;
-        let timeline_rect_min_x = timeline_rect.min.x;
-        let timeline_rect_width = timeline_rect.width();
+    // the analysis windows and the bandwidth view's edge list, taken out
+    // of the app while drawing like the views
+    fn ui_windows(&mut self, ctx: &egui::Context) {
+        let mut windows = std::mem::take(&mut self.windows);
+        let mut panes = std::mem::take(&mut self.panes);
+        let mut commands = Vec::new();
+        if let Some((data, state)) = self.view_state() {
+            commands = windows.show(ctx, data, &state);
+            commands.extend(panes.bandwidth.ui_edge_window(ctx, data, &state));
+        }
+        self.windows = windows;
+        self.panes = panes;
+        self.apply_view_commands(ctx, commands);
+    }
 
-        let time_to_x = |t: f64| {
-            let ratio = (t - timeline_start) / (timeline_end - timeline_start);
-            timeline_rect_min_x + ratio as f32 * timeline_rect_width
+    // the trace and what the viewer shows of it, for the views
+    fn view_state(&self) -> Option<(&ProfileData, ViewState<'_>)> {
+        let data = self.profile_data.as_ref()?;
+        let state = ViewState {
+            view: (self.timeline_start_time, self.timeline_end_time),
+            selection: self.selection,
+            analysis_range: self.analysis_range(),
+            analysis_range_name: self.analysis_range_name(),
+            cursor: self.cursor_time,
+            cursor_b: self.cursor_b,
+            hover: self.hover_time,
+            window: self.window_size_seconds,
+            loop_a: self.loop_a,
+            loop_b: self.loop_b,
+            loop_enabled: self.loop_enabled,
+            playing: self.playing,
+            selected_pe: self.selected_pe,
+            pinned_event: self.pinned_event,
+            annotations: &self.annotations,
+            grouping: &self.grouping,
+            colors: &self.colors,
+            derived: &self.derived,
+            search: &self.search,
+            track_order: &self.track_order,
+            lanes: &self.lanes,
+            phases: self.windows.phases.overlay(),
+            pe_labels: &self.pe_labels,
+            palette: &self.palette,
+            time_display: self.time_display,
+            visible_counters: &self.visible_counters,
+            symbolizer: &self.symbolizer,
+            demangle_frames: self.demangle_frames,
+            topology: self.topology.as_ref(),
+            group_level: self.group_level,
+            full_detail: self.config.full_detail_while_moving
+                || self.screenshot_path.is_some()
+                || self.pending_export.is_some()
+                || self.recorder.is_some(),
+            export: self.pending_export.as_ref().map(|r| r.target),
+            recording: self.recorder.is_some(),
         };
-        let x_to_time = |x: f32| {
-            let ratio = (x - timeline_rect_min_x) / timeline_rect_width;
-            timeline_start + ratio as f64 * (timeline_end - timeline_start)
-        };
-
-        painter.rect_filled(rect, 0.0, Color32::from_gray(18));
-
-        let data_painter = painter.with_clip_rect(timeline_rect);
-
-        if let Some(h_time) = self.hover_time {
-            let h_start = h_time - self.window_size_seconds / 2.0;
-            let h_end = h_time + self.window_size_seconds / 2.0;
-            let x_start = time_to_x(h_start);
-            let x_end = time_to_x(h_end);
-            let highlight_rect = Rect::from_min_max(
-                Pos2::new(x_start.max(timeline_rect.min.x), timeline_rect.min.y),
-                Pos2::new(x_end.min(timeline_rect.max.x), timeline_rect.max.y),
-            );
-            data_painter.rect_filled(
-                highlight_rect,
-                0.0,
-                Color32::from_rgba_premultiplied(255, 255, 0, 15),
-            );
-        }
+        Some((data, state))
+    }
 
-        for i in 0..=data.pe_count {
-            let y_in_content = i as f32 * self.timeline_track_height;
-            let y = timeline_rect.min.y + y_in_content - self.timeline_pe_scroll;
-            if y >= timeline_rect.min.y && y <= timeline_rect.max.y {
-                data_painter.line_segment(
-                    [
-                        Pos2::new(timeline_rect.min.x, y),
-                        Pos2::new(timeline_rect.max.x, y),
-                    ],
-                    Stroke::new(1.0, Color32::from_gray(30)),
-                );
+    fn apply_view_commands(&mut self, ctx: &egui::Context, commands: Vec<ViewCommand>) {
+        let pe_count = self.profile_data.as_ref().map_or(0, |d| d.pe_count);
+        for command in commands {
+            match command {
+                ViewCommand::SetView(start, end) => self.set_view(start, end),
+                ViewCommand::Pan(start, end) => {
+                    let now = ctx.input(|i| i.time);
+                    let current = (self.timeline_start_time, self.timeline_end_time);
+                    self.zoom_history.note_gesture(current, now);
+                    self.timeline_start_time = start;
+                    self.timeline_end_time = end;
+                }
+                ViewCommand::JumpTo(t) => {
+                    self.playing = false;
+                    self.jump_to_time(t);
+                }
+                ViewCommand::SetCursor(t) => self.cursor_time = t,
+                ViewCommand::SetCursorB(t) => self.cursor_b = t,
+                ViewCommand::Hover(t) => self.hover_next = Some(t),
+                ViewCommand::SetWindow(seconds) => self.window_size_seconds = seconds,
+                ViewCommand::Select(start, end) => self.selection = Some((start, end)),
+                ViewCommand::Deselect => self.selection = None,
+                ViewCommand::LoopA(t) => self.loop_a = Some(t),
+                ViewCommand::LoopB(t) => self.loop_b = Some(t),
+                ViewCommand::Loop(a, b) => {
+                    self.loop_a = Some(a);
+                    self.loop_b = Some(b);
+                    self.loop_enabled = true;
+                }
+                ViewCommand::Solo(function) => self.annotations.solo_function = function,
+                ViewCommand::HideFunction(function) => {
+                    self.annotations.hidden_functions.insert(function);
+                }
+                ViewCommand::ShowFunction(function) => {
+                    self.annotations.hidden_functions.remove(&function);
+                }
+                ViewCommand::AddBookmark(name, t, pe) => {
+                    self.annotations.bookmarks.add(name, t, pe);
+                }
+                ViewCommand::SelectPe(pe) => self.selected_pe = pe,
+                ViewCommand::PinEvent(i, pos) => {
+                    self.pinned_event = Some(i);
+                    if let Some(pos) = pos {
+                        self.pinned_event_pos = pos;
+                    }
+                }
+                ViewCommand::Unpin => self.pinned_event = None,
+                ViewCommand::MoveTrack(pe, before) => {
+                    self.track_order.move_pe(pe_count, pe, before);
+                }
+                ViewCommand::PinTrack(pe, true) => {
+                    self.track_order.pinned.insert(pe);
+                }
+                ViewCommand::PinTrack(pe, false) => {
+                    self.track_order.pinned.remove(&pe);
+                }
+                ViewCommand::HideTrack(pe) => {
+                    self.track_order.hidden.insert(pe);
+                }
+                ViewCommand::SoloTrack(pe) => {
+                    self.track_order.hidden = (0..pe_count).filter(|p| *p != pe).collect();
+                }
+                ViewCommand::InspectEdge(edge) => self.panes.bandwidth.inspected_edge = Some(edge),
+                ViewCommand::DerivedSettings(settings) => {
+                    self.derived
+                        .set_settings(settings, self.profile_data.as_ref());
+                }
+                ViewCommand::Status(msg) => self.status_msg = Some(msg),
+                ViewCommand::Captured(target, view) => self.captured(target, &view),
             }
         }
+    }
 
-        let start_idx = data
-            .events
-            .partition_point(|e| e.raw.time < self.timeline_start_time - 0.5);
-        let mut hovered_event = None;
-
-        for i in start_idx..data.events.len() {
-            let e = &data.events[i];
-            if e.raw.time > self.timeline_end_time {
-                break;
-            }
-
-            let x_start = time_to_x(e.raw.time);
-            let x_end = time_to_x(e.raw.time + e.raw.duration_sec.max(0.000000001));
-
-            if x_end < timeline_rect.min.x || x_start > timeline_rect.max.x {
-                continue;
-            }
-
-            let y_start_in_content = e.source_pe as f32 * self.timeline_track_height;
-            let y_start = timeline_rect.min.y + y_start_in_content - self.timeline_pe_scroll;
-            let y_end = y_start + self.timeline_track_height;
-
-            if y_end < timeline_rect.min.y || y_start > timeline_rect.max.y {
-                continue;
+    // finishes the export waiting for a pane, and records the bandwidth
+    // pane's frame when a video is being made
+    fn captured(&mut self, target: ExportTarget, view: &CapturedView) {
+        if let Some(request) = self.pending_export.take_if(|r| r.target == target) {
+            self.status_msg = Some(export_status(&request, view));
+        }
+        if target != ExportTarget::Bandwidth {
+            return;
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            let pushed = recorder.push_frame(view);
+            let (frame, total) = recorder.progress();
+            self.status_msg = Some(format!(
+                "recording {} frame {frame}/{total}",
+                recorder.path().display()
+            ));
+            if pushed.is_err() || recorder.is_done() {
+                let recorder = self.recorder.take().expect("recorder checked above");
+                self.status_msg = Some(match pushed.and_then(|_| recorder.finish()) {
+                    Ok(path) => format!("recorded {}", path.display()),
+                    Err(e) => format!("recording failed: {:#}", e),
+                });
             }
+        }
+    }
 
-            let color = self
-                .function_colors
-                .get(&e.raw.function)
-                .copied()
-                .unwrap_or(Color32::GRAY);
-            let event_rect = Rect::from_min_max(
-                Pos2::new(x_start.max(timeline_rect.min.x), y_start + 1.0),
-                Pos2::new(x_end.min(timeline_rect.max.x), y_end - 1.0),
-            );
+    // edits the config file: default directory, default colors and groups, keys
+    fn ui_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings_window;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Config file:");
+                    ui.text_edit_singleline(&mut self.config_path);
+                });
+                ui.separator();
 
-            if event_rect.width() > 2.0 {
-                data_painter.rect_filled(event_rect, 1.0, color);
-                data_painter.rect_stroke(
-                    event_rect,
-                    1.0,
-                    Stroke::new(0.5, Color32::BLACK.gamma_multiply(0.5)),
-                    StrokeKind::Inside,
+                ui.horizontal(|ui| {
+                    ui.label("Default directory:");
+                    let mut dir = self
+                        .config
+                        .data_dir
+                        .as_ref()
+                        .map_or_else(String::new, |d| d.display().to_string());
+                    if ui.text_edit_singleline(&mut dir).changed() {
+                        self.config.data_dir =
+                            (!dir.trim().is_empty()).then(|| PathBuf::from(dir.trim()));
+                    }
+                    if ui.button("Use current").clicked() {
+                        self.config.data_dir = Some(self.data_dir.clone());
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Topology:");
+                    let mut path = self
+                        .config
+                        .topology
+                        .as_ref()
+                        .map_or_else(String::new, |p| p.display().to_string());
+                    if ui.text_edit_singleline(&mut path).changed() {
+                        self.config.topology =
+                            (!path.trim().is_empty()).then(|| PathBuf::from(path.trim()));
+                    }
+                    if ui.button("Use current").clicked() {
+                        self.config.topology = self
+                            .topology
+                            .is_some()
+                            .then(|| PathBuf::from(self.topology_path.trim()));
+                    }
+                });
+                ui.checkbox(
+                    &mut self.config.auto_reload,
+                    "Reload automatically when the data files change",
                 );
-            } else {
-                data_painter.rect_filled(event_rect, 0.0, color);
-            }
+                ui.checkbox(
+                    &mut self.config.full_detail_while_moving,
+                    "Draw every event while dragging or zooming the timeline",
+                )
+                .on_hover_text(
+                    "otherwise events hidden under others are skipped until the view stops",
+                );
+                ui.horizontal(|ui| {
+                    let mut limit = self.config.memory_budget_mb.is_some();
+                    ui.checkbox(&mut limit, "Keep traces under").on_hover_text(
+                        "bigger traces load without their symbol traces, then stack \
+                             traces. applies to the next trace loaded",
+                    );
+                    let mut mb = self.config.memory_budget_mb.unwrap_or(2048);
+                    ui.add_enabled(
+                        limit,
+                        egui::DragValue::new(&mut mb)
+                            .range(64..=1_048_576)
+                            .speed(16.0)
+                            .suffix(" MiB"),
+                    );
+                    self.config.memory_budget_mb = limit.then_some(mb);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "Default colors: {}, groups: {}",
+                        self.config.color_overrides.len(),
+                        self.config.group_rules.len()
+                    ));
+                    if ui
+                        .button("Use current")
+                        .on_hover_text("take the colors and group rules set up now")
+                        .clicked()
+                    {
+                        self.config.color_overrides = self
+                            .annotations
+                            .color_overrides
+                            .iter()
+                            .map(|(f, c)| (f.clone(), [c.r(), c.g(), c.b()]))
+                            .collect();
+                        self.config.group_rules = self.group_rules.clone();
+                    }
+                });
+                ui.separator();
 
-            if let Some(mouse_pos) = response.hover_pos() {
-                if event_rect.contains(mouse_pos) {
-                    hovered_event = Some(e);
+                ui.strong("Keys");
+                egui::Grid::new("keys_grid").striped(true).show(ui, |ui| {
+                    for action in Action::ALL {
+                        ui.label(action.label());
+                        let binding = self
+                            .config
+                            .keys
+                            .bindings
+                            .entry(action)
+                            .or_insert_with(|| action.default_binding().to_string());
+                        let valid = keys::parse_shortcut(binding).is_some();
+                        let edit = egui::TextEdit::singleline(binding).desired_width(120.0);
+                        let edit = if valid {
+                            edit
+                        } else {
+                            edit.text_color(Color32::LIGHT_RED)
+                        };
+                        ui.add(edit)
+                            .on_hover_text("e.g. M, shift+M, ctrl+alt+ArrowLeft, F3, OpenBracket");
+                        ui.end_row();
+                    }
+                });
+                if ui.button("Default keys").clicked() {
+                    self.config.keys = Default::default();
                 }
-            }
-        }
+                ui.separator();
 
-        let label_area_rect =
-            Rect::from_min_max(rect.min, Pos2::new(timeline_rect.min.x, rect.max.y));
-        painter.rect_filled(label_area_rect, 0.0, Color32::from_gray(22));
+                ui.horizontal(|ui| {
+                    let path = PathBuf::from(self.config_path.trim());
+                    if ui.button("Save").clicked() {
+                        self.status_msg = Some(match self.config.save(&path) {
+                            Ok(()) => format!("saved settings to {}", path.display()),
+                            Err(e) => format!("failed to save settings: {e:#}"),
+                        });
+                    }
+                    if ui.button("Reload").clicked() {
+                        match Config::load(&path) {
+                            Ok(config) => {
+                                self.config = config;
+                                self.status_msg =
+                                    Some(format!("loaded settings from {}", path.display()));
+                            }
+                            Err(e) => {
+                                self.status_msg = Some(format!("failed to load settings: {e:#}"))
+                            }
+                        }
+                    }
+                });
+            });
+        self.show_settings_window = open;
+    }
 
-        //painter.line_segment(
-        //[
-        //Pos2::new(rect.min.x, timeline_rect.min.y),
-        //Pos2::new(timeline_rect.min.x, rect.max.y),
-        //],
-        //Stroke::new(1.0, Color32::from_gray(40)),
-        //);
+    // the viewer's own frame time and draw counts, in the corner of `area`
+    fn ui_hud(&mut self, ctx: &egui::Context, area: Rect) {
+        if !self.show_hud {
+            return;
+        }
+        // egui only redraws on input, so idle frame times say little
+        if self.hud_continuous {
+            ctx.request_repaint();
+        }
+        let frame = self.hud.mean_frame_time();
+        let worst = self.hud.worst_frame_time();
+        egui::Area::new(Id::new("perf_hud"))
+            .order(Order::Foreground)
+            .pivot(egui::Align2::RIGHT_TOP)
+            .fixed_pos(area.right_top() + Vec2::new(-8.0, 8.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(format!(
+                        "frame {:.2} ms ({:.0} fps), worst {:.2} ms",
+                        frame * 1e3,
+                        if frame > 0.0 { 1.0 / frame } else { 0.0 },
+                        worst * 1e3
+                    ));
+                    ui.label(format!(
+                        "update {:.2} ms",
+                        self.hud.mean_update_time() * 1e3
+                    ));
+
+                    // frame times, with a line at 60 fps
+                    let (rect, _) = ui.allocate_exact_size(Vec2::new(240.0, 40.0), Sense::hover());
+                    let painter = ui.painter_at(rect);
+                    painter.rect_filled(rect, 0.0, self.palette.plot_bg.gamma_multiply(0.8));
+                    let scale = worst.max(1.0 / 30.0);
+                    let times = self.hud.frame_times();
+                    let bar = rect.width() / times.len().max(1) as f32;
+                    for (i, t) in times.iter().enumerate() {
+                        let h = (t / scale).min(1.0) * rect.height();
+                        let x = rect.min.x + i as f32 * bar;
+                        let color = if *t > 1.0 / 30.0 {
+                            Color32::LIGHT_RED
+                        } else {
+                            Color32::LIGHT_GREEN
+                        };
+                        painter.rect_filled(
+                            Rect::from_min_max(
+                                Pos2::new(x, rect.max.y - h),
+                                Pos2::new(x + bar.max(1.0), rect.max.y),
+                            ),
+                            0.0,
+                            color,
+                        );
+                    }
+                    let target_y = rect.max.y - (1.0 / 60.0) / scale * rect.height();
+                    painter.hline(
+                        rect.x_range(),
+                        target_y,
+                        Stroke::new(1.0, Color32::YELLOW.gamma_multiply(0.6)),
+                    );
+
+                    let t = self.hud.timeline;
+                    ui.label(format!(
+                        "timeline: {} drawn, {} merged, {} culled, {} filtered of {}",
+                        t.drawn, t.merged, t.culled, t.filtered, t.scanned
+                    ));
+                    ui.label(format!("edges drawn: {}", self.hud.edges_drawn));
+                    let events = self.profile_data.as_ref().map_or(0, |d| d.events.len());
+                    ui.label(format!(
+                        "profile data: {} ({events} events)",
+                        units::bytes(self.hud.data_bytes as u64)
+                    ));
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.hud_continuous, "Repaint continuously")
+                            .on_hover_text("measure frame times while nothing moves");
+                        if ui.button("Copy").clicked() {
+                            ctx.copy_text(self.hud.report());
+                        }
+                    });
+                });
+            });
+    }
 
-        let labels_painter = painter.with_clip_rect(label_area_rect);
-        for i in 0..data.pe_count {
-            let y_in_content = i as f32 * self.timeline_track_height;
-            let y = timeline_rect.min.y + y_in_content - self.timeline_pe_scroll;
-            if y + self.timeline_track_height < timeline_rect.min.y {
-                continue;
+    // hands theme and scale changes to egui, and picks up zoom changes made
+    // with egui's own ctrl +/- keys
+    fn apply_appearance(&mut self, ctx: &egui::Context) {
+        let wanted = (self.theme, self.ui_scale);
+        if self.applied_appearance != Some(wanted) {
+            // rescaling under a dragged slider makes it jump, wait for release
+            if !ctx.is_using_pointer() {
+                ctx.set_theme(self.theme.preference());
+                ctx.set_zoom_factor(self.ui_scale);
+                self.applied_appearance = Some(wanted);
             }
-            if y > timeline_rect.max.y {
-                break;
-            }
-
-            let hostname = data.pe_hostnames.get(&i).cloned().unwrap_or_default();
+        } else if ctx.zoom_factor() != self.ui_scale {
+            self.ui_scale = ctx.zoom_factor();
+            self.applied_appearance = Some((self.theme, self.ui_scale));
+        }
+        self.palette = Palette::for_theme(ctx.theme());
+    }
 
-            labels_painter.text(
-                Pos2::new(rect.min.x + 5.0, y + 2.0),
-                egui::Align2::LEFT_TOP,
-                format!("PE {}", i),
-                egui::FontId::proportional(11.0),
-                Color32::from_gray(200),
+    fn ui_view_menu(&mut self, ui: &mut egui::Ui) {
+        ui.label("Theme");
+        for choice in ThemeChoice::ALL {
+            ui.radio_value(&mut self.theme, choice, choice.label());
+        }
+        ui.separator();
+        ui.label("UI scale");
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::Slider::new(
+                    &mut self.ui_scale,
+                    theme::MIN_UI_SCALE..=theme::MAX_UI_SCALE,
+                )
+                .step_by(0.05)
+                .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
             );
+            if ui.button("Reset").clicked() {
+                self.ui_scale = 1.0;
+            }
+        });
+        ui.small("ctrl + / ctrl - also work");
+        ui.separator();
+        ui.menu_button("Panels", |ui| self.ui_panels_menu(ui));
+    }
 
-            labels_painter.text(
-                Pos2::new(rect.min.x + 5.0, y + 12.0),
-                egui::Align2::LEFT_TOP,
-                hostname,
-                egui::FontId::proportional(8.0),
-                Color32::from_gray(120),
-            );
+    fn ui_tracks_menu(&mut self, ui: &mut egui::Ui) {
+        ui.radio_value(
+            &mut self.panes.timeline.function_rows,
+            false,
+            "A row per PE",
+        );
+        ui.radio_value(
+            &mut self.panes.timeline.function_rows,
+            true,
+            "A row per function",
+        )
+        .on_hover_text("how many PEs are in each function over time, click a hot spot to go there");
+        ui.separator();
+        let stacking = self.stacking;
+        ui.radio_value(
+            &mut self.stacking,
+            Stacking::Overlap,
+            "Stack overlapping events",
+        );
+        ui.radio_value(
+            &mut self.stacking,
+            Stacking::Depth,
+            "Nest by stack depth (flame chart)",
+        );
+        ui.radio_value(&mut self.stacking, Stacking::Flat, "Single strip");
+        if self.stacking != stacking {
+            self.rebuild_lanes();
         }
-
-        let ruler_area_rect =
-            Rect::from_min_max(rect.min, Pos2::new(rect.max.x, timeline_rect.min.y));
-        painter.rect_filled(ruler_area_rect, 0.0, Color32::from_gray(35));
-
-        painter.line_segment(
-            [
-                Pos2::new(rect.min.x, timeline_rect.min.y),
-                Pos2::new(rect.max.x, timeline_rect.min.y),
-            ],
-            Stroke::new(1.0, Color32::from_gray(60)),
+        ui.separator();
+        ui.checkbox(
+            &mut self.panes.timeline.message_lines_all,
+            "Message lines for every visible call",
+        )
+        .on_hover_text(format!(
+            "not only the hovered and pinned one, up to {}",
+            timeline::MAX_MESSAGE_LINES
+        ));
+        ui.checkbox(
+            &mut self.panes.timeline.show_overlap_strip,
+            "Communication overlap strip",
+        )
+        .on_hover_text(
+            "under each track: green where communication ran behind computation, \
+                 red where the PE waited in calls",
         );
-
-        let ruler_painter = painter.with_clip_rect(ruler_area_rect);
-        let time_range = self.timeline_end_time - self.timeline_start_time;
-        let ideal_tick_spacing = 100.0f64;
-        let time_per_tick = time_range * (ideal_tick_spacing / timeline_rect.width() as f64);
-        let log = time_per_tick.log10().floor();
-        let base = 10.0f64.powf(log);
-        let tick_step = if time_per_tick / base < 2.0 {
-            base
-        } else if time_per_tick / base < 5.0 {
-            base * 2.0
-        } else {
-            base * 5.0
+        ui.checkbox(
+            &mut self.panes.timeline.show_in_flight,
+            "In-flight puts and gets",
+        )
+        .on_hover_text("hatched from the call returning to the quiet, fence or barrier after it");
+        ui.horizontal(|ui| {
+            ui.label("Color calls by");
+            egui::ComboBox::from_id_salt("color_by")
+                .selected_text(self.colors.by.label())
+                .show_ui(ui, |ui| {
+                    for by in ColorBy::ALL {
+                        ui.selectable_value(&mut self.colors.by, by, by.label());
+                    }
+                });
+        });
+        if let Some((low, high)) = self.colors.scale() {
+            ui.horizontal(|ui| {
+                ui.small(low);
+                let (rect, _) = ui.allocate_exact_size(Vec2::new(120.0, 10.0), Sense::hover());
+                let steps = 24;
+                for k in 0..steps {
+                    let x0 = rect.min.x + rect.width() * k as f32 / steps as f32;
+                    let x1 = rect.min.x + rect.width() * (k + 1) as f32 / steps as f32;
+                    ui.painter().rect_filled(
+                        Rect::from_min_max(Pos2::new(x0, rect.min.y), Pos2::new(x1, rect.max.y)),
+                        0.0,
+                        coloring::heat(k as f32 / (steps - 1) as f32),
+                    );
+                }
+                ui.small(high);
+            });
+        }
+        ui.separator();
+        let Some(data) = &self.profile_data else {
+            return;
         };
-
-        let first_tick = (self.timeline_start_time / tick_step).ceil() * tick_step;
-        let mut curr_tick = first_tick;
-        while curr_tick <= self.timeline_end_time {
-            let x = time_to_x(curr_tick);
-            ruler_painter.line_segment(
-                [
-                    Pos2::new(x, ruler_area_rect.min.y),
-                    Pos2::new(x, ruler_area_rect.max.y),
-                ],
-                Stroke::new(1.0, Color32::from_gray(80)),
-            );
-            ruler_painter.text(
-                Pos2::new(x + 2.0, ruler_area_rect.min.y + 2.0),
-                egui::Align2::LEFT_TOP,
-                format!("{:.4}s", curr_tick),
-                egui::FontId::proportional(10.0),
-                Color32::LIGHT_GRAY,
-            );
-            curr_tick += tick_step;
+        let pe_count = data.pe_count;
+        ui.label("Drag PE labels to reorder, right-click to hide or pin");
+        if ui.button("Sort by PE").clicked() {
+            self.track_order.order.clear();
         }
-
-        let px = time_to_x(self.cursor_time);
-        if px >= timeline_rect.min.x && px <= timeline_rect.max.x {
-            painter.line_segment(
-                [Pos2::new(px, rect.min.y), Pos2::new(px, rect.max.y)],
-                Stroke::new(1.0, Color32::WHITE),
-            );
-            let head_size = 6.0;
-            painter.add(egui::Shape::convex_polygon(
-                vec![
-                    Pos2::new(px, timeline_rect.min.y),
-                    Pos2::new(px - head_size, timeline_rect.min.y - head_size),
-                    Pos2::new(px + head_size, timeline_rect.min.y - head_size),
-                ],
-                Color32::WHITE,
-                Stroke::NONE,
-            ));
+        // activity is measured over what's on screen
+        let (start, end) = (self.timeline_start_time, self.timeline_end_time);
+        if ui.button("Sort by events in view").clicked() {
+            let load = imbalance::per_pe(data, start, end);
+            self.track_order
+                .sort_by(pe_count, |pe| load[pe as usize].calls as f64);
         }
-
-        if let Some(pos) = response.hover_pos() {
-            if timeline_rect.contains(pos) {
-                self.hover_time = Some(x_to_time(pos.x));
-            } else {
-                self.hover_time = None;
-            }
-
-            if response.clicked() || response.dragged() {
-                if ruler_area_rect.contains(pos)
-                    || (timeline_rect.contains(pos) && ui.input(|i| i.modifiers.shift))
-                {
-                    self.cursor_time = x_to_time(pos.x).clamp(data.min_time, data.max_time);
-                }
-            }
-        } else {
-            self.hover_time = None;
+        if ui.button("Sort by bytes in view").clicked() {
+            let sums = &self.derived.byte_sums;
+            self.track_order.sort_by(pe_count, |pe| {
+                let (tx, rx) = sums.pe_bytes(pe, start, end);
+                (tx + rx) as f64
+            });
         }
-
-        if let Some(e) = hovered_event {
-            let ctx = ui.ctx().clone();
-            egui::Tooltip::always_open(
-                ctx,
-                LayerId::new(Order::Tooltip, Id::new("hover_tooltip")),
-                Id::new("hover_tooltip"),
-                PopupAnchor::Pointer,
+        ui.separator();
+        if ui
+            .add_enabled(
+                !self.track_order.hidden.is_empty(),
+                egui::Button::new(format!(
+                    "Show all ({} hidden)",
+                    self.track_order.hidden.len()
+                )),
             )
-            .show(|ui: &mut egui::Ui| {
-                ui.strong(&e.raw.function);
-                if let Some(hostname) = data.pe_hostnames.get(&e.source_pe) {
-                    ui.small(format!("PE {} on {hostname}", e.source_pe));
-                }
-                ui.label(format!("Time: {:.9}s", e.raw.duration_sec));
-                let total_bytes = e.raw.bytes_rx + e.raw.bytes_tx;
-                if total_bytes > 0 {
-                    if e.raw.bytes_rx > 0 && e.raw.bytes_tx > 0 {
-                        ui.label(format!(
-                            "Data: {} bytes (RX: {}, TX: {})",
-                            total_bytes, e.raw.bytes_rx, e.raw.bytes_tx
-                        ));
-                    } else if e.raw.bytes_rx > 0 {
-                        ui.label(format!("Data: {} bytes (RX)", e.raw.bytes_rx));
-                    } else {
-                        ui.label(format!("Data: {} bytes (TX)", e.raw.bytes_tx));
-                    }
-
-                    if e.raw.duration_sec > 0.0 {
-                        let bw_gbps = (total_bytes as f64 / e.raw.duration_sec) / 1e9;
-                        ui.label(format!("BW: {:.2} GB/s", bw_gbps));
-                    }
-                }
-
-                if let Some(trace) = &e.raw.symboltrace {
-                    if !trace.is_empty() {
-                        ui.separator();
-                        ui.label(egui::RichText::new("Call Stack:").strong());
-                        for line in trace.split('|') {
-                            if !line.trim().is_empty() {
-                                ui.label(egui::RichText::new(line).small());
-                            }
+            .clicked()
+        {
+            self.track_order.hidden.clear();
+        }
+        if ui
+            .add_enabled(
+                !self.track_order.pinned.is_empty(),
+                egui::Button::new(format!(
+                    "Unpin all ({} pinned)",
+                    self.track_order.pinned.len()
+                )),
+            )
+            .clicked()
+        {
+            self.track_order.pinned.clear();
+        }
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+                for pe in 0..pe_count {
+                    let mut shown = !self.track_order.hidden.contains(&pe);
+                    if ui.checkbox(&mut shown, self.pe_labels.name(pe)).changed() {
+                        if shown {
+                            self.track_order.hidden.remove(&pe);
+                        } else {
+                            self.track_order.hidden.insert(pe);
                         }
                     }
                 }
             });
-        }
     }
 }
 
-fn generate_color(s: &str) -> Color32 {
-    let mut hasher = DefaultHasher::new();
-    s.hash(&mut hasher);
-    let hash = hasher.finish();
-
-    // kinda a pastel theme
-    let r = ((hash >> 16) & 0xFF) as u8;
-    let g = ((hash >> 8) & 0xFF) as u8;
-    let b = (hash & 0xFF) as u8;
-
-    // help visibility on dark bg
-    Color32::from_rgb(
-        (r / 2).saturating_add(128),
-        (g / 2).saturating_add(128),
-        (b / 2).saturating_add(128),
-    )
+fn export_status(request: &ExportRequest, view: &CapturedView) -> String {
+    match export::write(view, request) {
+        Ok(path) => format!("exported {}", path.display()),
+        Err(e) => format!("export failed: {:#}", e),
+    }
+}
+
+// tab title of a trace: its directory, and how many files when only some were opened
+fn tab_name(dir: &Path, files: &[PathBuf], dirs: &[PathBuf]) -> String {
+    let name = dir.file_name().map_or_else(
+        || dir.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    );
+    if dirs.len() > 1 {
+        format!("{name} + {} more", dirs.len() - 1)
+    } else if files.is_empty() {
+        name
+    } else {
+        format!("{name} ({} files)", files.len())
+    }
 }
 
 impl eframe::App for VisualizerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if let Some(err) = &self.error_msg {
+        let update_start = Instant::now();
+        self.apply_appearance(ctx);
+        self.handle_screenshot(ctx);
+        self.handle_dropped_files(ctx);
+        self.ui_drop_overlay(ctx);
+        self.poll_remote_fetch();
+        self.poll_data_changes(ctx);
+        self.ui_data_changed(ctx);
+        egui::TopBottomPanel::top("tabs").show(ctx, |ui| self.ui_tab_bar(ui));
+        if let Some(err) = self.error_msg.clone() {
             egui::CentralPanel::default().show(ctx, |ui| {
                 ui.heading("Error");
                 ui.label(err);
+                ui.separator();
+                ui.menu_button("Session", |ui| self.ui_session_menu(ui));
+                if let Some(msg) = &self.status_msg {
+                    ui.label(msg);
+                }
             });
             return;
         }
+        if self.show_welcome {
+            egui::CentralPanel::default().show(ctx, |ui| self.ui_welcome(ui));
+            return;
+        }
 
         let max_time = self
             .profile_data
@@ -693,10 +3344,20 @@ impl eframe::App for VisualizerApp {
             .map(|d| d.min_time)
             .unwrap_or(0.0);
 
+        if let Some(recorder) = &self.recorder {
+            self.playing = false;
+            self.cursor_time = recorder.current_time();
+            ctx.request_repaint();
+        }
+
         if self.playing {
             let dt = ctx.input(|i| i.stable_dt) as f64;
             self.cursor_time += dt * self.playback_speed;
-            if self.cursor_time > max_time {
+            if let Some((a, b)) = self.loop_region() {
+                if self.cursor_time > b || self.cursor_time < a {
+                    self.cursor_time = a;
+                }
+            } else if self.cursor_time > max_time {
                 self.cursor_time = max_time;
                 self.playing = false;
             }
@@ -704,7 +3365,7 @@ impl eframe::App for VisualizerApp {
         }
 
         egui::TopBottomPanel::top("controls").show(ctx, |ui| {
-            ui.horizontal(|ui| {
+            ui.horizontal_wrapped(|ui| {
                 if ui
                     .button(if self.playing { "|| Pause" } else { "|> Play" })
                     .clicked()
@@ -715,6 +3376,32 @@ impl eframe::App for VisualizerApp {
                     self.playing = !self.playing;
                 }
 
+                if ui
+                    .button("A")
+                    .on_hover_text("Set loop start at cursor")
+                    .clicked()
+                {
+                    self.loop_a = Some(self.cursor_time);
+                }
+                if ui
+                    .button("B")
+                    .on_hover_text("Set loop end at cursor")
+                    .clicked()
+                {
+                    self.loop_b = Some(self.cursor_time);
+                }
+                ui.add_enabled(
+                    self.loop_a.is_some() && self.loop_b.is_some(),
+                    egui::Checkbox::new(&mut self.loop_enabled, "Loop"),
+                );
+                if (self.loop_a.is_some() || self.loop_b.is_some())
+                    && ui.small_button("clear").clicked()
+                {
+                    self.loop_a = None;
+                    self.loop_b = None;
+                    self.loop_enabled = false;
+                }
+
                 ui.label("Speed:");
                 ui.add(
                     egui::Slider::new(&mut self.playback_speed, 0.1..=max_time.max(1.0))
@@ -722,37 +3409,462 @@ impl eframe::App for VisualizerApp {
                 );
 
                 ui.separator();
-                ui.label(format!("Time: {:.6}s", self.cursor_time));
+                if ui.button("|<").on_hover_text("Previous event").clicked() {
+                    self.step_cursor_to_event(false);
+                }
+                if ui.button("<<").on_hover_text("Back one window").clicked() {
+                    self.step_cursor_by_window(-1.0);
+                }
+                if ui
+                    .button(">>")
+                    .on_hover_text("Forward one window")
+                    .clicked()
+                {
+                    self.step_cursor_by_window(1.0);
+                }
+                if ui.button(">|").on_hover_text("Next event").clicked() {
+                    self.step_cursor_to_event(true);
+                }
+
+                ui.separator();
+                if ui
+                    .add_enabled(self.zoom_history.can_go_back(), egui::Button::new("◀"))
+                    .on_hover_text("Previous zoom (alt+left)")
+                    .clicked()
+                {
+                    self.zoom_back();
+                }
+                if ui
+                    .add_enabled(self.zoom_history.can_go_forward(), egui::Button::new("▶"))
+                    .on_hover_text("Next zoom (alt+right)")
+                    .clicked()
+                {
+                    self.zoom_forward();
+                }
+                if ui
+                    .button("Fit")
+                    .on_hover_text("Zoom to fit all (F)")
+                    .clicked()
+                {
+                    self.zoom_to_fit();
+                }
+                let has_range =
+                    self.selection.is_some() || (self.loop_a.is_some() && self.loop_b.is_some());
+                if ui
+                    .add_enabled(has_range, egui::Button::new("Zoom sel"))
+                    .on_hover_text("Zoom to the selection or loop region (Z)")
+                    .clicked()
+                {
+                    self.zoom_to_selection();
+                }
+                let pe_count = self.profile_data.as_ref().map_or(0, |d| d.pe_count);
+                egui::ComboBox::from_id_salt("selected_pe")
+                    .width(60.0)
+                    .selected_text(match self.selected_pe {
+                        Some(pe) => self.pe_labels.name(pe),
+                        None => "any PE".to_string(),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.selected_pe, None, "any PE");
+                        for pe in 0..pe_count {
+                            ui.selectable_value(
+                                &mut self.selected_pe,
+                                Some(pe),
+                                self.pe_labels.name(pe),
+                            );
+                        }
+                    });
+
+                ui.separator();
+                ui.label("Time:");
+                let time_edit = ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.time_input)
+                            .desired_width(90.0)
+                            .hint_text(format!("{:.6}", self.cursor_time)),
+                    )
+                    .on_hover_text("a time (1.25, 300ms), an offset from the cursor (+5ms) or an event (#1234). ctrl+G");
+                if std::mem::take(&mut self.focus_time_input) {
+                    time_edit.request_focus();
+                }
+                if time_edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    match Goto::parse(&self.time_input) {
+                        Some(goto) => {
+                            self.playing = false;
+                            self.goto(goto);
+                            self.time_input.clear();
+                        }
+                        None => self.status_msg = Some("invalid time or event".to_string()),
+                    }
+                }
+                ui.label(format!("{:.6}s", self.cursor_time));
+                if let Some((delta, events, bytes)) = self.cursor_span() {
+                    ui.colored_label(
+                        Color32::YELLOW,
+                        format!(
+                            "Δ {} · {events} events · {bytes} B",
+                            units::time(delta, delta.abs() * 1e-3)
+                        ),
+                    )
+                    .on_hover_text("between the cursor and the second cursor (M)");
+                    if ui
+                        .small_button("x")
+                        .on_hover_text("remove second cursor (shift+M)")
+                        .clicked()
+                    {
+                        self.cursor_b = None;
+                    }
+                }
                 ui.separator();
                 ui.label("Window:");
-                let window_max = (max_time - min_time).max(0.0001);
+                let window_max = (max_time - min_time).max(traffic::MIN_WINDOW_SECONDS);
                 ui.add(
-                    egui::Slider::new(&mut self.window_size_seconds, 0.0001..=window_max)
+                    egui::Slider::new(&mut self.window_size_seconds, traffic::MIN_WINDOW_SECONDS..=window_max)
                         .text("s")
                         .logarithmic(true),
                 );
 
                 ui.separator();
-                ui.checkbox(&mut self.show_rx, "RX");
-                ui.checkbox(&mut self.show_tx, "TX");
-            });
-        });
+                ui.checkbox(&mut self.annotations.show_rx, "RX");
+                ui.checkbox(&mut self.annotations.show_tx, "TX");
+                let filter = &mut self.annotations.duration_filter;
+                let label = if filter.enabled {
+                    format!(
+                        "Duration {} - {}",
+                        units::duration(filter.min),
+                        units::duration(filter.max)
+                    )
+                } else {
+                    "Duration".to_string()
+                };
+                ui.menu_button(label, |ui| {
+                    ui.checkbox(&mut filter.enabled, "Only calls lasting")
+                        .on_hover_text("on the timeline, in the stats and in exports");
+                    let longest = (max_time - min_time).max(1e-6);
+                    ui.add_enabled_ui(filter.enabled, |ui| {
+                        for (name, value) in [("at least", &mut filter.min), ("at most", &mut filter.max)] {
+                            ui.add(
+                                egui::Slider::new(value, 1e-9..=longest)
+                                    .logarithmic(true)
+                                    .text(name)
+                                    .custom_formatter(|v, _| units::duration(v)),
+                            );
+                        }
+                    });
+                    filter.max = filter.max.max(filter.min);
+                });
+                let filter = &mut self.annotations.byte_filter;
+                let label = match (filter.enabled, filter.invert) {
+                    (false, _) => "Size".to_string(),
+                    (true, false) => format!("Size ≥ {}", units::bytes(filter.min)),
+                    (true, true) => format!("Size < {}", units::bytes(filter.min)),
+                };
+                ui.menu_button(label, |ui| {
+                    ui.checkbox(&mut filter.enabled, "Only messages")
+                        .on_hover_text("in the bandwidth view, its top talkers and edge details");
+                    ui.add_enabled_ui(filter.enabled, |ui| {
+                        ui.radio_value(&mut filter.invert, false, "of at least");
+                        ui.radio_value(&mut filter.invert, true, "smaller than");
+                        ui.add(
+                            egui::Slider::new(&mut filter.min, 1..=1 << 30)
+                                .logarithmic(true)
+                                .custom_formatter(|v, _| units::bytes(v as u64)),
+                        );
+                    });
+                });
+                egui::ComboBox::from_id_salt("edge_weight")
+                    .selected_text(self.panes.bandwidth.scaling.weight.label())
+                    .show_ui(ui, |ui| {
+                        for weight in EdgeWeight::ALL {
+                            ui.selectable_value(
+                                &mut self.panes.bandwidth.scaling.weight,
+                                weight,
+                                weight.label(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text("what edge width, chords and top talkers add up");
+                egui::ComboBox::from_id_salt("bandwidth_mode")
+                    .selected_text(match self.panes.bandwidth.mode {
+                        BandwidthMode::Graph => "Graph",
+                        BandwidthMode::Chord => "Chord",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut self.panes.bandwidth.mode,
+                            BandwidthMode::Graph,
+                            "Graph",
+                        );
+                        ui.selectable_value(
+                            &mut self.panes.bandwidth.mode,
+                            BandwidthMode::Chord,
+                            "Chord",
+                        );
+                    });
+                // a node per PE, or per host for big jobs where the traffic
+                // between hosts is what matters
+                let level = self.group_level.label();
+                let collapsed = self.panes.bandwidth.ring_grouping == RingGrouping::CollapseHosts;
+                if ui
+                    .selectable_label(!collapsed, "Per PE")
+                    .on_hover_text(format!("a node per PE, next to the rest of its {level}"))
+                    .clicked()
+                    && collapsed
+                {
+                    self.panes.bandwidth.ring_grouping = RingGrouping::ByHost;
+                }
+                if ui
+                    .selectable_label(collapsed, format!("Per {level}"))
+                    .on_hover_text(format!(
+                        "a node per {level}, edges sum the traffic between {}",
+                        self.group_level.plural()
+                    ))
+                    .clicked()
+                {
+                    self.panes.bandwidth.ring_grouping = RingGrouping::CollapseHosts;
+                }
+                if let Some((a, b)) = self.selection {
+                    ui.toggle_value(
+                        &mut self.panes.bandwidth.on_selection,
+                        format!("Ring: selection ({})", units::duration(b - a)),
+                    )
+                    .on_hover_text("aggregate the ring over the shift-dragged selection");
+                    if ui
+                        .small_button("x")
+                        .on_hover_text("clear selection")
+                        .clicked()
+                    {
+                        self.selection = None;
+                    }
+                }
+                ui.toggle_value(&mut self.panes.bandwidth.show_talkers, "Top talkers");
+                ui.menu_button("Edges", |ui| self.panes.bandwidth.ui_edges_menu(ui));
+                ui.menu_button("Layout", |ui| {
+                    ui.radio_value(&mut self.panes.bandwidth.ring_grouping, RingGrouping::Flat, "Flat ring");
+                    let level = self.group_level.label();
+                    ui.radio_value(
+                        &mut self.panes.bandwidth.ring_grouping,
+                        RingGrouping::ByHost,
+                        format!("Group by {level}"),
+                    );
+                    ui.radio_value(
+                        &mut self.panes.bandwidth.ring_grouping,
+                        RingGrouping::CollapseHosts,
+                        format!("Collapse {}", self.group_level.plural()),
+                    );
+                    if ui.button("Topology...").clicked() {
+                        self.show_topology_window = true;
+                        ui.close();
+                    }
+                    ui.separator();
+                    ui.radio_value(&mut self.panes.bandwidth.placement, Placement::Ring, "Ring");
+                    ui.radio_value(&mut self.panes.bandwidth.placement, Placement::Force, "Force-directed");
+                    if ui
+                        .add_enabled(
+                            self.panes.bandwidth.placement == Placement::Force,
+                            egui::Button::new("Re-layout"),
+                        )
+                        .clicked()
+                    {
+                        self.panes.bandwidth.relayout();
+                    }
+                });
+
+                ui.menu_button("Tracks", |ui| self.ui_tracks_menu(ui));
+                if let Some(function) = &self.annotations.solo_function {
+                    let label = format!("Only {function}");
+                    if ui
+                        .small_button(label)
+                        .on_hover_text("click to show every function again")
+                        .clicked()
+                    {
+                        self.annotations.solo_function = None;
+                    }
+                }
+                if !self.annotations.hidden_functions.is_empty() {
+                    let label = format!("{} hidden", self.annotations.hidden_functions.len());
+                    let names = self
+                        .annotations
+                        .hidden_functions
+                        .iter()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    if ui
+                        .small_button(label)
+                        .on_hover_text(format!("{names}\n\nclick to show them again"))
+                        .clicked()
+                    {
+                        self.annotations.hidden_functions.clear();
+                    }
+                }
+                ui.menu_button("Counters", |ui| self.ui_counters_menu(ui));
+                let has_epoch = self
+                    .profile_data
+                    .as_ref()
+                    .is_some_and(|d| d.epoch.is_some());
+                egui::ComboBox::from_id_salt("time_display")
+                    .selected_text(match self.time_display {
+                        TimeDisplay::Absolute => "Ruler: trace time",
+                        TimeDisplay::Relative => "Ruler: from start",
+                        TimeDisplay::WallClock => "Ruler: wall clock",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut self.time_display,
+                            TimeDisplay::Absolute,
+                            "Trace time",
+                        );
+                        ui.selectable_value(
+                            &mut self.time_display,
+                            TimeDisplay::Relative,
+                            "From start",
+                        );
+                        ui.add_enabled_ui(has_epoch, |ui| {
+                            ui.selectable_value(
+                                &mut self.time_display,
+                                TimeDisplay::WallClock,
+                                "Wall clock (UTC)",
+                            )
+                            .on_disabled_hover_text("no epoch= in the trace's Extra");
+                        });
+                        ui.separator();
+                        ui.checkbox(&mut self.panes.timeline.ruler_density, "Event density")
+                            .on_hover_text("calls starting in each pixel, along the bottom");
+                    });
 
-        // bottom panel
-        egui::TopBottomPanel::bottom("timeline")
-            .resizable(true)
-            .min_height(200.0)
-            .show(ctx, |ui| {
-                self.ui_timeline(ui);
+                ui.separator();
+                if ui
+                    .add_enabled(self.undo_history.can_undo(), egui::Button::new("Undo"))
+                    .on_hover_text(
+                        "Undo the last filter, color, bookmark or hidden PE change (ctrl+Z)",
+                    )
+                    .clicked()
+                {
+                    self.undo();
+                }
+                if ui
+                    .add_enabled(self.undo_history.can_redo(), egui::Button::new("Redo"))
+                    .on_hover_text("Redo (ctrl+shift+Z)")
+                    .clicked()
+                {
+                    self.redo();
+                }
+                ui.menu_button("View", |ui| self.ui_view_menu(ui));
+                ui.menu_button("Session", |ui| self.ui_session_menu(ui));
+                ui.menu_button("Export", |ui| self.ui_export_menu(ui));
+                ui.toggle_value(&mut self.show_colors_window, "Colors");
+                ui.toggle_value(&mut self.show_legend_window, "Legend");
+                ui.toggle_value(&mut self.show_rules_window, "Groups");
+                ui.toggle_value(&mut self.show_clocks_window, "Clocks");
+                ui.toggle_value(&mut self.show_labels_window, "Labels");
+                ui.toggle_value(&mut self.show_settings_window, "Settings");
+                ui.menu_button("Symbols", |ui| self.ui_symbols_menu(ui));
+                ui.toggle_value(&mut self.show_bookmarks_window, "Bookmarks");
+                let windows = &mut self.windows;
+                ui.toggle_value(&mut windows.phases.open, "Phases");
+                ui.toggle_value(&mut windows.metrics.open, "Metrics");
+                ui.toggle_value(&mut self.panes.stats.open, "Stats");
+                ui.toggle_value(&mut windows.flame.open, "Flame diff");
+                ui.toggle_value(&mut windows.run_diff.open, "Run diff");
+                ui.toggle_value(&mut windows.load.open, "Load balance");
+                ui.toggle_value(&mut windows.stragglers.open, "Stragglers");
+                ui.toggle_value(&mut windows.asymmetry.open, "TX/RX check");
+                ui.toggle_value(&mut windows.latency.open, "Latency");
+                ui.toggle_value(&mut windows.messages.open, "Messages");
+                ui.toggle_value(&mut windows.size_duration.open, "Size vs duration");
+                ui.toggle_value(&mut windows.cumulative.open, "Cumulative");
+                ui.toggle_value(&mut windows.script.open, "Script");
+                ui.toggle_value(&mut windows.sql.open, "SQL");
+                ui.toggle_value(
+                    &mut windows.anomalies.open,
+                    format!("Anomalies ({})", self.derived.anomalies.len()),
+                );
+                ui.toggle_value(&mut self.show_hud, "HUD")
+                    .on_hover_text("frame time, draw counts and memory (F3)");
+                if let Some(elided) = self
+                    .profile_data
+                    .as_ref()
+                    .map(|d| d.elided)
+                    .filter(|e| !e.is_empty())
+                {
+                    let hover = format!(
+                        "over the memory budget, so {} stack traces and {} symbol traces \
+                         were dropped, saving {}. stats by call site, flame graphs and \
+                         stack colors see those calls without a stack.\n\
+                         click to reload the trace with them",
+                        elided.stacktraces,
+                        elided.symboltraces,
+                        units::bytes(elided.bytes as u64)
+                    );
+                    let label = if elided.stacktraces > 0 {
+                        "⚠ stacks dropped"
+                    } else {
+                        "⚠ symbols dropped"
+                    };
+                    if ui
+                        .button(egui::RichText::new(label).color(Color32::from_rgb(255, 170, 60)))
+                        .on_hover_text(hover)
+                        .clicked()
+                    {
+                        self.ignore_memory_budget = true;
+                        self.reload_data();
+                    }
+                }
+                if !self.derived.missing_pes.is_empty() {
+                    let pes: Vec<String> =
+                        self.derived.missing_pes.iter().map(|pe| pe.to_string()).collect();
+                    ui.label(
+                        egui::RichText::new(format!("⚠ {} PEs missing", pes.len()))
+                            .color(timeline::MISSING_PE),
+                    )
+                    .on_hover_text(format!(
+                        "no pperf.<PE>.csv for PE {}, their tracks are empty",
+                        pes.join(", ")
+                    ));
+                }
+                if let Some(msg) = &self.status_msg {
+                    ui.label(egui::RichText::new(msg).small());
+                }
             });
+        });
 
-        // bandwidth graph
-        egui::CentralPanel::default().show(ctx, |ui| {
-            if self.profile_data.is_some() {
-                self.ui_bandwidth(ui);
-            } else {
-                ui.label("No data loaded.");
+        egui::TopBottomPanel::top("search").show(ctx, |ui| self.ui_search_bar(ui));
+
+        self.ui_colors_window(ctx);
+        self.ui_legend_window(ctx);
+        self.ui_rules_window(ctx);
+        self.ui_bookmarks_window(ctx);
+        self.ui_windows(ctx);
+        self.ui_settings_window(ctx);
+        self.ui_clocks_window(ctx);
+        self.ui_labels_window(ctx);
+        self.ui_topology_window(ctx);
+        self.ui_contention_window(ctx);
+        self.handle_keys(ctx);
+
+        self.ui_docked_panes(ctx);
+        self.ui_views(ctx);
+
+        let central = ctx.available_rect();
+        egui::CentralPanel::default().show(ctx, |ui| match self.dock.center() {
+            Some(pane) if self.pane_visible(pane) => self.ui_pane(ui, pane),
+            _ => {
+                ui.label("Nothing in the center, move a view here from View > Panels.");
             }
         });
+
+        if self.hover_time != self.hover_next {
+            // views drawn earlier this frame still show the old crosshair
+            ctx.request_repaint();
+        }
+        self.hover_time = self.hover_next.take();
+        let now = ctx.input(|i| i.time);
+        self.undo_history.observe(self.snapshot(), now);
+
+        let frame_dt = ctx.input(|i| i.unstable_dt);
+        self.hud.record_frame(frame_dt, update_start.elapsed());
+        self.ui_hud(ctx, central);
     }
 }
@@ -0,0 +1,239 @@
+use egui::{Color32, Pos2, Rect, Sense, Stroke, Vec2};
+
+use crate::coloring::generate_color;
+use crate::data::ProfileData;
+use crate::imbalance::{self, LoadMetric};
+use crate::units;
+use crate::views::{CROSSHAIR, ViewCommand, ViewState};
+
+const CUMULATIVE_SAMPLES: usize = 300;
+
+// per-PE load over the analysis range. clicking a bar selects its PE
+pub struct Load {
+    pub open: bool,
+    metric: LoadMetric,
+}
+
+impl Default for Load {
+    fn default() -> Self {
+        Self {
+            open: false,
+            metric: LoadMetric::Time,
+        }
+    }
+}
+
+impl Load {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        let (start, end) = state.analysis_range;
+        let range_name = state.analysis_range_name;
+        egui::Window::new("Load balance")
+            .open(&mut self.open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.label(format!("{:.6}s - {:.6}s ({})", start, end, range_name));
+                let loads = imbalance::per_pe(data, start, end);
+
+                egui::Grid::new("load_grid").striped(true).show(ui, |ui| {
+                    ui.strong("");
+                    ui.strong("Mean");
+                    ui.strong("Max");
+                    ui.strong("Max/mean");
+                    ui.strong("CV");
+                    ui.end_row();
+                    for metric in LoadMetric::ALL {
+                        let values: Vec<f64> = loads.iter().map(|l| metric.of(l)).collect();
+                        let stats = imbalance::imbalance(&values);
+                        ui.selectable_value(&mut self.metric, metric, metric.label());
+                        ui.label(metric.format(stats.mean));
+                        ui.label(metric.format(stats.max));
+                        let ratio = egui::RichText::new(format!("{:.2}", stats.max_over_mean));
+                        // a rank doing half again the average is worth a look
+                        if stats.max_over_mean > 1.5 {
+                            ui.label(ratio.color(Color32::LIGHT_RED));
+                        } else {
+                            ui.label(ratio);
+                        }
+                        ui.label(format!("{:.3}", stats.cv));
+                        ui.end_row();
+                    }
+                });
+
+                ui.separator();
+                let values: Vec<f64> = loads.iter().map(|l| self.metric.of(l)).collect();
+                let stats = imbalance::imbalance(&values);
+                let (response, painter) =
+                    ui.allocate_painter(Vec2::new(ui.available_width(), 160.0), Sense::click());
+                let plot_rect = response.rect.shrink(4.0);
+                painter.rect_filled(response.rect, 2.0, state.palette.plot_bg);
+                if values.is_empty() || stats.max <= 0.0 {
+                    return;
+                }
+                let bar_width = plot_rect.width() / values.len() as f32;
+                let value_y =
+                    |v: f64| plot_rect.max.y - plot_rect.height() * (v / stats.max) as f32;
+                let hovered = response.hover_pos().map(|p| {
+                    (((p.x - plot_rect.min.x) / bar_width) as usize).min(values.len() - 1)
+                });
+                for (pe, v) in values.iter().enumerate() {
+                    let x = plot_rect.min.x + pe as f32 * bar_width;
+                    let bar = Rect::from_min_max(
+                        Pos2::new(x + bar_width * 0.1, value_y(*v)),
+                        Pos2::new(x + bar_width * 0.9, plot_rect.max.y),
+                    );
+                    let color = if state.selected_pe == Some(pe as u32) {
+                        Color32::from_rgb(100, 100, 200)
+                    } else if hovered == Some(pe) {
+                        state.palette.bar_hovered
+                    } else {
+                        state.palette.bar
+                    };
+                    painter.rect_filled(bar, 0.0, color);
+                }
+                let mean_y = value_y(stats.mean);
+                painter.line_segment(
+                    [
+                        Pos2::new(plot_rect.min.x, mean_y),
+                        Pos2::new(plot_rect.max.x, mean_y),
+                    ],
+                    Stroke::new(1.0, Color32::YELLOW),
+                );
+                painter.text(
+                    Pos2::new(plot_rect.max.x, mean_y - 2.0),
+                    egui::Align2::RIGHT_BOTTOM,
+                    "mean",
+                    egui::FontId::proportional(9.0),
+                    Color32::YELLOW,
+                );
+                if let Some(pe) = hovered {
+                    if response.clicked() {
+                        // clicking the selected PE again deselects it
+                        let pe = pe as u32;
+                        let select = (state.selected_pe != Some(pe)).then_some(pe);
+                        commands.push(ViewCommand::SelectPe(select));
+                    }
+                    response.on_hover_text(format!(
+                        "PE {pe}: {} ({:+.1}% vs mean)",
+                        self.metric.format(values[pe]),
+                        (values[pe] / stats.mean - 1.0) * 100.0
+                    ));
+                }
+            });
+        commands
+    }
+}
+
+// running total of the bytes moved across the whole trace. flat stretches
+// are stalls, a PE whose line pulls ahead is racing the others
+pub struct Cumulative {
+    pub open: bool,
+    // a line per PE besides the total
+    per_pe: bool,
+    // each line as a share of its own final total
+    share: bool,
+}
+
+impl Default for Cumulative {
+    fn default() -> Self {
+        Self {
+            open: false,
+            per_pe: true,
+            share: false,
+        }
+    }
+}
+
+impl Cumulative {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        egui::Window::new("Cumulative bytes")
+            .open(&mut self.open)
+            .default_size([520.0, 320.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.per_pe, "Per PE");
+                    ui.checkbox(&mut self.share, "Share of own total")
+                        .on_hover_text("compare progress of PEs that move different amounts");
+                    ui.weak("click to move the cursor");
+                });
+                let (min, max) = (data.min_time, data.max_time);
+                let times: Vec<f64> = (0..=CUMULATIVE_SAMPLES)
+                    .map(|i| min + (max - min) * i as f64 / CUMULATIVE_SAMPLES as f64)
+                    .collect();
+                let share = self.share;
+                let line = |values: Vec<u64>| -> Vec<[f64; 2]> {
+                    let last = values.last().copied().unwrap_or(0).max(1) as f64;
+                    times
+                        .iter()
+                        .zip(values)
+                        .map(|(t, v)| [*t, if share { v as f64 / last } else { v as f64 }])
+                        .collect()
+                };
+                let sums = &state.derived.byte_sums;
+                let (tx, rx) = (state.annotations.show_tx, state.annotations.show_rx);
+                let total = line(sums.cumulative(None, &times, tx, rx));
+                let pes: Vec<_> = if self.per_pe {
+                    (0..data.pe_count)
+                        .map(|pe| {
+                            let name = state.pe_labels.name(pe);
+                            (name, line(sums.cumulative(Some(pe), &times, tx, rx)))
+                        })
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                let response = egui_plot::Plot::new("cumulative_plot")
+                    .legend(egui_plot::Legend::default())
+                    .x_axis_label("time (s)")
+                    .y_axis_formatter(move |mark, _| {
+                        if share {
+                            format!("{:.0}%", mark.value * 100.0)
+                        } else {
+                            units::bytes(mark.value.max(0.0) as u64)
+                        }
+                    })
+                    .show(ui, |plot_ui| {
+                        for (name, points) in pes {
+                            let color = generate_color(&name);
+                            plot_ui.line(egui_plot::Line::new(name, points).color(color));
+                        }
+                        plot_ui.line(
+                            egui_plot::Line::new("all PEs", total)
+                                .color(state.palette.text)
+                                .width(2.0),
+                        );
+                        plot_ui.vline(
+                            egui_plot::VLine::new("cursor", state.cursor).color(Color32::YELLOW),
+                        );
+                        if let Some(t) = state.hover {
+                            plot_ui.vline(egui_plot::VLine::new("hover", t).color(CROSSHAIR));
+                        }
+                        if plot_ui.response().hovered()
+                            && let Some(point) = plot_ui.pointer_coordinate()
+                        {
+                            commands.push(ViewCommand::Hover(point.x));
+                        }
+                        plot_ui
+                            .response()
+                            .clicked()
+                            .then(|| plot_ui.pointer_coordinate())
+                            .flatten()
+                    });
+                if let Some(point) = response.inner {
+                    commands.push(ViewCommand::JumpTo(point.x));
+                }
+            });
+        commands
+    }
+}
@@ -0,0 +1,198 @@
+use egui::epaint::Mesh;
+use egui::{Color32, Painter, Pos2, Rect, Shape, Stroke, Vec2, ecolor::Hsva};
+use std::f32::consts::{PI, TAU};
+
+use crate::bandwidth::{Collectives, Comms};
+
+// radians between neighbouring node arcs
+const GAP: f32 = 0.02;
+// so idle nodes still get a visible sliver
+const MIN_SHARE: f32 = 0.004;
+const ARC_WIDTH: f32 = 10.0;
+const RIBBON_STEPS: usize = 24;
+const ARC_STEPS: usize = 6;
+
+pub fn node_color(node: u32, count: u32) -> Color32 {
+    Hsva::new(node as f32 / count.max(1) as f32, 0.55, 0.9, 1.0).into()
+}
+
+// draws a chord diagram of `comms` where ribbon width is the combined
+// traffic of a pair in both directions. collectives only widen their
+// node's arc. returns the node under `pointer`
+pub fn draw(
+    painter: &Painter,
+    rect: Rect,
+    comms: &Comms,
+    collectives: &Collectives,
+    labels: &[String],
+    label_color: Color32,
+    pointer: Option<Pos2>,
+) -> Option<u32> {
+    let count = labels.len();
+    if count == 0 {
+        return None;
+    }
+    let center = rect.center();
+    let radius = rect.width().min(rect.height()) / 2.0 - 40.0;
+    if radius <= ARC_WIDTH {
+        return None;
+    }
+
+    // undirected pair totals plus which direction dominates
+    let mut pairs: Vec<(u32, u32, u64, u32)> = Vec::new();
+    let mut totals = vec![0u64; count];
+    for ((src, dst), (tx, rx)) in comms {
+        let (src, dst) = (*src, *dst);
+        if src as usize >= count
+            || dst as usize >= count
+            || src > dst && comms.contains_key(&(dst, src))
+        {
+            continue;
+        }
+        if src == dst {
+            // local traffic widens the node's arc but has no ribbon
+            totals[src as usize] += tx + rx;
+            continue;
+        }
+        let forward = tx + rx;
+        let backward = comms.get(&(dst, src)).map_or(0, |(t, r)| t + r);
+        let bytes = forward + backward;
+        if bytes == 0 {
+            continue;
+        }
+        let dominant = if forward >= backward { src } else { dst };
+        pairs.push((src.min(dst), src.max(dst), bytes, dominant));
+        totals[src as usize] += bytes;
+        totals[dst as usize] += bytes;
+    }
+    for (node, (tx, rx)) in collectives {
+        if let Some(total) = totals.get_mut(*node as usize) {
+            *total += tx + rx;
+        }
+    }
+    pairs.sort_by_key(|(a, b, _, _)| (*a, *b));
+    let grand_total: u64 = totals.iter().sum();
+
+    // node arcs, sized by total traffic
+    let available = TAU - GAP * count as f32;
+    let shares: Vec<f32> = totals
+        .iter()
+        .map(|t| {
+            let share = if grand_total > 0 {
+                *t as f32 / grand_total as f32
+            } else {
+                0.0
+            };
+            share.max(MIN_SHARE)
+        })
+        .collect();
+    let share_sum: f32 = shares.iter().sum();
+    let mut arcs = Vec::with_capacity(count);
+    let mut angle = -PI / 2.0;
+    for share in &shares {
+        let span = available * share / share_sum;
+        arcs.push((angle, angle + span));
+        angle += span + GAP;
+    }
+
+    let hovered = pointer.and_then(|p| {
+        let d = p - center;
+        if d.length() < radius - 30.0 || d.length() > radius + ARC_WIDTH + 6.0 {
+            return None;
+        }
+        let mut a = d.y.atan2(d.x);
+        if a < -PI / 2.0 {
+            a += TAU;
+        }
+        arcs.iter()
+            .position(|(s, e)| a >= *s && a <= *e)
+            .map(|i| i as u32)
+    });
+
+    // each ribbon consumes a slice of both of its nodes' arcs
+    let mut cursor: Vec<f32> = arcs.iter().map(|(s, _)| *s).collect();
+    let slice = |node: u32, bytes: u64, cursor: &mut Vec<f32>| -> (f32, f32) {
+        let (s, e) = arcs[node as usize];
+        let total = totals[node as usize].max(1) as f32;
+        let start = cursor[node as usize];
+        let end = start + (e - s) * bytes as f32 / total;
+        cursor[node as usize] = end;
+        (start, end)
+    };
+    let on_circle = |a: f32| center + Vec2::new(a.cos(), a.sin()) * radius;
+
+    let count_u32 = count as u32;
+    for (a, b, bytes, dominant) in &pairs {
+        let (a0, a1) = slice(*a, *bytes, &mut cursor);
+        let (b0, b1) = slice(*b, *bytes, &mut cursor);
+        let muted = hovered.is_some_and(|h| h != *a && h != *b);
+        let color = node_color(*dominant, count_u32).gamma_multiply(if muted { 0.08 } else { 0.6 });
+
+        // the ribbon is a strip between two chains that both start in the
+        // middle of a's slice and end in the middle of b's: one runs along
+        // a1 -> b0, the other along a0 -> b1, bending through the center
+        let arc = |from: f32, to: f32| -> Vec<Pos2> {
+            (0..=ARC_STEPS)
+                .map(|k| on_circle(from + (to - from) * k as f32 / ARC_STEPS as f32))
+                .collect()
+        };
+        let bend = |from: Pos2, to: Pos2| -> Vec<Pos2> {
+            (1..RIBBON_STEPS)
+                .map(|k| {
+                    let t = k as f32 / RIBBON_STEPS as f32;
+                    let u = 1.0 - t;
+                    (from.to_vec2() * u * u + center.to_vec2() * 2.0 * u * t + to.to_vec2() * t * t)
+                        .to_pos2()
+                })
+                .collect()
+        };
+        let (am, bm) = ((a0 + a1) / 2.0, (b0 + b1) / 2.0);
+        let chain = |a_end: f32, b_start: f32| -> Vec<Pos2> {
+            let mut points = arc(am, a_end);
+            points.extend(bend(on_circle(a_end), on_circle(b_start)));
+            points.extend(arc(b_start, bm));
+            points
+        };
+        let (left, right) = (chain(a1, b0), chain(a0, b1));
+
+        let mut mesh = Mesh::default();
+        for (k, (l, r)) in left.iter().zip(&right).enumerate() {
+            mesh.colored_vertex(*l, color);
+            mesh.colored_vertex(*r, color);
+            if k > 0 {
+                let i = (2 * k) as u32;
+                mesh.add_triangle(i - 2, i - 1, i);
+                mesh.add_triangle(i - 1, i + 1, i);
+            }
+        }
+        painter.add(Shape::mesh(mesh));
+    }
+
+    // node arcs and labels on top
+    for (i, (s, e)) in arcs.iter().enumerate() {
+        let steps = ((e - s) / 0.05).ceil().max(1.0) as usize;
+        let points: Vec<Pos2> = (0..=steps)
+            .map(|k| {
+                let a = s + (e - s) * k as f32 / steps as f32;
+                center + Vec2::new(a.cos(), a.sin()) * (radius + ARC_WIDTH / 2.0)
+            })
+            .collect();
+        let mut color = node_color(i as u32, count_u32);
+        if hovered.is_some_and(|h| h != i as u32) {
+            color = color.gamma_multiply(0.4);
+        }
+        painter.add(Shape::line(points, Stroke::new(ARC_WIDTH, color)));
+
+        let mid = (s + e) / 2.0;
+        let dir = Vec2::new(mid.cos(), mid.sin());
+        painter.text(
+            center + dir * (radius + ARC_WIDTH + 6.0),
+            egui::Align2::CENTER_CENTER,
+            &labels[i],
+            egui::FontId::proportional(11.0),
+            label_color,
+        );
+    }
+
+    hovered
+}
@@ -0,0 +1,129 @@
+use clap::Parser;
+use std::path::PathBuf;
+
+use crate::synthetic::{self, Pattern};
+
+// startup options, all optional
+#[derive(Debug, Parser)]
+#[command(name = "visualizer", about = "Visualizer for csvpshmem profiles")]
+pub struct Args {
+    #[arg(help = "directory holding the pperf.*.csv files, or a .parquet file [default: .]")]
+    pub data_dir: Option<PathBuf>,
+    #[arg(long, value_name = "START:END", value_parser = parse_range,
+          help = "timeline range to open at, in trace seconds")]
+    pub range: Option<(f64, f64)>,
+    #[arg(long, value_name = "LIST", value_parser = parse_pes,
+          help = "only show these PEs, e.g. 0-3,8")]
+    pub pes: Option<PeList>,
+    #[arg(long, help = "playback speed")]
+    pub speed: Option<f64>,
+    #[arg(long, value_name = "SECS", value_parser = parse_positive,
+          help = "length of the bandwidth window")]
+    pub window: Option<f64>,
+    #[arg(long, value_name = "WxH", value_parser = parse_size, help = "window size in pixels")]
+    pub size: Option<[f32; 2]>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "save a png of the window once loaded, then exit"
+    )]
+    pub screenshot: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "run a rhai script against the trace once loaded"
+    )]
+    pub script: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "write a made up trace into DIR and open it"
+    )]
+    pub generate_demo: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "generate_demo",
+        help = "PEs of the demo trace [default: 8]"
+    )]
+    pub demo_pes: Option<u32>,
+    #[arg(long, value_name = "CALLS", requires = "generate_demo", value_parser = parse_positive,
+          help = "calls per second on each PE of the demo trace [default: 2000]")]
+    pub demo_rate: Option<f64>,
+    #[arg(long, value_name = "SECS", requires = "generate_demo", value_parser = parse_positive,
+          help = "length of the demo trace [default: 1]")]
+    pub demo_seconds: Option<f64>,
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        requires = "generate_demo",
+        help = "who talks to whom in the demo trace: ring, all-to-all or nearest-neighbor [default: ring]"
+    )]
+    pub demo_pattern: Option<Pattern>,
+}
+
+impl Args {
+    // the --demo-* options over the defaults
+    pub fn demo_config(&self) -> synthetic::Config {
+        let default = synthetic::Config::default();
+        synthetic::Config {
+            pe_count: self.demo_pes.unwrap_or(default.pe_count),
+            rate: self.demo_rate.unwrap_or(default.rate),
+            seconds: self.demo_seconds.unwrap_or(default.seconds),
+            pattern: self.demo_pattern.unwrap_or(default.pattern),
+            ..default
+        }
+    }
+}
+
+// a Vec would make clap expect the flag several times
+#[derive(Debug, Clone)]
+pub struct PeList(pub Vec<u32>);
+
+fn parse_range(s: &str) -> Result<(f64, f64), String> {
+    let (start, end) = s.split_once(':').ok_or("expected START:END")?;
+    let start: f64 = start
+        .trim()
+        .parse()
+        .map_err(|e| format!("bad start: {e}"))?;
+    let end: f64 = end.trim().parse().map_err(|e| format!("bad end: {e}"))?;
+    if end <= start {
+        return Err("the end must be after the start".to_string());
+    }
+    Ok((start, end))
+}
+
+// "0-3,8" -> [0, 1, 2, 3, 8]
+fn parse_pes(list: &str) -> Result<PeList, String> {
+    let pe = |s: &str| {
+        s.trim()
+            .parse::<u32>()
+            .map_err(|e| format!("bad PE {s}: {e}"))
+    };
+    let mut pes = Vec::new();
+    for part in list.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((a, b)) => {
+                let (a, b) = (pe(a)?, pe(b)?);
+                pes.extend(a.min(b)..=a.max(b));
+            }
+            None => pes.push(pe(part)?),
+        }
+    }
+    Ok(PeList(pes))
+}
+
+fn parse_positive(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(v) if v > 0.0 => Ok(v),
+        Ok(_) => Err("must be positive".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_size(s: &str) -> Result<[f32; 2], String> {
+    let (w, h) = s.split_once('x').ok_or("expected WxH")?;
+    let w = w.parse().map_err(|e| format!("bad width: {e}"))?;
+    let h = h.parse().map_err(|e| format!("bad height: {e}"))?;
+    Ok([w, h])
+}
@@ -0,0 +1,177 @@
+use egui::Color32;
+use egui::ecolor::Hsva;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::data::{Event, ProfileData};
+use crate::units;
+
+// what decides the color of a call on the timeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorBy {
+    // its function's (or group's) color
+    #[default]
+    Function,
+    // log scale from the shortest to the longest call
+    Duration,
+    // log scale of bytes sent and received, gray when none
+    Bytes,
+    // one hue per target PE, gray without one
+    TargetPe,
+    // a hash of the stack trace, so calls from the same place match
+    Stack,
+}
+
+impl ColorBy {
+    pub const ALL: [ColorBy; 5] = [
+        Self::Function,
+        Self::Duration,
+        Self::Bytes,
+        Self::TargetPe,
+        Self::Stack,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Function => "Function",
+            Self::Duration => "Duration",
+            Self::Bytes => "Bytes",
+            Self::TargetPe => "Target PE",
+            Self::Stack => "Call stack",
+        }
+    }
+}
+
+// colors of the calls of a trace: one per function, plus the ranges the
+// heat scales span
+#[derive(Debug, Clone, Default)]
+pub struct ColorMap {
+    pub by: ColorBy,
+    functions: HashMap<String, Color32>,
+    // log10 of the shortest and longest positive durations
+    durations: (f64, f64),
+    // log10 of the most bytes one call moved, plus one
+    max_bytes: f64,
+    pe_count: u32,
+}
+
+// calls without a value on the current scale
+const NO_VALUE: Color32 = Color32::from_gray(110);
+
+impl ColorMap {
+    pub fn new(data: &ProfileData, functions: HashMap<String, Color32>, by: ColorBy) -> Self {
+        let (mut shortest, mut longest) = (f64::INFINITY, f64::NEG_INFINITY);
+        let mut max_bytes = 0;
+        for e in data.iter() {
+            let d = e.raw.duration_sec;
+            if d > 0.0 {
+                shortest = shortest.min(d);
+                longest = longest.max(d);
+            }
+            max_bytes = max_bytes.max(e.raw.bytes_tx + e.raw.bytes_rx);
+        }
+        let durations = if shortest <= longest {
+            (shortest.log10(), longest.log10())
+        } else {
+            (0.0, 0.0)
+        };
+        Self {
+            by,
+            functions,
+            durations,
+            max_bytes: (max_bytes as f64).ln_1p(),
+            pe_count: data.pe_count,
+        }
+    }
+
+    pub fn functions(&self) -> &HashMap<String, Color32> {
+        &self.functions
+    }
+
+    pub fn function(&self, function: &str) -> Color32 {
+        self.functions
+            .get(function)
+            .copied()
+            .unwrap_or_else(|| generate_color(function))
+    }
+
+    pub fn event(&self, e: &Event) -> Color32 {
+        match self.by {
+            ColorBy::Function => self.function(e.raw.function),
+            ColorBy::Duration => {
+                let d = e.raw.duration_sec;
+                if d <= 0.0 {
+                    return NO_VALUE;
+                }
+                let (lo, hi) = self.durations;
+                heat(((d.log10() - lo) / (hi - lo).max(f64::EPSILON)) as f32)
+            }
+            ColorBy::Bytes => {
+                let bytes = e.raw.bytes_tx + e.raw.bytes_rx;
+                if bytes == 0 {
+                    return NO_VALUE;
+                }
+                heat(((bytes as f64).ln_1p() / self.max_bytes.max(f64::EPSILON)) as f32)
+            }
+            ColorBy::TargetPe => {
+                if e.raw.target_pe < 0 {
+                    return NO_VALUE;
+                }
+                let hue = e.raw.target_pe as f32 / self.pe_count.max(1) as f32;
+                Hsva::new(hue, 0.65, 0.95, 1.0).into()
+            }
+            ColorBy::Stack => generate_color(e.raw.stacktrace),
+        }
+    }
+
+    // the ends of the heat scale in use, as text
+    pub fn scale(&self) -> Option<(String, String)> {
+        match self.by {
+            ColorBy::Duration => Some((
+                units::duration(10f64.powf(self.durations.0)),
+                units::duration(10f64.powf(self.durations.1)),
+            )),
+            ColorBy::Bytes => Some((
+                units::bytes(1),
+                units::bytes(self.max_bytes.exp_m1().round() as u64),
+            )),
+            _ => None,
+        }
+    }
+}
+
+// dark blue through green to yellow, t in 0..=1
+pub fn heat(t: f32) -> Color32 {
+    const STOPS: [[f32; 3]; 5] = [
+        [68.0, 1.0, 84.0],
+        [59.0, 82.0, 139.0],
+        [33.0, 145.0, 140.0],
+        [94.0, 201.0, 98.0],
+        [253.0, 231.0, 37.0],
+    ];
+    let t = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let i = (t as usize).min(STOPS.len() - 2);
+    let f = t - i as f32;
+    let [r, g, b] = std::array::from_fn(|c| STOPS[i][c] + (STOPS[i + 1][c] - STOPS[i][c]) * f);
+    Color32::from_rgb(r as u8, g as u8, b as u8)
+}
+
+pub fn generate_color(s: &str) -> Color32 {
+    let mut hasher = DefaultHasher::new();
+    s.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    // kinda a pastel theme
+    let r = ((hash >> 16) & 0xFF) as u8;
+    let g = ((hash >> 8) & 0xFF) as u8;
+    let b = (hash & 0xFF) as u8;
+
+    // help visibility on dark bg
+    Color32::from_rgb(
+        (r / 2).saturating_add(128),
+        (g / 2).saturating_add(128),
+        (b / 2).saturating_add(128),
+    )
+}
@@ -0,0 +1,335 @@
+use egui::{Color32, Rect, Sense, Vec2};
+
+use crate::data::ProfileData;
+use crate::flame::{self, FlameNode};
+use crate::rundiff;
+use crate::units;
+use crate::views::ViewState;
+
+// differential flame graph between two ranges of the trace
+pub struct FlameDiff {
+    pub open: bool,
+    a: Option<(f64, f64)>,
+    b: Option<(f64, f64)>,
+    normalize: bool,
+    tree: Option<FlameNode>,
+    // the events changed under the tree, made again on the next frame
+    stale: bool,
+}
+
+impl Default for FlameDiff {
+    fn default() -> Self {
+        Self {
+            open: false,
+            a: None,
+            b: None,
+            normalize: true,
+            tree: None,
+            stale: false,
+        }
+    }
+}
+
+impl FlameDiff {
+    // forgets the ranges, for a newly loaded trace
+    pub fn reset(&mut self) {
+        self.a = None;
+        self.b = None;
+        self.tree = None;
+    }
+
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    fn rebuild(&mut self, data: &ProfileData, state: &ViewState) {
+        self.stale = false;
+        self.tree = None;
+        let (Some((a0, a1)), Some((b0, b1))) = (self.a, self.b) else {
+            return;
+        };
+        let pe = state.selected_pe;
+        let mut before = flame::folded(data, a0, a1, pe, state.demangle_frames);
+        let after = flame::folded(data, b0, b1, pe, state.demangle_frames);
+        // compare shares of time rather than raw seconds when the ranges differ
+        let before_total: f64 = before.values().sum();
+        let after_total: f64 = after.values().sum();
+        if self.normalize && before_total > 0.0 {
+            for secs in before.values_mut() {
+                *secs *= after_total / before_total;
+            }
+        }
+        self.tree = Some(flame::diff_tree(&before, &after));
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, data: &ProfileData, state: &ViewState) {
+        if self.stale {
+            self.rebuild(data, state);
+        }
+        let mut open = self.open;
+        let mut rebuild = false;
+        let range = state.analysis_range;
+        egui::Window::new("Flame graph diff")
+            .open(&mut open)
+            .default_size([700.0, 400.0])
+            .show(ctx, |ui| {
+                let fmt = |r: Option<(f64, f64)>| {
+                    r.map_or("unset".to_string(), |(s, e)| {
+                        format!("{:.6}s - {:.6}s", s, e)
+                    })
+                };
+                egui::Grid::new("flame_ranges").show(ui, |ui| {
+                    ui.label("A (before)");
+                    ui.label(fmt(self.a));
+                    if ui.button("Use current range").clicked() {
+                        self.a = Some(range);
+                        rebuild = true;
+                    }
+                    ui.end_row();
+                    ui.label("B (after)");
+                    ui.label(fmt(self.b));
+                    if ui.button("Use current range").clicked() {
+                        self.b = Some(range);
+                        rebuild = true;
+                    }
+                    ui.end_row();
+                });
+                ui.horizontal(|ui| {
+                    rebuild |= ui.checkbox(&mut self.normalize, "Normalize totals").changed();
+                    match state.selected_pe {
+                        Some(pe) => ui.label(format!("PE {pe} only")),
+                        None => ui.label("all PEs"),
+                    };
+                    if ui.button("Refresh").clicked() {
+                        rebuild = true;
+                    }
+                });
+                ui.label(
+                    egui::RichText::new(
+                        "current range is the selection, the A/B loop region, or the visible timeline. \
+                         red grew from A to B, blue shrank",
+                    )
+                    .small()
+                    .weak(),
+                );
+                ui.separator();
+                let Some(tree) = &self.tree else {
+                    ui.label("set both ranges to compare them");
+                    return;
+                };
+                let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::hover());
+                painter.rect_filled(response.rect, 0.0, state.palette.plot_bg);
+                let hovered = flame::draw(&painter, response.rect, tree, response.hover_pos());
+                if let Some(node) = hovered {
+                    let change = if node.before > 0.0 {
+                        format!("{:+.1}%", node.delta() / node.before * 100.0)
+                    } else {
+                        "new".to_string()
+                    };
+                    response.on_hover_text(format!(
+                        "{}\nA: {}\nB: {}\nchange: {}{} ({change})",
+                        node.name,
+                        units::duration(node.before),
+                        units::duration(node.after),
+                        if node.delta() >= 0.0 { "+" } else { "" },
+                        units::duration(node.delta())
+                    ));
+                }
+            });
+        self.open = open;
+        if rebuild {
+            self.rebuild(data, state);
+        }
+    }
+}
+
+// two runs of a merged trace compared pair by pair, over all of them or
+// the bandwidth window at the same time into each
+pub struct RunDiff {
+    pub open: bool,
+    runs: (usize, usize),
+    whole_runs: bool,
+    diff: Option<rundiff::RunDiff>,
+}
+
+impl Default for RunDiff {
+    fn default() -> Self {
+        Self {
+            open: false,
+            runs: (0, 1),
+            whole_runs: true,
+            diff: None,
+        }
+    }
+}
+
+impl RunDiff {
+    pub fn invalidate(&mut self) {
+        self.diff = None;
+    }
+
+    // the offset into each run and length compared, None without two runs
+    fn range(&self, data: &ProfileData, state: &ViewState) -> Option<(f64, f64)> {
+        let (a, b) = self.runs;
+        let span_a = rundiff::run_span(data, a)?;
+        let span_b = rundiff::run_span(data, b)?;
+        if self.whole_runs {
+            let length = (span_a.1 - span_a.0).max(span_b.1 - span_b.0);
+            return Some((0.0, length));
+        }
+        // the window at the cursor, in whichever run it's in
+        let start = if (span_b.0..=span_b.1).contains(&state.cursor) {
+            span_b.0
+        } else {
+            span_a.0
+        };
+        let half = state.window / 2.0;
+        Some((state.cursor - half - start, state.window))
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, data: &ProfileData, state: &ViewState) {
+        if !self.open {
+            return;
+        }
+        if self.runs.0.max(self.runs.1) >= data.runs.len() {
+            self.runs = (0, 1);
+        }
+        if let Some((offset, length)) = self.range(data, state)
+            && self
+                .diff
+                .as_ref()
+                .is_none_or(|d| d.runs != self.runs || d.offset != offset || d.length != length)
+        {
+            self.diff = rundiff::compare(data, &state.derived.byte_sums, self.runs, offset, length);
+        }
+        egui::Window::new("Run diff")
+            .open(&mut self.open)
+            .default_size([520.0, 640.0])
+            .show(ctx, |ui| {
+                if data.runs.len() < 2 {
+                    ui.label(
+                        "Merge two runs to compare them: drop both directories on the window, \
+                         or Merge into this tab from the + menu.",
+                    );
+                    return;
+                }
+                let run_name = |i: usize| {
+                    let dir = &data.runs[i].dir;
+                    dir.file_name().map_or_else(
+                        || dir.display().to_string(),
+                        |n| n.to_string_lossy().into_owned(),
+                    )
+                };
+                ui.horizontal(|ui| {
+                    for (label, run) in [("A", &mut self.runs.0), ("B", &mut self.runs.1)] {
+                        ui.label(label);
+                        egui::ComboBox::from_id_salt(("run_diff", label))
+                            .selected_text(run_name(*run))
+                            .show_ui(ui, |ui| {
+                                for i in 0..data.runs.len() {
+                                    ui.selectable_value(run, i, run_name(i));
+                                }
+                            });
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.whole_runs, true, "Whole runs");
+                    ui.radio_value(&mut self.whole_runs, false, "Bandwidth window")
+                        .on_hover_text(
+                            "the window at the cursor, and the same time into the other run",
+                        );
+                });
+                let Some(diff) = &self.diff else {
+                    return;
+                };
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} - {} after the start of each run. bytes from each PE (rows) to each \
+                         PE (columns), red where B moved more, blue where A did",
+                        units::duration(diff.offset.max(0.0)),
+                        units::duration(diff.offset + diff.length)
+                    ))
+                    .small()
+                    .weak(),
+                );
+                let max = diff.max_delta();
+                if max == 0 {
+                    ui.label("No difference in traffic.");
+                    return;
+                }
+                let side = ui
+                    .available_width()
+                    .min(ui.available_height() - 140.0)
+                    .max(120.0);
+                let (response, painter) = ui.allocate_painter(Vec2::splat(side), Sense::hover());
+                let rect = response.rect;
+                painter.rect_filled(rect, 0.0, state.palette.plot_bg);
+                let n = diff.pe_count.max(1);
+                let cell = side / n as f32;
+                for src in 0..n {
+                    for dst in 0..n {
+                        let delta = diff.delta(src, dst);
+                        if delta == 0 {
+                            continue;
+                        }
+                        let min = rect.min + Vec2::new(dst as f32 * cell, src as f32 * cell);
+                        painter.rect_filled(
+                            Rect::from_min_size(min, Vec2::splat(cell)),
+                            0.0,
+                            run_diff_color(delta, max),
+                        );
+                    }
+                }
+                if let Some(pos) = response.hover_pos() {
+                    let at = (pos - rect.min) / cell;
+                    let (src, dst) = (at.y as u32, at.x as u32);
+                    if src < n && dst < n {
+                        let (a, b) = diff.bytes(src, dst);
+                        let delta = diff.delta(src, dst);
+                        response.on_hover_text(format!(
+                            "PE {src} -> PE {dst}\nA: {}\nB: {}\nchange: {}{}",
+                            units::bytes(a),
+                            units::bytes(b),
+                            if delta >= 0 { "+" } else { "-" },
+                            units::bytes(delta.unsigned_abs())
+                        ));
+                    }
+                }
+                ui.separator();
+                ui.strong("Biggest changes");
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("run_diff_changes")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (src, dst, delta) in diff.changes().into_iter().take(20) {
+                                let (a, b) = diff.bytes(src, dst);
+                                ui.label(format!("{src} -> {dst}"));
+                                ui.label(units::bytes(a));
+                                ui.label(units::bytes(b));
+                                ui.colored_label(
+                                    run_diff_color(delta, max),
+                                    format!(
+                                        "{}{}",
+                                        if delta >= 0 { "+" } else { "-" },
+                                        units::bytes(delta.unsigned_abs())
+                                    ),
+                                );
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+    }
+}
+
+// red where run B moved more than A, blue where less, by the square root
+// of the change so small ones still show
+fn run_diff_color(delta: i64, max: u64) -> Color32 {
+    let share = (delta.unsigned_abs() as f32 / max.max(1) as f32).sqrt();
+    let fade = (220.0 * (1.0 - share)) as u8 + 35;
+    if delta >= 0 {
+        Color32::from_rgb(255, fade, fade)
+    } else {
+        Color32::from_rgb(fade, fade, 255)
+    }
+}
@@ -0,0 +1,72 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::grouping::GroupRule;
+use crate::keys::Keymap;
+
+pub const FILE_NAME: &str = "csvpshmem-viewer.toml";
+
+// user defaults, read at startup. sessions loaded later win over these
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // opened when no directory is given on the command line
+    pub data_dir: Option<PathBuf>,
+    // function or group name -> rgb
+    pub color_overrides: BTreeMap<String, [u8; 3]>,
+    pub group_rules: Vec<GroupRule>,
+    pub keys: Keymap,
+    // reload when the data files change on disk instead of asking
+    pub auto_reload: bool,
+    // switch / rack of each host, see topology.rs
+    pub topology: Option<PathBuf>,
+    // draw every event even while the timeline is being dragged or zoomed
+    pub full_detail_while_moving: bool,
+    // stack and symbol traces are dropped from traces bigger than this
+    pub memory_budget_mb: Option<u64>,
+}
+
+impl Config {
+    // ./csvpshmem-viewer.toml first, then the user's config directory
+    pub fn candidates() -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::from(FILE_NAME)];
+        paths.extend(user_config_path());
+        paths
+    }
+
+    // the config in use, if there is one
+    pub fn locate() -> Option<PathBuf> {
+        Self::candidates().into_iter().find(|p| p.is_file())
+    }
+
+    // where a new config goes
+    pub fn default_path() -> PathBuf {
+        user_config_path().unwrap_or_else(|| PathBuf::from(FILE_NAME))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("reading config {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("parsing config {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        let text = toml::to_string(self)?;
+        fs::write(path, text).with_context(|| format!("writing config {}", path.display()))
+    }
+}
+
+// $XDG_CONFIG_HOME/csvpshmem-viewer/config.toml, ~/.config/... or %APPDATA%\...
+fn user_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(base.join("csvpshmem-viewer").join("config.toml"))
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// the views that can be moved around the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Pane {
+    Timeline,
+    Bandwidth,
+    Stats,
+    // details of the pinned event
+    Inspector,
+}
+
+impl Pane {
+    pub const ALL: [Pane; 4] = [
+        Self::Timeline,
+        Self::Bandwidth,
+        Self::Stats,
+        Self::Inspector,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Timeline => "Timeline",
+            Self::Bandwidth => "Bandwidth",
+            Self::Stats => "Stats",
+            Self::Inspector => "Event",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Slot {
+    // whatever space the docked panels leave, one pane at most
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    // its own window inside the main one
+    Floating,
+    // a separate OS window, e.g. for another monitor
+    Detached,
+    Hidden,
+}
+
+impl Slot {
+    pub const ALL: [Slot; 8] = [
+        Self::Center,
+        Self::Top,
+        Self::Bottom,
+        Self::Left,
+        Self::Right,
+        Self::Floating,
+        Self::Detached,
+        Self::Hidden,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Center => "Center",
+            Self::Top => "Top",
+            Self::Bottom => "Bottom",
+            Self::Left => "Left",
+            Self::Right => "Right",
+            Self::Floating => "Window",
+            Self::Detached => "Separate window",
+            Self::Hidden => "Hidden",
+        }
+    }
+
+    // panels along the top or bottom are resized by height
+    pub fn is_horizontal(self) -> bool {
+        matches!(self, Self::Top | Self::Bottom)
+    }
+}
+
+// where each pane goes, saved with the session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DockLayout {
+    pub slots: BTreeMap<Pane, Slot>,
+    // last height (top / bottom) or width (left / right) of docked panes
+    pub sizes: BTreeMap<Pane, f32>,
+}
+
+impl Default for DockLayout {
+    fn default() -> Self {
+        Self {
+            slots: BTreeMap::from([
+                (Pane::Timeline, Slot::Bottom),
+                (Pane::Bandwidth, Slot::Center),
+                (Pane::Stats, Slot::Floating),
+                (Pane::Inspector, Slot::Floating),
+            ]),
+            sizes: BTreeMap::new(),
+        }
+    }
+}
+
+impl DockLayout {
+    pub fn slot(&self, pane: Pane) -> Slot {
+        self.slots
+            .get(&pane)
+            .copied()
+            .unwrap_or_else(|| Self::default().slots[&pane])
+    }
+
+    // the pane in the center goes where `pane` was, so there is only ever one
+    pub fn place(&mut self, pane: Pane, slot: Slot) {
+        let old = self.slot(pane);
+        if slot == Slot::Center
+            && let Some(center) = self.center()
+            && center != pane
+        {
+            self.slots.insert(center, old);
+        }
+        self.slots.insert(pane, slot);
+    }
+
+    pub fn center(&self) -> Option<Pane> {
+        Pane::ALL
+            .into_iter()
+            .find(|p| self.slot(*p) == Slot::Center)
+    }
+
+    pub fn size(&self, pane: Pane, slot: Slot) -> f32 {
+        self.sizes
+            .get(&pane)
+            .copied()
+            .unwrap_or(match (pane, slot) {
+                (Pane::Timeline, s) if s.is_horizontal() => 300.0,
+                (_, s) if s.is_horizontal() => 220.0,
+                _ => 380.0,
+            })
+    }
+}
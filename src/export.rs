@@ -0,0 +1,352 @@
+use anyhow::{Context, Result, bail};
+use egui::epaint::{ClippedShape, ColorMode, PathShape, RectShape, TextShape};
+use egui::{Color32, LayerId, Pos2, Rect, Shape, Stroke};
+use resvg::tiny_skia::Pixmap;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportTarget {
+    Bandwidth,
+    Timeline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Png,
+    Svg,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Svg => "svg",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportRequest {
+    pub target: ExportTarget,
+    pub format: ExportFormat,
+    // output width in pixels, height follows the view's aspect
+    pub width: u32,
+    pub path: PathBuf,
+}
+
+// the shapes a view painted during one frame, in screen coordinates
+pub struct CapturedView {
+    pub rect: Rect,
+    pub background: Color32,
+    pub shapes: Vec<ClippedShape>,
+}
+
+// marks where in the layer's paint list a view starts drawing
+pub fn capture_start(ctx: &egui::Context, layer: LayerId) -> usize {
+    ctx.graphics(|g| g.get(layer).map_or(0, |l| l.next_idx().0))
+}
+
+pub fn capture_end(
+    ctx: &egui::Context,
+    layer: LayerId,
+    start: usize,
+    rect: Rect,
+    background: Color32,
+) -> CapturedView {
+    let shapes = ctx.graphics(|g| {
+        g.get(layer)
+            .map(|l| l.all_entries().skip(start).cloned().collect())
+            .unwrap_or_default()
+    });
+    CapturedView {
+        rect,
+        background,
+        shapes,
+    }
+}
+
+pub fn write(view: &CapturedView, request: &ExportRequest) -> Result<PathBuf> {
+    if view.rect.width() <= 0.0 || view.rect.height() <= 0.0 {
+        bail!("nothing to export");
+    }
+    let path = if request.path.extension().is_none() {
+        request.path.with_extension(request.format.extension())
+    } else {
+        request.path.clone()
+    };
+    let svg = to_svg(view, request.width);
+    match request.format {
+        ExportFormat::Svg => {
+            fs::write(&path, svg).with_context(|| format!("writing {}", path.display()))?
+        }
+        ExportFormat::Png => svg_to_png(&svg, &path)?,
+    }
+    Ok(path)
+}
+
+pub fn to_svg(view: &CapturedView, width: u32) -> String {
+    let scale = width as f32 / view.rect.width();
+    let height = (view.rect.height() * scale).round() as u32;
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+
+    // every distinct clip rect gets its own clipPath
+    let mut clips: HashMap<[u32; 4], usize> = HashMap::new();
+    let key = |r: Rect| [r.min.x, r.min.y, r.max.x, r.max.y].map(f32::to_bits);
+    out.push_str("<defs>\n");
+    for clipped in &view.shapes {
+        let next = clips.len();
+        if let std::collections::hash_map::Entry::Vacant(v) = clips.entry(key(clipped.clip_rect)) {
+            v.insert(next);
+            let r = clipped.clip_rect.intersect(view.rect);
+            let _ = writeln!(
+                out,
+                r#"<clipPath id="c{next}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+                r.min.x,
+                r.min.y,
+                r.width().max(0.0),
+                r.height().max(0.0)
+            );
+        }
+    }
+    out.push_str("</defs>\n");
+
+    let _ = writeln!(
+        out,
+        r#"<rect width="{width}" height="{height}" {}/>"#,
+        fill_attr(view.background)
+    );
+    let _ = writeln!(
+        out,
+        r#"<g transform="scale({scale}) translate({} {})">"#,
+        -view.rect.min.x, -view.rect.min.y
+    );
+    for clipped in &view.shapes {
+        let _ = writeln!(
+            out,
+            r#"<g clip-path="url(#c{})">"#,
+            clips[&key(clipped.clip_rect)]
+        );
+        write_shape(&mut out, &clipped.shape);
+        out.push_str("</g>\n");
+    }
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+fn write_shape(out: &mut String, shape: &Shape) {
+    match shape {
+        Shape::Vec(shapes) => {
+            for s in shapes {
+                write_shape(out, s);
+            }
+        }
+        Shape::Circle(c) => {
+            let _ = writeln!(
+                out,
+                r#"<circle cx="{}" cy="{}" r="{}" {} {}/>"#,
+                c.center.x,
+                c.center.y,
+                c.radius,
+                fill_attr(c.fill),
+                stroke_attr(c.stroke)
+            );
+        }
+        Shape::LineSegment { points, stroke } => {
+            let _ = writeln!(
+                out,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {}/>"#,
+                points[0].x,
+                points[0].y,
+                points[1].x,
+                points[1].y,
+                stroke_attr(*stroke)
+            );
+        }
+        Shape::Path(path) => write_path(out, path),
+        Shape::Rect(rect) => write_rect(out, rect),
+        Shape::Text(text) => write_text(out, text),
+        Shape::Mesh(mesh) => {
+            // untextured, single colored meshes only. triangles are drawn
+            // opaque inside one translucent group so their shared edges
+            // don't show as seams
+            let Some(first) = mesh.vertices.first() else {
+                return;
+            };
+            let [r, g, b, a] = first.color.to_srgba_unmultiplied();
+            let _ = writeln!(out, r#"<g opacity="{:.3}">"#, a as f32 / 255.0);
+            let color = Color32::from_rgb(r, g, b);
+            for tri in mesh.indices.chunks_exact(3) {
+                let v = |i: u32| mesh.vertices[i as usize].pos;
+                let points = points_attr(&[v(tri[0]), v(tri[1]), v(tri[2])]);
+                let _ = writeln!(
+                    out,
+                    r#"<polygon points="{points}" {} {}/>"#,
+                    fill_attr(color),
+                    stroke_attr(Stroke::new(0.5, color))
+                );
+            }
+            out.push_str("</g>\n");
+        }
+        // beziers and callbacks aren't used by our views
+        _ => {}
+    }
+}
+
+fn write_path(out: &mut String, path: &PathShape) {
+    let points = points_attr(&path.points);
+    let stroke = match path.stroke.color {
+        ColorMode::Solid(color) => stroke_attr(Stroke::new(path.stroke.width, color)),
+        ColorMode::UV(_) => stroke_attr(Stroke::NONE),
+    };
+    let tag = if path.closed { "polygon" } else { "polyline" };
+    let fill = if path.closed {
+        fill_attr(path.fill)
+    } else {
+        r#"fill="none""#.to_string()
+    };
+    let _ = writeln!(out, r#"<{tag} points="{points}" {fill} {stroke}/>"#);
+}
+
+fn write_rect(out: &mut String, rect: &RectShape) {
+    let r = rect.rect;
+    let _ = writeln!(
+        out,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" {} {}/>"#,
+        r.min.x,
+        r.min.y,
+        r.width(),
+        r.height(),
+        rect.corner_radius.nw,
+        fill_attr(rect.fill),
+        stroke_attr(rect.stroke)
+    );
+}
+
+fn write_text(out: &mut String, text: &TextShape) {
+    let galley = &text.galley;
+    let Some(section) = galley.job.sections.first() else {
+        return;
+    };
+    let color =
+        text.override_text_color
+            .unwrap_or(if section.format.color == Color32::PLACEHOLDER {
+                text.fallback_color
+            } else {
+                section.format.color
+            });
+    let family = match section.format.font_id.family {
+        egui::FontFamily::Monospace => "monospace",
+        _ => "sans-serif",
+    };
+    for placed in &galley.rows {
+        let Some(first) = placed.row.glyphs.first() else {
+            continue;
+        };
+        let line: String = placed.row.glyphs.iter().map(|g| g.chr).collect();
+        let _ = writeln!(
+            out,
+            r#"<text x="{}" y="{}" font-size="{}" font-family="{family}" {}>{}</text>"#,
+            text.pos.x + placed.pos.x + first.pos.x,
+            text.pos.y + placed.pos.y + first.pos.y,
+            section.format.font_id.size,
+            fill_attr(color),
+            escape(&line)
+        );
+    }
+}
+
+fn points_attr(points: &[Pos2]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn fill_attr(color: Color32) -> String {
+    if color.a() == 0 {
+        return r#"fill="none""#.to_string();
+    }
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    format!(
+        r#"fill="rgb({r},{g},{b})" fill-opacity="{:.3}""#,
+        a as f32 / 255.0
+    )
+}
+
+fn stroke_attr(stroke: Stroke) -> String {
+    if stroke.width <= 0.0 || stroke.color.a() == 0 {
+        return String::new();
+    }
+    let [r, g, b, a] = stroke.color.to_srgba_unmultiplied();
+    format!(
+        r#"stroke="rgb({r},{g},{b})" stroke-opacity="{:.3}" stroke-width="{}""#,
+        a as f32 / 255.0,
+        stroke.width
+    )
+}
+
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn svg_to_png(svg: &str, path: &Path) -> Result<()> {
+    rasterize_svg(svg)?
+        .save_png(path)
+        .with_context(|| format!("writing {}", path.display()))?;
+    Ok(())
+}
+
+// writes a screenshot of the window as png
+pub fn save_image(image: &egui::ColorImage, path: &Path) -> Result<()> {
+    let [w, h] = image.size;
+    let size =
+        resvg::tiny_skia::IntSize::from_wh(w as u32, h as u32).context("empty screenshot")?;
+    // both are premultiplied rgba
+    let bytes = image.pixels.iter().flat_map(|c| c.to_array()).collect();
+    Pixmap::from_vec(bytes, size)
+        .context("screenshot size mismatch")?
+        .save_png(path)
+        .with_context(|| format!("writing {}", path.display()))?;
+    Ok(())
+}
+
+pub fn rasterize(view: &CapturedView, width: u32) -> Result<Pixmap> {
+    rasterize_svg(&to_svg(view, width))
+}
+
+fn rasterize_svg(svg: &str) -> Result<Pixmap> {
+    let mut options = resvg::usvg::Options::default();
+    // use the same fonts egui draws with so the png matches the screen
+    let fontdb = options.fontdb_mut();
+    fontdb.load_font_data(epaint_default_fonts::UBUNTU_LIGHT.to_vec());
+    fontdb.load_font_data(epaint_default_fonts::HACK_REGULAR.to_vec());
+    let families: Vec<String> = fontdb
+        .faces()
+        .filter_map(|f| f.families.first().map(|(name, _)| name.clone()))
+        .collect();
+    if let [sans, mono, ..] = families.as_slice() {
+        fontdb.set_sans_serif_family(sans.clone());
+        fontdb.set_monospace_family(mono.clone());
+    }
+
+    let tree = resvg::usvg::Tree::from_str(svg, &options)?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = Pixmap::new(size.width(), size.height()).context("allocating export image")?;
+    resvg::render(
+        &tree,
+        resvg::tiny_skia::Transform::identity(),
+        &mut pixmap.as_mut(),
+    );
+    Ok(pixmap)
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+// hides calls shorter than `min` or longer than `max` seconds, e.g.
+// sub-microsecond noise, or everything but the long calls
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DurationFilter {
+    pub enabled: bool,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Default for DurationFilter {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min: 1e-6,
+            max: 1.0,
+        }
+    }
+}
+
+impl DurationFilter {
+    pub fn keeps(&self, duration: f64) -> bool {
+        !self.enabled || (self.min..=self.max).contains(&duration)
+    }
+}
@@ -0,0 +1,299 @@
+use anyhow::{Context, Result};
+use egui::{Color32, Painter, Pos2, Rect, Stroke, StrokeKind};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::data::{Event, ProfileData};
+use crate::symbols;
+
+// `root;child;leaf` -> seconds spent in calls with that stack
+pub type Folded = BTreeMap<String, f64>;
+
+// what the count column of an exported folded file means
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldedWeight {
+    Micros,
+    Calls,
+}
+
+pub const FOLDED_EXTENSION: &str = "folded";
+
+// Symboltrace stacks of events starting in [start, end], optionally for one PE
+pub fn folded(data: &ProfileData, start: f64, end: f64, pe: Option<u32>, demangle: bool) -> Folded {
+    fold_with(
+        data,
+        start,
+        end,
+        pe,
+        demangle,
+        |_| true,
+        |e| e.raw.duration_sec,
+    )
+}
+
+fn fold_with(
+    data: &ProfileData,
+    start: f64,
+    end: f64,
+    pe: Option<u32>,
+    demangle: bool,
+    keep: impl Fn(&Event) -> bool,
+    weight: impl Fn(&Event) -> f64,
+) -> Folded {
+    let first = data.events.time.partition_point(|&t| t < start);
+    let last = data.events.time.partition_point(|&t| t <= end);
+    let mut folded = Folded::new();
+    for e in data.range(first..last) {
+        if pe.is_some_and(|pe| pe != e.source_pe) || !keep(&e) {
+            continue;
+        }
+        let frames = e
+            .raw
+            .symboltrace
+            .unwrap_or("")
+            .split('|')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            // ; separates frames in the folded format
+            .map(|f| {
+                let f = if demangle {
+                    symbols::demangle(f)
+                } else {
+                    f.to_string()
+                };
+                f.replace(';', ":")
+            });
+        let mut stack = frames.collect::<Vec<_>>().join(";");
+        if stack.is_empty() {
+            stack = e.raw.function.to_string();
+        }
+        *folded.entry(stack).or_default() += weight(&e);
+    }
+    folded
+}
+
+// writes `frame;frame;frame count` lines for inferno / flamegraph.pl, either
+// merged into `path` or one `<path>.pe<N>.folded` per PE, for the calls
+// `keep` lets through. returns what was written
+#[allow(clippy::too_many_arguments)]
+pub fn write_folded(
+    data: &ProfileData,
+    start: f64,
+    end: f64,
+    per_pe: bool,
+    weight: FoldedWeight,
+    demangle: bool,
+    keep: impl Fn(&Event) -> bool,
+    path: &Path,
+) -> Result<Vec<PathBuf>> {
+    let path = if path.extension().is_none() {
+        path.with_extension(FOLDED_EXTENSION)
+    } else {
+        path.to_path_buf()
+    };
+    let fold = |pe| {
+        fold_with(data, start, end, pe, demangle, &keep, |e| match weight {
+            FoldedWeight::Micros => e.raw.duration_sec * 1e6,
+            FoldedWeight::Calls => 1.0,
+        })
+    };
+    let outputs: Vec<(PathBuf, Folded)> = if per_pe {
+        let stem = path.with_extension("");
+        (0..data.pe_count)
+            .map(|pe| {
+                let name = format!("{}.pe{pe}.{FOLDED_EXTENSION}", stem.display());
+                (PathBuf::from(name), fold(Some(pe)))
+            })
+            .filter(|(_, folded)| !folded.is_empty())
+            .collect()
+    } else {
+        vec![(path, fold(None))]
+    };
+
+    let mut written = Vec::new();
+    for (path, folded) in outputs {
+        let mut out = String::new();
+        for (stack, count) in folded {
+            let count = count.round() as u64;
+            if count > 0 {
+                let _ = writeln!(out, "{stack} {count}");
+            }
+        }
+        fs::write(&path, out).with_context(|| format!("writing {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FlameNode {
+    pub name: String,
+    // inclusive seconds in the first and second profile
+    pub before: f64,
+    pub after: f64,
+    pub children: Vec<FlameNode>,
+}
+
+impl FlameNode {
+    pub fn delta(&self) -> f64 {
+        self.after - self.before
+    }
+
+    // frames are sized by the time of both profiles together so paths that
+    // disappeared still show up (in blue), and children always fit
+    fn width(&self) -> f64 {
+        self.before + self.after
+    }
+
+    fn child(&mut self, name: &str) -> &mut FlameNode {
+        let idx = match self.children.iter().position(|c| c.name == name) {
+            Some(idx) => idx,
+            None => {
+                self.children.push(FlameNode {
+                    name: name.to_string(),
+                    ..Default::default()
+                });
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[idx]
+    }
+
+    fn sort(&mut self) {
+        self.children.sort_by(|a, b| a.name.cmp(&b.name));
+        for c in &mut self.children {
+            c.sort();
+        }
+    }
+
+    fn depth(&self) -> usize {
+        1 + self.children.iter().map(|c| c.depth()).max().unwrap_or(0)
+    }
+}
+
+// merges two folded profiles into one tree, like difffolded.pl
+pub fn diff_tree(before: &Folded, after: &Folded) -> FlameNode {
+    let mut root = FlameNode {
+        name: "all".to_string(),
+        ..Default::default()
+    };
+    for (profile, is_after) in [(before, false), (after, true)] {
+        for (stack, secs) in profile {
+            let mut node = &mut root;
+            add(node, *secs, is_after);
+            for frame in stack.split(';') {
+                node = node.child(frame);
+                add(node, *secs, is_after);
+            }
+        }
+    }
+    root.sort();
+    root
+}
+
+fn add(node: &mut FlameNode, secs: f64, is_after: bool) {
+    if is_after {
+        node.after += secs;
+    } else {
+        node.before += secs;
+    }
+}
+
+// red where time grew, blue where it shrank, scaled by the relative change
+fn diff_color(node: &FlameNode) -> Color32 {
+    let base = node.before.max(node.after);
+    if base <= 0.0 {
+        return Color32::from_gray(200);
+    }
+    let ratio = (node.delta() / base).clamp(-1.0, 1.0) as f32;
+    let fade = |x: f32| (220.0 * (1.0 - x.abs())) as u8 + 35;
+    if ratio >= 0.0 {
+        Color32::from_rgb(255, fade(ratio), fade(ratio))
+    } else {
+        Color32::from_rgb(fade(ratio), fade(ratio), 255)
+    }
+}
+
+const ROW_HEIGHT: f32 = 16.0;
+
+// draws the tree root-at-bottom and returns the frame under `pointer`
+pub fn draw<'a>(
+    painter: &Painter,
+    rect: Rect,
+    root: &'a FlameNode,
+    pointer: Option<Pos2>,
+) -> Option<&'a FlameNode> {
+    let total = root.width();
+    if total <= 0.0 {
+        return None;
+    }
+    let row_height = (rect.height() / root.depth() as f32).min(ROW_HEIGHT);
+    let mut hovered = None;
+    draw_node(
+        painter,
+        root,
+        rect.min.x,
+        rect.width() / total as f32,
+        rect.max.y,
+        row_height,
+        pointer,
+        &mut hovered,
+    );
+    hovered
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_node<'a>(
+    painter: &Painter,
+    node: &'a FlameNode,
+    x: f32,
+    px_per_sec: f32,
+    bottom: f32,
+    row_height: f32,
+    pointer: Option<Pos2>,
+    hovered: &mut Option<&'a FlameNode>,
+) {
+    let width = node.width() as f32 * px_per_sec;
+    if width < 0.5 {
+        return;
+    }
+    let frame = Rect::from_min_max(
+        Pos2::new(x, bottom - row_height),
+        Pos2::new(x + width, bottom),
+    );
+    painter.rect_filled(frame, 1.0, diff_color(node));
+    painter.rect_stroke(
+        frame,
+        1.0,
+        Stroke::new(0.5, Color32::from_gray(60)),
+        StrokeKind::Inside,
+    );
+    if width > 30.0 {
+        painter.with_clip_rect(frame.shrink(2.0)).text(
+            Pos2::new(frame.min.x + 3.0, frame.center().y),
+            egui::Align2::LEFT_CENTER,
+            &node.name,
+            egui::FontId::proportional(row_height * 0.65),
+            Color32::BLACK,
+        );
+    }
+    if pointer.is_some_and(|p| frame.contains(p)) {
+        *hovered = Some(node);
+    }
+    let mut child_x = x;
+    for child in &node.children {
+        draw_node(
+            painter,
+            child,
+            child_x,
+            px_per_sec,
+            bottom - row_height,
+            row_height,
+            pointer,
+            hovered,
+        );
+        child_x += child.width() as f32 * px_per_sec;
+    }
+}
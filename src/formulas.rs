@@ -0,0 +1,291 @@
+use egui::{Color32, Pos2, Rect, Sense, Stroke, Vec2};
+
+use crate::coloring::generate_color;
+use crate::data::ProfileData;
+use crate::metrics::{self, Aggregation, Metric};
+use crate::views::{ViewCommand, ViewState, paint_crosshair};
+
+const METRIC_PLOT_BINS: usize = 200;
+const METRIC_PLOT_GROUPS: usize = 8;
+
+// derived metrics typed in as formulas over the event fields, plotted over
+// the visible range per function group
+pub struct Metrics {
+    pub open: bool,
+    metrics: Vec<Metric>,
+    input: String,
+    error: Option<String>,
+    selected: Option<usize>,
+    aggregation: Aggregation,
+    // p50-p95-p99 of each bin behind the lines
+    bands: bool,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            open: false,
+            metrics: Vec::new(),
+            input: String::new(),
+            error: None,
+            selected: None,
+            aggregation: Aggregation::Sum,
+            bands: false,
+        }
+    }
+}
+
+impl Metrics {
+    // the formulas as typed, for the session
+    pub fn sources(&self) -> Vec<String> {
+        self.metrics.iter().map(|m| m.source.clone()).collect()
+    }
+
+    // formulas from a session, skipping those that no longer parse
+    pub fn set_sources(&mut self, sources: &[String]) {
+        self.metrics = sources
+            .iter()
+            .filter_map(|src| Metric::parse(src).ok())
+            .collect();
+        self.selected = (!self.metrics.is_empty()).then_some(0);
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        egui::Window::new("Metrics")
+            .open(&mut self.open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let input = ui.add(
+                        egui::TextEdit::singleline(&mut self.input)
+                            .hint_text("bytes_tx / duration_sec by function"),
+                    );
+                    let submitted =
+                        input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if ui.button("Add").clicked() || submitted {
+                        match Metric::parse(&self.input) {
+                            Ok(metric) => {
+                                self.metrics.push(metric);
+                                self.selected = Some(self.metrics.len() - 1);
+                                self.input.clear();
+                                self.error = None;
+                            }
+                            Err(e) => self.error = Some(format!("{:#}", e)),
+                        }
+                    }
+                });
+                if let Some(err) = &self.error {
+                    ui.colored_label(Color32::LIGHT_RED, err);
+                }
+                ui.label(
+                    egui::RichText::new(
+                        "fields: time duration tx rx bytes pe target_pe, or any Extra counter",
+                    )
+                    .small()
+                    .weak(),
+                );
+
+                let mut remove = None;
+                for (i, metric) in self.metrics.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("x").clicked() {
+                            remove = Some(i);
+                        }
+                        ui.selectable_value(&mut self.selected, Some(i), &metric.source);
+                    });
+                }
+                if let Some(i) = remove {
+                    self.metrics.remove(i);
+                    self.selected = match self.selected {
+                        Some(s) if s == i => None,
+                        Some(s) if s > i => Some(s - 1),
+                        s => s,
+                    };
+                }
+
+                let Some(metric) = self.selected.and_then(|i| self.metrics.get(i)) else {
+                    return;
+                };
+                let (start, end) = state.view;
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(format!("visible range {:.6}s - {:.6}s", start, end));
+                    ui.separator();
+                    ui.selectable_value(&mut self.aggregation, Aggregation::Sum, "Sum");
+                    ui.selectable_value(&mut self.aggregation, Aggregation::Mean, "Mean");
+                    ui.selectable_value(&mut self.aggregation, Aggregation::Max, "Max");
+                    ui.separator();
+                    ui.checkbox(&mut self.bands, "Percentile bands")
+                        .on_hover_text("p50 to p95 and p95 to p99 of all events in each bin");
+                });
+
+                let rows = metrics::table(data, state.grouping, metric, start, end);
+                // only the biggest groups get a line, the table has the rest
+                let plotted: Vec<&str> = rows
+                    .iter()
+                    .take(METRIC_PLOT_GROUPS)
+                    .map(|r| r.group.as_str())
+                    .collect();
+                let series: Vec<_> = metrics::series(
+                    data,
+                    state.grouping,
+                    metric,
+                    start,
+                    end,
+                    METRIC_PLOT_BINS,
+                    self.aggregation,
+                )
+                .into_iter()
+                .filter(|s| plotted.contains(&s.group.as_str()))
+                .collect();
+                let bands = if self.bands {
+                    metrics::percentile_bands(data, metric, start, end, METRIC_PLOT_BINS)
+                } else {
+                    Vec::new()
+                };
+
+                let (response, painter) =
+                    ui.allocate_painter(Vec2::new(ui.available_width(), 140.0), Sense::hover());
+                let plot_rect = response.rect.shrink(4.0);
+                painter.rect_filled(response.rect, 2.0, state.palette.plot_bg);
+                let values = series
+                    .iter()
+                    .flat_map(|s| &s.values)
+                    .chain(bands.iter().flatten().flat_map(|p| [&p.p50, &p.p99]))
+                    .filter(|v| !v.is_nan());
+                let (lo, hi) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                    (lo.min(*v), hi.max(*v))
+                });
+                if lo.is_finite() {
+                    let lo = lo.min(0.0);
+                    let span = (hi - lo).max(f64::EPSILON);
+                    let bin_x = |i: usize| {
+                        plot_rect.min.x
+                            + plot_rect.width() * (i as f32 + 0.5) / METRIC_PLOT_BINS as f32
+                    };
+                    let value_y =
+                        |v: f64| plot_rect.max.y - plot_rect.height() * ((v - lo) / span) as f32;
+                    let band_color = state.palette.text_weak;
+                    for (i, p) in bands.iter().enumerate() {
+                        let Some(p) = p else {
+                            continue;
+                        };
+                        let half = plot_rect.width() / METRIC_PLOT_BINS as f32 / 2.0;
+                        let x = (bin_x(i) - half)..=(bin_x(i) + half);
+                        for (from, to, alpha) in [(p.p50, p.p95, 0.35), (p.p95, p.p99, 0.15)] {
+                            painter.rect_filled(
+                                Rect::from_x_y_ranges(x.clone(), value_y(to)..=value_y(from)),
+                                0.0,
+                                band_color.gamma_multiply(alpha),
+                            );
+                        }
+                    }
+                    for s in &series {
+                        let color = generate_color(&s.group);
+                        // empty bins break the line
+                        let mut run = Vec::new();
+                        for (i, v) in s.values.iter().enumerate() {
+                            if v.is_nan() {
+                                if run.len() > 1 {
+                                    painter.line(std::mem::take(&mut run), Stroke::new(1.5, color));
+                                }
+                                run.clear();
+                            } else {
+                                run.push(Pos2::new(bin_x(i), value_y(*v)));
+                            }
+                        }
+                        match run.len() {
+                            0 => {}
+                            1 => {
+                                painter.circle_filled(run[0], 1.5, color);
+                            }
+                            _ => {
+                                painter.line(run, Stroke::new(1.5, color));
+                            }
+                        }
+                    }
+                    painter.text(
+                        plot_rect.left_top(),
+                        egui::Align2::LEFT_TOP,
+                        format!("{hi:.4}"),
+                        egui::FontId::proportional(9.0),
+                        state.palette.text_weak,
+                    );
+                    painter.text(
+                        plot_rect.left_bottom(),
+                        egui::Align2::LEFT_BOTTOM,
+                        format!("{lo:.4}"),
+                        egui::FontId::proportional(9.0),
+                        state.palette.text_weak,
+                    );
+                    paint_crosshair(&painter, plot_rect, start, end, state.hover);
+                    if let Some(pos) = response.hover_pos() {
+                        let share = ((pos.x - plot_rect.min.x) / plot_rect.width()) as f64;
+                        commands.push(ViewCommand::Hover(start + (end - start) * share));
+                        let bin = (((pos.x - plot_rect.min.x) / plot_rect.width())
+                            * METRIC_PLOT_BINS as f32)
+                            .clamp(0.0, (METRIC_PLOT_BINS - 1) as f32)
+                            as usize;
+                        let t =
+                            start + (end - start) * (bin as f64 + 0.5) / METRIC_PLOT_BINS as f64;
+                        let text = series
+                            .iter()
+                            .filter(|s| !s.values[bin].is_nan())
+                            .map(|s| format!("{}: {:.4}", s.group, s.values[bin]))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        let percentiles = match bands.get(bin) {
+                            Some(Some(p)) => format!(
+                                "\np50 {:.4}  p95 {:.4}  p99 {:.4} of {}",
+                                p.p50, p.p95, p.p99, p.count
+                            ),
+                            _ => String::new(),
+                        };
+                        response.on_hover_text(format!("{t:.6}s\n{text}{percentiles}"));
+                    }
+                } else {
+                    painter.text(
+                        plot_rect.center(),
+                        egui::Align2::CENTER_CENTER,
+                        "no events with this metric in range",
+                        egui::FontId::proportional(11.0),
+                        state.palette.text_weak,
+                    );
+                }
+
+                ui.separator();
+                egui::Grid::new("metrics_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Group");
+                        ui.strong("Events");
+                        ui.strong("Sum");
+                        ui.strong("Mean");
+                        ui.strong("Min");
+                        ui.strong("Max");
+                        ui.end_row();
+                        for row in &rows {
+                            let name = egui::RichText::new(&row.group);
+                            if plotted.contains(&row.group.as_str()) {
+                                ui.label(name.color(generate_color(&row.group)));
+                            } else {
+                                ui.label(name);
+                            }
+                            ui.label(format!("{}", row.count));
+                            ui.label(format!("{:.4}", row.sum));
+                            ui.label(format!("{:.4}", row.mean()));
+                            ui.label(format!("{:.4}", row.min));
+                            ui.label(format!("{:.4}", row.max));
+                            ui.end_row();
+                        }
+                    });
+            });
+        commands
+    }
+}
@@ -0,0 +1,28 @@
+use csvpshmem_core::units;
+
+// where the time box (ctrl+G) sends the cursor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Goto {
+    // "1.25", "1.25s", "300ms"
+    Time(f64),
+    // "+5ms", "-2us", from the cursor
+    Offset(f64),
+    // "#1234", an event index as shown in the event details
+    Event(usize),
+}
+
+impl Goto {
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Some(index) = input.strip_prefix('#') {
+            return index.trim().parse().ok().map(Self::Event);
+        }
+        if let Some(offset) = input.strip_prefix('+') {
+            return units::parse_duration(offset).map(Self::Offset);
+        }
+        if let Some(offset) = input.strip_prefix('-') {
+            return units::parse_duration(offset).map(|d| Self::Offset(-d));
+        }
+        units::parse_duration(input).map(Self::Time)
+    }
+}
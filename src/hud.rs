@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// frames kept for the frame time graph
+const HISTORY: usize = 240;
+
+// what the timeline did with the events it looked at in one frame
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DrawCounts {
+    // events between the first one that could be visible and the view end
+    pub scanned: usize,
+    pub drawn: usize,
+    // outside the visible area
+    pub culled: usize,
+    // hidden by function / group filters
+    pub filtered: usize,
+    // covered by an event already drawn in the same pixels, skipped while
+    // the view is moving
+    pub merged: usize,
+}
+
+// the viewer's own performance, for the overlay and bug reports
+#[derive(Debug, Default)]
+pub struct PerfHud {
+    // seconds between frames and seconds spent in update, newest last
+    frame_times: VecDeque<f32>,
+    update_times: VecDeque<f32>,
+    pub timeline: DrawCounts,
+    pub edges_drawn: usize,
+    // estimated heap use of the loaded ProfileData
+    pub data_bytes: usize,
+}
+
+impl PerfHud {
+    pub fn record_frame(&mut self, frame_dt: f32, update: Duration) {
+        for (history, value) in [
+            (&mut self.frame_times, frame_dt),
+            (&mut self.update_times, update.as_secs_f32()),
+        ] {
+            if history.len() == HISTORY {
+                history.pop_front();
+            }
+            history.push_back(value);
+        }
+    }
+
+    pub fn frame_times(&self) -> &VecDeque<f32> {
+        &self.frame_times
+    }
+
+    pub fn mean_frame_time(&self) -> f32 {
+        mean(&self.frame_times)
+    }
+
+    pub fn mean_update_time(&self) -> f32 {
+        mean(&self.update_times)
+    }
+
+    pub fn worst_frame_time(&self) -> f32 {
+        self.frame_times.iter().copied().fold(0.0, f32::max)
+    }
+
+    // plain text for pasting into an issue
+    pub fn report(&self) -> String {
+        let t = &self.timeline;
+        format!(
+            "frame {:.2} ms avg, {:.2} ms worst, update {:.2} ms\n\
+             timeline: {} scanned, {} drawn, {} merged, {} culled, {} filtered\n\
+             edges drawn: {}\n\
+             profile data: {}",
+            self.mean_frame_time() * 1e3,
+            self.worst_frame_time() * 1e3,
+            self.mean_update_time() * 1e3,
+            t.scanned,
+            t.drawn,
+            t.merged,
+            t.culled,
+            t.filtered,
+            self.edges_drawn,
+            crate::units::bytes(self.data_bytes as u64),
+        )
+    }
+}
+
+fn mean(values: &VecDeque<f32>) -> f32 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f32>() / values.len() as f32
+    }
+}
@@ -0,0 +1,140 @@
+use egui::{Key, KeyboardShortcut, Modifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    ZoomToSelection,
+    ZoomToFit,
+    ZoomBack,
+    ZoomForward,
+    PlaceCursorB,
+    ClearCursorB,
+    AddBookmark,
+    PrevBookmark,
+    NextBookmark,
+    ToggleHud,
+    Undo,
+    Redo,
+    Goto,
+}
+
+impl Action {
+    pub const ALL: [Action; 13] = [
+        Self::ZoomToSelection,
+        Self::ZoomToFit,
+        Self::ZoomBack,
+        Self::ZoomForward,
+        Self::PlaceCursorB,
+        Self::ClearCursorB,
+        Self::AddBookmark,
+        Self::PrevBookmark,
+        Self::NextBookmark,
+        Self::ToggleHud,
+        Self::Undo,
+        Self::Redo,
+        Self::Goto,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::ZoomToSelection => "Zoom to selection",
+            Self::ZoomToFit => "Fit whole trace",
+            Self::ZoomBack => "Previous zoom",
+            Self::ZoomForward => "Next zoom",
+            Self::PlaceCursorB => "Place second cursor",
+            Self::ClearCursorB => "Remove second cursor",
+            Self::AddBookmark => "Bookmark cursor",
+            Self::PrevBookmark => "Previous bookmark",
+            Self::NextBookmark => "Next bookmark",
+            Self::ToggleHud => "Performance overlay",
+            Self::Undo => "Undo filter, color or bookmark change",
+            Self::Redo => "Redo",
+            Self::Goto => "Go to a time or event",
+        }
+    }
+
+    pub fn default_binding(self) -> &'static str {
+        match self {
+            Self::ZoomToSelection => "Z",
+            Self::ZoomToFit => "F",
+            Self::ZoomBack => "alt+ArrowLeft",
+            Self::ZoomForward => "alt+ArrowRight",
+            Self::PlaceCursorB => "M",
+            Self::ClearCursorB => "shift+M",
+            Self::AddBookmark => "B",
+            Self::PrevBookmark => "OpenBracket",
+            Self::NextBookmark => "CloseBracket",
+            Self::ToggleHud => "F3",
+            Self::Undo => "ctrl+Z",
+            Self::Redo => "ctrl+shift+Z",
+            Self::Goto => "ctrl+G",
+        }
+    }
+}
+
+// "shift+M", "ctrl+alt+ArrowLeft". ctrl means cmd on macs
+pub fn parse_shortcut(s: &str) -> Option<KeyboardShortcut> {
+    let mut modifiers = Modifiers::NONE;
+    let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+    let key = Key::from_name(parts.pop()?)?;
+    for part in parts {
+        modifiers |= match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "cmd" | "command" => Modifiers::COMMAND,
+            "shift" => Modifiers::SHIFT,
+            "alt" | "option" => Modifiers::ALT,
+            _ => return None,
+        };
+    }
+    Some(KeyboardShortcut::new(modifiers, key))
+}
+
+// key bindings as written in the config file, action -> shortcut
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Keymap {
+    pub bindings: BTreeMap<Action, String>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: Action::ALL
+                .into_iter()
+                .map(|a| (a, a.default_binding().to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    // unset or unreadable bindings fall back to the default one
+    pub fn shortcut(&self, action: Action) -> KeyboardShortcut {
+        self.bindings
+            .get(&action)
+            .and_then(|s| parse_shortcut(s))
+            .or_else(|| parse_shortcut(action.default_binding()))
+            .expect("default bindings parse")
+    }
+
+    // actions whose shortcut was pressed this frame. shortcuts with more
+    // modifiers go first and eat the key press, so shift+M isn't also M
+    pub fn pressed(&self, ctx: &egui::Context) -> Vec<Action> {
+        let mut shortcuts: Vec<(Action, KeyboardShortcut)> = Action::ALL
+            .into_iter()
+            .map(|a| (a, self.shortcut(a)))
+            .collect();
+        shortcuts.sort_by_key(|(_, s)| {
+            let m = s.modifiers;
+            std::cmp::Reverse(u8::from(m.command) + u8::from(m.shift) + u8::from(m.alt))
+        });
+        ctx.input_mut(|i| {
+            shortcuts
+                .into_iter()
+                .filter(|(_, s)| i.consume_shortcut(s))
+                .map(|(a, _)| a)
+                .collect()
+        })
+    }
+}
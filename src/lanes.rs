@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::data::ProfileData;
+
+// how events sharing a track are spread vertically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stacking {
+    // one strip, overlapping events draw over each other
+    Flat,
+    // overlapping events go to the first free lane
+    Overlap,
+    // lane = call stack depth, a flame chart per PE with callers on top
+    Depth,
+}
+
+// more lanes than this and the extra events share the least busy one
+pub const MAX_LANES: usize = 8;
+// calls deeper than this share the last lane
+pub const MAX_DEPTH_LANES: usize = 16;
+
+// frames in the Stacktrace
+fn depth(stacktrace: &str) -> usize {
+    stacktrace
+        .split('|')
+        .filter(|f| !f.trim().is_empty())
+        .count()
+}
+
+#[derive(Debug, Default)]
+pub struct Lanes {
+    // parallel to ProfileData::events
+    lane_of: Vec<u16>,
+    // (pe, sub-row) -> lanes used
+    counts: HashMap<(u32, usize), usize>,
+}
+
+impl Lanes {
+    pub fn new(data: &ProfileData, stacking: Stacking) -> Self {
+        match stacking {
+            Stacking::Flat => Self::default(),
+            Stacking::Overlap => Self::overlap(data),
+            Stacking::Depth => Self::depth(data),
+        }
+    }
+
+    // depths are counted from the shallowest call of each sub-row so a
+    // common prefix (main, _start, ...) doesn't waste lanes
+    fn depth(data: &ProfileData) -> Self {
+        let depths: Vec<usize> = data
+            .events
+            .iter()
+            .map(|e| depth(e.raw.stacktrace))
+            .collect();
+        let mut shallowest: HashMap<(u32, usize), usize> = HashMap::new();
+        for (e, d) in data.iter().zip(&depths) {
+            let min = shallowest
+                .entry((e.source_pe, data.sub_row(&e)))
+                .or_insert(*d);
+            *min = (*min).min(*d);
+        }
+        let mut counts: HashMap<(u32, usize), usize> = HashMap::new();
+        let lane_of = data
+            .events
+            .iter()
+            .zip(&depths)
+            .map(|(e, d)| {
+                let key = (e.source_pe, data.sub_row(&e));
+                let lane = (d - shallowest[&key]).min(MAX_DEPTH_LANES - 1);
+                let count = counts.entry(key).or_insert(1);
+                *count = (*count).max(lane + 1);
+                lane as u16
+            })
+            .collect();
+        Self { lane_of, counts }
+    }
+
+    // greedy interval colouring, events are already in start order
+    fn overlap(data: &ProfileData) -> Self {
+        let mut lane_ends: HashMap<(u32, usize), Vec<f64>> = HashMap::new();
+        let lane_of = data
+            .events
+            .iter()
+            .map(|e| {
+                let ends = lane_ends
+                    .entry((e.source_pe, data.sub_row(&e)))
+                    .or_default();
+                let end = e.raw.time + e.raw.duration_sec;
+                let lane = match ends.iter().position(|t| *t <= e.raw.time) {
+                    Some(lane) => lane,
+                    None if ends.len() < MAX_LANES => {
+                        ends.push(f64::NEG_INFINITY);
+                        ends.len() - 1
+                    }
+                    None => (0..ends.len())
+                        .min_by(|a, b| ends[*a].total_cmp(&ends[*b]))
+                        .unwrap_or(0),
+                };
+                ends[lane] = ends[lane].max(end);
+                lane as u16
+            })
+            .collect();
+        let counts = lane_ends
+            .into_iter()
+            .map(|(key, ends)| (key, ends.len()))
+            .collect();
+        Self { lane_of, counts }
+    }
+
+    pub fn lane(&self, event: usize) -> usize {
+        self.lane_of.get(event).map_or(0, |l| *l as usize)
+    }
+
+    // lanes of each sub-row of `pe`
+    pub fn per_sub_row(&self, data: &ProfileData, pe: u32) -> Vec<usize> {
+        (0..data.sub_rows(pe))
+            .map(|k| self.counts.get(&(pe, k)).copied().unwrap_or(1).max(1))
+            .collect()
+    }
+}
@@ -0,0 +1,252 @@
+use egui::Vec2;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+use crate::data::ProfileData;
+use crate::topology::{GroupLevel, Topology};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RingGrouping {
+    Flat,
+    // PEs of the same host sit together with gaps between hosts
+    ByHost,
+    // one node per host, intra-host traffic dropped
+    CollapseHosts,
+}
+
+// hosts in order of their lowest PE
+pub struct HostGroups {
+    pub names: Vec<String>,
+    pub host_of_pe: Vec<u32>,
+}
+
+impl HostGroups {
+    pub fn new(data: &ProfileData) -> Self {
+        Self::by(data, str::to_string)
+    }
+
+    // groups of hosts instead, e.g. the switch each is on
+    pub fn by(data: &ProfileData, group_of: impl Fn(&str) -> String) -> Self {
+        let mut names = Vec::new();
+        let mut index: HashMap<String, u32> = HashMap::new();
+        let mut host_of_pe = Vec::with_capacity(data.pe_count as usize);
+        for pe in 0..data.pe_count {
+            let name = group_of(data.pe_hostnames.get(&pe).map_or("?", String::as_str));
+            let host = *index.entry(name.clone()).or_insert_with(|| {
+                names.push(name);
+                names.len() as u32 - 1
+            });
+            host_of_pe.push(host);
+        }
+        Self { names, host_of_pe }
+    }
+
+    // hosts, or their switches or racks when there's a topology
+    pub fn at(data: &ProfileData, topology: Option<&Topology>, level: GroupLevel) -> Self {
+        match topology {
+            Some(topology) if level != GroupLevel::Host => {
+                Self::by(data, |host| topology.group(host, level))
+            }
+            _ => Self::new(data),
+        }
+    }
+
+    pub fn host_of(&self, pe: u32) -> u32 {
+        self.host_of_pe.get(pe as usize).copied().unwrap_or(0)
+    }
+
+    pub fn pe_count(&self, host: u32) -> usize {
+        self.host_of_pe.iter().filter(|h| **h == host).count()
+    }
+}
+
+pub struct HostArc {
+    pub name: String,
+    pub start_angle: f32,
+    pub end_angle: f32,
+}
+
+// node angles in radians, starting at 12 o'clock and going clockwise
+pub struct RingLayout {
+    pub angles: Vec<f32>,
+    pub arcs: Vec<HostArc>,
+}
+
+// fraction of the circle left empty between hosts
+const HOST_GAP: f32 = 0.25;
+
+pub fn ring_layout(grouping: RingGrouping, groups: &HostGroups) -> RingLayout {
+    let uniform = |count: usize| -> Vec<f32> {
+        let step = TAU / count.max(1) as f32;
+        (0..count).map(|i| i as f32 * step - PI / 2.0).collect()
+    };
+    match grouping {
+        RingGrouping::Flat => RingLayout {
+            angles: uniform(groups.host_of_pe.len()),
+            arcs: Vec::new(),
+        },
+        RingGrouping::CollapseHosts => RingLayout {
+            angles: uniform(groups.names.len()),
+            arcs: Vec::new(),
+        },
+        RingGrouping::ByHost => {
+            let pe_count = groups.host_of_pe.len();
+            let host_count = groups.names.len();
+            if host_count <= 1 {
+                return ring_layout(RingGrouping::Flat, groups);
+            }
+            // each host gap is worth HOST_GAP of a full revolution split evenly
+            let gap = TAU * HOST_GAP / host_count as f32;
+            let step = (TAU - gap * host_count as f32) / pe_count as f32;
+
+            // walk PEs host by host so each group is contiguous
+            let mut angles = vec![0.0; pe_count];
+            let mut arcs = Vec::with_capacity(host_count);
+            let mut angle = -PI / 2.0 + gap / 2.0;
+            for (host, name) in groups.names.iter().enumerate() {
+                let start_angle = angle;
+                for (pe, _) in groups
+                    .host_of_pe
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, h)| **h as usize == host)
+                {
+                    angles[pe] = angle + step / 2.0;
+                    angle += step;
+                }
+                arcs.push(HostArc {
+                    name: name.clone(),
+                    start_angle,
+                    end_angle: angle,
+                });
+                angle += gap;
+            }
+            RingLayout { angles, arcs }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Placement {
+    Ring,
+    // nodes pulled together by how much they talk over the whole trace
+    Force,
+}
+
+// fruchterman-reingold style simulation in unit coordinates. stepped a
+// little every frame so the nodes visibly settle
+pub struct ForceLayout {
+    positions: Vec<Vec2>,
+    // symmetric (a < b) edge weights normalized to 0..=1
+    weights: Vec<(usize, usize, f32)>,
+    temperature: f32,
+}
+
+const START_TEMPERATURE: f32 = 0.1;
+const MIN_TEMPERATURE: f32 = 0.001;
+const COOLING: f32 = 0.97;
+const STEPS_PER_FRAME: usize = 3;
+
+impl ForceLayout {
+    // `initial` are angles to start from, usually the ring layout
+    pub fn new(initial: &[f32], traffic: &HashMap<(u32, u32), u64>) -> Self {
+        let positions = initial
+            .iter()
+            .map(|a| Vec2::new(a.cos(), a.sin()))
+            .collect();
+
+        let mut symmetric: HashMap<(usize, usize), u64> = HashMap::new();
+        for ((a, b), bytes) in traffic {
+            let (a, b) = (*a.min(b) as usize, *a.max(b) as usize);
+            if a != b && b < initial.len() {
+                *symmetric.entry((a, b)).or_default() += bytes;
+            }
+        }
+        let max = symmetric.values().copied().max().unwrap_or(1).max(1) as f32;
+        let weights = symmetric
+            .into_iter()
+            .map(|((a, b), bytes)| (a, b, bytes as f32 / max))
+            .collect();
+
+        Self {
+            positions,
+            weights,
+            temperature: START_TEMPERATURE,
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.temperature < MIN_TEMPERATURE
+    }
+
+    pub fn position(&self, node: usize) -> Vec2 {
+        self.positions[node]
+    }
+
+    // advances the animation, returns true while still moving
+    pub fn animate(&mut self) -> bool {
+        if self.is_settled() {
+            return false;
+        }
+        for _ in 0..STEPS_PER_FRAME {
+            self.step();
+        }
+        true
+    }
+
+    fn step(&mut self) {
+        let n = self.positions.len();
+        if n < 2 {
+            self.temperature = 0.0;
+            return;
+        }
+        // ideal distance for n nodes spread over the unit disc
+        let k = (PI / n as f32).sqrt();
+        let mut disp = vec![Vec2::ZERO; n];
+
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let delta = self.positions[i] - self.positions[j];
+                let d = delta.length().max(0.01);
+                let push = delta / d * (k * k / d);
+                disp[i] += push;
+                disp[j] -= push;
+            }
+        }
+        for (a, b, w) in &self.weights {
+            let delta = self.positions[*a] - self.positions[*b];
+            let d = delta.length().max(0.01);
+            // heavier traffic pulls harder
+            let pull = delta / d * (d * d / k) * (0.2 + *w * 4.0);
+            disp[*a] -= pull;
+            disp[*b] += pull;
+        }
+
+        for (p, d) in self.positions.iter_mut().zip(&disp) {
+            // gravity keeps disconnected nodes from drifting off
+            let d = *d - *p * 0.5;
+            let len = d.length();
+            if len > 0.0 {
+                *p += d / len * len.min(self.temperature);
+            }
+        }
+
+        // rescale so the layout fills the unit circle
+        let extent = self
+            .positions
+            .iter()
+            .map(|p| p.length())
+            .fold(0.0f32, f32::max);
+        if extent > 0.0 {
+            for p in &mut self.positions {
+                *p /= extent;
+            }
+        }
+        self.temperature *= COOLING;
+    }
+}
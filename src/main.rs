@@ -1,18 +1,76 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
+mod annotations;
 mod app;
-mod data;
+mod balance;
+mod chord;
+mod cli;
+mod coloring;
+mod comparison;
+mod config;
+mod dock;
+mod export;
+mod filters;
+mod flame;
+mod formulas;
+mod goto;
+mod hud;
+mod keys;
+mod lanes;
+mod layout;
+mod messages;
+mod outliers;
+mod panes;
+mod phasing;
+mod queries;
+mod remote;
+mod report;
+mod ruler;
+mod script;
+mod session;
+mod shares;
+mod symbols;
+mod tabs;
+mod theme;
+mod timeline;
+mod tracks;
+mod traffic;
+mod undo;
+mod video;
+mod views;
+mod watch;
+mod windows;
+mod zoom;
+
+// the trace model and analyses live in the core crate, kept at the crate
+// root so the frontend refers to them as before
+use csvpshmem_core::{
+    activity, anomalies, bandwidth, bookmarks, completion, contention, data, derived, gantt,
+    grouping, imbalance, labels, latency, manifest, metrics, parquet, phases, prefix, rundiff,
+    scatter, search, skew, sql, stats, synthetic, topology, trim, units,
+};
 
 use app::VisualizerApp;
+use clap::Parser;
+use cli::Args;
 
 fn main() -> eframe::Result<()> {
+    let mut args = Args::parse();
+    if let Some(dir) = args.generate_demo.take() {
+        if let Err(e) = synthetic::write(&dir, &args.demo_config()) {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        }
+        args.data_dir = Some(dir);
+    }
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([1024.0, 768.0]),
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size(args.size.unwrap_or([1024.0, 768.0])),
         ..Default::default()
     };
     eframe::run_native(
         "csvpshmem visualizer",
         options,
-        Box::new(|cc| Ok(Box::new(VisualizerApp::new(cc)))),
+        Box::new(|cc| Ok(Box::new(VisualizerApp::new(cc, args)))),
     )
 }
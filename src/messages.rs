@@ -0,0 +1,445 @@
+use std::collections::BTreeMap;
+
+use crate::data::ProfileData;
+use crate::latency::{self, LatencyBy, LatencyKey, LatencyTable};
+use crate::outliers::MAX_ROWS;
+use crate::scatter::{FitBy, PairFits, PairKey, Scatter};
+use crate::units;
+use crate::views::{ViewCommand, ViewState};
+
+// calls drawn in the size / duration scatter, evenly thinned beyond that
+const SCATTER_MAX_POINTS: usize = 20_000;
+
+// sends matched with their receives on the target PE, and how long the
+// messages took per pair
+#[derive(Default)]
+pub struct Messages {
+    pub open: bool,
+}
+
+impl Messages {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        let derived = state.derived;
+        let mut settings = derived.settings;
+        let mut jump = None;
+        egui::Window::new("Messages")
+            .open(&mut self.open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Receive may start up to");
+                    let mut ms = settings.match_window * 1e3;
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut ms)
+                                .speed(0.01)
+                                .range(0.0..=10_000.0)
+                                .suffix(" ms"),
+                        )
+                        .on_hover_text(
+                            "after the send ends. sends pair with a receive of the same \
+                             size on the target PE, from the sender",
+                        )
+                        .changed()
+                    {
+                        settings.match_window = ms / 1e3;
+                        commands.push(ViewCommand::DerivedSettings(settings));
+                    }
+                });
+                let m = &derived.matching;
+                ui.label(format!(
+                    "{} matched, {} sends and {} receives without a partner",
+                    m.messages.len(),
+                    m.unmatched_sends,
+                    m.unmatched_recvs
+                ));
+                let Some(all) = m.latency() else {
+                    ui.label("no sends with a matching receive");
+                    return;
+                };
+                ui.label(format!(
+                    "send start to receive end: p50 {}, p99 {}, max {}",
+                    units::duration(all.p50),
+                    units::duration(all.p99),
+                    units::duration(all.max)
+                ));
+                ui.separator();
+                egui::Grid::new("messages_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for title in ["Pair", "Messages", "Bytes", "p50", "p99", "Max"] {
+                            ui.strong(title);
+                        }
+                        ui.end_row();
+                        for pair in derived.message_pairs.iter().take(MAX_ROWS) {
+                            if ui
+                                .link(format!(
+                                    "{} -> {}",
+                                    state.pe_labels.name(pair.src),
+                                    state.pe_labels.name(pair.dst)
+                                ))
+                                .on_hover_text("Jump to the slowest message")
+                                .clicked()
+                            {
+                                jump = Some(m.messages[pair.slowest]);
+                            }
+                            ui.label(pair.latency.count.to_string());
+                            ui.label(units::bytes(pair.bytes));
+                            for v in [pair.latency.p50, pair.latency.p99, pair.latency.max] {
+                                ui.label(units::duration(v));
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if derived.message_pairs.len() > MAX_ROWS {
+                    ui.small(format!(
+                        "{} more not shown",
+                        derived.message_pairs.len() - MAX_ROWS
+                    ));
+                }
+            });
+        if let Some(message) = jump {
+            // both ends with some room around them
+            let recv = data.event(message.recv).raw;
+            let start = data.event(message.send).raw.time.min(recv.time);
+            let end = (recv.time + recv.duration_sec).max(start + 1e-6);
+            let pad = (end - start) * 0.5;
+            commands.push(ViewCommand::PinEvent(message.send, None));
+            commands.push(ViewCommand::SetView(start - pad, end + pad));
+        }
+        commands
+    }
+}
+
+// p50 to p99.9 of the calls with a target PE, per function or pair, over
+// the analysis range. follows the view as it pans
+pub struct Latency {
+    pub open: bool,
+    by: LatencyBy,
+    // kept with the range and grouping it was made for
+    table: Option<((f64, f64, LatencyBy), LatencyTable)>,
+}
+
+impl Default for Latency {
+    fn default() -> Self {
+        Self {
+            open: false,
+            by: LatencyBy::Function,
+            table: None,
+        }
+    }
+}
+
+impl Latency {
+    pub fn invalidate(&mut self) {
+        self.table = None;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, data: &ProfileData, state: &ViewState) {
+        let (start, end) = state.analysis_range;
+        let range_name = state.analysis_range_name;
+        egui::Window::new("Latency")
+            .open(&mut self.open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.by, LatencyBy::Function, "By function");
+                    ui.selectable_value(&mut self.by, LatencyBy::Pair, "By PE pair");
+                });
+                ui.small(format!("{range_name} {start:.6}s to {end:.6}s"));
+                let key = (start, end, self.by);
+                if self.table.as_ref().is_none_or(|(k, _)| *k != key) {
+                    self.table = Some((key, latency::table(data, start, end, self.by)));
+                }
+                let Some((_, rows)) = &self.table else {
+                    return;
+                };
+                if rows.is_empty() {
+                    ui.label("no calls with a target PE in range");
+                    return;
+                }
+                ui.separator();
+                egui::Grid::new("latency_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong(match self.by {
+                            LatencyBy::Function => "Function",
+                            LatencyBy::Pair => "Pair",
+                        });
+                        for title in ["Calls", "p50", "p95", "p99", "p99.9", "Max"] {
+                            ui.strong(title);
+                        }
+                        ui.end_row();
+                        for (key, p) in rows.iter().take(MAX_ROWS) {
+                            match key {
+                                LatencyKey::Function(f) => ui.label(f),
+                                LatencyKey::Pair(src, dst) => ui.label(format!(
+                                    "{} -> {}",
+                                    state.pe_labels.name(*src),
+                                    state.pe_labels.name(*dst)
+                                )),
+                            };
+                            ui.label(p.count.to_string());
+                            for v in [p.p50, p.p95, p.p99, p.p999, p.max] {
+                                ui.label(units::duration(v));
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if rows.len() > MAX_ROWS {
+                    ui.small(format!("{} more not shown", rows.len() - MAX_ROWS));
+                }
+            });
+    }
+}
+
+// message size against duration of the calls with a target PE in the
+// analysis range, and the alpha-beta fit of every pair. the fits window
+// opens the scatter on a pair, so they share their state
+pub struct SizeDuration {
+    pub open: bool,
+    // calls between these PEs only, any PEs when None
+    pair: Option<(u32, u32)>,
+    log: bool,
+    scatter: Option<Scatter>,
+    // alpha and beta per PE or host pair
+    pub fits_open: bool,
+    fit_by: FitBy,
+    fits: Option<PairFits>,
+}
+
+impl Default for SizeDuration {
+    fn default() -> Self {
+        Self {
+            open: false,
+            pair: None,
+            log: true,
+            scatter: None,
+            fits_open: false,
+            fit_by: FitBy::HostPair,
+            fits: None,
+        }
+    }
+}
+
+impl SizeDuration {
+    pub fn invalidate(&mut self) {
+        self.scatter = None;
+        self.fits = None;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, data: &ProfileData, state: &ViewState) {
+        self.ui_scatter_window(ctx, data, state);
+        self.ui_fits_window(ctx, data, state);
+    }
+
+    // bytes against duration of every call with a target PE in the analysis
+    // range, colored by function, with the alpha + bytes / beta line through
+    // them
+    fn ui_scatter_window(&mut self, ctx: &egui::Context, data: &ProfileData, state: &ViewState) {
+        let (start, end) = state.analysis_range;
+        let range_name = state.analysis_range_name;
+        egui::Window::new("Size vs duration")
+            .open(&mut self.open)
+            .default_size([520.0, 360.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let mut only_pair = self.pair.is_some();
+                    if ui.checkbox(&mut only_pair, "Only between").changed() {
+                        self.pair =
+                            only_pair.then_some((0, 1.min(data.pe_count.saturating_sub(1))));
+                    }
+                    if let Some((a, b)) = &mut self.pair {
+                        let last = data.pe_count.saturating_sub(1);
+                        ui.add(egui::DragValue::new(a).range(0..=last).prefix("PE "));
+                        ui.label("and");
+                        ui.add(egui::DragValue::new(b).range(0..=last).prefix("PE "));
+                    }
+                    ui.separator();
+                    ui.checkbox(&mut self.log, "Log axes");
+                    ui.toggle_value(&mut self.fits_open, "Fit per pair");
+                });
+                if self
+                    .scatter
+                    .as_ref()
+                    .is_none_or(|s| s.range != (start, end) || s.pair != self.pair)
+                {
+                    self.scatter = Some(Scatter::new(data, start, end, self.pair));
+                }
+                let Some(scatter) = &self.scatter else {
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.small(format!(
+                        "{} calls in the {range_name}",
+                        scatter.points.len()
+                    ));
+                    if let Some(fit) = scatter.fit {
+                        ui.separator();
+                        ui.label(format!(
+                            "alpha {}  beta {}  (R² {:.2})",
+                            units::duration(fit.alpha),
+                            units::rate(fit.beta),
+                            fit.r2
+                        ));
+                    }
+                });
+
+                let log = self.log;
+                let to_plot = |bytes: f64, duration: f64| {
+                    if log {
+                        [bytes.max(1.0).log10(), duration.max(1e-12).log10()]
+                    } else {
+                        [bytes, duration]
+                    }
+                };
+                let from_plot = move |v: f64| if log { 10f64.powf(v) } else { v };
+
+                // one series per function so the legend can toggle them
+                let stride = scatter.points.len().div_ceil(SCATTER_MAX_POINTS).max(1);
+                let mut series: BTreeMap<&str, Vec<[f64; 2]>> = BTreeMap::new();
+                for p in scatter.points.iter().step_by(stride) {
+                    let function = data.event(p.event).raw.function;
+                    series
+                        .entry(function)
+                        .or_default()
+                        .push(to_plot(p.bytes as f64, p.duration));
+                }
+                let fit_line = scatter.fit.map(|fit| {
+                    let max = scatter.points.iter().map(|p| p.bytes).max().unwrap_or(1) as f64;
+                    let min = scatter.points.iter().map(|p| p.bytes).min().unwrap_or(1) as f64;
+                    (0..=64)
+                        .map(|i| {
+                            let f = i as f64 / 64.0;
+                            let bytes = if log {
+                                min.max(1.0) * (max.max(1.0) / min.max(1.0)).powf(f)
+                            } else {
+                                min + (max - min) * f
+                            };
+                            to_plot(bytes, fit.duration(bytes))
+                        })
+                        .collect::<Vec<_>>()
+                });
+
+                egui_plot::Plot::new("scatter_plot")
+                    .legend(egui_plot::Legend::default())
+                    .x_axis_label("bytes")
+                    .y_axis_label("duration")
+                    .x_axis_formatter(move |mark, _| units::bytes(from_plot(mark.value) as u64))
+                    .y_axis_formatter(move |mark, _| units::duration(from_plot(mark.value)))
+                    .label_formatter(move |name, point| {
+                        format!(
+                            "{name}\n{} in {}",
+                            units::bytes(from_plot(point.x) as u64),
+                            units::duration(from_plot(point.y))
+                        )
+                    })
+                    .show(ui, |plot_ui| {
+                        for (function, points) in series {
+                            let color = state.colors.function(function);
+                            plot_ui.points(
+                                egui_plot::Points::new(function, points)
+                                    .color(color)
+                                    .radius(2.0),
+                            );
+                        }
+                        if let Some(line) = fit_line {
+                            plot_ui.line(
+                                egui_plot::Line::new("alpha + bytes / beta", line)
+                                    .color(state.palette.text)
+                                    .style(egui_plot::LineStyle::dashed_loose()),
+                            );
+                        }
+                    });
+            });
+    }
+
+    // the alpha-beta fit of every PE or host pair over the analysis range,
+    // to read the effective latency and bandwidth of the interconnect off
+    // real traffic
+    fn ui_fits_window(&mut self, ctx: &egui::Context, data: &ProfileData, state: &ViewState) {
+        let (start, end) = state.analysis_range;
+        let range_name = state.analysis_range_name;
+        let mut show_pair = None;
+        egui::Window::new("Alpha-beta fits")
+            .open(&mut self.fits_open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.fit_by, FitBy::HostPair, "Host pairs");
+                    ui.selectable_value(&mut self.fit_by, FitBy::PePair, "PE pairs");
+                });
+                if self
+                    .fits
+                    .as_ref()
+                    .is_none_or(|f| f.range != (start, end) || f.by != self.fit_by)
+                {
+                    self.fits = Some(PairFits::new(data, start, end, self.fit_by));
+                }
+                let Some(fits) = &self.fits else {
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.small(format!("{range_name} {start:.6}s to {end:.6}s"));
+                    if ui
+                        .add_enabled(!fits.rows.is_empty(), egui::Button::new("Copy CSV"))
+                        .clicked()
+                    {
+                        ui.ctx().copy_text(fits.to_csv());
+                    }
+                });
+                if fits.rows.is_empty() {
+                    ui.label("no pair with calls of more than one size");
+                    return;
+                }
+                ui.separator();
+                egui::Grid::new("fits_grid").striped(true).show(ui, |ui| {
+                    for title in ["Pair", "Calls", "Alpha", "Beta", "R²"] {
+                        ui.strong(title);
+                    }
+                    ui.end_row();
+                    for (key, fit) in &fits.rows {
+                        match key {
+                            PairKey::Pes(a, b) => {
+                                if ui
+                                    .link(format!(
+                                        "{} <-> {}",
+                                        state.pe_labels.name(*a),
+                                        state.pe_labels.name(*b)
+                                    ))
+                                    .on_hover_text("Show in the scatter")
+                                    .clicked()
+                                {
+                                    show_pair = Some((*a, *b));
+                                }
+                            }
+                            PairKey::Hosts(a, b) => {
+                                ui.label(format!("{a} <-> {b}"));
+                            }
+                        }
+                        ui.label(fit.samples.to_string());
+                        ui.label(units::duration(fit.alpha));
+                        ui.label(units::rate(fit.beta));
+                        // a poor fit means size isn't what sets the time
+                        let r2 = format!("{:.2}", fit.r2);
+                        if fit.r2 < 0.5 {
+                            ui.weak(r2);
+                        } else {
+                            ui.label(r2);
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        if let Some(pair) = show_pair {
+            self.pair = Some(pair);
+            self.open = true;
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use egui::Color32;
+
+use crate::data::ProfileData;
+use crate::traffic::InspectedEdge;
+use crate::units;
+use crate::views::{ViewCommand, ViewState};
+
+// rows listed in the outlier, latency and message windows
+pub const MAX_ROWS: usize = 500;
+
+// calls that took far longer than their function usually does
+#[derive(Default)]
+pub struct Anomalies {
+    pub open: bool,
+}
+
+impl Anomalies {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        let derived = state.derived;
+        let mut settings = derived.settings;
+        egui::Window::new("Anomalies")
+            .open(&mut self.open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Flag durations above");
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut settings.anomaly_threshold)
+                                .speed(0.1)
+                                .range(1.0..=20.0)
+                                .suffix(" σ"),
+                        )
+                        .changed()
+                    {
+                        commands.push(ViewCommand::DerivedSettings(settings));
+                    }
+                    ui.label("of their function's mean");
+                });
+                ui.label(format!("{} events flagged", derived.anomalies.len()));
+                ui.separator();
+                egui::Grid::new("anomalies_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("σ");
+                        ui.strong("Function");
+                        ui.strong("PE");
+                        ui.strong("Time");
+                        ui.strong("Duration");
+                        ui.end_row();
+                        for a in derived.anomalies.iter().take(MAX_ROWS) {
+                            let e = data.event(a.event);
+                            ui.label(format!("{:.1}", a.sigma));
+                            ui.label(e.raw.function);
+                            ui.label(format!("{}", e.source_pe));
+                            if ui.link(format!("{:.6}s", e.raw.time)).clicked() {
+                                commands.push(ViewCommand::JumpTo(e.raw.time));
+                                commands.push(ViewCommand::SelectPe(Some(e.source_pe)));
+                            }
+                            ui.label(units::duration(e.raw.duration_sec));
+                            ui.end_row();
+                        }
+                    });
+                if derived.anomalies.len() > MAX_ROWS {
+                    ui.label(format!("... {} more", derived.anomalies.len() - MAX_ROWS));
+                }
+            });
+        commands
+    }
+}
+
+// PEs that keep arriving last at collectives
+#[derive(Default)]
+pub struct Stragglers {
+    pub open: bool,
+}
+
+impl Stragglers {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        let mut settings = state.derived.settings;
+        egui::Window::new("Stragglers")
+            .open(&mut self.open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Collective if every PE returns within");
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut settings.straggler_spread)
+                                .speed(0.0001)
+                                .range(0.0..=10.0)
+                                .suffix("s"),
+                        )
+                        .changed()
+                    {
+                        commands.push(ViewCommand::DerivedSettings(settings));
+                    }
+                });
+                let report = &state.derived.stragglers;
+                if report.instances == 0 {
+                    ui.label("no collective calls found");
+                    return;
+                }
+                ui.label(format!(
+                    "{} instances of {}",
+                    report.instances,
+                    report.functions.join(", ")
+                ));
+                ui.separator();
+                // arriving last this often is more than bad luck
+                let expected = 1.0 / data.pe_count.max(1) as f64;
+                egui::Grid::new("stragglers_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("PE");
+                        ui.strong("Last");
+                        ui.strong("Mean lateness");
+                        ui.strong("Worst");
+                        ui.end_row();
+                        for pe in &report.pes {
+                            let share = pe.last_count as f64 / report.instances as f64;
+                            let name = egui::RichText::new(format!("PE {}", pe.pe));
+                            if share > 2.0 * expected {
+                                ui.label(name.color(Color32::LIGHT_RED));
+                            } else {
+                                ui.label(name);
+                            }
+                            ui.label(format!("{} ({:.0}%)", pe.last_count, share * 100.0));
+                            ui.label(units::duration(pe.mean_lateness));
+                            if pe.worst_lateness > 0.0 {
+                                if ui
+                                    .link(format!(
+                                        "{} at {:.6}s",
+                                        units::duration(pe.worst_lateness),
+                                        pe.worst_time
+                                    ))
+                                    .clicked()
+                                {
+                                    commands.push(ViewCommand::JumpTo(pe.worst_time));
+                                    commands.push(ViewCommand::SelectPe(Some(pe.pe)));
+                                }
+                            } else {
+                                ui.label("-");
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        commands
+    }
+}
+
+// directions where the sender's bytes_tx and the receiver's bytes_rx
+// disagree, to check the instrumentation
+#[derive(Default)]
+pub struct Asymmetry {
+    pub open: bool,
+}
+
+impl Asymmetry {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        let mut settings = state.derived.settings;
+        egui::Window::new("TX/RX asymmetry")
+            .open(&mut self.open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Flag differences over");
+                    let mut percent = settings.asymmetry_tolerance * 100.0;
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut percent)
+                                .speed(0.1)
+                                .range(0.0..=100.0)
+                                .suffix("%"),
+                        )
+                        .changed()
+                    {
+                        settings.asymmetry_tolerance = percent / 100.0;
+                        commands.push(ViewCommand::DerivedSettings(settings));
+                    }
+                });
+                let report = &state.derived.asymmetry;
+                if report.compared == 0 {
+                    ui.label("no PE pair where both ends report bytes");
+                    return;
+                }
+                ui.label(format!(
+                    "{} of {} directions disagree",
+                    report.mismatches.len(),
+                    report.compared
+                ));
+                if report.mismatches.is_empty() {
+                    return;
+                }
+                ui.separator();
+                egui::Grid::new("asymmetry_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Pair");
+                        ui.strong("Sent (TX)");
+                        ui.strong("Received (RX)");
+                        ui.strong("Difference");
+                        ui.end_row();
+                        for m in report.mismatches.iter().take(MAX_ROWS) {
+                            if ui
+                                .link(format!(
+                                    "{} -> {}",
+                                    state.pe_labels.name(m.src),
+                                    state.pe_labels.name(m.dst)
+                                ))
+                                .on_hover_text("Show the calls behind it")
+                                .clicked()
+                            {
+                                commands.push(ViewCommand::InspectEdge(InspectedEdge {
+                                    edge: (m.src, m.dst),
+                                    start: data.min_time,
+                                    end: data.max_time,
+                                    hosts: false,
+                                }));
+                            }
+                            ui.label(format!("{} in {}", units::bytes(m.sent), m.sends));
+                            ui.label(format!("{} in {}", units::bytes(m.received), m.receives));
+                            ui.colored_label(
+                                Color32::LIGHT_RED,
+                                format!("{:.1}%", m.difference() * 100.0),
+                            );
+                            ui.end_row();
+                        }
+                    });
+            });
+        commands
+    }
+}
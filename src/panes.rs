@@ -0,0 +1,376 @@
+use egui::Color32;
+use std::fmt::Write as _;
+
+use crate::completion::{self, PeOverlap};
+use crate::data::{Event, ProfileData};
+use crate::dock::Pane;
+use crate::stats;
+use crate::symbols;
+use crate::timeline::Timeline;
+use crate::traffic::Bandwidth;
+use crate::units;
+use crate::views::{View, ViewCommand, ViewState};
+
+const MAX_SITE_EVENTS: usize = 50;
+
+// what draws each pane, kept for as long as the viewer runs
+#[derive(Default)]
+pub struct Panes {
+    pub timeline: Timeline,
+    pub bandwidth: Bandwidth,
+    pub stats: Stats,
+    pub inspector: Inspector,
+}
+
+impl Panes {
+    pub fn view(&mut self, pane: Pane) -> &mut dyn View {
+        match pane {
+            Pane::Timeline => &mut self.timeline,
+            Pane::Bandwidth => &mut self.bandwidth,
+            Pane::Stats => &mut self.stats,
+            Pane::Inspector => &mut self.inspector,
+        }
+    }
+
+    // drops what was made from the event times
+    pub fn invalidate(&mut self) {
+        self.timeline.invalidate();
+        self.bandwidth.invalidate();
+        self.stats.overlap = None;
+    }
+}
+
+// calls of the analysis range by function and call site
+#[derive(Default)]
+pub struct Stats {
+    // shown at all, unlike the other panes it starts hidden
+    pub open: bool,
+    // share of in-flight time the PEs spent outside calls, kept with the
+    // range it was made for
+    overlap: Option<((f64, f64), Vec<PeOverlap>)>,
+}
+
+impl View for Stats {
+    fn name(&self) -> &str {
+        Pane::Stats.title()
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, data: &ProfileData, state: &ViewState) -> Vec<ViewCommand> {
+        let (start, end) = state.analysis_range;
+        let range_name = state.analysis_range_name;
+        if self
+            .overlap
+            .as_ref()
+            .is_none_or(|(range, _)| *range != (start, end))
+        {
+            let rows = completion::overlap(data, &state.derived.completion, start, end);
+            self.overlap = Some(((start, end), rows));
+        }
+        let mut jump = None;
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                ui.label(format!("{:.6}s - {:.6}s ({})", start, end, range_name));
+                if let Some((_, rows)) = &self.overlap
+                    && !rows.is_empty()
+                {
+                    let in_flight: f64 = rows.iter().map(|r| r.in_flight).sum();
+                    let overlapped: f64 = rows.iter().map(|r| r.overlapped).sum();
+                    egui::CollapsingHeader::new(format!(
+                        "Communication overlap  {:.1}% of {} in flight",
+                        overlapped / in_flight.max(f64::EPSILON) * 100.0,
+                        units::duration(in_flight)
+                    ))
+                    .id_salt("stats_overlap")
+                    .show(ui, |ui| {
+                        ui.small(
+                            "time puts and gets were in flight while their PE was outside \
+                             any call, i.e. computing",
+                        );
+                        egui::Grid::new("overlap_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for title in ["PE", "In flight", "Overlapped", "Share"] {
+                                    ui.strong(title);
+                                }
+                                ui.end_row();
+                                for r in rows {
+                                    ui.label(state.pe_labels.name(r.pe));
+                                    ui.label(units::duration(r.in_flight));
+                                    ui.label(units::duration(r.overlapped));
+                                    ui.label(format!("{:.1}%", r.share() * 100.0));
+                                    ui.end_row();
+                                }
+                            });
+                        if state.derived.completion.unfinished > 0 {
+                            ui.small(format!(
+                                "{} puts and gets have no quiet, fence or barrier after them",
+                                state.derived.completion.unfinished
+                            ));
+                        }
+                    });
+                }
+                ui.separator();
+                let (shown, filtered): (Vec<_>, Vec<_>) =
+                    stats::by_call_site(data, start, end, |e| {
+                        state.annotations.duration_filter.keeps(e.raw.duration_sec)
+                    })
+                    .into_iter()
+                    .partition(|f| {
+                        state
+                            .annotations
+                            .function_shown(state.grouping, &f.function)
+                    });
+                if !filtered.is_empty() {
+                    ui.small(format!(
+                        "{} functions left out by the filters, see Legend",
+                        filtered.len()
+                    ));
+                }
+                for function in shown {
+                    let header = format!(
+                        "{}  {} calls, {}, {}",
+                        function.function,
+                        function.calls,
+                        units::duration(function.total_time),
+                        units::bytes(function.bytes)
+                    );
+                    egui::CollapsingHeader::new(header)
+                        .id_salt(("stats_fn", &function.function))
+                        .show(ui, |ui| {
+                            for site in &function.sites {
+                                let place = symbols::parse_address(&site.site)
+                                    .and_then(|a| state.symbolizer.resolve(a))
+                                    .map_or_else(String::new, |p| format!(" {p}"));
+                                let name = if site.site.is_empty() {
+                                    "(no stack)"
+                                } else {
+                                    &site.site
+                                };
+                                let header = format!(
+                                    "{name}{place}  {} calls, {}, {}",
+                                    site.calls,
+                                    units::duration(site.total_time),
+                                    units::bytes(site.bytes)
+                                );
+                                egui::CollapsingHeader::new(header)
+                                    .id_salt(("stats_site", &function.function, &site.site))
+                                    .show(ui, |ui| {
+                                        for i in site.events.iter().take(MAX_SITE_EVENTS) {
+                                            let e = data.event(*i);
+                                            let text = format!(
+                                                "PE {} at {:.6}s for {}",
+                                                e.source_pe,
+                                                e.raw.time,
+                                                units::duration(e.raw.duration_sec)
+                                            );
+                                            if ui.link(text).clicked() {
+                                                jump = Some((e.raw.time, e.source_pe));
+                                            }
+                                        }
+                                        if site.events.len() > MAX_SITE_EVENTS {
+                                            ui.label(format!(
+                                                "... {} more",
+                                                site.events.len() - MAX_SITE_EVENTS
+                                            ));
+                                        }
+                                    });
+                            }
+                        });
+                }
+            });
+        match jump {
+            Some((t, pe)) => vec![ViewCommand::JumpTo(t), ViewCommand::SelectPe(Some(pe))],
+            None => Vec::new(),
+        }
+    }
+}
+
+// details of the pinned event
+#[derive(Default)]
+pub struct Inspector;
+
+impl View for Inspector {
+    fn name(&self) -> &str {
+        Pane::Inspector.title()
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, data: &ProfileData, state: &ViewState) -> Vec<ViewCommand> {
+        let Some(idx) = state.pinned_event else {
+            return Vec::new();
+        };
+        let Some(e) = data.events.get(idx) else {
+            return vec![ViewCommand::Unpin];
+        };
+        ui_event_details(
+            ui,
+            data,
+            state,
+            &e,
+            state.derived.anomaly_sigma.get(&idx).copied(),
+        );
+        ui.small(format!("event #{idx}"))
+            .on_hover_text("ctrl+G and #index comes back here");
+        ui.separator();
+        let (copy, jump, unpin) = ui
+            .horizontal(|ui| {
+                (
+                    ui.button("Copy details").clicked(),
+                    ui.button("Go to").clicked(),
+                    ui.button("Unpin").clicked(),
+                )
+            })
+            .inner;
+        let mut commands = Vec::new();
+        if copy {
+            ui.ctx().copy_text(event_details_text(data, state, &e));
+            commands.push(ViewCommand::Status("copied event details".to_string()));
+        }
+        if jump {
+            commands.push(ViewCommand::JumpTo(e.raw.time));
+            commands.push(ViewCommand::SelectPe(Some(e.source_pe)));
+        }
+        if unpin {
+            commands.push(ViewCommand::Unpin);
+        }
+        commands
+    }
+}
+
+// function, place, timing, bytes and stacks of one event, shared by the
+// hover tooltip and the pinned details window
+pub fn ui_event_details(
+    ui: &mut egui::Ui,
+    data: &ProfileData,
+    state: &ViewState,
+    e: &Event,
+    sigma: Option<f64>,
+) {
+    ui.strong(e.raw.function);
+    let group = state.grouping.group(e.raw.function);
+    if group != e.raw.function {
+        ui.small(format!("group {group}"));
+    }
+    if let Some(hostname) = data.pe_hostnames.get(&e.source_pe) {
+        ui.small(format!(
+            "{} on {hostname}",
+            state.pe_labels.name(e.source_pe)
+        ));
+    }
+    if let Some(run) = data.runs.get(e.run as usize) {
+        ui.small(format!(
+            "PE {} of {}",
+            e.source_pe - run.first_pe,
+            run.dir.display()
+        ));
+    }
+    ui.label(format!("Time: {}", units::duration(e.raw.duration_sec)));
+    if let Some(sigma) = sigma {
+        ui.colored_label(
+            Color32::from_rgb(255, 140, 0),
+            format!("{sigma:.1}σ slower than usual"),
+        );
+    }
+    let total_bytes = e.raw.bytes_rx + e.raw.bytes_tx;
+    if total_bytes > 0 {
+        if e.raw.bytes_rx > 0 && e.raw.bytes_tx > 0 {
+            ui.label(format!(
+                "Data: {} (RX: {}, TX: {})",
+                units::bytes(total_bytes),
+                units::bytes(e.raw.bytes_rx),
+                units::bytes(e.raw.bytes_tx)
+            ));
+        } else if e.raw.bytes_rx > 0 {
+            ui.label(format!("Data: {} (RX)", units::bytes(e.raw.bytes_rx)));
+        } else {
+            ui.label(format!("Data: {} (TX)", units::bytes(e.raw.bytes_tx)));
+        }
+
+        if e.raw.duration_sec > 0.0 {
+            let rate = total_bytes as f64 / e.raw.duration_sec;
+            ui.label(format!("BW: {}", units::rate(rate)));
+        }
+    }
+
+    if let Some(trace) = e.raw.symboltrace
+        && !trace.is_empty()
+    {
+        ui.separator();
+        ui.label(egui::RichText::new("Call Stack:").strong());
+        for line in trace.split('|') {
+            if !line.trim().is_empty() {
+                let frame = if state.demangle_frames {
+                    symbols::demangle(line)
+                } else {
+                    line.to_string()
+                };
+                ui.label(egui::RichText::new(frame).small());
+            }
+        }
+    }
+
+    if !state.symbolizer.is_empty() {
+        let resolved: Vec<String> = e
+            .raw
+            .stacktrace
+            .split('|')
+            .filter_map(|frame| {
+                let address = symbols::parse_address(frame)?;
+                Some(match state.symbolizer.resolve(address) {
+                    Some(place) => format!("{:#x} {place}", address),
+                    None => format!("{:#x} ??", address),
+                })
+            })
+            .collect();
+        if !resolved.is_empty() {
+            ui.separator();
+            ui.label(egui::RichText::new("Source:").strong());
+            for line in resolved {
+                ui.label(egui::RichText::new(line).small().monospace());
+            }
+        }
+    }
+}
+
+// the full record as text, for pasting into bug reports
+pub fn event_details_text(data: &ProfileData, state: &ViewState, e: &Event) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "function: {}", e.raw.function);
+    let group = state.grouping.group(e.raw.function);
+    if group != e.raw.function {
+        let _ = writeln!(out, "group: {group}");
+    }
+    let host = data
+        .pe_hostnames
+        .get(&e.source_pe)
+        .map_or("?", String::as_str);
+    let _ = writeln!(out, "pe: {} ({host})", e.source_pe);
+    if let Some(run) = data.runs.get(e.run as usize) {
+        let _ = writeln!(out, "run: {}", run.dir.display());
+    }
+    if let Some(context) = e.context {
+        let _ = writeln!(out, "context: {context}");
+    }
+    let _ = writeln!(out, "time: {:.9}s", e.raw.time);
+    let _ = writeln!(out, "duration: {:.9}s", e.raw.duration_sec);
+    let _ = writeln!(out, "target_pe: {}", e.raw.target_pe);
+    let _ = writeln!(out, "bytes_rx: {}", e.raw.bytes_rx);
+    let _ = writeln!(out, "bytes_tx: {}", e.raw.bytes_tx);
+    if let Some(extra) = e.raw.extra.filter(|x| !x.is_empty()) {
+        let _ = writeln!(out, "extra: {extra}");
+    }
+    let _ = writeln!(out, "stacktrace:");
+    for frame in e.raw.stacktrace.split('|').filter(|f| !f.trim().is_empty()) {
+        let place = symbols::parse_address(frame)
+            .and_then(|a| state.symbolizer.resolve(a))
+            .unwrap_or_default();
+        let _ = writeln!(out, "  {} {place}", frame.trim());
+    }
+    if let Some(trace) = e.raw.symboltrace.filter(|t| !t.is_empty()) {
+        let _ = writeln!(out, "symboltrace:");
+        for frame in trace.split('|').filter(|f| !f.trim().is_empty()) {
+            let _ = writeln!(out, "  {}", symbols::demangle(frame));
+        }
+    }
+    out
+}
@@ -0,0 +1,145 @@
+use egui::Color32;
+
+use crate::data::ProfileData;
+use crate::phases::{self, PhaseDetection, PhaseSource, PhaseStats};
+use crate::units;
+use crate::views::{ViewCommand, ViewState};
+
+// the trace split into iterations, by barrier, period or bookmarks, with
+// what each one did
+pub struct Phases {
+    pub open: bool,
+    detection: Option<PhaseDetection>,
+    stats: Vec<PhaseStats>,
+    // phases drawn over the timeline
+    overlay: bool,
+}
+
+impl Default for Phases {
+    fn default() -> Self {
+        Self {
+            open: false,
+            detection: None,
+            stats: Vec::new(),
+            overlay: true,
+        }
+    }
+}
+
+impl Phases {
+    // the phases to draw over the timeline, if any
+    pub fn overlay(&self) -> Option<&PhaseDetection> {
+        self.detection.as_ref().filter(|_| self.overlay)
+    }
+
+    pub fn clear(&mut self) {
+        self.detection = None;
+        self.stats.clear();
+    }
+
+    fn set(&mut self, detection: PhaseDetection, data: &ProfileData) {
+        self.stats = detection
+            .phases
+            .iter()
+            .map(|p| phases::phase_stats(data, p))
+            .collect();
+        self.detection = Some(detection);
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        let mut detection = None;
+        egui::Window::new("Phases")
+            .open(&mut self.open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Detect").clicked() {
+                        detection = Some(phases::detect_phases(data));
+                    }
+                    if ui.button("From bookmarks").clicked() {
+                        detection = Some(phases::from_bookmarks(&state.annotations.bookmarks));
+                    }
+                    ui.checkbox(&mut self.overlay, "Overlay on timeline");
+                });
+                let Some(detection) = &self.detection else {
+                    return;
+                };
+                match &detection.source {
+                    PhaseSource::Barrier(function) => {
+                        ui.label(format!("Delimited by {function}"));
+                    }
+                    PhaseSource::Autocorrelation { period } => {
+                        ui.label(format!("Autocorrelation period: {:.6}s", period));
+                    }
+                    PhaseSource::Bookmarks => {
+                        ui.label("Delimited by bookmarks");
+                    }
+                }
+
+                // anything noticeably slower than the typical phase is flagged
+                let mut durations: Vec<f64> = self.stats.iter().map(|s| s.duration).collect();
+                durations.sort_by(f64::total_cmp);
+                let median = durations.get(durations.len() / 2).copied().unwrap_or(0.0);
+
+                ui.separator();
+                egui::Grid::new("phases_grid").striped(true).show(ui, |ui| {
+                    ui.strong("#");
+                    ui.strong("Start");
+                    ui.strong("Duration");
+                    ui.strong("Events");
+                    ui.strong("TX");
+                    ui.strong("RX");
+                    ui.strong("Top functions");
+                    ui.strong("Slowest PE");
+                    ui.end_row();
+                    for (i, (phase, stats)) in detection.phases.iter().zip(&self.stats).enumerate()
+                    {
+                        if ui.link(format!("{}", i + 1)).clicked() {
+                            commands.push(ViewCommand::JumpTo(phase.start));
+                        }
+                        ui.label(format!("{:.6}s", phase.start));
+                        let duration = egui::RichText::new(units::duration(stats.duration));
+                        if median > 0.0 && stats.duration > median * 1.1 {
+                            ui.label(duration.color(Color32::LIGHT_RED))
+                                .on_hover_text(format!(
+                                    "{:+.1}% vs median",
+                                    (stats.duration / median - 1.0) * 100.0
+                                ));
+                        } else {
+                            ui.label(duration);
+                        }
+                        ui.label(format!("{}", stats.events));
+                        ui.label(units::bytes(stats.bytes_tx));
+                        ui.label(units::bytes(stats.bytes_rx));
+                        let top = stats
+                            .top_functions
+                            .iter()
+                            .map(|(f, t)| format!("{f} ({})", units::duration(*t)))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        ui.label(top);
+                        match stats.slowest_pe {
+                            Some(pe) => ui.label(format!("PE {pe}")),
+                            None => ui.label("-"),
+                        };
+                        ui.end_row();
+                    }
+                });
+            });
+        match detection {
+            Some(Some(detection)) => self.set(detection, data),
+            Some(None) => {
+                self.clear();
+                commands.push(ViewCommand::Status("no phases found".to_string()));
+            }
+            None => {}
+        }
+        commands
+    }
+}
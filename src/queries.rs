@@ -0,0 +1,369 @@
+use egui::Color32;
+use std::time::Instant;
+
+use crate::data::ProfileData;
+use crate::script;
+use crate::sql::{self, QueryResult};
+use crate::units;
+use crate::views::{CROSSHAIR, ViewCommand, ViewState};
+
+// rows of a query result listed in the sql window, all of them are plotted
+const SQL_TABLE_ROWS: usize = 1000;
+// what the sql window starts with
+const SQL_EXAMPLE: &str = "SELECT function, count(*) AS calls, sum(duration) AS total\n\
+                           FROM events\n\
+                           GROUP BY function\n\
+                           ORDER BY total DESC";
+// what the script window starts with
+const SCRIPT_EXAMPLE: &str = r#"// the slowest call of each function in the current range
+for f in functions() {
+    let worst = ();
+    for e in events(range_start(), range_end()) {
+        if e.function == f && (worst == () || e.duration > worst.duration) {
+            worst = e;
+        }
+    }
+    if worst != () {
+        print(`${f}: ${worst.duration}s on PE ${worst.pe}`);
+    }
+}
+"#;
+
+// sql console over the events, with the database behind it once a query
+// ran against this trace
+pub struct Sql {
+    pub open: bool,
+    source: String,
+    database: Option<sql::Database>,
+    error: Option<String>,
+    result: Option<QueryResult>,
+    // seconds the last query took
+    elapsed: f64,
+    // only events starting in the analysis range
+    in_range: bool,
+    // result columns plotted against each other, None for no plot
+    plot: Option<(usize, usize)>,
+}
+
+impl Default for Sql {
+    fn default() -> Self {
+        Self {
+            open: false,
+            source: SQL_EXAMPLE.to_string(),
+            database: None,
+            error: None,
+            result: None,
+            elapsed: 0.0,
+            in_range: false,
+            plot: None,
+        }
+    }
+}
+
+impl Sql {
+    // drops the database and the last result, which show the old events
+    pub fn invalidate(&mut self) {
+        self.database = None;
+        self.result = None;
+    }
+
+    fn run(&mut self, data: &ProfileData, range: (f64, f64)) {
+        if self.database.is_none() {
+            match sql::Database::new(data) {
+                Ok(database) => self.database = Some(database),
+                Err(e) => {
+                    self.error = Some(format!("{:#}", e));
+                    return;
+                }
+            }
+        }
+        let Some(database) = &self.database else {
+            return;
+        };
+        let range = self.in_range.then_some(range);
+        let started = Instant::now();
+        let result = match database.run(&self.source, range) {
+            Ok(result) => result,
+            Err(e) => {
+                self.error = Some(format!("{:#}", e));
+                return;
+            }
+        };
+        self.elapsed = started.elapsed().as_secs_f64();
+        // time against the first other column that has a number, if any
+        let numeric = |c: usize| {
+            result
+                .rows
+                .iter()
+                .any(|row| matches!(row[c], sql::Value::Number(_)))
+        };
+        let x = result.columns.iter().position(|c| c == "time").unwrap_or(0);
+        self.plot = (0..result.columns.len())
+            .find(|&c| c != x && numeric(c))
+            .filter(|_| numeric(x))
+            .map(|y| (x, y));
+        self.error = None;
+        self.result = Some(result);
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        let mut open = self.open;
+        let mut run = false;
+        let mut jump_event = None;
+        let range_name = state.analysis_range_name;
+        egui::Window::new("SQL")
+            .open(&mut open)
+            .default_width(600.0)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    run = ui
+                        .button("Run")
+                        .on_hover_text("Ctrl+Enter in the editor")
+                        .clicked();
+                    if ui.button("Example").clicked() {
+                        self.source = SQL_EXAMPLE.to_string();
+                    }
+                    ui.checkbox(&mut self.in_range, format!("Only the {range_name}"));
+                });
+                let editor = ui.add(
+                    egui::TextEdit::multiline(&mut self.source)
+                        .code_editor()
+                        .desired_rows(5)
+                        .desired_width(f32::INFINITY),
+                );
+                run |= editor.has_focus()
+                    && ui.input(|i| i.modifiers.command && i.key_pressed(egui::Key::Enter));
+                ui.label(
+                    egui::RichText::new(format!(
+                        "sqlite, one table, events: {} and a column per Extra counter, \
+                         extra_field(extra, 'key') for the rest",
+                        sql::COLUMNS.join(" ")
+                    ))
+                    .small()
+                    .weak(),
+                );
+                if let Some(err) = &self.error {
+                    ui.colored_label(Color32::LIGHT_RED, err);
+                }
+                let Some(result) = &self.result else {
+                    return;
+                };
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} rows{} in {}",
+                        result.rows.len(),
+                        if result.truncated { " (cut off)" } else { "" },
+                        units::duration(self.elapsed)
+                    ));
+                    ui.separator();
+                    let mut plot = self.plot.is_some();
+                    if ui.checkbox(&mut plot, "Plot").changed() {
+                        self.plot = plot.then_some((0, result.columns.len().min(2) - 1));
+                    }
+                    if let Some((x, y)) = &mut self.plot {
+                        for (axis, column) in [("x", x), ("y", y)] {
+                            egui::ComboBox::from_id_salt(("sql_plot", axis))
+                                .selected_text(format!("{axis}: {}", result.columns[*column]))
+                                .show_ui(ui, |ui| {
+                                    for (c, name) in result.columns.iter().enumerate() {
+                                        ui.selectable_value(column, c, name);
+                                    }
+                                });
+                        }
+                    }
+                });
+                if let Some((x, y)) = self.plot {
+                    let mut points: Vec<[f64; 2]> = result
+                        .rows
+                        .iter()
+                        .filter_map(|row| Some([row[x].as_number()?, row[y].as_number()?]))
+                        .collect();
+                    points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+                    let over_time = result.columns[x] == "time";
+                    let name = result.columns[y].clone();
+                    let clicked = egui_plot::Plot::new("sql_plot")
+                        .height(180.0)
+                        .x_axis_label(result.columns[x].clone())
+                        .show(ui, |plot_ui| {
+                            plot_ui.line(egui_plot::Line::new(name.clone(), points.clone()));
+                            plot_ui.points(egui_plot::Points::new(name, points).radius(2.0));
+                            if over_time {
+                                if let Some(t) = state.hover {
+                                    plot_ui
+                                        .vline(egui_plot::VLine::new("hover", t).color(CROSSHAIR));
+                                }
+                                if plot_ui.response().hovered()
+                                    && let Some(point) = plot_ui.pointer_coordinate()
+                                {
+                                    commands.push(ViewCommand::Hover(point.x));
+                                }
+                            }
+                            plot_ui
+                                .response()
+                                .clicked()
+                                .then(|| plot_ui.pointer_coordinate())
+                                .flatten()
+                        })
+                        .inner;
+                    // over time, a click moves there
+                    if over_time && let Some(point) = clicked {
+                        commands.push(ViewCommand::JumpTo(point.x));
+                    }
+                }
+                egui::Grid::new("sql_grid").striped(true).show(ui, |ui| {
+                    for column in &result.columns {
+                        ui.strong(column);
+                    }
+                    ui.end_row();
+                    for (row, event) in result.rows.iter().zip(&result.events).take(SQL_TABLE_ROWS)
+                    {
+                        for (c, value) in row.iter().enumerate() {
+                            let text = value.to_string();
+                            match event {
+                                Some(i) if c == 0 => {
+                                    if ui.link(text).on_hover_text("Jump to this event").clicked() {
+                                        jump_event = Some(*i);
+                                    }
+                                }
+                                _ => {
+                                    ui.label(text);
+                                }
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+                if result.rows.len() > SQL_TABLE_ROWS {
+                    ui.small(format!(
+                        "{} more not listed",
+                        result.rows.len() - SQL_TABLE_ROWS
+                    ));
+                }
+            });
+        self.open = open;
+        if run {
+            self.run(data, state.analysis_range);
+        }
+        if let Some(i) = jump_event {
+            let e = data.event(i).raw;
+            let pad = e.duration_sec.max(1e-6) * 2.0;
+            commands.push(ViewCommand::PinEvent(i, None));
+            commands.push(ViewCommand::SetView(
+                e.time - pad,
+                e.time + e.duration_sec + pad,
+            ));
+        }
+        commands
+    }
+}
+
+// rhai source run against the trace, and what it printed last time
+pub struct Script {
+    pub open: bool,
+    pub source: String,
+    outcome: Option<script::Outcome>,
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        Self {
+            open: false,
+            source: SCRIPT_EXAMPLE.to_string(),
+            outcome: None,
+        }
+    }
+}
+
+impl Script {
+    // runs the script, returning what it asked for: bookmarks, hidden or
+    // solo functions, a selection, a zoom
+    pub fn run(&mut self, data: &ProfileData, range: (f64, f64)) -> Vec<ViewCommand> {
+        let outcome = script::run(data, range, &self.source);
+        let commands = outcome
+            .actions
+            .iter()
+            .map(|action| match action {
+                script::Action::Bookmark { name, time, pe } => {
+                    ViewCommand::AddBookmark(name.clone(), *time, *pe)
+                }
+                script::Action::Hide(function) => ViewCommand::HideFunction(function.clone()),
+                script::Action::Show(function) => ViewCommand::ShowFunction(function.clone()),
+                script::Action::Solo(function) => ViewCommand::Solo(function.clone()),
+                script::Action::Select(start, end) => ViewCommand::Select(*start, *end),
+                script::Action::Zoom(start, end) => ViewCommand::SetView(*start, *end),
+            })
+            .collect();
+        self.outcome = Some(outcome);
+        commands
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut open = self.open;
+        let mut run = false;
+        egui::Window::new("Script")
+            .open(&mut open)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    run = ui
+                        .button("Run")
+                        .on_hover_text("Ctrl+Enter in the editor")
+                        .clicked();
+                    if ui.button("Example").clicked() {
+                        self.source = SCRIPT_EXAMPLE.to_string();
+                    }
+                    ui.small("rhai, see the README for what a script can call");
+                });
+                egui::ScrollArea::vertical()
+                    .id_salt("script_source")
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        let editor = ui.add(
+                            egui::TextEdit::multiline(&mut self.source)
+                                .code_editor()
+                                .desired_rows(14)
+                                .desired_width(f32::INFINITY),
+                        );
+                        run |= editor.has_focus()
+                            && ui.input(|i| i.modifiers.command && i.key_pressed(egui::Key::Enter));
+                    });
+                let Some(outcome) = &self.outcome else {
+                    return;
+                };
+                ui.separator();
+                if let Some(error) = &outcome.error {
+                    ui.colored_label(Color32::LIGHT_RED, error);
+                }
+                if !outcome.actions.is_empty() {
+                    ui.small(format!("{} actions applied", outcome.actions.len()));
+                }
+                egui::ScrollArea::vertical()
+                    .id_salt("script_output")
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::Label::new(egui::RichText::new(&outcome.output).monospace())
+                                .wrap(),
+                        );
+                    });
+            });
+        self.open = open;
+        if run {
+            return self.run(data, state.analysis_range);
+        }
+        Vec::new()
+    }
+}
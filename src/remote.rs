@@ -0,0 +1,272 @@
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{Receiver, channel};
+
+use crate::{data, manifest};
+
+// where the pperf files of a trace that isn't on this machine are
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Remote {
+    // a directory listing served over http(s), e.g. `python -m http.server`
+    // or an nginx autoindex
+    Http { url: String },
+    // a directory on a host reachable with `ssh`, using its config and keys
+    Ssh { host: String, dir: String },
+}
+
+impl Remote {
+    // http(s)://server/dir/, or ssh://[user@]host/path (sftp:// too). None
+    // for anything else, which is taken as a local path
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if s.starts_with("http://") || s.starts_with("https://") {
+            let url = if s.ends_with('/') {
+                s.to_string()
+            } else {
+                format!("{s}/")
+            };
+            return Some(Remote::Http { url });
+        }
+        let rest = s
+            .strip_prefix("ssh://")
+            .or_else(|| s.strip_prefix("sftp://"))?;
+        let (host, dir) = rest.split_once('/').unwrap_or((rest, ""));
+        if host.is_empty() {
+            return None;
+        }
+        // ssh://host/~/run is relative to the home directory, like scp
+        let dir = match dir.strip_prefix("~/") {
+            Some(home) => home.to_string(),
+            None if dir.is_empty() || dir == "~" => ".".to_string(),
+            None => format!("/{dir}"),
+        };
+        Some(Remote::Ssh {
+            host: host.to_string(),
+            dir,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Remote::Http { url } => url.clone(),
+            Remote::Ssh { host, dir } => format!("{host}:{dir}"),
+        }
+    }
+
+    // one directory per source under the temp dir, kept between runs so
+    // reopening a trace only fetches what changed
+    pub fn cache_dir(&self) -> PathBuf {
+        let slug: String = self
+            .name()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        std::env::temp_dir().join("csvpshmem-remote").join(slug)
+    }
+
+    // the pperf files there and the manifest, with their size when the
+    // listing gives it
+    fn list(&self) -> Result<Vec<(String, Option<u64>)>> {
+        let mut files = match self {
+            Remote::Http { url } => {
+                let index = ureq::get(url)
+                    .call()
+                    .with_context(|| format!("listing {url}"))?
+                    .body_mut()
+                    .read_to_string()?;
+                let href = Regex::new(r#"href="([^"?#]+)""#).unwrap();
+                href.captures_iter(&index)
+                    .filter_map(|c| c[1].rsplit('/').next().map(str::to_string))
+                    .filter(|name| data::is_trace_file(Path::new(name)))
+                    .map(|name| (name, None))
+                    .collect::<Vec<_>>()
+            }
+            Remote::Ssh { host, dir } => {
+                let listing = ssh(
+                    host,
+                    &format!(
+                        "find {} -maxdepth 1 \\( -name 'pperf.*.csv' -o -name 'pperf.meta.json' \\) \
+                         -printf '%s %f\\n'",
+                        quote(dir)
+                    ),
+                )
+                .output()
+                .context("running ssh")?;
+                if !listing.status.success() {
+                    bail!(
+                        "listing {}: {}",
+                        self.name(),
+                        String::from_utf8_lossy(&listing.stderr).trim()
+                    );
+                }
+                String::from_utf8_lossy(&listing.stdout)
+                    .lines()
+                    .filter_map(|line| line.split_once(' '))
+                    .filter(|(_, name)| data::is_trace_file(Path::new(name)))
+                    .map(|(size, name)| (name.to_string(), size.parse().ok()))
+                    .collect()
+            }
+        };
+        files.sort();
+        files.dedup();
+        if !files
+            .iter()
+            .any(|(name, _)| data::is_pperf_file(Path::new(name)))
+        {
+            bail!("no pperf.*.csv files at {}", self.name());
+        }
+        Ok(files)
+    }
+
+    // size of a file as the server reports it, for telling whether the
+    // cached copy is current
+    fn size(&self, name: &str) -> Option<u64> {
+        match self {
+            Remote::Http { url } => ureq::head(&format!("{url}{name}"))
+                .call()
+                .ok()?
+                .headers()
+                .get("content-length")?
+                .to_str()
+                .ok()?
+                .parse()
+                .ok(),
+            Remote::Ssh { .. } => None,
+        }
+    }
+
+    // downloads next to `dest` and renames, so an interrupted fetch never
+    // leaves a truncated file that looks cached
+    fn fetch(&self, name: &str, dest: &Path) -> Result<()> {
+        let part = dest.with_extension("csv.part");
+        match self {
+            Remote::Http { url } => {
+                let url = format!("{url}{name}");
+                let mut response = ureq::get(&url)
+                    .call()
+                    .with_context(|| format!("fetching {url}"))?;
+                let mut file = fs::File::create(&part)?;
+                io::copy(&mut response.body_mut().as_reader(), &mut file)?;
+            }
+            Remote::Ssh { host, dir } => {
+                let file = fs::File::create(&part)?;
+                let status = ssh(host, &format!("cat {}", quote(&format!("{dir}/{name}"))))
+                    .stdout(file)
+                    .status()
+                    .context("running ssh")?;
+                if !status.success() {
+                    bail!("fetching {name} from {host} failed");
+                }
+            }
+        }
+        fs::rename(&part, dest)?;
+        Ok(())
+    }
+}
+
+fn ssh(host: &str, command: &str) -> Command {
+    let mut ssh = Command::new("ssh");
+    // a password prompt would hang with no terminal to type it in
+    ssh.args(["-o", "BatchMode=yes", host, command])
+        .stdin(Stdio::null());
+    ssh
+}
+
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+pub enum Progress {
+    Listed(usize),
+    // false when the cached copy was current
+    Fetched { downloaded: bool },
+    Done(Result<PathBuf>),
+}
+
+// copies a remote trace into its cache directory on a background thread
+pub struct RemoteFetch {
+    pub remote: Remote,
+    rx: Receiver<Progress>,
+    pub total: usize,
+    pub done: usize,
+    pub downloaded: usize,
+}
+
+impl RemoteFetch {
+    // `ctx` is woken up after every file so progress shows without input
+    pub fn start(remote: Remote, ctx: egui::Context) -> Self {
+        let (tx, rx) = channel();
+        let source = remote.clone();
+        std::thread::spawn(move || {
+            let result = (|| {
+                let files = source.list()?;
+                let _ = tx.send(Progress::Listed(files.len()));
+                ctx.request_repaint();
+                let dir = source.cache_dir();
+                fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+                // files gone from the server would otherwise still load
+                let manifest = dir.join(manifest::FILE_NAME);
+                let old_files = data::pperf_files(&dir)?;
+                for old in old_files
+                    .into_iter()
+                    .chain(manifest.is_file().then_some(manifest))
+                {
+                    let name = old.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                    if !files.iter().any(|(f, _)| f == name) {
+                        fs::remove_file(&old)?;
+                    }
+                }
+                for (name, size) in files {
+                    let dest = dir.join(&name);
+                    let cached = fs::metadata(&dest).ok().map(|m| m.len());
+                    let current = cached.is_some()
+                        && size
+                            .or_else(|| source.size(&name))
+                            .is_some_and(|s| Some(s) == cached);
+                    if !current {
+                        source.fetch(&name, &dest)?;
+                    }
+                    let _ = tx.send(Progress::Fetched {
+                        downloaded: !current,
+                    });
+                    ctx.request_repaint();
+                }
+                Ok(dir)
+            })();
+            let _ = tx.send(Progress::Done(result));
+            ctx.request_repaint();
+        });
+        Self {
+            remote,
+            rx,
+            total: 0,
+            done: 0,
+            downloaded: 0,
+        }
+    }
+
+    // the cache directory once every file is there
+    pub fn poll(&mut self) -> Option<Result<PathBuf>> {
+        while let Ok(progress) = self.rx.try_recv() {
+            match progress {
+                Progress::Listed(total) => self.total = total,
+                Progress::Fetched { downloaded } => {
+                    self.done += 1;
+                    self.downloaded += downloaded as usize;
+                }
+                Progress::Done(result) => return Some(result),
+            }
+        }
+        None
+    }
+}
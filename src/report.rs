@@ -0,0 +1,287 @@
+use anyhow::{Context, Result};
+use egui::Color32;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::anomalies::Anomaly;
+use crate::data::{Event, ProfileData};
+use crate::export::escape;
+use crate::imbalance::{self, LoadMetric};
+use crate::labels::PeLabels;
+use crate::prefix::ByteSums;
+use crate::stats;
+use crate::units;
+
+const TOP_FUNCTIONS: usize = 15;
+const TOP_ANOMALIES: usize = 25;
+const CHART_WIDTH: f64 = 640.0;
+const BAR_HEIGHT: f64 = 18.0;
+const HEATMAP_SIZE: f64 = 480.0;
+
+// everything the report is drawn from, as the viewer currently has it
+pub struct ReportInput<'a> {
+    pub data: &'a ProfileData,
+    pub title: &'a str,
+    // top functions and imbalance cover this
+    pub range: (f64, f64),
+    // the heatmap is the bandwidth window around the cursor
+    pub snapshot: (f64, f64),
+    pub sums: &'a ByteSums,
+    pub anomalies: &'a [Anomaly],
+    pub labels: &'a PeLabels,
+    pub function_colors: &'a HashMap<String, Color32>,
+    // the timeline's filters, which top functions follow
+    pub keep: &'a dyn Fn(&Event) -> bool,
+}
+
+// a single html file with inline svg charts, no scripts or external files,
+// so it can be mailed around and opened anywhere
+pub fn write(input: &ReportInput, path: &Path) -> Result<PathBuf> {
+    let path = path.with_extension("html");
+    fs::write(&path, html(input)).with_context(|| format!("writing {}", path.display()))?;
+    Ok(path)
+}
+
+fn html(input: &ReportInput) -> String {
+    let data = input.data;
+    let (start, end) = input.range;
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title>\n\
+         <style>{STYLE}</style></head><body>\n<h1>{}</h1>\n",
+        escape(input.title),
+        escape(input.title)
+    );
+
+    let hosts: std::collections::BTreeSet<&String> = data.pe_hostnames.values().collect();
+    out += "<table>\n";
+    for (name, value) in [
+        ("PEs", data.pe_count.to_string()),
+        ("Hosts", hosts.len().to_string()),
+        ("Events", data.events.len().to_string()),
+        (
+            "Trace",
+            format!(
+                "{:.6}s to {:.6}s ({})",
+                data.min_time,
+                data.max_time,
+                units::duration(data.max_time - data.min_time)
+            ),
+        ),
+        (
+            "Report range",
+            format!(
+                "{start:.6}s to {end:.6}s ({})",
+                units::duration(end - start)
+            ),
+        ),
+    ] {
+        let _ = writeln!(out, "<tr><th>{name}</th><td>{}</td></tr>", escape(&value));
+    }
+    out += "</table>\n";
+
+    top_functions(&mut out, input);
+    load_imbalance(&mut out, input);
+    heatmap(&mut out, input);
+    anomalies(&mut out, input);
+    out += "</body></html>\n";
+    out
+}
+
+fn top_functions(out: &mut String, input: &ReportInput) {
+    let (start, end) = input.range;
+    let functions = stats::by_call_site(input.data, start, end, input.keep);
+    out.push_str("<h2>Top functions</h2>\n");
+    if functions.is_empty() {
+        out.push_str("<p>No calls in range.</p>\n");
+        return;
+    }
+    let shown = &functions[..functions.len().min(TOP_FUNCTIONS)];
+    let max = shown[0].total_time.max(f64::EPSILON);
+    let bars: Vec<(String, f64, Color32, String)> = shown
+        .iter()
+        .map(|f| {
+            let color = input
+                .function_colors
+                .get(&f.function)
+                .copied()
+                .unwrap_or(Color32::GRAY);
+            (
+                f.function.clone(),
+                f.total_time / max,
+                color,
+                units::duration(f.total_time),
+            )
+        })
+        .collect();
+    bar_chart(out, &bars);
+    out.push_str("<table>\n<tr><th>Function</th><th>Calls</th><th>Time</th><th>Bytes</th></tr>\n");
+    for f in shown {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&f.function),
+            f.calls,
+            units::duration(f.total_time),
+            units::bytes(f.bytes)
+        );
+    }
+    out.push_str("</table>\n");
+}
+
+fn load_imbalance(out: &mut String, input: &ReportInput) {
+    let (start, end) = input.range;
+    let loads = imbalance::per_pe(input.data, start, end);
+    out.push_str("<h2>Load balance</h2>\n");
+    out.push_str(
+        "<table>\n<tr><th>Metric</th><th>Mean</th><th>Max</th><th>Max / mean</th><th>CV</th></tr>\n",
+    );
+    for metric in LoadMetric::ALL {
+        let values: Vec<f64> = loads.iter().map(|l| metric.of(l)).collect();
+        let stats = imbalance::imbalance(&values);
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.2}</td><td>{:.2}</td></tr>",
+            metric.label(),
+            metric.format(stats.mean),
+            metric.format(stats.max),
+            stats.max_over_mean,
+            stats.cv
+        );
+    }
+    out.push_str("</table>\n<h3>Time in calls per PE</h3>\n");
+    let max = loads
+        .iter()
+        .map(|l| l.time_in_calls)
+        .fold(f64::EPSILON, f64::max);
+    let bars: Vec<_> = loads
+        .iter()
+        .enumerate()
+        .map(|(pe, l)| {
+            (
+                input.labels.name(pe as u32),
+                l.time_in_calls / max,
+                Color32::from_rgb(90, 140, 200),
+                units::duration(l.time_in_calls),
+            )
+        })
+        .collect();
+    bar_chart(out, &bars);
+}
+
+// bytes between every pair of PEs in the bandwidth window, sender down the
+// side, receiver along the top, log shaded
+fn heatmap(out: &mut String, input: &ReportInput) {
+    let (start, end) = input.snapshot;
+    let _ = writeln!(
+        out,
+        "<h2>Communication at {:.6}s</h2>\n<p>Bytes from each PE (rows) to each PE \
+         (columns) between {start:.6}s and {end:.6}s.</p>",
+        (start + end) / 2.0
+    );
+    let comms = input.sums.comms(start, end, true, true);
+    let n = input.data.pe_count.max(1) as f64;
+    let max = comms.values().map(|(tx, rx)| tx + rx).max().unwrap_or(0);
+    if max == 0 {
+        out.push_str("<p>No traffic in the window.</p>\n");
+        return;
+    }
+    let cell = HEATMAP_SIZE / n;
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{HEATMAP_SIZE}\" height=\"{HEATMAP_SIZE}\">\
+         <rect width=\"100%\" height=\"100%\" fill=\"#f4f4f4\"/>"
+    );
+    let scale = (max as f64).ln_1p();
+    let mut cells: Vec<_> = comms.iter().collect();
+    cells.sort_by_key(|(edge, _)| **edge);
+    for ((src, dst), (tx, rx)) in cells {
+        let bytes = tx + rx;
+        let level = (bytes as f64).ln_1p() / scale;
+        let shade = (255.0 * (1.0 - level)) as u8;
+        let _ = writeln!(
+            out,
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{cell:.2}\" height=\"{cell:.2}\" \
+             fill=\"rgb(255,{shade},{shade})\"><title>{} -&gt; {}: {}</title></rect>",
+            *dst as f64 * cell,
+            *src as f64 * cell,
+            escape(&input.labels.name(*src)),
+            escape(&input.labels.name(*dst)),
+            units::bytes(bytes)
+        );
+    }
+    out.push_str("</svg>\n");
+}
+
+fn anomalies(out: &mut String, input: &ReportInput) {
+    let data = input.data;
+    out.push_str("<h2>Anomalies</h2>\n");
+    if input.anomalies.is_empty() {
+        out.push_str("<p>No call stands out from its function's usual duration.</p>\n");
+        return;
+    }
+    let _ = writeln!(
+        out,
+        "<p>{} calls far slower than usual for their function, worst first.</p>",
+        input.anomalies.len()
+    );
+    out.push_str(
+        "<table>\n<tr><th>Time</th><th>PE</th><th>Function</th><th>Duration</th><th>Sigma</th></tr>\n",
+    );
+    for a in input.anomalies.iter().take(TOP_ANOMALIES) {
+        let e = data.event(a.event);
+        let _ = writeln!(
+            out,
+            "<tr><td>{:.6}s</td><td>{}</td><td>{}</td><td>{}</td><td>{:.1}</td></tr>",
+            e.raw.time,
+            escape(&input.labels.name(e.source_pe)),
+            escape(e.raw.function),
+            units::duration(e.raw.duration_sec),
+            a.sigma
+        );
+    }
+    out.push_str("</table>\n");
+}
+
+// (label, 0..=1, color, value text) as horizontal bars
+fn bar_chart(out: &mut String, bars: &[(String, f64, Color32, String)]) {
+    let label_width = 200.0;
+    let height = bars.len() as f64 * BAR_HEIGHT;
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{CHART_WIDTH}\" height=\"{height}\" \
+         font-family=\"sans-serif\" font-size=\"11\">"
+    );
+    let room = CHART_WIDTH - label_width - 90.0;
+    for (i, (label, fraction, color, value)) in bars.iter().enumerate() {
+        let y = i as f64 * BAR_HEIGHT;
+        let width = (fraction * room).max(1.0);
+        let _ = writeln!(
+            out,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\
+             <rect x=\"{label_width}\" y=\"{:.1}\" width=\"{width:.1}\" height=\"{:.1}\" \
+             fill=\"rgb({},{},{})\"/>\
+             <text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+            label_width - 6.0,
+            y + BAR_HEIGHT - 5.0,
+            escape(label),
+            y + 2.0,
+            BAR_HEIGHT - 4.0,
+            color.r(),
+            color.g(),
+            color.b(),
+            label_width + width + 6.0,
+            y + BAR_HEIGHT - 5.0,
+            escape(value)
+        );
+    }
+    out.push_str("</svg>\n");
+}
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin: 0.5em 0 1em; }
+th, td { padding: 2px 10px; text-align: left; border-bottom: 1px solid #ddd; }
+h2 { margin-top: 1.5em; border-bottom: 2px solid #888; }";
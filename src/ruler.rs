@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+use crate::units;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeDisplay {
+    // trace timestamps as recorded
+    Absolute,
+    // seconds since the first event
+    Relative,
+    // time of day, needs an `epoch` in the data
+    WallClock,
+}
+
+pub struct Tick {
+    pub time: f64,
+    pub label: String,
+}
+
+// 1, 2 or 5 times a power of ten, at least `min_step`
+fn nice_step(min_step: f64) -> f64 {
+    let base = 10.0f64.powf(min_step.log10().floor());
+    if min_step / base < 2.0 {
+        base
+    } else if min_step / base < 5.0 {
+        base * 2.0
+    } else {
+        base * 5.0
+    }
+}
+
+// ticks roughly `spacing` pixels apart over [start, end]. `origin` is the
+// trace time shown as zero, `epoch` the unix time of trace time zero
+pub fn ticks(
+    start: f64,
+    end: f64,
+    width: f32,
+    spacing: f32,
+    display: TimeDisplay,
+    origin: f64,
+    epoch: Option<f64>,
+) -> Vec<Tick> {
+    if end <= start || width <= 0.0 {
+        return Vec::new();
+    }
+    let step = nice_step((end - start) * (spacing / width) as f64);
+    // unix times are too large to add sub-microsecond steps to, so only the
+    // fraction of the epoch goes into tick positions
+    let epoch_secs = epoch.map_or(0.0, f64::floor);
+    // ticks sit on round values of whatever is being displayed
+    let zero = match (display, epoch) {
+        (TimeDisplay::Relative, _) => origin,
+        (TimeDisplay::WallClock, Some(epoch)) => epoch_secs - epoch,
+        _ => 0.0,
+    };
+    let first = ((start - zero) / step).ceil() as i64;
+    let last = ((end - zero) / step).floor() as i64;
+    (first..=last)
+        .map(|k| {
+            let value = k as f64 * step;
+            let label = match (display, epoch) {
+                (TimeDisplay::Relative, _) => format!("+{}", units::time(value, step)),
+                (TimeDisplay::WallClock, Some(_)) => {
+                    format_time_of_day(epoch_secs.rem_euclid(86400.0) + value, step)
+                }
+                _ => units::time(value, step),
+            };
+            Tick {
+                time: zero + value,
+                label,
+            }
+        })
+        .collect()
+}
+
+// HH:MM:SS(.fraction) UTC of a unix timestamp
+pub fn format_time_of_day(unix: f64, step: f64) -> String {
+    let places = units::decimals(step, 1.0);
+    // rounded to the digits shown before splitting, so .9996 carries into
+    // the seconds (and on past midnight) instead of printing as 1.000
+    let scale = 10u64.pow(places as u32);
+    let ticks = (unix.rem_euclid(86400.0) * scale as f64).round() as u64 % (86400 * scale);
+    let (whole, fraction) = (ticks / scale, ticks % scale);
+    let (h, m, s) = (whole / 3600, whole / 60 % 60, whole % 60);
+    if places == 0 {
+        return format!("{h:02}:{m:02}:{s:02}");
+    }
+    format!("{h:02}:{m:02}:{s:02}.{fraction:0places$}")
+}
+
+// YYYY-MM-DD UTC of a unix timestamp
+pub fn format_date(unix: f64) -> String {
+    // days to civil date, from Howard Hinnant's date algorithms
+    let z = (unix / 86400.0).floor() as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_of_day() {
+        assert_eq!(format_time_of_day(45296.25, 0.01), "12:34:56.25");
+        assert_eq!(format_time_of_day(45296.25, 1.0), "12:34:56");
+        // rounding carries into the seconds, minutes and hours
+        assert_eq!(format_time_of_day(45296.9996, 0.001), "12:34:57.000");
+        assert_eq!(format_time_of_day(3599.9996, 0.001), "01:00:00.000");
+        assert_eq!(format_time_of_day(45296.6, 1.0), "12:34:57");
+        // and past midnight into the next day
+        assert_eq!(format_time_of_day(86399.9996, 0.001), "00:00:00.000");
+        assert_eq!(format_time_of_day(1_700_000_000.5, 0.1), "22:13:20.5");
+    }
+}
@@ -0,0 +1,312 @@
+use rhai::{Array, Dynamic, Engine, EvalAltResult, ImmutableString};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::data::ProfileData;
+
+// stops runaway loops from freezing the viewer
+const MAX_OPERATIONS: u64 = 500_000_000;
+
+// a call as scripts see it, copied out of the trace so the engine owns it
+#[derive(Debug, Clone)]
+pub struct Call {
+    index: i64,
+    pe: i64,
+    time: f64,
+    duration: f64,
+    function: ImmutableString,
+    target: i64,
+    bytes_tx: i64,
+    bytes_rx: i64,
+    host: ImmutableString,
+}
+
+// what a script asked the viewer to do, applied in order once it finishes
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Bookmark {
+        name: String,
+        time: f64,
+        pe: Option<u32>,
+    },
+    Hide(String),
+    Show(String),
+    // None clears it
+    Solo(Option<String>),
+    Select(f64, f64),
+    Zoom(f64, f64),
+}
+
+#[derive(Debug, Default)]
+pub struct Outcome {
+    // everything print() and debug() wrote
+    pub output: String,
+    pub actions: Vec<Action>,
+    pub error: Option<String>,
+}
+
+// runs `source` against the trace. `range` is what range_start() and
+// range_end() report, the viewer's current analysis range
+pub fn run(data: &ProfileData, range: (f64, f64), source: &str) -> Outcome {
+    let calls = Rc::new(calls(data));
+    let output = Rc::new(RefCell::new(String::new()));
+    let actions = Rc::new(RefCell::new(Vec::new()));
+
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    {
+        let output = output.clone();
+        engine.on_print(move |s| {
+            let mut output = output.borrow_mut();
+            output.push_str(s);
+            output.push('\n');
+        });
+    }
+    {
+        let output = output.clone();
+        engine.on_debug(move |s, _, pos| {
+            let mut output = output.borrow_mut();
+            output.push_str(&format!("[{pos}] {s}\n"));
+        });
+    }
+
+    engine
+        .register_type_with_name::<Call>("Call")
+        .register_get("index", |c: &mut Call| c.index)
+        .register_get("pe", |c: &mut Call| c.pe)
+        .register_get("time", |c: &mut Call| c.time)
+        .register_get("duration", |c: &mut Call| c.duration)
+        .register_get("end", |c: &mut Call| c.time + c.duration)
+        .register_get("function", |c: &mut Call| c.function.clone())
+        .register_get("target", |c: &mut Call| c.target)
+        .register_get("bytes_tx", |c: &mut Call| c.bytes_tx)
+        .register_get("bytes_rx", |c: &mut Call| c.bytes_rx)
+        .register_get("bytes", |c: &mut Call| c.bytes_tx + c.bytes_rx)
+        .register_get("host", |c: &mut Call| c.host.clone())
+        .register_fn("to_string", |c: &mut Call| {
+            format!(
+                "{} on PE {} at {:.6}s for {:.3e}s",
+                c.function, c.pe, c.time, c.duration
+            )
+        });
+
+    // the trace
+    let pe_count = data.pe_count as i64;
+    let (trace_start, trace_end) = (data.min_time, data.max_time);
+    engine.register_fn("pe_count", move || pe_count);
+    engine.register_fn("trace_start", move || trace_start);
+    engine.register_fn("trace_end", move || trace_end);
+    engine.register_fn("range_start", move || range.0);
+    engine.register_fn("range_end", move || range.1);
+    {
+        let calls = calls.clone();
+        engine.register_fn("event_count", move || calls.len() as i64);
+    }
+    {
+        let calls = calls.clone();
+        engine.register_fn("event", move |i: i64| -> Result<Call, Box<EvalAltResult>> {
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| calls.get(i))
+                .cloned()
+                .ok_or_else(|| format!("no event {i}").into())
+        });
+    }
+    {
+        let calls = calls.clone();
+        engine.register_fn("events", move || -> Array {
+            calls.iter().cloned().map(Dynamic::from).collect()
+        });
+    }
+    {
+        let calls = calls.clone();
+        engine.register_fn("events", move |start: f64, end: f64| -> Array {
+            starting_in(&calls, start, end)
+                .iter()
+                .cloned()
+                .map(Dynamic::from)
+                .collect()
+        });
+    }
+    {
+        let calls = calls.clone();
+        engine.register_fn("functions", move || -> Array {
+            let mut names: Vec<_> = calls.iter().map(|c| c.function.clone()).collect();
+            names.sort();
+            names.dedup();
+            names.into_iter().map(Dynamic::from).collect()
+        });
+    }
+    let hosts = data.pe_hostnames.clone();
+    engine.register_fn("host", move |pe: i64| -> ImmutableString {
+        u32::try_from(pe)
+            .ok()
+            .and_then(|pe| hosts.get(&pe))
+            .map_or_else(ImmutableString::new, |h| h.as_str().into())
+    });
+
+    // aggregates over calls starting in [start, end], done natively since
+    // they're the common case and looping in script is slow
+    {
+        let calls = calls.clone();
+        engine.register_fn(
+            "total_time",
+            move |function: &str, start: f64, end: f64| -> f64 {
+                starting_in(&calls, start, end)
+                    .iter()
+                    .filter(|c| c.function == function)
+                    .map(|c| c.duration)
+                    .sum()
+            },
+        );
+    }
+    {
+        let calls = calls.clone();
+        engine.register_fn(
+            "call_count",
+            move |function: &str, start: f64, end: f64| -> i64 {
+                starting_in(&calls, start, end)
+                    .iter()
+                    .filter(|c| c.function == function)
+                    .count() as i64
+            },
+        );
+    }
+    {
+        let calls = calls.clone();
+        engine.register_fn("bytes_between", move |src: i64, dst: i64| -> i64 {
+            calls
+                .iter()
+                .filter(|c| c.pe == src && c.target == dst)
+                .map(|c| c.bytes_tx + c.bytes_rx)
+                .sum()
+        });
+    }
+    engine.register_fn("percentile", |values: Array, p: f64| -> f64 {
+        let mut values: Vec<f64> = values
+            .into_iter()
+            .filter_map(|v| v.as_float().ok().or(v.as_int().ok().map(|i| i as f64)))
+            .collect();
+        if values.is_empty() {
+            return 0.0;
+        }
+        values.sort_by(f64::total_cmp);
+        let rank = ((p / 100.0 * values.len() as f64).ceil() as usize).clamp(1, values.len());
+        values[rank - 1]
+    });
+
+    // the viewer
+    {
+        let actions = actions.clone();
+        engine.register_fn("bookmark", move |name: &str, time: f64| {
+            actions.borrow_mut().push(Action::Bookmark {
+                name: name.to_string(),
+                time,
+                pe: None,
+            });
+        });
+    }
+    {
+        let actions = actions.clone();
+        engine.register_fn("bookmark", move |name: &str, time: f64, pe: i64| {
+            actions.borrow_mut().push(Action::Bookmark {
+                name: name.to_string(),
+                time,
+                pe: u32::try_from(pe).ok(),
+            });
+        });
+    }
+    {
+        let actions = actions.clone();
+        engine.register_fn("hide", move |function: &str| {
+            actions
+                .borrow_mut()
+                .push(Action::Hide(function.to_string()));
+        });
+    }
+    {
+        let actions = actions.clone();
+        engine.register_fn("show", move |function: &str| {
+            actions
+                .borrow_mut()
+                .push(Action::Show(function.to_string()));
+        });
+    }
+    {
+        let actions = actions.clone();
+        engine.register_fn("solo", move |function: &str| {
+            actions
+                .borrow_mut()
+                .push(Action::Solo(Some(function.to_string())));
+        });
+    }
+    {
+        let actions = actions.clone();
+        engine.register_fn("unsolo", move || {
+            actions.borrow_mut().push(Action::Solo(None));
+        });
+    }
+    {
+        let actions = actions.clone();
+        engine.register_fn("select", move |start: f64, end: f64| {
+            actions
+                .borrow_mut()
+                .push(Action::Select(start.min(end), start.max(end)));
+        });
+    }
+    {
+        let actions = actions.clone();
+        engine.register_fn("zoom", move |start: f64, end: f64| {
+            actions
+                .borrow_mut()
+                .push(Action::Zoom(start.min(end), start.max(end)));
+        });
+    }
+
+    let error = engine.run(source).err().map(|e| e.to_string());
+    drop(engine);
+    Outcome {
+        output: output.take(),
+        actions: actions.take(),
+        error,
+    }
+}
+
+fn calls(data: &ProfileData) -> Vec<Call> {
+    // one shared string per function and host rather than one per call
+    let mut names: HashMap<&str, ImmutableString> = HashMap::new();
+    let mut hosts: HashMap<u32, ImmutableString> = HashMap::new();
+    data.events
+        .iter()
+        .enumerate()
+        .map(|(i, e)| Call {
+            index: i as i64,
+            pe: e.source_pe as i64,
+            time: e.raw.time,
+            duration: e.raw.duration_sec,
+            function: names
+                .entry(e.raw.function)
+                .or_insert_with(|| e.raw.function.into())
+                .clone(),
+            target: e.raw.target_pe as i64,
+            bytes_tx: e.raw.bytes_tx as i64,
+            bytes_rx: e.raw.bytes_rx as i64,
+            host: hosts
+                .entry(e.source_pe)
+                .or_insert_with(|| {
+                    data.pe_hostnames
+                        .get(&e.source_pe)
+                        .map_or_else(ImmutableString::new, |h| h.as_str().into())
+                })
+                .clone(),
+        })
+        .collect()
+}
+
+// events are in time order, so a range is a slice
+fn starting_in(calls: &[Call], start: f64, end: f64) -> &[Call] {
+    let first = calls.partition_point(|c| c.time < start);
+    let last = calls.partition_point(|c| c.time <= end).max(first);
+    &calls[first..last]
+}
@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::bandwidth::{BandwidthMode, ByteFilter, EdgeScaling, Muting};
+use crate::bookmarks::Bookmarks;
+use crate::coloring::ColorBy;
+use crate::dock::DockLayout;
+use crate::filters::DurationFilter;
+use crate::grouping::GroupRule;
+use crate::labels::PeLabels;
+use crate::lanes::Stacking;
+use crate::layout::{Placement, RingGrouping};
+use crate::ruler::TimeDisplay;
+use crate::skew::ClockOffsets;
+use crate::theme::ThemeChoice;
+use crate::topology::GroupLevel;
+use crate::tracks::TrackOrder;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SESSION_EXTENSION: &str = "cpsv";
+
+// everything needed to pick an investigation back up later.
+// stored as json so it can be diffed / hand edited
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub data_dir: PathBuf,
+    // set when only some files were opened
+    pub data_files: Vec<PathBuf>,
+    // set when several directories were merged, data_dir is the first
+    pub data_dirs: Vec<PathBuf>,
+
+    pub cursor_time: f64,
+    pub window_size_seconds: f64,
+    pub playback_speed: f64,
+    pub loop_a: Option<f64>,
+    pub loop_b: Option<f64>,
+    pub loop_enabled: bool,
+
+    pub show_rx: bool,
+    pub show_tx: bool,
+    pub edge_scaling: EdgeScaling,
+    pub muting: Muting,
+    // edges get byte / message labels when at most this many are drawn
+    pub edge_label_limit: usize,
+    // a PE's traffic to itself as a ring around its node
+    pub show_local: bool,
+    // calls with a negative target_pe as spokes out of their node
+    pub show_collectives: bool,
+    // arcs inside each node, its TX and RX rates against this many bytes
+    // per second
+    pub show_gauges: bool,
+    pub peak_bandwidth: f64,
+    pub bandwidth_mode: BandwidthMode,
+    pub ring_grouping: RingGrouping,
+    pub placement: Placement,
+
+    pub timeline_start_time: f64,
+    pub timeline_end_time: f64,
+    pub timeline_pe_scroll: f32,
+    pub timeline_track_height: f32,
+    pub time_display: TimeDisplay,
+    // events per pixel along the bottom of the ruler
+    pub ruler_density: bool,
+    pub track_order: TrackOrder,
+    pub stacking: Stacking,
+    // Extra counters plotted under the tracks
+    pub visible_counters: BTreeSet<String>,
+
+    // function or group name -> rgb
+    pub color_overrides: BTreeMap<String, [u8; 3]>,
+    pub group_rules: Vec<GroupRule>,
+    // groups left off the timeline
+    pub hidden_groups: BTreeSet<String>,
+    pub hidden_functions: BTreeSet<String>,
+    // when set, the only function drawn on the timeline
+    pub solo_function: Option<String>,
+    pub duration_filter: DurationFilter,
+    // messages left out of the bandwidth views
+    pub byte_filter: ByteFilter,
+    pub color_by: ColorBy,
+
+    // binaries used to resolve Stacktrace addresses
+    pub symbol_binaries: Vec<PathBuf>,
+    pub demangle_frames: bool,
+
+    pub bookmarks: Bookmarks,
+
+    // derived metric expressions, as typed
+    pub metrics: Vec<String>,
+
+    // host -> seconds added to its timestamps
+    pub clock_offsets: ClockOffsets,
+    pub pe_labels: PeLabels,
+    // switches and racks of the hosts, and which of them the ring groups by
+    pub topology_path: Option<PathBuf>,
+    pub group_level: GroupLevel,
+
+    pub theme: ThemeChoice,
+    pub ui_scale: f32,
+    pub dock: DockLayout,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("."),
+            data_files: Vec::new(),
+            data_dirs: Vec::new(),
+            cursor_time: 0.0,
+            window_size_seconds: 0.01,
+            playback_speed: 1.0,
+            loop_a: None,
+            loop_b: None,
+            loop_enabled: false,
+            show_rx: true,
+            show_tx: true,
+            edge_scaling: EdgeScaling::default(),
+            muting: Muting::default(),
+            edge_label_limit: 12,
+            show_local: false,
+            show_collectives: true,
+            show_gauges: false,
+            peak_bandwidth: 12.5e9,
+            bandwidth_mode: BandwidthMode::Graph,
+            ring_grouping: RingGrouping::Flat,
+            placement: Placement::Ring,
+            timeline_start_time: 0.0,
+            timeline_end_time: 1.0,
+            timeline_pe_scroll: 0.0,
+            timeline_track_height: 16.0,
+            time_display: TimeDisplay::Absolute,
+            ruler_density: true,
+            track_order: TrackOrder::default(),
+            stacking: Stacking::Overlap,
+            visible_counters: BTreeSet::new(),
+            color_overrides: BTreeMap::new(),
+            group_rules: Vec::new(),
+            hidden_groups: BTreeSet::new(),
+            hidden_functions: BTreeSet::new(),
+            solo_function: None,
+            duration_filter: DurationFilter::default(),
+            byte_filter: ByteFilter::default(),
+            color_by: ColorBy::Function,
+            symbol_binaries: Vec::new(),
+            demangle_frames: true,
+            bookmarks: Bookmarks::default(),
+            metrics: Vec::new(),
+            clock_offsets: ClockOffsets::new(),
+            pe_labels: PeLabels::default(),
+            topology_path: None,
+            group_level: GroupLevel::Host,
+            theme: ThemeChoice::System,
+            ui_scale: 1.0,
+            dock: DockLayout::default(),
+        }
+    }
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("reading session {}", path.display()))?;
+        let session = serde_json::from_str(&text)
+            .with_context(|| format!("parsing session {}", path.display()))?;
+        Ok(session)
+    }
+
+    // returns the path actually written (extension is added if missing)
+    pub fn save(&self, path: &Path) -> Result<PathBuf> {
+        let path = if path.extension().is_none() {
+            path.with_extension(SESSION_EXTENSION)
+        } else {
+            path.to_path_buf()
+        };
+        let text = serde_json::to_string_pretty(self)?;
+        fs::write(&path, text).with_context(|| format!("writing session {}", path.display()))?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,130 @@
+use egui::{Color32, Sense, Vec2};
+
+use crate::data::ProfileData;
+use crate::units;
+use crate::views::{View, ViewCommand, ViewState};
+
+// share of the time in calls each function takes over the visible range
+// (or the selection), as bars. clicking one solos it, double-clicking zooms
+// to its calls and right-clicking selects them
+#[derive(Default)]
+pub struct FunctionShares {
+    // range the rows were summed over
+    range: Option<(f64, f64)>,
+    rows: Vec<Share>,
+}
+
+struct Share {
+    function: String,
+    // inside the range only
+    time: f64,
+    calls: usize,
+    first_start: f64,
+    last_end: f64,
+}
+
+impl FunctionShares {
+    fn refresh(&mut self, data: &ProfileData, start: f64, end: f64) {
+        if self.range == Some((start, end)) {
+            return;
+        }
+        let mut rows: Vec<Share> = Vec::new();
+        let mut by_function = std::collections::HashMap::new();
+        for i in data.overlapping(start, end) {
+            let e = data.event(i);
+            let (t0, t1) = (e.raw.time, e.raw.time + e.raw.duration_sec);
+            let row = *by_function.entry(e.raw.function).or_insert_with(|| {
+                rows.push(Share {
+                    function: e.raw.function.to_string(),
+                    time: 0.0,
+                    calls: 0,
+                    first_start: t0,
+                    last_end: t1,
+                });
+                rows.len() - 1
+            });
+            let share = &mut rows[row];
+            share.time += (t1.min(end) - t0.max(start)).max(0.0);
+            share.calls += 1;
+            share.first_start = share.first_start.min(t0);
+            share.last_end = share.last_end.max(t1);
+        }
+        rows.sort_by(|a, b| b.time.total_cmp(&a.time));
+        self.rows = rows;
+        self.range = Some((start, end));
+    }
+}
+
+impl View for FunctionShares {
+    fn name(&self) -> &str {
+        "Function shares"
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, data: &ProfileData, state: &ViewState) -> Vec<ViewCommand> {
+        let (start, end) = state.selection.unwrap_or(state.view);
+        self.refresh(data, start, end);
+        let mut commands = Vec::new();
+        ui.label(format!(
+            "{} of {}",
+            if state.selection.is_some() {
+                "Selection"
+            } else {
+                "Visible range"
+            },
+            units::duration(end - start)
+        ));
+        let rows: Vec<_> = self
+            .rows
+            .iter()
+            .filter(|s| !state.annotations.hidden_functions.contains(&s.function))
+            .collect();
+        let total: f64 = rows.iter().map(|s| s.time).sum();
+        if total <= 0.0 {
+            ui.label("no calls in range");
+            return commands;
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for row in rows {
+                let function = &row.function;
+                let share = row.time / total;
+                let color = state
+                    .colors
+                    .functions()
+                    .get(function)
+                    .copied()
+                    .unwrap_or(Color32::GRAY);
+                let width = ui.available_width();
+                let (rect, response) =
+                    ui.allocate_exact_size(Vec2::new(width, 18.0), Sense::click());
+                let bar = egui::Rect::from_min_size(
+                    rect.min,
+                    Vec2::new(rect.width() * share as f32, rect.height() - 2.0),
+                );
+                let painter = ui.painter();
+                painter.rect_filled(bar, 2.0, color.gamma_multiply(0.7));
+                painter.text(
+                    rect.left_center() + Vec2::new(4.0, -1.0),
+                    egui::Align2::LEFT_CENTER,
+                    format!("{function}  {:.1}%", share * 100.0),
+                    egui::FontId::proportional(12.0),
+                    ui.visuals().strong_text_color(),
+                );
+                let response = response.on_hover_text(format!(
+                    "{} in {} calls\nclick to show only this function, double-click to \
+                     zoom to its calls, right-click to select them",
+                    units::duration(row.time),
+                    row.calls
+                ));
+                let span = (row.first_start, row.last_end.max(row.first_start));
+                if response.double_clicked() {
+                    commands.push(ViewCommand::SetView(span.0, span.1));
+                } else if response.clicked() {
+                    commands.push(ViewCommand::Solo(Some(function.clone())));
+                } else if response.secondary_clicked() {
+                    commands.push(ViewCommand::Select(span.0, span.1));
+                }
+            }
+        });
+        commands
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::{Result, anyhow};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// readable name for a Symboltrace frame. frames may carry an offset
+// (`_ZN3foo3barEv+0x1c`), which is kept as is
+pub fn demangle(frame: &str) -> String {
+    let frame = frame.trim();
+    let (symbol, offset) = match frame.split_once('+') {
+        Some((s, o)) => (s, Some(o)),
+        None => (frame, None),
+    };
+    let demangled = if let Ok(d) = rustc_demangle::try_demangle(symbol) {
+        // drop the hash suffix, it's noise in a tooltip
+        format!("{d:#}")
+    } else if let Some(d) = cpp_demangle::Symbol::new(symbol)
+        .ok()
+        .and_then(|s| s.demangle(&Default::default()).ok())
+    {
+        d
+    } else {
+        return frame.to_string();
+    };
+    match offset {
+        Some(o) => format!("{demangled}+{o}"),
+        None => demangled,
+    }
+}
+
+// Stacktrace frames are hex addresses like `0x4005d0`
+pub fn parse_address(frame: &str) -> Option<u64> {
+    let frame = frame.trim();
+    let hex = frame
+        .strip_prefix("0x")
+        .or_else(|| frame.strip_prefix("0X"))?;
+    u64::from_str_radix(hex, 16).ok()
+}
+
+// resolves Stacktrace addresses to file:line using the debug info of
+// binaries the user points us at. the first binary that knows an address wins
+#[derive(Default)]
+pub struct Symbolizer {
+    binaries: Vec<(PathBuf, addr2line::Loader)>,
+    cache: RefCell<HashMap<u64, Option<String>>>,
+}
+
+impl Symbolizer {
+    pub fn add_binary(&mut self, path: &Path) -> Result<()> {
+        if self.binaries.iter().any(|(p, _)| p == path) {
+            return Ok(());
+        }
+        let loader = addr2line::Loader::new(path)
+            .map_err(|e| anyhow!("can't read debug info from {}: {e}", path.display()))?;
+        self.binaries.push((path.to_path_buf(), loader));
+        self.cache.borrow_mut().clear();
+        Ok(())
+    }
+
+    pub fn remove_binary(&mut self, path: &Path) {
+        self.binaries.retain(|(p, _)| p != path);
+        self.cache.borrow_mut().clear();
+    }
+
+    pub fn binaries(&self) -> impl Iterator<Item = &Path> {
+        self.binaries.iter().map(|(p, _)| p.as_path())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.binaries.is_empty()
+    }
+
+    // `function (file:line)`, innermost inlined frame first
+    pub fn resolve(&self, address: u64) -> Option<String> {
+        if let Some(cached) = self.cache.borrow().get(&address) {
+            return cached.clone();
+        }
+        let resolved = self
+            .binaries
+            .iter()
+            .find_map(|(_, loader)| describe(loader, address));
+        self.cache.borrow_mut().insert(address, resolved.clone());
+        resolved
+    }
+}
+
+fn describe(loader: &addr2line::Loader, address: u64) -> Option<String> {
+    let location = loader.find_location(address).ok().flatten();
+    let function = loader
+        .find_frames(address)
+        .ok()
+        .and_then(|mut frames| frames.next().ok().flatten())
+        .and_then(|frame| frame.function)
+        .and_then(|f| f.raw_name().ok().map(|n| demangle(&n)))
+        .or_else(|| loader.find_symbol(address).map(demangle));
+    let place = location.and_then(|l| {
+        let file = Path::new(l.file?)
+            .file_name()?
+            .to_string_lossy()
+            .into_owned();
+        Some(match l.line {
+            Some(line) => format!("{file}:{line}"),
+            None => file,
+        })
+    });
+    match (function, place) {
+        (Some(f), Some(p)) => Some(format!("{f} ({p})")),
+        (Some(f), None) => Some(f),
+        (None, Some(p)) => Some(p),
+        (None, None) => None,
+    }
+}
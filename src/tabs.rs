@@ -0,0 +1,71 @@
+use crate::data::ProfileData;
+use crate::session::Session;
+
+// a trace open in a tab that isn't on screen. the view state is kept as a
+// session and applied again when the tab comes back
+pub struct Parked {
+    pub data: Option<ProfileData>,
+    pub session: Session,
+}
+
+// open traces: the active one lives in the app, the others are parked
+pub struct Tabs {
+    parked: Vec<Option<Parked>>,
+    active: usize,
+}
+
+impl Default for Tabs {
+    fn default() -> Self {
+        Self {
+            parked: vec![None],
+            active: 0,
+        }
+    }
+}
+
+impl Tabs {
+    pub fn count(&self) -> usize {
+        self.parked.len()
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    // None for the active tab
+    pub fn parked(&self, i: usize) -> Option<&Parked> {
+        self.parked.get(i)?.as_ref()
+    }
+
+    // parks `current` and makes an empty tab right after it active
+    pub fn open(&mut self, current: Parked) {
+        self.parked[self.active] = Some(current);
+        self.active += 1;
+        self.parked.insert(self.active, None);
+    }
+
+    // parks `current` and returns the tab to show instead
+    pub fn switch(&mut self, to: usize, current: Parked) -> Parked {
+        debug_assert_ne!(to, self.active);
+        self.parked[self.active] = Some(current);
+        self.active = to;
+        self.parked[to].take().expect("inactive tabs are parked")
+    }
+
+    // closes tab `i`. returns the tab to show when the active one was closed
+    pub fn close(&mut self, i: usize) -> Option<Parked> {
+        if self.parked.len() <= 1 || i >= self.parked.len() {
+            return None;
+        }
+        self.parked.remove(i);
+        if i < self.active {
+            self.active -= 1;
+            None
+        } else if i == self.active {
+            self.active = self.active.min(self.parked.len() - 1);
+            self.parked[self.active].take()
+        } else {
+            None
+        }
+    }
+}
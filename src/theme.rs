@@ -0,0 +1,129 @@
+use egui::{Color32, Theme, ThemePreference};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ThemeChoice {
+    // whatever the OS is set to
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+impl ThemeChoice {
+    pub const ALL: [ThemeChoice; 3] = [Self::System, Self::Dark, Self::Light];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::System => "Follow system",
+            Self::Dark => "Dark",
+            Self::Light => "Light",
+        }
+    }
+
+    pub fn preference(self) -> ThemePreference {
+        match self {
+            Self::System => ThemePreference::System,
+            Self::Dark => ThemePreference::Dark,
+            Self::Light => ThemePreference::Light,
+        }
+    }
+}
+
+pub const MIN_UI_SCALE: f32 = 0.5;
+pub const MAX_UI_SCALE: f32 = 3.0;
+
+// colors of the hand painted views (timeline, ring, plots). egui widgets
+// follow the theme on their own, these have to be picked per theme
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    // behind the timeline and the plots
+    pub plot_bg: Color32,
+    pub label_bg: Color32,
+    pub label_pinned: Color32,
+    pub label_selected: Color32,
+    pub ruler_bg: Color32,
+    pub ruler_border: Color32,
+    pub tick: Color32,
+    // track separators, and the lighter lines between sub-rows
+    pub grid: Color32,
+    pub grid_minor: Color32,
+    pub text: Color32,
+    pub text_weak: Color32,
+    pub text_faint: Color32,
+    // pinned track boundary, host arcs, bars
+    pub separator: Color32,
+    pub bar: Color32,
+    pub bar_hovered: Color32,
+    pub cursor: Color32,
+    pub node_fill: Color32,
+    pub node_stroke: Color32,
+    pub node_muted_fill: Color32,
+    pub node_muted_stroke: Color32,
+    // small labels drawn over other things, e.g. on edges
+    pub badge_bg: Color32,
+    pub badge_text: Color32,
+}
+
+impl Palette {
+    pub fn dark() -> Self {
+        Self {
+            plot_bg: Color32::from_gray(18),
+            label_bg: Color32::from_gray(22),
+            label_pinned: Color32::from_gray(32),
+            label_selected: Color32::from_rgb(40, 50, 80),
+            ruler_bg: Color32::from_gray(35),
+            ruler_border: Color32::from_gray(60),
+            tick: Color32::from_gray(80),
+            grid: Color32::from_gray(30),
+            grid_minor: Color32::from_gray(24),
+            text: Color32::from_gray(200),
+            text_weak: Color32::from_gray(140),
+            text_faint: Color32::from_gray(120),
+            separator: Color32::from_gray(110),
+            bar: Color32::from_gray(110),
+            bar_hovered: Color32::from_gray(170),
+            cursor: Color32::WHITE,
+            node_fill: Color32::DARK_GRAY,
+            node_stroke: Color32::WHITE,
+            node_muted_fill: Color32::from_gray(50),
+            node_muted_stroke: Color32::from_gray(200),
+            badge_bg: Color32::from_black_alpha(180),
+            badge_text: Color32::from_gray(230),
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            plot_bg: Color32::from_gray(250),
+            label_bg: Color32::from_gray(240),
+            label_pinned: Color32::from_gray(226),
+            label_selected: Color32::from_rgb(200, 215, 245),
+            ruler_bg: Color32::from_gray(228),
+            ruler_border: Color32::from_gray(180),
+            tick: Color32::from_gray(190),
+            grid: Color32::from_gray(215),
+            grid_minor: Color32::from_gray(232),
+            text: Color32::from_gray(30),
+            text_weak: Color32::from_gray(100),
+            text_faint: Color32::from_gray(120),
+            separator: Color32::from_gray(150),
+            bar: Color32::from_gray(170),
+            bar_hovered: Color32::from_gray(110),
+            cursor: Color32::BLACK,
+            node_fill: Color32::from_gray(215),
+            node_stroke: Color32::from_gray(30),
+            node_muted_fill: Color32::from_gray(235),
+            node_muted_stroke: Color32::from_gray(120),
+            badge_bg: Color32::from_white_alpha(220),
+            badge_text: Color32::from_gray(20),
+        }
+    }
+
+    pub fn for_theme(theme: Theme) -> Self {
+        match theme {
+            Theme::Dark => Self::dark(),
+            Theme::Light => Self::light(),
+        }
+    }
+}
@@ -0,0 +1,1772 @@
+use egui::{Color32, Id, LayerId, Order, PopupAnchor, Pos2, Rect, Sense, Stroke, StrokeKind, Vec2};
+use std::collections::{HashMap, HashSet};
+
+use crate::activity::{self, FunctionActivity};
+use crate::coloring::{self, generate_color};
+use crate::completion::{self, OverlapBins};
+use crate::data::ProfileData;
+use crate::dock::Pane;
+use crate::export::{self, ExportTarget};
+use crate::filters::DurationFilter;
+use crate::hud::DrawCounts;
+use crate::panes;
+use crate::ruler::{self, TimeDisplay};
+use crate::tracks::TrackLayout;
+use crate::traffic::MIN_WINDOW_SECONDS;
+use crate::units;
+use crate::views::{View, ViewCommand, ViewState, paint_crosshair};
+
+// the timeline is drawn coarsely until the view has been still this long
+const TIMELINE_REFINE_DELAY: f32 = 0.15;
+// calls narrower than a pixel before the timeline warns it's saturated,
+// once they're also most of what's drawn
+const SATURATION_MIN_CALLS: usize = 1000;
+// message lines drawn with "all visible calls" on, the rest are left out
+pub const MAX_MESSAGE_LINES: usize = 2000;
+// height of the communication overlap strip under each track
+const OVERLAP_STRIP_HEIGHT: f32 = 6.0;
+// height of the event density strip at the bottom of the ruler
+const RULER_DENSITY_HEIGHT: f32 = 6.0;
+// how close to an edge of the bandwidth window in the ruler grabs it
+const WINDOW_HANDLE_GRAB: f32 = 4.0;
+// labels of PEs that left no file
+pub const MISSING_PE: Color32 = Color32::from_rgb(255, 170, 60);
+
+// the view, bins and duration filter the function rows were computed for
+type ActivityKey = (f64, f64, usize, DurationFilter);
+
+// a track per PE with its calls laid out in time, or a row per function
+pub struct Timeline {
+    pub track_height: f32,
+    pub pe_scroll: f32,
+    // a row per function, a heat strip of how many PEs are in it over
+    // time, instead of one per PE
+    pub function_rows: bool,
+    // a line from every visible call with a target PE to that PE's track,
+    // not only the hovered and pinned ones
+    pub message_lines_all: bool,
+    // puts and gets run on as a hatched bar until the quiet, fence or
+    // barrier that completed them
+    pub show_in_flight: bool,
+    // per PE, the overlapped share of communication in each bin of the
+    // view, green when it ran behind computation and red when it blocked
+    pub show_overlap_strip: bool,
+    pub ruler_density: bool,
+    // what the last frame did with the events it looked at, for the HUD
+    pub counts: DrawCounts,
+
+    // view drawn last frame and when it last changed, for drawing coarsely
+    // while it moves
+    last_view: (f64, f64, f32, f32),
+    moved_at: f64,
+    // PE whose label is being dragged to a new position
+    track_drag: Option<u32>,
+    // an edge of the bandwidth window is being dragged in the ruler
+    window_drag: bool,
+    // where the shift-drag selection started
+    selection_anchor: Option<f64>,
+    // time under the pointer when the context menu was opened
+    context_time: Option<f64>,
+    // track label the context menu was opened on
+    context_pe: Option<u32>,
+    // event the context menu was opened on
+    context_event: Option<usize>,
+    function_activity: Option<(ActivityKey, Vec<FunctionActivity>)>,
+    function_rows_scroll: f32,
+    overlap_strip: Option<((f64, f64, usize), OverlapBins)>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            track_height: 16.0,
+            pe_scroll: 0.0,
+            function_rows: false,
+            message_lines_all: false,
+            show_in_flight: true,
+            show_overlap_strip: false,
+            ruler_density: true,
+            counts: DrawCounts::default(),
+            last_view: (0.0, 0.0, 0.0, 0.0),
+            moved_at: f64::NEG_INFINITY,
+            track_drag: None,
+            window_drag: false,
+            selection_anchor: None,
+            context_time: None,
+            context_pe: None,
+            context_event: None,
+            function_activity: None,
+            function_rows_scroll: 0.0,
+            overlap_strip: None,
+        }
+    }
+}
+
+impl Timeline {
+    // drops what was made from the event times
+    pub fn invalidate(&mut self) {
+        self.function_activity = None;
+        self.overlap_strip = None;
+    }
+
+    // follows the events to their new indices, see ProfileData::set_clock_offsets
+    pub fn remap(&mut self, moved: &[usize]) {
+        self.context_event = self.context_event.map(|i| moved[i]);
+    }
+}
+
+impl View for Timeline {
+    fn name(&self) -> &str {
+        Pane::Timeline.title()
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, data: &ProfileData, state: &ViewState) -> Vec<ViewCommand> {
+        if self.function_rows {
+            self.ui_function_rows(ui, data, state)
+        } else {
+            self.ui_tracks(ui, data, state)
+        }
+    }
+}
+
+impl Timeline {
+    // the timeline with a row per function: each bin is colored by how
+    // many PEs were in the function then. wheel zooms, drag pans,
+    // shift-wheel scrolls the rows and clicking a bin goes back to the PE
+    // rows zoomed in on it
+    fn ui_function_rows(
+        &mut self,
+        ui: &mut egui::Ui,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        let mut view = state.view;
+        let ruler_height = 30.0;
+        let label_width = 120.0;
+        let row_height = 20.0;
+
+        let capture = (state.export == Some(ExportTarget::Timeline))
+            .then(|| export::capture_start(ui.ctx(), ui.layer_id()));
+        let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
+        let rect = response.rect;
+        let rows_rect =
+            Rect::from_min_max(rect.min + Vec2::new(label_width, ruler_height), rect.max);
+
+        if response.hovered() {
+            let delta = ui.input(|i| i.smooth_scroll_delta.y);
+            if delta != 0.0 {
+                if ui.input(|i| i.modifiers.shift) {
+                    self.function_rows_scroll -= delta;
+                } else if let Some(pos) = response.hover_pos() {
+                    zoom_at(
+                        &mut view.0,
+                        &mut view.1,
+                        ((pos.x - rows_rect.min.x) / rows_rect.width()) as f64,
+                        ((-delta / 200.0) as f64).exp(),
+                    );
+                }
+            }
+        }
+        if response.dragged() {
+            let delta = response.drag_delta();
+            let shift = delta.x as f64 * (view.1 - view.0) / rows_rect.width() as f64;
+            view.0 -= shift;
+            view.1 -= shift;
+            self.function_rows_scroll -= delta.y;
+        }
+        if view != state.view {
+            commands.push(ViewCommand::Pan(view.0, view.1));
+        }
+        let (start, end) = view;
+
+        // two pixels per bin
+        let bins = (rows_rect.width() / 2.0).max(1.0) as usize;
+        let filter = state.annotations.duration_filter;
+        let key = (start, end, bins, filter);
+        if self
+            .function_activity
+            .as_ref()
+            .is_none_or(|(k, _)| *k != key)
+        {
+            let rows =
+                activity::by_function(data, start, end, bins, |e| filter.keeps(e.raw.duration_sec));
+            self.function_activity = Some((key, rows));
+        }
+        let Some((_, all_rows)) = &self.function_activity else {
+            return commands;
+        };
+        let rows: Vec<&FunctionActivity> = all_rows
+            .iter()
+            .filter(|r| {
+                state
+                    .annotations
+                    .function_shown(state.grouping, &r.function)
+            })
+            .collect();
+        let max_scroll = (rows.len() as f32 * row_height - rows_rect.height()).max(0.0);
+        self.function_rows_scroll = self.function_rows_scroll.clamp(0.0, max_scroll);
+        let scroll = self.function_rows_scroll;
+
+        let time_to_x =
+            |t: f64| rows_rect.min.x + ((t - start) / (end - start)) as f32 * rows_rect.width();
+        painter.rect_filled(rect, 0.0, state.palette.plot_bg);
+        let ruler_rect = Rect::from_min_max(rect.min, Pos2::new(rect.max.x, rows_rect.min.y));
+        painter.rect_filled(ruler_rect, 0.0, state.palette.ruler_bg);
+        painter.line_segment(
+            [ruler_rect.left_bottom(), ruler_rect.right_bottom()],
+            Stroke::new(1.0, state.palette.ruler_border),
+        );
+        let ruler_painter = painter.with_clip_rect(ruler_rect);
+        let ticks = ruler::ticks(
+            start,
+            end,
+            rows_rect.width(),
+            100.0,
+            state.time_display,
+            data.min_time,
+            data.epoch,
+        );
+        for tick in ticks {
+            let x = time_to_x(tick.time);
+            ruler_painter.line_segment(
+                [
+                    Pos2::new(x, ruler_rect.min.y),
+                    Pos2::new(x, ruler_rect.max.y),
+                ],
+                Stroke::new(1.0, state.palette.tick),
+            );
+            ruler_painter.text(
+                Pos2::new(x + 2.0, ruler_rect.min.y + 2.0),
+                egui::Align2::LEFT_TOP,
+                tick.label,
+                egui::FontId::proportional(10.0),
+                state.palette.text,
+            );
+        }
+
+        let heat_painter = painter.with_clip_rect(rows_rect);
+        let labels_painter = painter.with_clip_rect(Rect::from_min_max(
+            Pos2::new(rect.min.x, rows_rect.min.y),
+            Pos2::new(rows_rect.min.x, rect.max.y),
+        ));
+        let bin_width = rows_rect.width() / bins as f32;
+        let pe_count = data.pe_count.max(1) as f32;
+        let mut hovered = None;
+        for (k, row) in rows.iter().enumerate() {
+            let top = rows_rect.min.y + k as f32 * row_height - scroll;
+            if top + row_height < rows_rect.min.y || top > rows_rect.max.y {
+                continue;
+            }
+            heat_painter.line_segment(
+                [
+                    Pos2::new(rows_rect.min.x, top + row_height),
+                    Pos2::new(rows_rect.max.x, top + row_height),
+                ],
+                Stroke::new(1.0, state.palette.grid),
+            );
+            for (b, &pes) in row.bins.iter().enumerate() {
+                if pes <= 0.0 {
+                    continue;
+                }
+                let x = rows_rect.min.x + b as f32 * bin_width;
+                let bin = Rect::from_min_max(
+                    Pos2::new(x, top + 1.0),
+                    Pos2::new(x + bin_width, top + row_height - 1.0),
+                );
+                // square root so a few PEs still show
+                heat_painter.rect_filled(bin, 0.0, coloring::heat((pes / pe_count).sqrt()));
+            }
+            labels_painter.rect_filled(
+                Rect::from_min_size(Pos2::new(rect.min.x + 4.0, top + 6.0), Vec2::splat(8.0)),
+                1.0,
+                state.colors.function(&row.function),
+            );
+            labels_painter.text(
+                Pos2::new(rect.min.x + 16.0, top + row_height / 2.0),
+                egui::Align2::LEFT_CENTER,
+                &row.function,
+                egui::FontId::proportional(11.0),
+                state.palette.text,
+            );
+            if let Some(pos) = response.hover_pos()
+                && rows_rect.contains(pos)
+                && pos.y >= top
+                && pos.y < top + row_height
+            {
+                let b = (((pos.x - rows_rect.min.x) / bin_width) as usize).min(bins - 1);
+                hovered = Some((*row, b));
+            }
+        }
+        if rows.is_empty() {
+            painter.text(
+                rows_rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "no calls in view",
+                egui::FontId::proportional(12.0),
+                state.palette.text_weak,
+            );
+        }
+        if let Some(pos) = response.hover_pos()
+            && rows_rect.contains(pos)
+        {
+            let share = ((pos.x - rows_rect.min.x) / rows_rect.width()) as f64;
+            commands.push(ViewCommand::Hover(start + (end - start) * share));
+        }
+        paint_crosshair(
+            &painter.with_clip_rect(rows_rect),
+            rows_rect,
+            start,
+            end,
+            state.hover,
+        );
+
+        let bin_span = |b: usize| {
+            let width = (end - start) / bins as f64;
+            (start + b as f64 * width, start + (b + 1) as f64 * width)
+        };
+        let mut go = None;
+        if let Some((row, b)) = hovered {
+            let (t0, t1) = bin_span(b);
+            let pes = row.bins[b];
+            response.clone().on_hover_ui_at_pointer(|ui| {
+                ui.strong(&row.function);
+                ui.label(format!("{:.6}s to {:.6}s", t0, t1));
+                ui.label(format!(
+                    "{pes:.2} of {} PEs in it on average ({:.0}%)",
+                    data.pe_count,
+                    pes / pe_count * 100.0
+                ));
+                ui.small("click to see the PEs here");
+            });
+            if response.clicked() {
+                // the bin with a few more either side
+                let pad = (t1 - t0) * 8.0;
+                go = Some((t0 - pad, t1 + pad));
+            }
+        }
+
+        if let Some(start) = capture {
+            let view =
+                export::capture_end(ui.ctx(), ui.layer_id(), start, rect, state.palette.plot_bg);
+            commands.push(ViewCommand::Captured(ExportTarget::Timeline, view));
+        }
+        if let Some((t0, t1)) = go {
+            self.function_rows = false;
+            commands.push(ViewCommand::SetView(t0, t1));
+        }
+        commands
+    }
+
+    fn ui_tracks(
+        &mut self,
+        ui: &mut egui::Ui,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        let mut view = state.view;
+        let mut window = state.window;
+        let mut selection = state.selection;
+        let available_size = ui.available_size();
+        let track_height = self.track_height;
+        let ruler_height = 30.0;
+        let label_width = 120.0;
+
+        let capture = (state.export == Some(ExportTarget::Timeline))
+            .then(|| export::capture_start(ui.ctx(), ui.layer_id()));
+        let (response, painter) = ui.allocate_painter(available_size, Sense::click_and_drag());
+        let rect = response.rect;
+
+        let timeline_rect =
+            Rect::from_min_max(rect.min + Vec2::new(label_width, ruler_height), rect.max);
+
+        if response.hovered() {
+            let zoom_delta = ui.input(|i| i.smooth_scroll_delta.y);
+            if zoom_delta != 0.0 {
+                if ui.input(|i| i.modifiers.shift) {
+                    let zoom_factor = (-zoom_delta / 200.0).exp();
+                    let old_track_height = self.track_height;
+                    self.track_height = (self.track_height * zoom_factor).clamp(8.0, 100.0);
+
+                    if let Some(hover_pos) = response.hover_pos() {
+                        let y_in_content = (hover_pos.y - timeline_rect.min.y) + self.pe_scroll;
+                        let new_y_in_content =
+                            y_in_content * (self.track_height / old_track_height);
+                        self.pe_scroll = new_y_in_content - (hover_pos.y - timeline_rect.min.y);
+                    }
+                } else if let Some(hover_pos) = response.hover_pos() {
+                    let ratio =
+                        ((hover_pos.x - timeline_rect.min.x) / timeline_rect.width()) as f64;
+                    zoom_at(
+                        &mut view.0,
+                        &mut view.1,
+                        ratio,
+                        ((-zoom_delta / 200.0) as f64).exp(),
+                    );
+                }
+            }
+        }
+
+        // shift-drag selects a range instead of panning
+        let pointer_time = |x: f32| {
+            view.0 + ((x - timeline_rect.min.x) / timeline_rect.width()) as f64 * (view.1 - view.0)
+        };
+        if response.drag_started()
+            && ui.input(|i| i.modifiers.shift)
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            self.selection_anchor = Some(pointer_time(pos.x));
+        }
+        // dragging a PE label reorders the tracks instead of panning
+        let label_drag_started = response.drag_started()
+            && response
+                .interact_pointer_pos()
+                .is_some_and(|pos| pos.x < timeline_rect.min.x && pos.y > timeline_rect.min.y);
+        // the edges of the bandwidth window, drawn around the cursor in the
+        // ruler, resize it symmetrically
+        let half = window / 2.0;
+        let on_window_edge = |pos: Pos2| {
+            let x_of = |t: f64| {
+                timeline_rect.min.x
+                    + ((t - view.0) / (view.1 - view.0)) as f32 * timeline_rect.width()
+            };
+            pos.y < timeline_rect.min.y
+                && pos.x >= timeline_rect.min.x
+                && [state.cursor - half, state.cursor + half]
+                    .iter()
+                    .any(|t| (x_of(*t) - pos.x).abs() <= WINDOW_HANDLE_GRAB)
+        };
+        if response.hover_pos().is_some_and(on_window_edge) || self.window_drag {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeHorizontal);
+        }
+        if response.drag_started()
+            && self.selection_anchor.is_none()
+            && response.interact_pointer_pos().is_some_and(on_window_edge)
+        {
+            self.window_drag = true;
+        }
+        if self.window_drag {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let half = (pointer_time(pos.x) - state.cursor).abs();
+                let widest = (data.max_time - data.min_time).max(MIN_WINDOW_SECONDS);
+                window = (2.0 * half).clamp(MIN_WINDOW_SECONDS, widest);
+            }
+            if response.drag_stopped() {
+                self.window_drag = false;
+            }
+        } else if let Some(anchor) = self.selection_anchor {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let t = pointer_time(pos.x);
+                selection = Some((anchor.min(t), anchor.max(t)));
+            }
+            if response.drag_stopped() {
+                self.selection_anchor = None;
+                if selection.is_some_and(|(a, b)| a == b) {
+                    selection = None;
+                }
+            }
+        } else if response.dragged() && self.track_drag.is_none() && !label_drag_started {
+            let delta = response.drag_delta();
+            let time_per_pixel = (view.1 - view.0) / timeline_rect.width() as f64;
+            let time_delta = delta.x as f64 * time_per_pixel;
+            view.0 -= time_delta;
+            view.1 -= time_delta;
+
+            self.pe_scroll -= delta.y;
+        }
+
+        let duration = view.1 - view.0;
+        if view.0 > data.max_time {
+            view.0 = data.max_time;
+            view.1 = view.0 + duration;
+        }
+        if view.1 < data.min_time {
+            view.1 = data.min_time;
+            view.0 = view.1 - duration;
+        }
+        if view != state.view {
+            commands.push(ViewCommand::Pan(view.0, view.1));
+        }
+        if window != state.window {
+            commands.push(ViewCommand::SetWindow(window));
+        }
+        if selection != state.selection {
+            commands.push(match selection {
+                Some((a, b)) => ViewCommand::Select(a, b),
+                None => ViewCommand::Deselect,
+            });
+        }
+
+        // a PE only gets a counter plot if it reported one of the shown counters
+        let counter_height = 28.0;
+        let visible_counters = state.visible_counters;
+        let order = state.track_order.visible(data.pe_count);
+        let pinned = order
+            .iter()
+            .take_while(|pe| state.track_order.pinned.contains(pe))
+            .count();
+        let tracks = TrackLayout::new(
+            &order,
+            pinned,
+            data.pe_count,
+            self.track_height,
+            if self.show_overlap_strip {
+                OVERLAP_STRIP_HEIGHT
+            } else {
+                0.0
+            },
+            |pe| state.lanes.per_sub_row(data, pe),
+            |pe| {
+                let has_counter = visible_counters.iter().any(|name| {
+                    data.counters
+                        .get(name)
+                        .is_some_and(|c| c.samples.contains_key(&pe))
+                });
+                if has_counter { counter_height } else { 0.0 }
+            },
+        );
+
+        let total_content_height = tracks.total_height();
+        let max_scroll = (total_content_height - (timeline_rect.height() - track_height)).max(0.0);
+        self.pe_scroll = self.pe_scroll.clamp(0.0, max_scroll);
+        let pe_scroll = self.pe_scroll;
+        let content_to_y = |y: f32| timeline_rect.min.y + tracks.screen_offset(y, pe_scroll);
+        let y_to_content = |y: f32| tracks.content_y(y - timeline_rect.min.y, pe_scroll);
+
+        // the drop lands before the row whose upper half is under the pointer
+        let drop_target = |y: f32| {
+            let y_in_content = y_to_content(y);
+            let idx = tracks
+                .rows()
+                .partition_point(|r| r.top + r.height() / 2.0 <= y_in_content);
+            tracks.rows().get(idx)
+        };
+        if label_drag_started
+            && !ui.input(|i| i.modifiers.shift)
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            self.track_drag = tracks.row_at(y_to_content(pos.y)).map(|r| r.pe);
+        }
+        if let Some(pe) = self.track_drag
+            && response.drag_stopped()
+        {
+            self.track_drag = None;
+            if let Some(pos) = response.interact_pointer_pos() {
+                let before = drop_target(pos.y).map(|r| r.pe);
+                if before != Some(pe) {
+                    commands.push(ViewCommand::MoveTrack(pe, before));
+                }
+            }
+        }
+
+        let timeline_start = view.0;
+        let timeline_end = view.1;
+        let timeline_rect_min_x = timeline_rect.min.x;
+        let timeline_rect_width = timeline_rect.width();
+
+        let time_to_x = |t: f64| {
+            let ratio = (t - timeline_start) / (timeline_end - timeline_start);
+            timeline_rect_min_x + ratio as f32 * timeline_rect_width
+        };
+        let x_to_time = |x: f32| {
+            let ratio = (x - timeline_rect_min_x) / timeline_rect_width;
+            timeline_start + ratio as f64 * (timeline_end - timeline_start)
+        };
+
+        painter.rect_filled(rect, 0.0, state.palette.plot_bg);
+
+        let data_painter = painter.with_clip_rect(timeline_rect);
+        // scrolling tracks slide under the pinned ones
+        let scroll_rect = Rect::from_min_max(
+            Pos2::new(
+                timeline_rect.min.x,
+                timeline_rect.min.y + tracks.pinned_height(),
+            ),
+            timeline_rect.max,
+        );
+        let scroll_painter = painter.with_clip_rect(scroll_rect);
+
+        // around the cursor while its edges are dragged in the ruler
+        let window_time = state.hover.or(self.window_drag.then_some(state.cursor));
+        if let Some(h_time) = window_time {
+            let h_start = h_time - window / 2.0;
+            let h_end = h_time + window / 2.0;
+            let x_start = time_to_x(h_start);
+            let x_end = time_to_x(h_end);
+            let highlight_rect = Rect::from_min_max(
+                Pos2::new(x_start.max(timeline_rect.min.x), timeline_rect.min.y),
+                Pos2::new(x_end.min(timeline_rect.max.x), timeline_rect.max.y),
+            );
+            data_painter.rect_filled(
+                highlight_rect,
+                0.0,
+                Color32::from_rgba_premultiplied(255, 255, 0, 15),
+            );
+            paint_crosshair(&data_painter, timeline_rect, view.0, view.1, state.hover);
+        }
+
+        if let Some((a, b)) = selection {
+            let selection_rect = Rect::from_min_max(
+                Pos2::new(time_to_x(a), timeline_rect.min.y),
+                Pos2::new(time_to_x(b), timeline_rect.max.y),
+            );
+            data_painter.rect_filled(
+                selection_rect,
+                0.0,
+                Color32::from_rgba_unmultiplied(90, 150, 255, 40),
+            );
+            for x in [selection_rect.min.x, selection_rect.max.x] {
+                data_painter.line_segment(
+                    [
+                        Pos2::new(x, timeline_rect.min.y),
+                        Pos2::new(x, timeline_rect.max.y),
+                    ],
+                    Stroke::new(1.0, Color32::from_rgb(90, 150, 255)),
+                );
+            }
+        }
+
+        if let Some(detection) = state.phases {
+            for (i, phase) in detection.phases.iter().enumerate() {
+                let x_start = time_to_x(phase.start);
+                let x_end = time_to_x(phase.end);
+                if x_end < timeline_rect.min.x || x_start > timeline_rect.max.x {
+                    continue;
+                }
+                if i % 2 == 0 {
+                    data_painter.rect_filled(
+                        Rect::from_min_max(
+                            Pos2::new(x_start, timeline_rect.min.y),
+                            Pos2::new(x_end, timeline_rect.max.y),
+                        ),
+                        0.0,
+                        Color32::from_rgba_premultiplied(0, 40, 60, 30),
+                    );
+                }
+                data_painter.line_segment(
+                    [
+                        Pos2::new(x_start, timeline_rect.min.y),
+                        Pos2::new(x_start, timeline_rect.max.y),
+                    ],
+                    Stroke::new(1.0, Color32::from_rgb(60, 140, 180)),
+                );
+                data_painter.text(
+                    Pos2::new(x_start + 3.0, timeline_rect.min.y + 2.0),
+                    egui::Align2::LEFT_TOP,
+                    format!("#{}", i + 1),
+                    egui::FontId::proportional(9.0),
+                    Color32::from_rgb(60, 140, 180),
+                );
+            }
+        }
+
+        let grid_lines = tracks
+            .rows()
+            .iter()
+            .map(|r| (r.top, state.palette.grid))
+            .chain(
+                tracks
+                    .rows()
+                    .iter()
+                    .filter(|r| r.counters_height > 0.0)
+                    .map(|r| (r.top + r.counters_top(), state.palette.grid_minor)),
+            )
+            .chain(tracks.rows().iter().flat_map(|r| {
+                (1..r.sub_rows()).map(|k| (r.top + r.sub_row_top(k), state.palette.grid_minor))
+            }))
+            .chain(std::iter::once((tracks.total_height(), state.palette.grid)));
+        for (y_in_content, color) in grid_lines {
+            let y = content_to_y(y_in_content);
+            let line_painter = if y_in_content < tracks.pinned_height() {
+                &data_painter
+            } else {
+                &scroll_painter
+            };
+            if y >= timeline_rect.min.y && y <= timeline_rect.max.y {
+                line_painter.line_segment(
+                    [
+                        Pos2::new(timeline_rect.min.x, y),
+                        Pos2::new(timeline_rect.max.x, y),
+                    ],
+                    Stroke::new(1.0, color),
+                );
+            }
+        }
+
+        let mut hovered_event = None;
+        let mut hovered_index = None;
+        let mut hovered_sigma = None;
+        let mut hovered_flight: Option<completion::InFlight> = None;
+        let mut counts = DrawCounts::default();
+        // calls narrower than a pixel, which hide each other
+        let mut subpixel = 0;
+
+        // while dragging or zooming, events hidden under one already drawn
+        // in the same lane are skipped, then a repaint once the view settles
+        // draws everything
+        let shown = (view.0, view.1, self.pe_scroll, self.track_height);
+        let now = ui.input(|i| i.time);
+        if shown != self.last_view {
+            self.last_view = shown;
+            self.moved_at = now;
+        }
+        let since_moved = (now - self.moved_at) as f32;
+        // screenshots, exports and videos always get the full picture
+        let coarse = !state.full_detail && since_moved < TIMELINE_REFINE_DELAY;
+        if coarse {
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs_f32(
+                    TIMELINE_REFINE_DELAY - since_moved,
+                ));
+        }
+        // lane (by its top in screen pixels) -> pixel span drawn last in it
+        let mut covered: HashMap<u32, (f32, f32)> = HashMap::new();
+        // drawn calls with a target PE, for the message lines
+        let mut messages: Vec<(usize, Rect)> = Vec::new();
+
+        for i in data.overlapping(view.0, view.1) {
+            let e = data.event(i);
+            counts.scanned += 1;
+
+            // clamped to the view first, so a call spanning the whole trace
+            // stays in f32 range however far in the view is zoomed
+            let end = e.raw.time + e.raw.duration_sec.max(0.000000001);
+            let x_start = time_to_x(e.raw.time.max(timeline_start));
+            let x_end = time_to_x(end.min(timeline_end));
+
+            if x_end < timeline_rect.min.x || x_start > timeline_rect.max.x {
+                counts.culled += 1;
+                continue;
+            }
+            if !state.annotations.hidden_groups.is_empty()
+                && state
+                    .annotations
+                    .hidden_groups
+                    .contains(state.grouping.group(e.raw.function))
+            {
+                counts.filtered += 1;
+                continue;
+            }
+            if state.annotations.hidden_functions.contains(e.raw.function)
+                || state
+                    .annotations
+                    .solo_function
+                    .as_ref()
+                    .is_some_and(|f| *f != e.raw.function)
+                || !state.annotations.duration_filter.keeps(e.raw.duration_sec)
+            {
+                counts.filtered += 1;
+                continue;
+            }
+
+            // hidden track
+            let Some(row) = tracks.row_for_pe(e.source_pe) else {
+                counts.filtered += 1;
+                continue;
+            };
+            let y_start =
+                content_to_y(row.top) + row.lane_top(data.sub_row(&e), state.lanes.lane(i));
+            let y_end = y_start + row.lane_height;
+            let row_painter = if row.pinned {
+                &data_painter
+            } else {
+                &scroll_painter
+            };
+            let row_clip = row_painter.clip_rect();
+
+            if y_end < row_clip.min.y || y_start > row_clip.max.y {
+                counts.culled += 1;
+                continue;
+            }
+
+            let color = state.colors.event(&e);
+            let event_rect = Rect::from_min_max(
+                Pos2::new(x_start.max(timeline_rect.min.x), y_start + 1.0),
+                Pos2::new(x_end.min(timeline_rect.max.x), y_end - 1.0),
+            );
+
+            if event_rect.width() < 1.0 {
+                subpixel += 1;
+            }
+            if coarse {
+                let (x0, x1) = (event_rect.min.x.floor(), event_rect.max.x.ceil());
+                let span = covered.entry(y_start.to_bits()).or_insert((x0, x0));
+                if x0 >= span.0 && x1 <= span.1 {
+                    counts.merged += 1;
+                    continue;
+                }
+                *span = if x0 <= span.1 {
+                    (span.0.min(x0), span.1.max(x1))
+                } else {
+                    (x0, x1)
+                };
+                counts.drawn += 1;
+                row_painter.rect_filled(event_rect, 0.0, color);
+                continue;
+            }
+            counts.drawn += 1;
+            if e.raw.target_pe >= 0 && e.raw.target_pe as u32 != e.source_pe {
+                messages.push((i, event_rect));
+            }
+
+            if event_rect.width() > 2.0 {
+                row_painter.rect_filled(event_rect, 1.0, color);
+                row_painter.rect_stroke(
+                    event_rect,
+                    1.0,
+                    Stroke::new(0.5, Color32::BLACK.gamma_multiply(0.5)),
+                    StrokeKind::Inside,
+                );
+            } else {
+                row_painter.rect_filled(event_rect, 0.0, color);
+            }
+
+            if self.show_in_flight
+                && let Some(flight) = state.derived.completion.of(i)
+            {
+                let x1 = time_to_x(flight.end.min(timeline_end)).min(timeline_rect.max.x);
+                let hatch = Rect::from_min_max(
+                    Pos2::new(event_rect.max.x, event_rect.min.y + 2.0),
+                    Pos2::new(x1, event_rect.max.y - 2.0),
+                );
+                if hatch.width() >= 2.0 {
+                    draw_hatch(row_painter, hatch.intersect(row_clip), color);
+                    if let Some(mouse_pos) = response.hover_pos()
+                        && hatch.contains(mouse_pos)
+                        && row_clip.contains(mouse_pos)
+                    {
+                        hovered_flight = Some(*flight);
+                    }
+                }
+            }
+
+            if let Some(current) = state.search.hit(i) {
+                row_painter.rect_stroke(
+                    event_rect.expand(if current { 2.0 } else { 1.0 }),
+                    1.0,
+                    if current {
+                        Stroke::new(2.0, state.palette.cursor)
+                    } else {
+                        Stroke::new(1.5, Color32::from_rgb(0, 220, 255))
+                    },
+                    StrokeKind::Outside,
+                );
+            }
+
+            if state.pinned_event == Some(i) {
+                row_painter.rect_stroke(
+                    event_rect.expand(2.0),
+                    1.0,
+                    Stroke::new(2.0, Color32::YELLOW),
+                    StrokeKind::Outside,
+                );
+            }
+
+            let sigma = state.derived.anomaly_sigma.get(&i).copied();
+            if sigma.is_some() {
+                row_painter.rect_stroke(
+                    event_rect.expand(1.0),
+                    1.0,
+                    Stroke::new(1.5, Color32::from_rgb(255, 140, 0)),
+                    StrokeKind::Outside,
+                );
+            }
+
+            if let Some(mouse_pos) = response.hover_pos()
+                && event_rect.contains(mouse_pos)
+                && row_clip.contains(mouse_pos)
+            {
+                hovered_event = Some(e);
+                hovered_index = Some(i);
+                hovered_sigma = sigma;
+            }
+        }
+        self.counts = counts;
+
+        // from the call on its PE's track to the target PE's track, leaving
+        // at the start of the call and landing at its end. a send matched
+        // with its receive lands at the end of the receive instead
+        let highlighted = |i: usize| hovered_index == Some(i) || state.pinned_event == Some(i);
+        // middle of a call's lane at its start and end, even when it's off
+        // screen
+        let anchors = |j: usize| {
+            let e = data.event(j);
+            let row = tracks.row_for_pe(e.source_pe)?;
+            let y = content_to_y(row.top)
+                + row.lane_top(data.sub_row(&e), state.lanes.lane(j))
+                + row.lane_height / 2.0;
+            let x = |t: f64| time_to_x(t.clamp(timeline_start, timeline_end));
+            Some((
+                Pos2::new(x(e.raw.time), y),
+                Pos2::new(x(e.raw.time + e.raw.duration_sec), y),
+            ))
+        };
+        let mut matched_drawn = HashSet::new();
+        let mut lines = 0;
+        for &(i, from) in &messages {
+            let matched = state.derived.matching.of(i);
+            let highlight = highlighted(i)
+                || matched.is_some_and(|m| highlighted(m.send) || highlighted(m.recv));
+            if !highlight && (!self.message_lines_all || lines >= MAX_MESSAGE_LINES) {
+                continue;
+            }
+            let (p0, p1) = match matched {
+                Some(m) => {
+                    // both ends can be on screen, draw it once
+                    if !matched_drawn.insert(m.send) {
+                        continue;
+                    }
+                    let (Some((p0, _)), Some((_, p1))) = (anchors(m.send), anchors(m.recv)) else {
+                        continue;
+                    };
+                    (p0, p1)
+                }
+                None => {
+                    let e = data.event(i);
+                    let Some(target) = tracks.row_for_pe(e.raw.target_pe as u32) else {
+                        continue;
+                    };
+                    let end = e.raw.time + e.raw.duration_sec;
+                    let p1 = Pos2::new(
+                        time_to_x(end.clamp(timeline_start, timeline_end)),
+                        content_to_y(target.top) + target.events_height / 2.0,
+                    );
+                    (from.left_center(), p1)
+                }
+            };
+            lines += 1;
+            // bulges sideways when the call is too short to slope
+            let bulge = ((p1.y - p0.y).abs() * 0.15).min(40.0);
+            let mid = (p0.x + p1.x) / 2.0;
+            let c1 = Pos2::new(mid.max(p0.x + bulge), p0.y);
+            let c2 = Pos2::new(mid.max(p1.x - bulge).max(p0.x + bulge), p1.y);
+            let stroke = if highlight {
+                Stroke::new(2.0, Color32::YELLOW)
+            } else if matched.is_some() {
+                Stroke::new(1.0, Color32::from_rgb(120, 200, 255).gamma_multiply(0.5))
+            } else {
+                Stroke::new(1.0, Color32::WHITE.gamma_multiply(0.35))
+            };
+            data_painter.add(egui::epaint::CubicBezierShape::from_points_stroke(
+                [p0, c1, c2, p1],
+                false,
+                Color32::TRANSPARENT,
+                stroke,
+            ));
+            let back = (c2 - p1).normalized() * 7.0;
+            for side in [-0.45f32, 0.45] {
+                let (sin, cos) = side.sin_cos();
+                let tip = Vec2::new(back.x * cos - back.y * sin, back.x * sin + back.y * cos);
+                data_painter.line_segment([p1, p1 + tip], stroke);
+            }
+        }
+
+        let mut hovered_strip = None;
+        if self.show_overlap_strip {
+            // two pixels per bin
+            let bins = (timeline_rect.width() / 2.0).max(1.0) as usize;
+            let key = (timeline_start, timeline_end, bins);
+            if self.overlap_strip.as_ref().is_none_or(|(k, _)| *k != key) {
+                let shares = completion::overlap_bins(
+                    data,
+                    &state.derived.completion,
+                    timeline_start,
+                    timeline_end,
+                    bins,
+                );
+                self.overlap_strip = Some((key, shares));
+            }
+            let bin_width = timeline_rect.width() / bins as f32;
+            for row in tracks.rows() {
+                let Some(shares) = self
+                    .overlap_strip
+                    .as_ref()
+                    .and_then(|(_, strips)| strips.get(&row.pe))
+                else {
+                    continue;
+                };
+                let row_painter = if row.pinned {
+                    &data_painter
+                } else {
+                    &scroll_painter
+                };
+                let top = content_to_y(row.top + row.events_height);
+                let strip = Rect::from_min_max(
+                    Pos2::new(timeline_rect.min.x, top),
+                    Pos2::new(timeline_rect.max.x, top + row.strip_height - 1.0),
+                );
+                if !strip.intersects(row_painter.clip_rect()) {
+                    continue;
+                }
+                for (k, share) in shares.iter().enumerate() {
+                    let Some(share) = share else {
+                        continue;
+                    };
+                    let x = timeline_rect.min.x + k as f32 * bin_width;
+                    let bin = Rect::from_min_max(
+                        Pos2::new(x, strip.min.y),
+                        Pos2::new(x + bin_width, strip.max.y),
+                    );
+                    row_painter.rect_filled(bin, 0.0, overlap_color(*share));
+                }
+                if let Some(pos) = response.hover_pos()
+                    && strip.contains(pos)
+                    && row_painter.clip_rect().contains(pos)
+                {
+                    let k = (((pos.x - strip.min.x) / bin_width) as usize).min(shares.len() - 1);
+                    hovered_strip = Some((row.pe, shares[k]));
+                }
+            }
+        }
+
+        for row in tracks.rows().iter().filter(|r| r.counters_height > 0.0) {
+            let plot_top = content_to_y(row.top + row.counters_top()) + 2.0;
+            let plot_bottom = content_to_y(row.bottom()) - 2.0;
+            let row_painter = if row.pinned {
+                &data_painter
+            } else {
+                &scroll_painter
+            };
+            if plot_bottom < row_painter.clip_rect().min.y || plot_top > timeline_rect.max.y {
+                continue;
+            }
+            for name in state.visible_counters {
+                let Some(counter) = data.counters.get(name) else {
+                    continue;
+                };
+                let Some(samples) = counter.samples.get(&row.pe) else {
+                    continue;
+                };
+                let span = (counter.max - counter.min).max(f64::EPSILON);
+                let value_to_y = |v: f64| {
+                    let ratio = ((v - counter.min) / span) as f32;
+                    plot_bottom - ratio * (plot_bottom - plot_top)
+                };
+
+                // keep one sample either side of the view so the line reaches the edges
+                let first = samples
+                    .partition_point(|(t, _)| *t < timeline_start)
+                    .saturating_sub(1);
+                let last =
+                    (samples.partition_point(|(t, _)| *t <= timeline_end) + 1).min(samples.len());
+                // counters hold their value until the next sample, so draw steps
+                let mut points: Vec<Pos2> = Vec::with_capacity((last - first) * 2);
+                for (t, v) in &samples[first..last] {
+                    let x = time_to_x(*t);
+                    let y = value_to_y(*v);
+                    if let Some(prev) = points.last().copied() {
+                        points.push(Pos2::new(x, prev.y));
+                    }
+                    points.push(Pos2::new(x, y));
+                }
+                if let Some(prev) = points.last().copied() {
+                    points.push(Pos2::new(
+                        timeline_rect.max.x.min(time_to_x(data.max_time)),
+                        prev.y,
+                    ));
+                }
+                if points.len() > 1 {
+                    row_painter.add(egui::Shape::line(
+                        points,
+                        Stroke::new(1.0, generate_color(name)),
+                    ));
+                }
+            }
+        }
+
+        let label_area_rect =
+            Rect::from_min_max(rect.min, Pos2::new(timeline_rect.min.x, rect.max.y));
+        painter.rect_filled(label_area_rect, 0.0, state.palette.label_bg);
+
+        //painter.line_segment(
+        //[
+        //Pos2::new(rect.min.x, timeline_rect.min.y),
+        //Pos2::new(timeline_rect.min.x, rect.max.y),
+        //],
+        //Stroke::new(1.0, Color32::from_gray(40)),
+        //);
+
+        let pinned_labels_painter = painter.with_clip_rect(label_area_rect);
+        let scroll_labels_painter = painter.with_clip_rect(Rect::from_min_max(
+            Pos2::new(label_area_rect.min.x, scroll_rect.min.y),
+            label_area_rect.max,
+        ));
+        for row in tracks.rows() {
+            let i = row.pe;
+            let y = content_to_y(row.top);
+            let labels_painter = if row.pinned {
+                &pinned_labels_painter
+            } else {
+                &scroll_labels_painter
+            };
+            if y + row.height() < labels_painter.clip_rect().min.y {
+                continue;
+            }
+            if y > timeline_rect.max.y {
+                break;
+            }
+
+            let hostname = data.pe_hostnames.get(&i).cloned().unwrap_or_default();
+            // a labelled PE keeps its number next to the host
+            let subtitle = match state.pe_labels.label(i) {
+                Some(_) => format!("PE {i} {hostname}"),
+                None => hostname,
+            };
+            let missing = state.derived.missing_pes.binary_search(&i).is_ok();
+            let (subtitle, subtitle_color) = if missing {
+                (format!("no file {subtitle}"), MISSING_PE)
+            } else {
+                (subtitle, state.palette.text_faint)
+            };
+
+            if state.selected_pe == Some(i) || row.pinned {
+                labels_painter.rect_filled(
+                    Rect::from_min_size(
+                        Pos2::new(rect.min.x, y),
+                        Vec2::new(label_width, row.height()),
+                    ),
+                    0.0,
+                    if state.selected_pe == Some(i) {
+                        state.palette.label_selected
+                    } else {
+                        state.palette.label_pinned
+                    },
+                );
+            }
+
+            if row.counters_height > 0.0 {
+                let names = state
+                    .visible_counters
+                    .iter()
+                    .filter(|n| {
+                        data.counters
+                            .get(*n)
+                            .is_some_and(|c| c.samples.contains_key(&i))
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                labels_painter.text(
+                    Pos2::new(rect.min.x + 12.0, y + row.counters_top() + 2.0),
+                    egui::Align2::LEFT_TOP,
+                    names,
+                    egui::FontId::proportional(8.0),
+                    state.palette.text_weak,
+                );
+            }
+
+            labels_painter.text(
+                Pos2::new(rect.min.x + 5.0, y + 2.0),
+                egui::Align2::LEFT_TOP,
+                state.pe_labels.name(i),
+                egui::FontId::proportional(11.0),
+                state.palette.text,
+            );
+
+            labels_painter.text(
+                Pos2::new(rect.min.x + 5.0, y + 12.0),
+                egui::Align2::LEFT_TOP,
+                subtitle,
+                egui::FontId::proportional(8.0),
+                subtitle_color,
+            );
+
+            if let Some(contexts) = data.pe_contexts.get(&i) {
+                for (k, context) in contexts.iter().enumerate() {
+                    labels_painter.text(
+                        Pos2::new(
+                            label_area_rect.max.x - 4.0,
+                            y + row.sub_row_top(k) + row.sub_row_height(k) / 2.0,
+                        ),
+                        egui::Align2::RIGHT_CENTER,
+                        match context {
+                            Some(c) => format!("t{c}"),
+                            None => "main".to_string(),
+                        },
+                        egui::FontId::proportional(8.0),
+                        state.palette.text_weak,
+                    );
+                }
+            }
+        }
+
+        if tracks.pinned_height() > 0.0 {
+            painter.line_segment(
+                [
+                    Pos2::new(rect.min.x, scroll_rect.min.y),
+                    Pos2::new(rect.max.x, scroll_rect.min.y),
+                ],
+                Stroke::new(1.5, state.palette.separator),
+            );
+        }
+
+        let ruler_area_rect =
+            Rect::from_min_max(rect.min, Pos2::new(rect.max.x, timeline_rect.min.y));
+        painter.rect_filled(ruler_area_rect, 0.0, state.palette.ruler_bg);
+
+        painter.line_segment(
+            [
+                Pos2::new(rect.min.x, timeline_rect.min.y),
+                Pos2::new(rect.max.x, timeline_rect.min.y),
+            ],
+            Stroke::new(1.0, state.palette.ruler_border),
+        );
+
+        let ruler_painter = painter.with_clip_rect(ruler_area_rect);
+        if self.ruler_density {
+            paint_ruler_density(
+                data,
+                &ruler_painter,
+                ruler_area_rect,
+                timeline_rect,
+                view,
+                state.palette.text_weak.gamma_multiply(0.7),
+            );
+        }
+        let ticks = ruler::ticks(
+            view.0,
+            view.1,
+            timeline_rect.width(),
+            100.0,
+            state.time_display,
+            data.min_time,
+            data.epoch,
+        );
+        for tick in ticks {
+            let x = time_to_x(tick.time);
+            ruler_painter.line_segment(
+                [
+                    Pos2::new(x, ruler_area_rect.min.y),
+                    Pos2::new(x, ruler_area_rect.max.y),
+                ],
+                Stroke::new(1.0, state.palette.tick),
+            );
+            ruler_painter.text(
+                Pos2::new(x + 2.0, ruler_area_rect.min.y + 2.0),
+                egui::Align2::LEFT_TOP,
+                tick.label,
+                egui::FontId::proportional(10.0),
+                state.palette.text,
+            );
+        }
+        // the date doesn't fit on every tick, show it once in the corner
+        if state.time_display == TimeDisplay::WallClock
+            && let Some(epoch) = data.epoch
+        {
+            painter.text(
+                Pos2::new(rect.min.x + 5.0, rect.min.y + 2.0),
+                egui::Align2::LEFT_TOP,
+                ruler::format_date(epoch + view.0),
+                egui::FontId::proportional(10.0),
+                state.palette.text,
+            );
+        }
+
+        // loop region
+        if let (Some(a), Some(b)) = (state.loop_a, state.loop_b) {
+            let alpha = if state.loop_enabled { 40 } else { 15 };
+            let loop_rect = Rect::from_min_max(
+                Pos2::new(time_to_x(a.min(b)), ruler_area_rect.min.y),
+                Pos2::new(time_to_x(a.max(b)), ruler_area_rect.max.y),
+            );
+            ruler_painter.rect_filled(
+                loop_rect,
+                0.0,
+                Color32::from_rgba_unmultiplied(80, 220, 120, alpha),
+            );
+        }
+        // the bandwidth window around the cursor, with grips on its edges
+        let half = window / 2.0;
+        let (wx0, wx1) = (
+            time_to_x(state.cursor - half),
+            time_to_x(state.cursor + half),
+        );
+        let window_color = Color32::from_rgb(220, 200, 40);
+        ruler_painter.rect_filled(
+            Rect::from_min_max(
+                Pos2::new(wx0, ruler_area_rect.max.y - 10.0),
+                Pos2::new(wx1, ruler_area_rect.max.y),
+            ),
+            0.0,
+            window_color.gamma_multiply(if self.window_drag { 0.35 } else { 0.15 }),
+        );
+        for x in [wx0, wx1] {
+            ruler_painter.line_segment(
+                [
+                    Pos2::new(x, ruler_area_rect.max.y - 10.0),
+                    Pos2::new(x, ruler_area_rect.max.y),
+                ],
+                Stroke::new(2.0, window_color),
+            );
+        }
+
+        for (label, time) in [("A", state.loop_a), ("B", state.loop_b)] {
+            let Some(time) = time else {
+                continue;
+            };
+            let x = time_to_x(time);
+            let color = Color32::from_rgb(80, 220, 120);
+            ruler_painter.line_segment(
+                [
+                    Pos2::new(x, ruler_area_rect.min.y),
+                    Pos2::new(x, ruler_area_rect.max.y),
+                ],
+                Stroke::new(1.5, color),
+            );
+            ruler_painter.text(
+                Pos2::new(x, ruler_area_rect.max.y - 1.0),
+                egui::Align2::CENTER_BOTTOM,
+                label,
+                egui::FontId::monospace(10.0),
+                color,
+            );
+        }
+
+        // bookmark flags
+        let flag_color = Color32::from_rgb(255, 160, 40);
+        for b in state.annotations.bookmarks.iter() {
+            let x = time_to_x(b.time);
+            if x < timeline_rect.min.x || x > timeline_rect.max.x {
+                continue;
+            }
+            data_painter.line_segment(
+                [
+                    Pos2::new(x, timeline_rect.min.y),
+                    Pos2::new(x, timeline_rect.max.y),
+                ],
+                Stroke::new(1.0, flag_color.gamma_multiply(0.4)),
+            );
+            let top = ruler_area_rect.min.y + 14.0;
+            ruler_painter.line_segment(
+                [Pos2::new(x, top), Pos2::new(x, ruler_area_rect.max.y)],
+                Stroke::new(1.0, flag_color),
+            );
+            ruler_painter.add(egui::Shape::convex_polygon(
+                vec![
+                    Pos2::new(x, top),
+                    Pos2::new(x + 8.0, top + 3.5),
+                    Pos2::new(x, top + 7.0),
+                ],
+                flag_color,
+                Stroke::NONE,
+            ));
+            ruler_painter.text(
+                Pos2::new(x + 10.0, top),
+                egui::Align2::LEFT_TOP,
+                &b.name,
+                egui::FontId::proportional(9.0),
+                flag_color,
+            );
+        }
+
+        let px = time_to_x(state.cursor);
+        if px >= timeline_rect.min.x && px <= timeline_rect.max.x {
+            painter.line_segment(
+                [Pos2::new(px, rect.min.y), Pos2::new(px, rect.max.y)],
+                Stroke::new(1.0, state.palette.cursor),
+            );
+            let head_size = 6.0;
+            painter.add(egui::Shape::convex_polygon(
+                vec![
+                    Pos2::new(px, timeline_rect.min.y),
+                    Pos2::new(px - head_size, timeline_rect.min.y - head_size),
+                    Pos2::new(px + head_size, timeline_rect.min.y - head_size),
+                ],
+                state.palette.cursor,
+                Stroke::NONE,
+            ));
+        }
+
+        if let Some(b) = state.cursor_b {
+            let bx = time_to_x(b);
+            let (lo, hi) = (px.min(bx), px.max(bx));
+            let span = Rect::from_min_max(
+                Pos2::new(lo.max(timeline_rect.min.x), ruler_area_rect.min.y),
+                Pos2::new(hi.min(timeline_rect.max.x), ruler_area_rect.max.y),
+            );
+            if span.width() > 0.0 {
+                painter.rect_filled(span, 0.0, Color32::from_rgba_unmultiplied(255, 200, 0, 30));
+            }
+            if bx >= timeline_rect.min.x && bx <= timeline_rect.max.x {
+                painter.line_segment(
+                    [Pos2::new(bx, rect.min.y), Pos2::new(bx, rect.max.y)],
+                    Stroke::new(1.0, Color32::YELLOW),
+                );
+                painter.text(
+                    Pos2::new(bx + 2.0, ruler_area_rect.max.y - 2.0),
+                    egui::Align2::LEFT_BOTTOM,
+                    "M",
+                    egui::FontId::proportional(9.0),
+                    Color32::YELLOW,
+                );
+            }
+        }
+
+        if let Some(pos) = response.hover_pos() {
+            if timeline_rect.contains(pos) {
+                commands.push(ViewCommand::Hover(x_to_time(pos.x)));
+            }
+
+            if (response.clicked() || response.dragged())
+                && !self.window_drag
+                && (ruler_area_rect.contains(pos)
+                    || (timeline_rect.contains(pos) && ui.input(|i| i.modifiers.shift)))
+            {
+                let t = x_to_time(pos.x).clamp(data.min_time, data.max_time);
+                commands.push(ViewCommand::SetCursor(t));
+            }
+
+            // ctrl+click drops a bookmark on the event under the pointer
+            if response.clicked()
+                && ui.input(|i| i.modifiers.command)
+                && let Some(e) = hovered_event
+            {
+                commands.push(ViewCommand::AddBookmark(
+                    e.raw.function.to_string(),
+                    e.raw.time,
+                    Some(e.source_pe),
+                ));
+            } else if response.clicked()
+                && !ui.input(|i| i.modifiers.shift)
+                && timeline_rect.contains(pos)
+                && let Some(i) = hovered_index
+            {
+                // plain click pins the tooltip
+                commands.push(ViewCommand::PinEvent(i, Some(pos + Vec2::new(16.0, 16.0))));
+            }
+        }
+
+        // clicking a label selects that PE
+        if response.clicked()
+            && let Some(pos) = response.interact_pointer_pos()
+            && label_area_rect.contains(pos)
+            && pos.y > timeline_rect.min.y
+            && let Some(pe) = tracks.row_at(y_to_content(pos.y)).map(|r| r.pe)
+        {
+            commands.push(ViewCommand::SelectPe(
+                (state.selected_pe != Some(pe)).then_some(pe),
+            ));
+        }
+
+        if self.track_drag.is_some()
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            let y = content_to_y(drop_target(pos.y).map_or(tracks.total_height(), |r| r.top));
+            painter.line_segment(
+                [Pos2::new(rect.min.x, y), Pos2::new(rect.max.x, y)],
+                Stroke::new(2.0, Color32::from_rgb(90, 150, 255)),
+            );
+        }
+
+        if response.secondary_clicked()
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            if label_area_rect.contains(pos) {
+                self.context_time = None;
+                self.context_pe = tracks.row_at(y_to_content(pos.y)).map(|r| r.pe);
+                self.context_event = None;
+            } else {
+                self.context_time = Some(x_to_time(pos.x));
+                self.context_pe = None;
+                self.context_event = hovered_index;
+            }
+        }
+        response.context_menu(|ui| {
+            if let Some(pe) = self.context_pe {
+                ui.label(egui::RichText::new(state.pe_labels.name(pe)).small());
+                if ui.button("Solo this PE").clicked() {
+                    commands.push(ViewCommand::SoloTrack(pe));
+                    ui.close();
+                }
+                if ui.button("Jump to first event").clicked() {
+                    if let Some(e) = data.events.iter().find(|e| e.source_pe == pe) {
+                        commands.push(ViewCommand::JumpTo(e.raw.time));
+                    }
+                    ui.close();
+                }
+                if ui.button("Jump to last event").clicked() {
+                    if let Some(e) = data.events.iter().rev().find(|e| e.source_pe == pe) {
+                        commands.push(ViewCommand::JumpTo(e.raw.time));
+                    }
+                    ui.close();
+                }
+                ui.separator();
+                let pinned = state.track_order.pinned.contains(&pe);
+                if ui
+                    .button(if pinned { "Unpin track" } else { "Pin track" })
+                    .clicked()
+                {
+                    commands.push(ViewCommand::PinTrack(pe, !pinned));
+                    ui.close();
+                }
+                if ui.button("Hide track").clicked() {
+                    commands.push(ViewCommand::HideTrack(pe));
+                    ui.close();
+                }
+                let first = order.first().copied();
+                if ui.button("Move to top").clicked() {
+                    commands.push(ViewCommand::MoveTrack(pe, first));
+                    ui.close();
+                }
+                if ui.button("Move to bottom").clicked() {
+                    commands.push(ViewCommand::MoveTrack(pe, None));
+                    ui.close();
+                }
+                return;
+            }
+            if let Some(idx) = self.context_event
+                && let Some(e) = data.events.get(idx)
+            {
+                let function = e.raw.function;
+                ui.label(egui::RichText::new(function).strong());
+                if ui.button("Show only this function").clicked() {
+                    commands.push(ViewCommand::Solo(Some(function.to_string())));
+                    ui.close();
+                }
+                if ui.button("Hide this function").clicked() {
+                    commands.push(ViewCommand::HideFunction(function.to_string()));
+                    ui.close();
+                }
+                if ui.button("Copy details").clicked() {
+                    ui.ctx()
+                        .copy_text(panes::event_details_text(data, state, &e));
+                    commands.push(ViewCommand::Status("copied event details".to_string()));
+                    ui.close();
+                }
+                if ui.button("Zoom to event").clicked() {
+                    let (start, end) = (e.raw.time, e.raw.time + e.raw.duration_sec);
+                    let pad = ((end - start) * 0.25).max(1e-6);
+                    commands.push(ViewCommand::SetView(start - pad, end + pad));
+                    ui.close();
+                }
+                if ui.button("Pin details").clicked() {
+                    commands.push(ViewCommand::PinEvent(idx, None));
+                    ui.close();
+                }
+                ui.separator();
+            }
+            let Some(t) = self.context_time else {
+                ui.close();
+                return;
+            };
+            ui.label(egui::RichText::new(format!("{:.6}s", t)).small());
+            if ui.button("Set loop A here").clicked() {
+                commands.push(ViewCommand::LoopA(t));
+                ui.close();
+            }
+            if ui.button("Set loop B here").clicked() {
+                commands.push(ViewCommand::LoopB(t));
+                ui.close();
+            }
+            if ui.button("Add bookmark here").clicked() {
+                let name = format!("Bookmark {}", state.annotations.bookmarks.len() + 1);
+                commands.push(ViewCommand::AddBookmark(name, t, None));
+                ui.close();
+            }
+            if ui.button("Set second cursor here (M)").clicked() {
+                commands.push(ViewCommand::SetCursorB(Some(t)));
+                ui.close();
+            }
+            if state.cursor_b.is_some() && ui.button("Remove second cursor").clicked() {
+                commands.push(ViewCommand::SetCursorB(None));
+                ui.close();
+            }
+            if let Some((a, b)) = selection {
+                ui.separator();
+                if ui.button("Loop selection").clicked() {
+                    commands.push(ViewCommand::Loop(a, b));
+                    ui.close();
+                }
+                if ui.button("Clear selection").clicked() {
+                    commands.push(ViewCommand::Deselect);
+                    ui.close();
+                }
+            }
+        });
+
+        if let Some(start) = capture {
+            let view =
+                export::capture_end(ui.ctx(), ui.layer_id(), start, rect, state.palette.plot_bg);
+            commands.push(ViewCommand::Captured(ExportTarget::Timeline, view));
+        }
+
+        // how many of the calls in the visible range made it to the screen,
+        // left out of exports
+        let t = self.counts;
+        let saturated = subpixel > SATURATION_MIN_CALLS && subpixel * 2 > t.drawn + t.merged;
+        let badge = if saturated {
+            format!(
+                "{} / {} calls drawn, saturated: zoom in",
+                t.drawn, t.scanned
+            )
+        } else {
+            format!("{} / {} calls drawn", t.drawn, t.scanned)
+        };
+        let galley = painter.layout_no_wrap(
+            badge,
+            egui::FontId::proportional(11.0),
+            if saturated {
+                Color32::from_rgb(255, 170, 60)
+            } else {
+                Color32::from_gray(220)
+            },
+        );
+        let badge_rect = egui::Align2::RIGHT_TOP
+            .anchor_size(
+                timeline_rect.right_top() + Vec2::new(-8.0, 6.0),
+                galley.size(),
+            )
+            .expand(3.0);
+        painter.rect_filled(badge_rect, 3.0, Color32::from_black_alpha(170));
+        painter.galley(badge_rect.min + Vec2::splat(3.0), galley, Color32::WHITE);
+        ui.interact(badge_rect, ui.id().with("draw_badge"), Sense::hover())
+            .on_hover_text(format!(
+                "{} calls overlap the visible range\n{} drawn\n{} merged into calls drawn over \
+                 them while moving\n{} outside the visible area\n{} hidden by filters\n\
+                 {subpixel} narrower than a pixel{}",
+                t.scanned,
+                t.drawn,
+                t.merged,
+                t.culled,
+                t.filtered,
+                if saturated {
+                    "\n\nmost calls share pixels with others, so colors and gaps \
+                     are unreliable here: zoom in for an accurate picture"
+                } else {
+                    ""
+                }
+            ));
+
+        if let Some(e) = hovered_event {
+            let ctx = ui.ctx().clone();
+            egui::Tooltip::always_open(
+                ctx,
+                LayerId::new(Order::Tooltip, Id::new("hover_tooltip")),
+                Id::new("hover_tooltip"),
+                PopupAnchor::Pointer,
+            )
+            .show(|ui: &mut egui::Ui| {
+                panes::ui_event_details(ui, data, state, &e, hovered_sigma);
+                ui.small("click to pin");
+            });
+        } else if let Some((pe, share)) = hovered_strip {
+            let ctx = ui.ctx().clone();
+            egui::Tooltip::always_open(
+                ctx,
+                LayerId::new(Order::Tooltip, Id::new("strip_tooltip")),
+                Id::new("strip_tooltip"),
+                PopupAnchor::Pointer,
+            )
+            .show(|ui: &mut egui::Ui| {
+                ui.strong(state.pe_labels.name(pe));
+                match share {
+                    Some(share) => ui.label(format!(
+                        "{:.0}% of communication here overlapped computation, {:.0}% blocked",
+                        share * 100.0,
+                        (1.0 - share) * 100.0
+                    )),
+                    None => ui.label("no communication here"),
+                };
+            });
+        } else if let Some(flight) = hovered_flight {
+            let ctx = ui.ctx().clone();
+            egui::Tooltip::always_open(
+                ctx,
+                LayerId::new(Order::Tooltip, Id::new("flight_tooltip")),
+                Id::new("flight_tooltip"),
+                PopupAnchor::Pointer,
+            )
+            .show(|ui: &mut egui::Ui| {
+                let init = data.event(flight.init).raw;
+                ui.strong(format!("{} in flight", init.function));
+                ui.label(format!(
+                    "{} after returning, until {} returned",
+                    units::duration(flight.end - flight.start),
+                    data.event(flight.done).raw.function
+                ));
+            });
+        }
+
+        commands
+    }
+}
+
+// calls starting in each pixel column of the view, over all PEs, as
+// bars along the bottom of the ruler. log scaled so quiet stretches
+// still show next to busy ones
+fn paint_ruler_density(
+    data: &ProfileData,
+    painter: &egui::Painter,
+    ruler_rect: Rect,
+    timeline_rect: Rect,
+    (start, end): (f64, f64),
+    color: Color32,
+) {
+    let columns = timeline_rect.width().max(1.0) as usize;
+    let column_start = |k: usize| start + (end - start) * k as f64 / columns as f64;
+    let times = &data.events.time;
+    let mut before = times.partition_point(|&t| t < start);
+    let counts: Vec<usize> = (1..=columns)
+        .map(|k| {
+            let next = times.partition_point(|&t| t < column_start(k));
+            let n = next - before;
+            before = next;
+            n
+        })
+        .collect();
+    let peak = counts.iter().copied().max().unwrap_or(0);
+    if peak == 0 {
+        return;
+    }
+    let width = timeline_rect.width() / columns as f32;
+    let scale = RULER_DENSITY_HEIGHT / (peak as f32 + 1.0).ln();
+    for (k, &n) in counts.iter().enumerate().filter(|(_, n)| **n > 0) {
+        let x = timeline_rect.min.x + k as f32 * width;
+        let top = ruler_rect.max.y - (n as f32 + 1.0).ln() * scale;
+        painter.rect_filled(
+            Rect::from_min_max(Pos2::new(x, top), Pos2::new(x + width, ruler_rect.max.y)),
+            0.0,
+            color,
+        );
+    }
+}
+
+// scales [start, end] by `factor` around the time `ratio` of the way
+// across it, keeping at least a nanosecond in view
+fn zoom_at(start: &mut f64, end: &mut f64, ratio: f64, factor: f64) {
+    let at = *start + ratio * (*end - *start);
+    *start = at - (at - *start) * factor;
+    *end = at + (*end - at) * factor;
+    if *end - *start < 0.000000001 {
+        let center = (*start + *end) / 2.0;
+        *start = center - 0.0000000005;
+        *end = center + 0.0000000005;
+    }
+}
+
+// red when communication blocked the PE, green when it overlapped
+// computation
+fn overlap_color(share: f32) -> Color32 {
+    let lerp = |a: f32, b: f32| (a + (b - a) * share) as u8;
+    Color32::from_rgb(lerp(220.0, 60.0), lerp(60.0, 200.0), lerp(60.0, 90.0))
+}
+
+// diagonal stripes over `rect`, with its outline
+fn draw_hatch(painter: &egui::Painter, rect: Rect, color: Color32) {
+    if !rect.is_positive() {
+        return;
+    }
+    let painter = painter.with_clip_rect(rect);
+    let stroke = Stroke::new(1.0, color.gamma_multiply(0.8));
+    let h = rect.height();
+    let mut x = rect.min.x - h;
+    while x < rect.max.x {
+        painter.line_segment(
+            [Pos2::new(x, rect.max.y), Pos2::new(x + h, rect.min.y)],
+            stroke,
+        );
+        x += 5.0;
+    }
+    painter.rect_stroke(rect, 0.0, stroke, StrokeKind::Inside);
+}
@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// which PE tracks are shown and in what order. PEs missing from `order`
+// (e.g. after loading a bigger run) go after the ordered ones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackOrder {
+    pub order: Vec<u32>,
+    pub hidden: BTreeSet<u32>,
+    // kept above the scrolling tracks
+    pub pinned: BTreeSet<u32>,
+}
+
+impl TrackOrder {
+    // visible PEs, top to bottom, pinned ones first
+    pub fn visible(&self, pe_count: u32) -> Vec<u32> {
+        let mut seen = vec![false; pe_count as usize];
+        let mut out = Vec::with_capacity(pe_count as usize);
+        let rest = 0..pe_count;
+        for pe in self.order.iter().copied().chain(rest) {
+            if pe < pe_count && !std::mem::replace(&mut seen[pe as usize], true) {
+                out.push(pe);
+            }
+        }
+        out.retain(|pe| !self.hidden.contains(pe));
+        // stable, so pinned tracks keep their relative order
+        out.sort_by_key(|pe| !self.pinned.contains(pe));
+        out
+    }
+
+    // puts `pe` right before `before` (or last for None), both visible PEs
+    pub fn move_pe(&mut self, pe_count: u32, pe: u32, before: Option<u32>) {
+        let mut full = Self {
+            order: self.order.clone(),
+            ..Default::default()
+        }
+        .visible(pe_count);
+        full.retain(|p| *p != pe);
+        let idx = before
+            .and_then(|b| full.iter().position(|p| *p == b))
+            .unwrap_or(full.len());
+        full.insert(idx, pe);
+        self.order = full;
+    }
+
+    // orders every PE by `key`, largest first, ties by PE
+    pub fn sort_by(&mut self, pe_count: u32, key: impl Fn(u32) -> f64) {
+        let mut order: Vec<u32> = (0..pe_count).collect();
+        order.sort_by(|a, b| key(*b).total_cmp(&key(*a)).then(a.cmp(b)));
+        self.order = order;
+    }
+}
+
+// vertical placement of the PE tracks on the timeline, in content
+// coordinates (before scrolling)
+#[derive(Debug, Clone)]
+pub struct TrackRow {
+    pub pe: u32,
+    pub top: f32,
+    // the strip events are drawn in: one sub-row per thread / context, each
+    // split into lanes for overlapping events
+    pub events_height: f32,
+    pub lanes: Vec<usize>,
+    pub lane_height: f32,
+    // overlap strip right under the events, 0 when it's off
+    pub strip_height: f32,
+    // counter plot under that, 0 when there is none
+    pub counters_height: f32,
+    pub pinned: bool,
+}
+
+impl TrackRow {
+    pub fn height(&self) -> f32 {
+        self.events_height + self.strip_height + self.counters_height
+    }
+
+    // offset of the counter plot from the top of the row
+    pub fn counters_top(&self) -> f32 {
+        self.events_height + self.strip_height
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.top + self.height()
+    }
+
+    pub fn sub_rows(&self) -> usize {
+        self.lanes.len()
+    }
+
+    // offset of sub-row `k` from the top of the row
+    pub fn sub_row_top(&self, k: usize) -> f32 {
+        self.lanes[..k].iter().sum::<usize>() as f32 * self.lane_height
+    }
+
+    pub fn sub_row_height(&self, k: usize) -> f32 {
+        self.lanes[k] as f32 * self.lane_height
+    }
+
+    // offset of a lane of a sub-row from the top of the row
+    pub fn lane_top(&self, sub_row: usize, lane: usize) -> f32 {
+        self.sub_row_top(sub_row) + lane as f32 * self.lane_height
+    }
+}
+
+pub struct TrackLayout {
+    rows: Vec<TrackRow>,
+    // pe -> index into rows
+    row_of_pe: Vec<Option<usize>>,
+    // pinned rows come first and don't scroll
+    pinned_height: f32,
+}
+
+impl TrackLayout {
+    // one row per PE in `order`, top to bottom. the first `pinned` are pinned.
+    // `lanes` gives the lanes of each sub-row of a PE, `track_height` is per lane
+    pub fn new(
+        order: &[u32],
+        pinned: usize,
+        pe_count: u32,
+        track_height: f32,
+        strip_height: f32,
+        lanes: impl Fn(u32) -> Vec<usize>,
+        counters_height: impl Fn(u32) -> f32,
+    ) -> Self {
+        let mut rows = Vec::with_capacity(order.len());
+        let mut row_of_pe = vec![None; pe_count as usize];
+        let mut top = 0.0;
+        let mut pinned_height = 0.0;
+        for (i, &pe) in order.iter().enumerate() {
+            let mut lanes = lanes(pe);
+            if lanes.is_empty() {
+                lanes.push(1);
+            }
+            let row = TrackRow {
+                pe,
+                top,
+                events_height: track_height * lanes.iter().sum::<usize>() as f32,
+                lanes,
+                lane_height: track_height,
+                strip_height,
+                counters_height: counters_height(pe),
+                pinned: i < pinned,
+            };
+            top = row.bottom();
+            if row.pinned {
+                pinned_height = top;
+            }
+            row_of_pe[pe as usize] = Some(rows.len());
+            rows.push(row);
+        }
+        Self {
+            rows,
+            row_of_pe,
+            pinned_height,
+        }
+    }
+
+    pub fn pinned_height(&self) -> f32 {
+        self.pinned_height
+    }
+
+    // content y shown `offset` pixels below the top of the track area
+    pub fn content_y(&self, offset: f32, scroll: f32) -> f32 {
+        if offset < self.pinned_height {
+            offset
+        } else {
+            offset + scroll
+        }
+    }
+
+    // inverse of content_y
+    pub fn screen_offset(&self, y: f32, scroll: f32) -> f32 {
+        if y < self.pinned_height {
+            y
+        } else {
+            y - scroll
+        }
+    }
+
+    pub fn rows(&self) -> &[TrackRow] {
+        &self.rows
+    }
+
+    pub fn row_for_pe(&self, pe: u32) -> Option<&TrackRow> {
+        let idx = (*self.row_of_pe.get(pe as usize)?)?;
+        self.rows.get(idx)
+    }
+
+    // row containing content-space `y`
+    pub fn row_at(&self, y: f32) -> Option<&TrackRow> {
+        let idx = self.rows.partition_point(|r| r.bottom() <= y);
+        self.rows.get(idx).filter(|r| r.top <= y)
+    }
+
+    pub fn total_height(&self) -> f32 {
+        self.rows.last().map_or(0.0, |r| r.bottom())
+    }
+}
@@ -0,0 +1,1038 @@
+use egui::{Color32, Pos2, Rect, Sense, Stroke, Vec2};
+use std::collections::HashMap;
+
+use crate::bandwidth::{
+    self, BandwidthMode, EdgeScale, EdgeScaling, EdgeWeight, MuteStyle, Muting,
+};
+use crate::chord;
+use crate::data::ProfileData;
+use crate::dock::Pane;
+use crate::export::{self, ExportTarget};
+use crate::layout::{self, ForceLayout, HostGroups, Placement, RingGrouping};
+use crate::units;
+use crate::views::{View, ViewCommand, ViewState};
+
+// the narrowest bandwidth window
+pub const MIN_WINDOW_SECONDS: f64 = 0.0001;
+// outline of a ring node sending or receiving at the peak bandwidth
+const GAUGE_SATURATED: Color32 = Color32::from_rgb(255, 210, 0);
+
+// edge clicked in the ring plus the window it was aggregated over
+#[derive(Clone, Copy)]
+pub struct InspectedEdge {
+    pub edge: (u32, u32),
+    pub start: f64,
+    pub end: f64,
+    // edge is between hosts rather than PEs
+    pub hosts: bool,
+}
+
+// who sent how much to whom around the cursor, as a ring of nodes or a
+// chord diagram
+pub struct Bandwidth {
+    pub show_talkers: bool,
+    pub talkers_count: usize,
+    pub highlighted_edge: Option<(u32, u32)>,
+    pub scaling: EdgeScaling,
+    pub muting: Muting,
+    pub edge_label_limit: usize,
+    pub show_local: bool,
+    pub show_collectives: bool,
+    pub show_gauges: bool,
+    pub peak_bandwidth: f64,
+    pub inspected_edge: Option<InspectedEdge>,
+    pub mode: BandwidthMode,
+    pub ring_grouping: RingGrouping,
+    pub placement: Placement,
+    // the selection is shown instead of the window around the cursor
+    pub on_selection: bool,
+    // edges, rings and spokes the last frame drew, for the HUD
+    pub edges_drawn: usize,
+
+    // ring node kept in focus after a click, see Muting::lock_on_click
+    locked_pe: Option<u32>,
+    force_layout: Option<ForceLayout>,
+    // comms of the last window
+    cache: bandwidth::AggregateCache,
+}
+
+impl Default for Bandwidth {
+    fn default() -> Self {
+        Self {
+            show_talkers: true,
+            talkers_count: 10,
+            highlighted_edge: None,
+            scaling: EdgeScaling::default(),
+            muting: Muting::default(),
+            edge_label_limit: 12,
+            show_local: false,
+            show_collectives: true,
+            show_gauges: false,
+            peak_bandwidth: 12.5e9,
+            inspected_edge: None,
+            mode: BandwidthMode::Graph,
+            ring_grouping: RingGrouping::Flat,
+            placement: Placement::Ring,
+            on_selection: true,
+            edges_drawn: 0,
+            locked_pe: None,
+            force_layout: None,
+            cache: Default::default(),
+        }
+    }
+}
+
+impl Bandwidth {
+    // forgets what belonged to the last trace or layout
+    pub fn reset(&mut self) {
+        self.locked_pe = None;
+        self.force_layout = None;
+    }
+
+    // places the nodes again, starting from the ring
+    pub fn relayout(&mut self) {
+        self.force_layout = None;
+    }
+
+    // drops what was made from the event times
+    pub fn invalidate(&mut self) {
+        self.cache.clear();
+    }
+
+    // how edges are weighed, scaled and labelled, and what happens to the
+    // ones unrelated to the focused node
+    pub fn ui_edges_menu(&mut self, ui: &mut egui::Ui) {
+        let scaling = &mut self.scaling;
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut scaling.scale, EdgeScale::Linear, "Linear");
+            ui.selectable_value(&mut scaling.scale, EdgeScale::Sqrt, "Sqrt");
+            ui.selectable_value(&mut scaling.scale, EdgeScale::Log, "Log");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Weight:");
+            for weight in EdgeWeight::ALL {
+                ui.selectable_value(&mut scaling.weight, weight, weight.label());
+            }
+        });
+        ui.add_enabled(
+            scaling.weight != EdgeWeight::MessageSize,
+            egui::Checkbox::new(&mut scaling.per_second, "Normalize by window (per second)"),
+        );
+        ui.horizontal(|ui| {
+            let mut capped = scaling.max.is_some();
+            if ui.checkbox(&mut capped, "Max:").changed() {
+                scaling.max = capped.then_some(1e6);
+            }
+            let suffix = scaling.weight.suffix(scaling.per_second());
+            if let Some(max) = scaling.max.as_mut() {
+                let speed = *max * 0.01;
+                ui.add(
+                    egui::DragValue::new(max)
+                        .range(1.0..=f64::MAX)
+                        .speed(speed)
+                        .suffix(suffix),
+                );
+            } else {
+                ui.label("busiest edge");
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Label edges when at most");
+            ui.add(egui::DragValue::new(&mut self.edge_label_limit).range(0..=200));
+            ui.label("are shown");
+        });
+        ui.checkbox(&mut self.show_local, "Local traffic")
+            .on_hover_text("a PE's puts and gets to itself, as a ring around its node");
+        ui.checkbox(&mut self.show_collectives, "Collectives")
+            .on_hover_text(
+                "calls with a negative target PE (-1 = every PE), as spokes out of their node",
+            );
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_gauges, "Rate gauges, peak")
+                .on_hover_text(
+                    "arcs inside each node, TX down its right side and RX down its left, \
+                     full at the peak. saturated nodes get a yellow outline",
+                );
+            let mut gbs = self.peak_bandwidth / 1e9;
+            if ui
+                .add_enabled(
+                    self.show_gauges,
+                    egui::DragValue::new(&mut gbs)
+                        .speed(0.5)
+                        .range(0.001..=f64::MAX)
+                        .suffix(" GB/s"),
+                )
+                .changed()
+            {
+                self.peak_bandwidth = gbs * 1e9;
+            }
+        });
+
+        ui.separator();
+        ui.label("Unrelated to the focused PE:");
+        let muting = &mut self.muting;
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut muting.style, MuteStyle::Ghost, "Ghost");
+            ui.selectable_value(&mut muting.style, MuteStyle::Hide, "Hide");
+        });
+        ui.add_enabled(
+            muting.style == MuteStyle::Ghost,
+            egui::Slider::new(&mut muting.opacity, 0.0..=1.0).text("opacity"),
+        );
+        if ui
+            .checkbox(&mut muting.lock_on_click, "Click a node to lock focus")
+            .changed()
+            && !muting.lock_on_click
+        {
+            self.locked_pe = None;
+        }
+    }
+
+    // the calls behind the inspected edge, each a link to its time
+    pub fn ui_edge_window(
+        &mut self,
+        ctx: &egui::Context,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        let Some(InspectedEdge {
+            edge,
+            start,
+            end,
+            hosts,
+        }) = self.inspected_edge
+        else {
+            return commands;
+        };
+        let groups = HostGroups::at(data, state.topology, state.group_level);
+        let name = |node: u32| {
+            if hosts {
+                groups.names[node as usize].clone()
+            } else {
+                node.to_string()
+            }
+        };
+        let title = if edge.0 == edge.1 {
+            format!("Local traffic of {}", name(edge.0))
+        } else {
+            format!("Edge {} -> {}", name(edge.0), name(edge.1))
+        };
+        let mut open = true;
+        egui::Window::new(title)
+            .id(egui::Id::new("edge_window"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.small(format!("window {:.6}s to {:.6}s", start, end));
+                let events = bandwidth::contributing_events(
+                    data,
+                    start,
+                    end,
+                    edge,
+                    state.annotations.show_tx,
+                    state.annotations.show_rx,
+                    state.annotations.byte_filter,
+                    |pe| if hosts { groups.host_of(pe) } else { pe },
+                );
+                ui.label(format!("{} events", events.len()));
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("edge_events").striped(true).show(ui, |ui| {
+                            ui.strong("Time");
+                            ui.strong("PE");
+                            ui.strong("Function");
+                            ui.strong("TX");
+                            ui.strong("RX");
+                            ui.end_row();
+                            for idx in events {
+                                let e = data.event(idx);
+                                if ui.link(format!("{:.9}s", e.raw.time)).clicked() {
+                                    commands.push(ViewCommand::JumpTo(e.raw.time));
+                                }
+                                ui.label(format!("{}", e.source_pe));
+                                ui.label(e.raw.function);
+                                ui.label(units::bytes(e.raw.bytes_tx));
+                                ui.label(units::bytes(e.raw.bytes_rx));
+                                ui.end_row();
+                            }
+                        });
+                    });
+            });
+        if !open {
+            self.inspected_edge = None;
+        }
+        commands
+    }
+}
+
+impl View for Bandwidth {
+    fn name(&self) -> &str {
+        Pane::Bandwidth.title()
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, data: &ProfileData, state: &ViewState) -> Vec<ViewCommand> {
+        let mut commands = Vec::new();
+        self.edges_drawn = 0;
+        let recording = state.recording;
+
+        // viewing around what time
+        // recording ignores the mouse so frames follow the cursor only
+        let hover_time = state.hover.filter(|_| !recording);
+        let is_hovering = hover_time.is_some();
+        let view_time = hover_time.unwrap_or(state.cursor);
+
+        // range, the selection wins unless the cursor is moving on its own
+        let (start_time, end_time) = match state.selection {
+            Some(selection) if self.on_selection && !state.playing && !recording => selection,
+            _ => (
+                view_time - state.window / 2.0,
+                view_time + state.window / 2.0,
+            ),
+        };
+
+        // ring nodes are either PEs or whole hosts (switches, racks)
+        let groups = HostGroups::at(data, state.topology, state.group_level);
+        let (bytes, messages) = self.cache.get(
+            data,
+            &state.derived.byte_sums,
+            start_time,
+            end_time,
+            state.annotations.show_tx,
+            state.annotations.show_rx,
+            state.annotations.byte_filter,
+        );
+        let collapsed = self.ring_grouping == RingGrouping::CollapseHosts;
+        let (bytes, messages) = if collapsed {
+            (
+                bandwidth::collapse(bytes, |pe| groups.host_of(pe)),
+                bandwidth::collapse(messages, |pe| groups.host_of(pe)),
+            )
+        } else {
+            (bytes.clone(), messages.clone())
+        };
+        let mut comms = bandwidth::weigh(&bytes, &messages, self.scaling.weight);
+        if !self.show_local {
+            bandwidth::take_local(&mut comms);
+        }
+        let (collective_bytes, collective_messages) = self.cache.collectives();
+        let (collective_bytes, collective_messages) = if !self.show_collectives {
+            Default::default()
+        } else if collapsed {
+            (
+                bandwidth::collapse_collectives(collective_bytes, |pe| groups.host_of(pe)),
+                bandwidth::collapse_collectives(collective_messages, |pe| groups.host_of(pe)),
+            )
+        } else {
+            (collective_bytes.clone(), collective_messages.clone())
+        };
+        let collectives =
+            bandwidth::weigh(&collective_bytes, &collective_messages, self.scaling.weight);
+        let window_seconds = end_time - start_time;
+        let comms_node_count = if collapsed {
+            groups.names.len() as u32
+        } else {
+            data.pe_count
+        };
+        let node_name = |i: u32| -> String {
+            if collapsed {
+                groups.names[i as usize].clone()
+            } else {
+                state.pe_labels.short(i)
+            }
+        };
+
+        if self.show_talkers {
+            egui::SidePanel::right("top_talkers")
+                .resizable(true)
+                .default_width(220.0)
+                .show_inside(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.strong("Top talkers");
+                        ui.add(egui::DragValue::new(&mut self.talkers_count).range(1..=100));
+                    });
+                    ui.separator();
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        egui::Grid::new("talkers_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("Pair");
+                                ui.strong("TX");
+                                ui.strong("RX");
+                                ui.end_row();
+                                for (edge, (tx, rx)) in
+                                    bandwidth::top_talkers(&comms, self.talkers_count)
+                                {
+                                    let selected = self.highlighted_edge == Some(edge);
+                                    let pair = if edge.0 == edge.1 {
+                                        format!("{} local", node_name(edge.0))
+                                    } else {
+                                        format!("{} -> {}", node_name(edge.0), node_name(edge.1))
+                                    };
+                                    if ui.selectable_label(selected, pair).clicked() {
+                                        self.highlighted_edge = (!selected).then_some(edge);
+                                    }
+                                    ui.label(self.scaling.format(tx, window_seconds));
+                                    ui.label(self.scaling.format(rx, window_seconds));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                });
+        }
+
+        let rect = ui.available_rect_before_wrap();
+        let capture = (recording || state.export == Some(ExportTarget::Bandwidth))
+            .then(|| export::capture_start(ui.ctx(), ui.layer_id()));
+        let center = rect.center();
+        let radius = rect.width().min(rect.height()) / 3.0;
+        let node_radius = 15.0;
+
+        ui.vertical_centered(|ui| {
+            if is_hovering {
+                ui.label(
+                    egui::RichText::new(format!("Showing bandwidth at Hover: {:.6}s", view_time))
+                        .color(Color32::YELLOW),
+                );
+            } else {
+                ui.label(format!("Showing bandwidth at Cursor: {:.6}s", view_time));
+            }
+        });
+
+        let painter = ui.painter();
+
+        if self.mode == BandwidthMode::Chord {
+            let labels: Vec<String> = (0..comms_node_count).map(node_name).collect();
+            let pointer = ui
+                .input(|i| i.pointer.hover_pos())
+                .filter(|p| !recording && rect.contains(*p));
+            chord::draw(
+                painter,
+                rect,
+                &comms,
+                &collectives,
+                &labels,
+                state.palette.text,
+                pointer,
+            );
+        } else {
+            // nodes
+            let ring = layout::ring_layout(self.ring_grouping, &groups);
+            let count = ring.angles.len() as u32;
+
+            let node_positions: Vec<Pos2> = match self.placement {
+                Placement::Ring => ring
+                    .angles
+                    .iter()
+                    .map(|a| center + Vec2::new(a.cos(), a.sin()) * radius)
+                    .collect(),
+                Placement::Force => {
+                    let force = match self.force_layout.take() {
+                        Some(f) if f.node_count() == count as usize => f,
+                        // (re)start from the ring whenever the node set changes
+                        _ => {
+                            let full = state.derived.byte_sums.comms(
+                                data.min_time,
+                                data.max_time,
+                                state.annotations.show_tx,
+                                state.annotations.show_rx,
+                            );
+                            let full = if collapsed {
+                                bandwidth::collapse(&full, |pe| groups.host_of(pe))
+                            } else {
+                                full
+                            };
+                            let traffic =
+                                full.into_iter().map(|(k, (tx, rx))| (k, tx + rx)).collect();
+                            ForceLayout::new(&ring.angles, &traffic)
+                        }
+                    };
+                    let force = self.force_layout.insert(force);
+                    if !recording && force.animate() {
+                        ui.ctx().request_repaint();
+                    }
+                    (0..count as usize)
+                        .map(|i| center + force.position(i) * radius)
+                        .collect()
+                }
+            };
+            let get_pos = |node: u32| -> Pos2 { node_positions[node as usize] };
+
+            // host arcs around their group of PEs
+            let arc_radius = radius + node_radius + 8.0;
+            let arcs = if self.placement == Placement::Ring {
+                ring.arcs.as_slice()
+            } else {
+                &[]
+            };
+            for arc in arcs {
+                let steps = 24;
+                let points: Vec<Pos2> = (0..=steps)
+                    .map(|k| {
+                        let a = arc.start_angle
+                            + (arc.end_angle - arc.start_angle) * k as f32 / steps as f32;
+                        center + Vec2::new(a.cos(), a.sin()) * arc_radius
+                    })
+                    .collect();
+                painter.add(egui::Shape::line(
+                    points,
+                    Stroke::new(2.0, state.palette.separator),
+                ));
+                let mid = (arc.start_angle + arc.end_angle) / 2.0;
+                let dir = Vec2::new(mid.cos(), mid.sin());
+                painter.text(
+                    center + dir * (arc_radius + 6.0),
+                    label_anchor(dir),
+                    &arc.name,
+                    egui::FontId::proportional(11.0),
+                    state.palette.text_weak,
+                );
+            }
+
+            // hovered node?
+            let mut hovered_pe = None;
+            if !recording && let Some(pointer_pos) = ui.input(|i| i.pointer.hover_pos()) {
+                for i in 0..count {
+                    let pos = get_pos(i);
+                    if pos.distance(pointer_pos) <= node_radius * 1.5 {
+                        hovered_pe = Some(i);
+                        break;
+                    }
+                }
+            }
+
+            let ring_response = ui.interact(rect, ui.id().with("ring"), Sense::click());
+            if self.muting.lock_on_click && ring_response.clicked() {
+                self.locked_pe = match hovered_pe {
+                    Some(h) if self.locked_pe != Some(h) => Some(h),
+                    _ => None,
+                };
+            }
+            // the node count changes with the ring grouping
+            let locked_pe = self.locked_pe.filter(|pe| *pe < count);
+            let focused_pe = locked_pe.or(hovered_pe);
+
+            // drawn as rings around the nodes rather than edges
+            let local = bandwidth::take_local(&mut comms);
+
+            // interaction stats if focused: (tx, rx)
+            let mut interaction_bytes: HashMap<u32, (u64, u64)> = HashMap::new();
+            let mut max_interaction = 0;
+
+            if let Some(h) = focused_pe {
+                for ((src, dst), (tx, rx)) in &comms {
+                    if *src == h {
+                        let e = interaction_bytes.entry(*dst).or_insert((0, 0));
+                        e.0 += tx;
+                        e.1 += rx;
+                        max_interaction = max_interaction.max(e.0 + e.1);
+                    } else if *dst == h {
+                        let e = interaction_bytes.entry(*src).or_insert((0, 0));
+                        e.0 += tx;
+                        e.1 += rx;
+                        max_interaction = max_interaction.max(e.0 + e.1);
+                    }
+                }
+            }
+
+            let pointer = ring_response.hover_pos().filter(|_| !recording);
+            // red for TX, blue for RX, gray when muted
+            let opacity = self.muting.opacity;
+            let edge_color = |tx: u64, rx: u64, weight: f32, muted: bool| {
+                let total = (tx + rx).max(1) as f32;
+                let alpha = (50.0 + weight * 150.0) as u8;
+                let r = (255.0 * (tx as f32 / total)) as u8;
+                let b = (255.0 * (rx as f32 / total)) as u8;
+                let color = Color32::from_rgba_premultiplied(r, 0, b, alpha);
+                if muted {
+                    // convert to grayscale and lower alpha
+                    let gray = (color.r() as f32 * 0.2126
+                        + color.g() as f32 * 0.7152
+                        + color.b() as f32 * 0.0722) as u8;
+                    Color32::from_rgba_premultiplied(
+                        gray,
+                        gray,
+                        gray,
+                        (alpha as f32 * opacity) as u8,
+                    )
+                } else {
+                    color
+                }
+            };
+            // (distance, edge) of the arrow closest to the pointer
+            let mut edge_under_pointer: Option<(f32, (u32, u32))> = None;
+
+            let max_edge_value = comms
+                .values()
+                .chain(local.values())
+                .chain(collectives.values())
+                .map(|(tx, rx)| self.scaling.value(tx + rx, window_seconds))
+                .fold(0.0, f64::max);
+
+            // (edge, start, end, weight) of arrows drawn at full strength
+            let mut drawn_edges = Vec::new();
+
+            // bandwidth arrows
+            for ((src, dst), (tx, rx)) in &comms {
+                let p1 = get_pos(*src);
+                let p2 = get_pos(*dst);
+
+                let total = *tx + *rx;
+                if total == 0 {
+                    continue;
+                }
+                let mut is_muted = false;
+
+                if let Some(h) = focused_pe
+                    && *src != h
+                    && *dst != h
+                {
+                    is_muted = true;
+                }
+                let is_highlighted = self.highlighted_edge == Some((*src, *dst));
+                if focused_pe.is_none() && self.highlighted_edge.is_some() && !is_highlighted {
+                    is_muted = true;
+                }
+                if is_muted && self.muting.style == MuteStyle::Hide {
+                    continue;
+                }
+
+                let weight = self
+                    .scaling
+                    .normalize(self.scaling.value(total, window_seconds), max_edge_value);
+                let mut width = 0.5 + weight * 7.5;
+                if is_highlighted {
+                    width += 2.0;
+                }
+                let color = edge_color(*tx, *rx, weight, is_muted);
+                let stroke = Stroke::new(width, color);
+
+                // avoid overlaps
+                let dir = (p2 - p1).normalized();
+                let normal = Vec2::new(-dir.y, dir.x);
+                let offset = normal * 6.0; // offset
+
+                // direction for shortening and head
+                let start_point = p1 + dir * node_radius + offset;
+                let end_point = p2 - dir * node_radius + offset;
+
+                painter.line_segment([start_point, end_point], stroke);
+                self.edges_drawn += 1;
+                if !is_muted {
+                    drawn_edges.push(((*src, *dst), start_point, end_point, total));
+                }
+
+                if let Some(p) = pointer {
+                    let d = distance_to_segment(p, start_point, end_point);
+                    if d <= width / 2.0 + 4.0 && edge_under_pointer.is_none_or(|(best, _)| d < best)
+                    {
+                        edge_under_pointer = Some((d, (*src, *dst)));
+                    }
+                }
+
+                // head
+                let arrow_len = 8.0 + width;
+                let arrow_angle = std::f32::consts::PI / 6.0;
+
+                // rotation to target
+                let angle_vec = (end_point - start_point).normalized();
+                let angle = angle_vec.y.atan2(angle_vec.x);
+                let angle1 = angle + std::f32::consts::PI - arrow_angle;
+                let angle2 = angle + std::f32::consts::PI + arrow_angle;
+
+                let p_arrow1 = end_point + Vec2::new(angle1.cos(), angle1.sin()) * arrow_len;
+                let p_arrow2 = end_point + Vec2::new(angle2.cos(), angle2.sin()) * arrow_len;
+
+                painter.add(egui::Shape::convex_polygon(
+                    vec![end_point, p_arrow1, p_arrow2],
+                    color,
+                    Stroke::NONE,
+                ));
+            }
+
+            // local traffic, a ring around the node as thick as an edge of
+            // the same weight
+            for (node, (tx, rx)) in &local {
+                let total = tx + rx;
+                if total == 0 || *node >= count {
+                    continue;
+                }
+                let is_highlighted = self.highlighted_edge == Some((*node, *node));
+                let is_muted = focused_pe.is_some_and(|h| h != *node)
+                    || focused_pe.is_none() && self.highlighted_edge.is_some() && !is_highlighted;
+                if is_muted && self.muting.style == MuteStyle::Hide {
+                    continue;
+                }
+                let weight = self
+                    .scaling
+                    .normalize(self.scaling.value(total, window_seconds), max_edge_value);
+                let mut width = 0.5 + weight * 7.5;
+                if is_highlighted {
+                    width += 2.0;
+                }
+                let pos = get_pos(*node);
+                let ring_radius = node_radius + 3.0 + width / 2.0;
+                painter.circle_stroke(
+                    pos,
+                    ring_radius,
+                    Stroke::new(width, edge_color(*tx, *rx, weight, is_muted)),
+                );
+                self.edges_drawn += 1;
+                if let Some(p) = pointer {
+                    let d = (p.distance(pos) - ring_radius).abs();
+                    if d <= width / 2.0 + 4.0 && edge_under_pointer.is_none_or(|(best, _)| d < best)
+                    {
+                        edge_under_pointer = Some((d, (*node, *node)));
+                    }
+                }
+            }
+
+            // collectives, spokes out of the node and, from the focused node,
+            // dashed lines to every node they reach
+            for (node, (tx, rx)) in &collectives {
+                let total = tx + rx;
+                if total == 0 || *node >= count {
+                    continue;
+                }
+                let is_muted = focused_pe.is_some_and(|h| h != *node)
+                    || focused_pe.is_none() && self.highlighted_edge.is_some();
+                if is_muted && self.muting.style == MuteStyle::Hide {
+                    continue;
+                }
+                let weight = self
+                    .scaling
+                    .normalize(self.scaling.value(total, window_seconds), max_edge_value);
+                let color = edge_color(*tx, *rx, weight, is_muted);
+                let pos = get_pos(*node);
+                paint_spokes(
+                    painter,
+                    pos,
+                    node_radius + 2.0,
+                    6.0 + weight * 14.0,
+                    Stroke::new(1.0 + weight * 2.0, color),
+                );
+                self.edges_drawn += 1;
+                if focused_pe == Some(*node) {
+                    let stroke = Stroke::new(1.0, color.gamma_multiply(0.6));
+                    for other in (0..count).filter(|other| other != node) {
+                        painter.extend(egui::Shape::dashed_line(
+                            &[pos, get_pos(other)],
+                            stroke,
+                            4.0,
+                            4.0,
+                        ));
+                    }
+                }
+            }
+
+            // what the rings and spokes mean, when there are any
+            let legend = [
+                (!local.is_empty(), "local: target PE is the source PE"),
+                (
+                    !collectives.is_empty(),
+                    "collective: negative target PE, -1 = every PE",
+                ),
+            ];
+            let mut y = rect.bottom() - 14.0;
+            for (i, (shown, text)) in legend.into_iter().enumerate().rev() {
+                if !shown {
+                    continue;
+                }
+                let glyph = Pos2::new(rect.left() + 16.0, y);
+                let stroke = Stroke::new(1.5, state.palette.text_weak);
+                if i == 0 {
+                    painter.circle_stroke(glyph, 5.0, stroke);
+                } else {
+                    paint_spokes(painter, glyph, 2.0, 4.0, stroke);
+                }
+                painter.text(
+                    glyph + Vec2::new(12.0, 0.0),
+                    egui::Align2::LEFT_CENTER,
+                    text,
+                    egui::FontId::proportional(11.0),
+                    state.palette.text_weak,
+                );
+                y -= 16.0;
+            }
+
+            // exact values on the edges when there are few enough to read,
+            // or just on the one under the pointer
+            let labelled: Vec<_> = drawn_edges
+                .iter()
+                .filter(|(edge, ..)| {
+                    drawn_edges.len() <= self.edge_label_limit
+                        || edge_under_pointer.is_some_and(|(_, e)| e == *edge)
+                        || self.highlighted_edge == Some(*edge)
+                })
+                .collect();
+            if !labelled.is_empty() {
+                let total = |comms: &bandwidth::Comms, edge| {
+                    comms.get(edge).map_or(0, |(tx, rx): &(u64, u64)| tx + rx)
+                };
+                for (edge, start, end, value) in labelled {
+                    let (size, count) = (total(&bytes, edge), total(&messages, edge));
+                    let normal = (*end - *start).normalized().rot90();
+                    // the weight first, then whichever of bytes and
+                    // messages it isn't
+                    let value = self.scaling.format(*value, window_seconds);
+                    let count = EdgeWeight::Messages.format(count as f64, false);
+                    let text = match self.scaling.weight {
+                        EdgeWeight::Bytes => format!("{value} · {count}"),
+                        EdgeWeight::Messages => format!("{value} · {}", units::bytes(size)),
+                        EdgeWeight::MessageSize => format!("{value} avg · {count}"),
+                    };
+                    let galley = painter.layout_no_wrap(
+                        text,
+                        egui::FontId::proportional(10.0),
+                        state.palette.badge_text,
+                    );
+                    let mid = start.lerp(*end, 0.5) + normal * 10.0;
+                    let label_rect =
+                        Rect::from_center_size(mid, galley.size()).expand2(Vec2::new(3.0, 1.0));
+                    painter.rect_filled(label_rect, 2.0, state.palette.badge_bg);
+                    painter.galley(
+                        label_rect.min + Vec2::new(3.0, 1.0),
+                        galley,
+                        state.palette.badge_text,
+                    );
+                }
+            }
+
+            if let Some((_, edge)) = edge_under_pointer {
+                ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                if ring_response.clicked() {
+                    self.inspected_edge = Some(InspectedEdge {
+                        edge,
+                        start: start_time,
+                        end: end_time,
+                        hosts: collapsed,
+                    });
+                }
+            }
+
+            // bytes per second out of and into each node over the window,
+            // for the gauges
+            let mut rates = vec![(0.0, 0.0); count as usize];
+            if self.show_gauges {
+                let seconds = window_seconds.max(1e-12);
+                for ((src, dst), (tx, rx)) in &bytes {
+                    if (src == dst && !self.show_local) || src.max(dst) >= &count {
+                        continue;
+                    }
+                    rates[*src as usize].0 += (tx + rx) as f64 / seconds;
+                    rates[*dst as usize].1 += (tx + rx) as f64 / seconds;
+                }
+                for (node, (tx, rx)) in &collective_bytes {
+                    if let Some(rate) = rates.get_mut(*node as usize) {
+                        rate.0 += *tx as f64 / seconds;
+                        rate.1 += *rx as f64 / seconds;
+                    }
+                }
+            }
+
+            // draw nodes
+            for i in 0..count {
+                let pos = get_pos(i);
+
+                let mut fill_color = state.palette.node_fill;
+                let mut stroke_color = state.palette.node_stroke;
+                let mut stroke_width = 1.0;
+
+                if let Some(h) = focused_pe {
+                    if i == h {
+                        // hovered node
+                        fill_color = Color32::from_rgb(100, 100, 200); // highlight
+                        stroke_width = 2.0;
+                        if locked_pe == Some(i) {
+                            stroke_color = Color32::YELLOW;
+                        }
+                    } else if let Some((tx, rx)) = interaction_bytes.get(&i) {
+                        // node interacting with hovered node
+                        let total = tx + rx;
+                        if total > 0 && max_interaction > 0 {
+                            let ratio_tx = *tx as f32 / total as f32;
+                            let ratio_rx = *rx as f32 / total as f32;
+
+                            let r_target = (255.0 * ratio_tx) as u8;
+                            let b_target = (255.0 * ratio_rx) as u8;
+
+                            let intensity = (total as f32 / max_interaction as f32)
+                                .sqrt()
+                                .clamp(0.0, 1.0);
+
+                            let base = 50.0;
+                            let r = (base + (r_target as f32 - base) * intensity) as u8;
+                            let g = (base * (1.0 - intensity)) as u8;
+                            let b = (base + (b_target as f32 - base) * intensity) as u8;
+
+                            fill_color = Color32::from_rgb(r, g, b);
+                        }
+                    } else {
+                        // irrelevant node
+                        let alpha = (self.muting.opacity * 2.0).min(1.0);
+                        fill_color = state.palette.node_muted_fill.gamma_multiply(alpha);
+                        stroke_color = state.palette.node_muted_stroke.gamma_multiply(alpha);
+                    }
+                }
+
+                painter.circle_filled(pos, node_radius, fill_color);
+                painter.circle_stroke(pos, node_radius, Stroke::new(stroke_width, stroke_color));
+                if self.show_gauges {
+                    let (out, into) = rates[i as usize];
+                    let peak = self.peak_bandwidth.max(1.0);
+                    let radius = node_radius - 2.5;
+                    let track = Stroke::new(3.0, stroke_color.gamma_multiply(0.25));
+                    painter.circle_stroke(pos, radius, track);
+                    let top = -std::f32::consts::FRAC_PI_2;
+                    let half = std::f32::consts::PI;
+                    let tx = (out / peak).min(1.0) as f32;
+                    let rx = (into / peak).min(1.0) as f32;
+                    let tx_stroke = Stroke::new(3.0, Color32::from_rgb(230, 60, 60));
+                    let rx_stroke = Stroke::new(3.0, Color32::from_rgb(70, 110, 240));
+                    paint_arc(painter, pos, radius, top, half * tx, tx_stroke);
+                    paint_arc(painter, pos, radius, top, -half * rx, rx_stroke);
+                    if out >= peak || into >= peak {
+                        painter.circle_stroke(
+                            pos,
+                            node_radius + 1.5,
+                            Stroke::new(2.0, GAUGE_SATURATED),
+                        );
+                    }
+                }
+                painter.text(
+                    pos,
+                    egui::Align2::CENTER_CENTER,
+                    if collapsed {
+                        format!("{}", groups.pe_count(i))
+                    } else {
+                        format!("{}", i)
+                    },
+                    egui::FontId::proportional(14.0),
+                    stroke_color,
+                );
+                if collapsed {
+                    let dir = (pos - center).normalized();
+                    painter.text(
+                        pos + dir * (node_radius + 4.0),
+                        label_anchor(dir),
+                        node_name(i),
+                        egui::FontId::proportional(11.0),
+                        stroke_color,
+                    );
+                }
+            }
+
+            // everything the hovered node moved in the window, its local
+            // traffic included when that is shown
+            if let Some(h) = hovered_pe {
+                let moved = |comms: &bandwidth::Comms| -> u64 {
+                    comms
+                        .iter()
+                        .filter(|((src, dst), _)| {
+                            (*src == h || *dst == h) && (src != dst || self.show_local)
+                        })
+                        .map(|(_, (tx, rx))| tx + rx)
+                        .sum()
+                };
+                let mut text = format!(
+                    "{} · {}",
+                    units::bytes(moved(&bytes)),
+                    EdgeWeight::Messages.format(moved(&messages) as f64, false)
+                );
+                if self.show_local {
+                    let own = bytes.get(&(h, h)).map_or(0, |(tx, rx)| tx + rx);
+                    text += &format!(" ({} local)", units::bytes(own));
+                }
+                if let Some((tx, rx)) = collective_bytes.get(&h) {
+                    text += &format!(" + {} collective", units::bytes(tx + rx));
+                }
+                if self.show_gauges
+                    && let Some((out, into)) = rates.get(h as usize)
+                {
+                    text += &format!(" · {} out, {} in", units::rate(*out), units::rate(*into));
+                }
+                let galley = painter.layout_no_wrap(
+                    text,
+                    egui::FontId::proportional(10.0),
+                    state.palette.badge_text,
+                );
+                let mid = get_pos(h) + Vec2::new(0.0, node_radius + 16.0);
+                let label_rect =
+                    Rect::from_center_size(mid, galley.size()).expand2(Vec2::new(3.0, 1.0));
+                painter.rect_filled(label_rect, 2.0, state.palette.badge_bg);
+                painter.galley(
+                    label_rect.min + Vec2::new(3.0, 1.0),
+                    galley,
+                    state.palette.badge_text,
+                );
+            }
+        }
+
+        if let Some(start) = capture {
+            let background = ui.visuals().panel_fill;
+            let view = export::capture_end(ui.ctx(), ui.layer_id(), start, rect, background);
+            commands.push(ViewCommand::Captured(ExportTarget::Bandwidth, view));
+        }
+        commands
+    }
+}
+
+// a collective's glyph: short lines pointing out of a node in every
+// direction, starting `inner` from its center
+fn paint_spokes(painter: &egui::Painter, center: Pos2, inner: f32, length: f32, stroke: Stroke) {
+    const SPOKES: usize = 8;
+    for k in 0..SPOKES {
+        let dir = Vec2::angled(k as f32 * std::f32::consts::TAU / SPOKES as f32);
+        let start = center + dir * inner;
+        painter.line_segment([start, start + dir * length], stroke);
+    }
+}
+
+// an arc of `sweep` radians from `start`, clockwise on screen when positive
+fn paint_arc(
+    painter: &egui::Painter,
+    center: Pos2,
+    radius: f32,
+    start: f32,
+    sweep: f32,
+    stroke: Stroke,
+) {
+    if sweep == 0.0 {
+        return;
+    }
+    let steps = ((sweep.abs() * radius / 2.0).ceil() as usize).max(2);
+    let points = (0..=steps)
+        .map(|k| center + Vec2::angled(start + sweep * k as f32 / steps as f32) * radius)
+        .collect();
+    painter.add(egui::Shape::line(points, stroke));
+}
+
+// anchor so text placed at `dir` from a center grows away from it
+fn label_anchor(dir: Vec2) -> egui::Align2 {
+    let h = if dir.x > 0.3 {
+        egui::Align::Min
+    } else if dir.x < -0.3 {
+        egui::Align::Max
+    } else {
+        egui::Align::Center
+    };
+    let v = if dir.y > 0.3 {
+        egui::Align::Min
+    } else if dir.y < -0.3 {
+        egui::Align::Max
+    } else {
+        egui::Align::Center
+    };
+    egui::Align2([h, v])
+}
+fn distance_to_segment(p: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let len_sq = ab.length_sq();
+    if len_sq == 0.0 {
+        return p.distance(a);
+    }
+    let t = ((p - a).dot(ab) / len_sq).clamp(0.0, 1.0);
+    p.distance(a + ab * t)
+}
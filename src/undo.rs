@@ -0,0 +1,76 @@
+use std::collections::BTreeSet;
+
+use crate::annotations::Annotations;
+
+// what undo and redo bring back: the annotations and the hidden PEs, which
+// are kept with the track order. the view itself has ZoomHistory
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub annotations: Annotations,
+    pub hidden_pes: BTreeSet<u32>,
+}
+
+const MAX_ENTRIES: usize = 100;
+
+// changes further apart than this are separate steps, closer ones (dragging
+// a color, typing a bookmark name) are one
+const MERGE_SECONDS: f64 = 0.5;
+
+#[derive(Debug, Default)]
+pub struct UndoHistory {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    // as of the last frame
+    current: Option<Snapshot>,
+    last_change: f64,
+}
+
+impl UndoHistory {
+    // called every frame with the state as it is now, which makes an entry
+    // when it changed. a new change drops the redo list
+    pub fn observe(&mut self, now: Snapshot, time: f64) {
+        let Some(before) = self.current.take_if(|c| *c != now) else {
+            self.current.get_or_insert(now);
+            return;
+        };
+        if time - self.last_change > MERGE_SECONDS || self.undo.is_empty() {
+            self.undo.push(before);
+            if self.undo.len() > MAX_ENTRIES {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+        self.last_change = time;
+        self.current = Some(now);
+    }
+
+    // the state to go back to
+    pub fn undo(&mut self) -> Option<Snapshot> {
+        let before = self.undo.pop()?;
+        self.redo.extend(self.current.replace(before.clone()));
+        self.last_change = f64::NEG_INFINITY;
+        Some(before)
+    }
+
+    pub fn redo(&mut self) -> Option<Snapshot> {
+        let after = self.redo.pop()?;
+        self.undo.extend(self.current.replace(after.clone()));
+        self.last_change = f64::NEG_INFINITY;
+        Some(after)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // forgets everything, the next observed state is the starting point
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.current = None;
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::{Context, Result, bail};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+use crate::export::{self, CapturedView};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoFormat {
+    Gif,
+    // piped through an ffmpeg binary on PATH
+    Mp4,
+}
+
+impl VideoFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            VideoFormat::Gif => "gif",
+            VideoFormat::Mp4 => "mp4",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VideoSettings {
+    pub format: VideoFormat,
+    pub start: f64,
+    pub end: f64,
+    pub fps: u32,
+    // trace seconds per second of video
+    pub speed: f64,
+    pub width: u32,
+    pub path: PathBuf,
+}
+
+enum Encoder {
+    Gif(gif::Encoder<BufWriter<File>>),
+    Ffmpeg(Child),
+}
+
+// one frame is rendered per ui frame: the app moves the cursor to
+// `current_time`, the ring draws, and the captured shapes come back here
+pub struct VideoRecorder {
+    settings: VideoSettings,
+    path: PathBuf,
+    frame: u32,
+    frame_count: u32,
+    size: Option<(u32, u32)>,
+    encoder: Option<Encoder>,
+}
+
+impl VideoRecorder {
+    pub fn new(settings: VideoSettings) -> Result<Self> {
+        if settings.end <= settings.start || settings.fps == 0 || settings.speed <= 0.0 {
+            bail!("empty video range");
+        }
+        let path = if settings.path.extension().is_none() {
+            settings.path.with_extension(settings.format.extension())
+        } else {
+            settings.path.clone()
+        };
+        let step = settings.speed / settings.fps as f64;
+        let frame_count = ((settings.end - settings.start) / step).ceil() as u32 + 1;
+        Ok(Self {
+            settings,
+            path,
+            frame: 0,
+            frame_count,
+            size: None,
+            encoder: None,
+        })
+    }
+
+    pub fn current_time(&self) -> f64 {
+        let step = self.settings.speed / self.settings.fps as f64;
+        (self.settings.start + self.frame as f64 * step).min(self.settings.end)
+    }
+
+    pub fn progress(&self) -> (u32, u32) {
+        (self.frame, self.frame_count)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.frame >= self.frame_count
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn push_frame(&mut self, view: &CapturedView) -> Result<()> {
+        let pixmap = export::rasterize(view, self.settings.width)?;
+        // encoders want even dimensions
+        let (w, h) = (pixmap.width() & !1, pixmap.height() & !1);
+        match self.size {
+            None => self.size = Some((w, h)),
+            Some(size) if size != (w, h) => bail!("view was resized while recording"),
+            Some(_) => {}
+        }
+
+        // background is opaque so premultiplied == straight alpha
+        let stride = pixmap.width() as usize * 4;
+        let mut rgba: Vec<u8> = pixmap
+            .data()
+            .chunks(stride)
+            .take(h as usize)
+            .flat_map(|row| &row[..w as usize * 4])
+            .copied()
+            .collect();
+
+        if self.encoder.is_none() {
+            self.encoder = Some(self.open_encoder(w, h)?);
+        }
+        match self.encoder.as_mut().expect("encoder opened above") {
+            Encoder::Gif(encoder) => {
+                let mut frame = gif::Frame::from_rgba_speed(w as u16, h as u16, &mut rgba, 10);
+                frame.delay = (100 / self.settings.fps).max(1) as u16;
+                encoder.write_frame(&frame)?;
+            }
+            Encoder::Ffmpeg(child) => {
+                child
+                    .stdin
+                    .as_mut()
+                    .context("ffmpeg stdin closed")?
+                    .write_all(&rgba)
+                    .context("writing frame to ffmpeg")?;
+            }
+        }
+        self.frame += 1;
+        Ok(())
+    }
+
+    fn open_encoder(&self, w: u32, h: u32) -> Result<Encoder> {
+        match self.settings.format {
+            VideoFormat::Gif => {
+                let file = File::create(&self.path)
+                    .with_context(|| format!("creating {}", self.path.display()))?;
+                let mut encoder = gif::Encoder::new(BufWriter::new(file), w as u16, h as u16, &[])?;
+                encoder.set_repeat(gif::Repeat::Infinite)?;
+                Ok(Encoder::Gif(encoder))
+            }
+            VideoFormat::Mp4 => {
+                let child = Command::new("ffmpeg")
+                    .args([
+                        "-y",
+                        "-loglevel",
+                        "error",
+                        "-f",
+                        "rawvideo",
+                        "-pix_fmt",
+                        "rgba",
+                    ])
+                    .args(["-s", &format!("{w}x{h}")])
+                    .args(["-r", &self.settings.fps.to_string()])
+                    .args(["-i", "-", "-pix_fmt", "yuv420p"])
+                    .arg(&self.path)
+                    .stdin(Stdio::piped())
+                    .spawn()
+                    .context("starting ffmpeg (is it on PATH?)")?;
+                Ok(Encoder::Ffmpeg(child))
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> Result<PathBuf> {
+        match self.encoder.take() {
+            // dropping the encoder writes the gif trailer
+            Some(Encoder::Gif(encoder)) => {
+                encoder.into_inner()?.flush()?;
+            }
+            Some(Encoder::Ffmpeg(mut child)) => {
+                drop(child.stdin.take());
+                let status = child.wait()?;
+                if !status.success() {
+                    bail!("ffmpeg exited with {status}");
+                }
+            }
+            None => bail!("no frames were recorded"),
+        }
+        Ok(self.path)
+    }
+}
@@ -0,0 +1,164 @@
+use egui::{Color32, Pos2, Rect, Stroke};
+use std::collections::BTreeSet;
+
+use crate::annotations::Annotations;
+use crate::coloring::ColorMap;
+use crate::data::ProfileData;
+use crate::derived::{self, Derived};
+use crate::export::{CapturedView, ExportTarget};
+use crate::grouping::Grouping;
+use crate::labels::PeLabels;
+use crate::lanes::Lanes;
+use crate::phases::PhaseDetection;
+use crate::ruler::TimeDisplay;
+use crate::search::Search;
+use crate::symbols::Symbolizer;
+use crate::theme::Palette;
+use crate::topology::{GroupLevel, Topology};
+use crate::tracks::TrackOrder;
+use crate::traffic::InspectedEdge;
+
+// the line every time axis draws at the time under the pointer
+pub const CROSSHAIR: Color32 = Color32::from_rgba_premultiplied(200, 200, 0, 200);
+
+// what the viewer shows right now, handed to every view each frame
+pub struct ViewState<'a> {
+    // the visible part of the timeline
+    pub view: (f64, f64),
+    pub selection: Option<(f64, f64)>,
+    // selection, else the loop region, else the view, and which one it is
+    pub analysis_range: (f64, f64),
+    pub analysis_range_name: &'static str,
+    pub cursor: f64,
+    // secondary measurement cursor
+    pub cursor_b: Option<f64>,
+    // time under the pointer in any view last frame
+    pub hover: Option<f64>,
+    // seconds the bandwidth view sums around the cursor
+    pub window: f64,
+    pub loop_a: Option<f64>,
+    pub loop_b: Option<f64>,
+    pub loop_enabled: bool,
+    pub playing: bool,
+    pub selected_pe: Option<u32>,
+    pub pinned_event: Option<usize>,
+
+    pub annotations: &'a Annotations,
+    pub grouping: &'a Grouping,
+    pub colors: &'a ColorMap,
+    pub derived: &'a Derived,
+    pub search: &'a Search,
+    pub track_order: &'a TrackOrder,
+    pub lanes: &'a Lanes,
+    // detected phases, when they're drawn over the timeline
+    pub phases: Option<&'a PhaseDetection>,
+    pub pe_labels: &'a PeLabels,
+    pub palette: &'a Palette,
+    pub time_display: TimeDisplay,
+    pub visible_counters: &'a BTreeSet<String>,
+    pub symbolizer: &'a Symbolizer,
+    pub demangle_frames: bool,
+    // what the bandwidth view groups PEs into
+    pub topology: Option<&'a Topology>,
+    pub group_level: GroupLevel,
+
+    // draw everything even while the view moves, e.g. for screenshots
+    pub full_detail: bool,
+    // pane an export is waiting to capture
+    pub export: Option<ExportTarget>,
+    // a video is being recorded, so the pointer is ignored
+    pub recording: bool,
+}
+
+// how a view changes the rest of the viewer, applied after it's drawn
+pub enum ViewCommand {
+    // moves the timeline, remembering where it was
+    SetView(f64, f64),
+    // moves the timeline by wheel or drag, remembered once per gesture
+    Pan(f64, f64),
+    // centers the timeline on a time and puts the cursor there
+    JumpTo(f64),
+    SetCursor(f64),
+    SetCursorB(Option<f64>),
+    // time under the pointer, for the crosshair of every view
+    Hover(f64),
+    // width of the bandwidth window
+    SetWindow(f64),
+    Select(f64, f64),
+    Deselect,
+    LoopA(f64),
+    LoopB(f64),
+    // loops over a range
+    Loop(f64, f64),
+    Solo(Option<String>),
+    HideFunction(String),
+    ShowFunction(String),
+    AddBookmark(String, f64, Option<u32>),
+    SelectPe(Option<u32>),
+    // event whose details get their own window, placed there if given
+    PinEvent(usize, Option<Pos2>),
+    Unpin,
+    // track of a PE moves before another one, to the bottom for None
+    MoveTrack(u32, Option<u32>),
+    PinTrack(u32, bool),
+    HideTrack(u32),
+    // hides every other track
+    SoloTrack(u32),
+    // lists the calls behind an edge of the bandwidth view
+    InspectEdge(InspectedEdge),
+    // new knobs for the analyses kept for the trace, which are redone
+    DerivedSettings(derived::Settings),
+    Status(String),
+    // what a pane drew for a pending export or the video being recorded
+    Captured(ExportTarget, CapturedView),
+}
+
+// a visualization drawn into a dock slot or its own window, from the
+// trace and the viewer state. it changes the viewer through the commands
+// it returns
+pub trait View {
+    // window title and menu entry, unique among registered views
+    fn name(&self) -> &str;
+    fn ui(&mut self, ui: &mut egui::Ui, data: &ProfileData, state: &ViewState) -> Vec<ViewCommand>;
+}
+
+// views added on top of the built-in panes, each with whether it's open
+#[derive(Default)]
+pub struct ViewRegistry {
+    views: Vec<(Box<dyn View>, bool)>,
+}
+
+impl ViewRegistry {
+    // the views compiled into the viewer
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register(Box::new(crate::shares::FunctionShares::default()));
+        registry
+    }
+
+    pub fn register(&mut self, view: Box<dyn View>) {
+        self.views.push((view, false));
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&mut Box<dyn View>, &mut bool)> {
+        self.views.iter_mut().map(|(view, open)| (view, open))
+    }
+}
+
+// a line at the shared hover time on a chart of [start, end]
+pub fn paint_crosshair(
+    painter: &egui::Painter,
+    rect: Rect,
+    start: f64,
+    end: f64,
+    time: Option<f64>,
+) {
+    let Some(t) = time.filter(|t| (start..=end).contains(t) && end > start) else {
+        return;
+    };
+    let x = rect.min.x + ((t - start) / (end - start)) as f32 * rect.width();
+    painter.line_segment(
+        [Pos2::new(x, rect.min.y), Pos2::new(x, rect.max.y)],
+        Stroke::new(1.0, CROSSHAIR),
+    );
+}
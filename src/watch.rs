@@ -0,0 +1,84 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, channel};
+use std::time::{Duration, Instant};
+
+use crate::data;
+
+// a run writes its files one after the other, so a change only counts once
+// the directory has been quiet for this long
+pub const SETTLE_TIME: Duration = Duration::from_millis(750);
+
+// notices pperf files (or the manifest) in a directory being written,
+// created or removed
+pub struct DataWatcher {
+    dir: PathBuf,
+    // kept alive for the callback. None when the directory can't be watched
+    _watcher: Option<RecommendedWatcher>,
+    rx: Receiver<PathBuf>,
+    // names of the files touched since the last take()
+    changed: Vec<PathBuf>,
+    last_change: Option<Instant>,
+}
+
+impl DataWatcher {
+    // `ctx` is woken up on every change so the app notices without input
+    pub fn new(dir: &Path, ctx: egui::Context) -> anyhow::Result<Self> {
+        let (tx, rx) = channel();
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+                let Ok(event) = res else {
+                    return;
+                };
+                if matches!(event.kind, EventKind::Access(_) | EventKind::Other) {
+                    return;
+                }
+                for path in event.paths {
+                    if data::is_trace_file(&path) {
+                        let _ = tx.send(path);
+                        ctx.request_repaint_after(SETTLE_TIME);
+                    }
+                }
+            })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            _watcher: Some(watcher),
+            rx,
+            changed: Vec::new(),
+            last_change: None,
+        })
+    }
+
+    // stands in for a directory that can't be watched, so it isn't retried
+    // every frame
+    pub fn unwatched(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            _watcher: None,
+            rx: channel().1,
+            changed: Vec::new(),
+            last_change: None,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // the files changed since the last call, once writing has settled
+    pub fn take(&mut self) -> Option<Vec<PathBuf>> {
+        for path in self.rx.try_iter() {
+            if !self.changed.contains(&path) {
+                self.changed.push(path);
+            }
+            self.last_change = Some(Instant::now());
+        }
+        let settled = self.last_change?.elapsed() >= SETTLE_TIME;
+        if !settled {
+            return None;
+        }
+        self.last_change = None;
+        Some(std::mem::take(&mut self.changed))
+    }
+}
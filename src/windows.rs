@@ -0,0 +1,70 @@
+use crate::balance::{Cumulative, Load};
+use crate::comparison::{FlameDiff, RunDiff};
+use crate::data::ProfileData;
+use crate::formulas::Metrics;
+use crate::messages::{Latency, Messages, SizeDuration};
+use crate::outliers::{Anomalies, Asymmetry, Stragglers};
+use crate::phasing::Phases;
+use crate::queries::{Script, Sql};
+use crate::views::{ViewCommand, ViewState};
+
+// the analysis windows the toolbar opens, each with its own state. like the
+// views they get the trace and the viewer state and return what to change
+#[derive(Default)]
+pub struct Windows {
+    pub phases: Phases,
+    pub metrics: Metrics,
+    pub anomalies: Anomalies,
+    pub stragglers: Stragglers,
+    pub asymmetry: Asymmetry,
+    pub latency: Latency,
+    pub messages: Messages,
+    // the size vs duration scatter and the alpha-beta fits
+    pub size_duration: SizeDuration,
+    pub cumulative: Cumulative,
+    pub script: Script,
+    pub sql: Sql,
+    pub load: Load,
+    pub flame: FlameDiff,
+    pub run_diff: RunDiff,
+}
+
+impl Windows {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        data: &ProfileData,
+        state: &ViewState,
+    ) -> Vec<ViewCommand> {
+        let mut commands = self.phases.show(ctx, data, state);
+        commands.extend(self.metrics.show(ctx, data, state));
+        commands.extend(self.anomalies.show(ctx, data, state));
+        commands.extend(self.stragglers.show(ctx, data, state));
+        commands.extend(self.asymmetry.show(ctx, data, state));
+        self.latency.show(ctx, data, state);
+        commands.extend(self.messages.show(ctx, data, state));
+        self.size_duration.show(ctx, data, state);
+        commands.extend(self.cumulative.show(ctx, data, state));
+        commands.extend(self.script.show(ctx, data, state));
+        commands.extend(self.sql.show(ctx, data, state));
+        commands.extend(self.load.show(ctx, data, state));
+        self.flame.show(ctx, data, state);
+        self.run_diff.show(ctx, data, state);
+        commands
+    }
+
+    // drops what was made from the event times
+    pub fn invalidate(&mut self) {
+        self.phases.clear();
+        self.latency.invalidate();
+        self.size_duration.invalidate();
+        self.sql.invalidate();
+        self.flame.invalidate();
+        self.run_diff.invalidate();
+    }
+
+    // forgets the ranges picked in the last trace
+    pub fn reset(&mut self) {
+        self.flame.reset();
+    }
+}
@@ -0,0 +1,57 @@
+// browser-style back/forward through timeline ranges
+#[derive(Debug, Default)]
+pub struct ZoomHistory {
+    back: Vec<(f64, f64)>,
+    forward: Vec<(f64, f64)>,
+    // input time of the last wheel zoom / pan, to tell gestures apart
+    last_gesture: f64,
+}
+
+const MAX_ENTRIES: usize = 100;
+
+impl ZoomHistory {
+    // remember `view` before leaving it. a new branch drops the forward list
+    pub fn push(&mut self, view: (f64, f64)) {
+        if self.back.last() != Some(&view) {
+            self.back.push(view);
+            if self.back.len() > MAX_ENTRIES {
+                self.back.remove(0);
+            }
+        }
+        self.forward.clear();
+    }
+
+    // wheel zooms and pans only make an entry when they start
+    pub fn note_gesture(&mut self, view: (f64, f64), now: f64) {
+        if now - self.last_gesture > 0.5 {
+            self.push(view);
+        }
+        self.last_gesture = now;
+    }
+
+    // the view to go back to from `current`
+    pub fn back(&mut self, current: (f64, f64)) -> Option<(f64, f64)> {
+        let view = self.back.pop()?;
+        self.forward.push(current);
+        Some(view)
+    }
+
+    pub fn forward(&mut self, current: (f64, f64)) -> Option<(f64, f64)> {
+        let view = self.forward.pop()?;
+        self.back.push(current);
+        Some(view)
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.back.clear();
+        self.forward.clear();
+    }
+}